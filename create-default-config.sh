@@ -32,6 +32,26 @@ else
   DID_SOMETHING="Copied opening-config"
 fi
 
+# Check if theme-config exists
+THEME_CONF="$CONF_DIR/theme.toml"
+if [[ -e "$THEME_CONF" ]]; then
+  echo "Found theme-config..."
+else
+  echo "Copying default theme-config to \"$THEME_CONF\""
+  cp $DIR/examples/theme.toml $THEME_CONF
+  DID_SOMETHING="Copied theme-config"
+fi
+
+# Check if general config exists
+GENERAL_CONF="$CONF_DIR/config.toml"
+if [[ -e "$GENERAL_CONF" ]]; then
+  echo "Found general config..."
+else
+  echo "Copying default general config to \"$GENERAL_CONF\""
+  cp $DIR/examples/config.toml $GENERAL_CONF
+  DID_SOMETHING="Copied general config"
+fi
+
 if [[ -z "$DID_SOMETHING" ]]; then
   echo "Nothing to do."
 else
@@ -0,0 +1,125 @@
+use std::{
+    collections::HashSet,
+    path::{Path, PathBuf},
+    process::{Command, Stdio},
+};
+
+use log::debug;
+
+use crate::{commands::ExpandedPath, util::exec_allowed};
+
+/// Maximum number of directories that are imported from the z- and zoxide-databases.
+const MAX_FRECENT: usize = 50;
+
+/// A directory that shows up in the bookmark overlay.
+#[derive(Debug, Clone)]
+pub struct Bookmark {
+    pub path: PathBuf,
+
+    /// Where the bookmark comes from (e.g. the keys of a jump-mark, or "zoxide")
+    pub source: String,
+}
+
+/// Collects bookmark candidates from the shell:
+/// `CDPATH`, `DIRSTACK` (as a colon-separated list) and the databases of z and zoxide.
+///
+/// Only existing directories are returned, and every directory only once.
+pub fn import_bookmarks() -> Vec<Bookmark> {
+    let mut candidates = Vec::new();
+    let env_list = |name: &str| {
+        std::env::var(name)
+            .map(|value| split_list(&value))
+            .unwrap_or_default()
+    };
+    for path in env_list("CDPATH") {
+        candidates.push((path, "CDPATH"));
+    }
+    for path in env_list("DIRSTACK") {
+        candidates.push((path, "dirstack"));
+    }
+    let z_data = std::env::var("_Z_DATA")
+        .map(PathBuf::from)
+        .unwrap_or_else(|_| ExpandedPath::from("~/.z").into());
+    if let Ok(content) = std::fs::read_to_string(z_data) {
+        for path in parse_z_data(&content).into_iter().take(MAX_FRECENT) {
+            candidates.push((path, "z"));
+        }
+    }
+    for path in query_zoxide().into_iter().take(MAX_FRECENT) {
+        candidates.push((path, "zoxide"));
+    }
+
+    let mut seen = HashSet::new();
+    let bookmarks: Vec<Bookmark> = candidates
+        .into_iter()
+        .filter(|(path, _)| path.is_dir())
+        .filter(|(path, _)| seen.insert(path.clone()))
+        .map(|(path, source)| Bookmark {
+            path,
+            source: source.to_string(),
+        })
+        .collect();
+    debug!("Imported {} bookmarks", bookmarks.len());
+    bookmarks
+}
+
+/// Splits a colon-separated list of paths (like `CDPATH`).
+fn split_list(value: &str) -> Vec<PathBuf> {
+    value
+        .split(':')
+        .filter(|entry| !entry.is_empty() && *entry != ".")
+        .map(|entry| ExpandedPath::from(entry).into())
+        .collect()
+}
+
+/// Parses the database of z (lines of `path|rank|time`), sorted by rank.
+fn parse_z_data(content: &str) -> Vec<PathBuf> {
+    let mut entries: Vec<(f64, &str)> = content
+        .lines()
+        .filter_map(|line| {
+            let mut fields = line.rsplitn(3, '|');
+            let _time = fields.next()?;
+            let rank = fields.next()?.parse().ok()?;
+            Some((rank, fields.next()?))
+        })
+        .collect();
+    entries.sort_by(|a, b| b.0.total_cmp(&a.0));
+    entries
+        .into_iter()
+        .map(|(_, path)| Path::new(path).to_path_buf())
+        .collect()
+}
+
+/// Asks zoxide for its directories, sorted by score.
+fn query_zoxide() -> Vec<PathBuf> {
+    if !exec_allowed() {
+        return Vec::new();
+    }
+    Command::new("zoxide")
+        .args(["query", "--list"])
+        .stderr(Stdio::null())
+        .output()
+        .ok()
+        .filter(|out| out.status.success())
+        .map(|out| {
+            String::from_utf8_lossy(&out.stdout)
+                .lines()
+                .map(PathBuf::from)
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+#[test]
+fn test_parse_z_data() {
+    let content = "/home/user/src|12.5|1700000000\n/tmp|3|1700000001\nbroken\n/home/user/my|dir|40|1700000002\n";
+    let paths = parse_z_data(content);
+    assert_eq!(
+        paths,
+        vec![
+            PathBuf::from("/home/user/my|dir"),
+            PathBuf::from("/home/user/src"),
+            PathBuf::from("/tmp"),
+        ]
+    );
+}
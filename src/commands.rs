@@ -1,13 +1,15 @@
 use std::{
-    collections::HashMap,
+    collections::{BTreeMap, HashMap},
     path::{Path, PathBuf},
 };
 
 use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
-use log::trace;
+use log::{trace, warn};
 use patricia_tree::PatriciaMap;
 use serde::Deserialize;
 
+use crate::project::Project;
+
 const CTRL_C: KeyEvent = KeyEvent::new(KeyCode::Char('c'), KeyModifiers::CONTROL);
 const CTRL_X: KeyEvent = KeyEvent::new(KeyCode::Char('x'), KeyModifiers::CONTROL);
 const CTRL_V: KeyEvent = KeyEvent::new(KeyCode::Char('v'), KeyModifiers::CONTROL);
@@ -48,13 +50,33 @@ impl From<ExpandedPath> for PathBuf {
 struct Manipulation {
     change_directory: Vec<String>,
     rename: Vec<String>,
+    #[serde(default)]
+    rename_before_extension: Vec<String>,
+    #[serde(default)]
+    rename_start: Vec<String>,
+    #[serde(default)]
+    rename_replace: Vec<String>,
     mkdir: Vec<String>,
     touch: Vec<String>,
     cut: Vec<String>,
     copy: Vec<String>,
     delete: Vec<String>,
+    #[serde(default)]
+    delete_permanently: Vec<String>,
+    #[serde(default)]
+    shred: Vec<String>,
     paste: Vec<String>,
     paste_overwrite: Vec<String>,
+    #[serde(default)]
+    paste_symlink: Vec<String>,
+    #[serde(default)]
+    paste_relative_symlink: Vec<String>,
+    #[serde(default)]
+    paste_hardlink: Vec<String>,
+    #[serde(default)]
+    chown: Vec<String>,
+    #[serde(default)]
+    chgrp: Vec<String>,
 }
 
 #[derive(Deserialize, Debug)]
@@ -70,6 +92,18 @@ struct Movement {
     half_page_forward: Vec<String>,
     half_page_backward: Vec<String>,
     jump_previous: Vec<String>,
+    #[serde(default)]
+    follow_link: Vec<String>,
+    #[serde(default)]
+    percent: Vec<String>,
+    #[serde(default)]
+    first_letter: Vec<String>,
+    #[serde(default)]
+    frecent_jump: Vec<String>,
+    #[serde(default)]
+    set_mark: Vec<String>,
+    #[serde(default)]
+    jump_mark: Vec<String>,
     jump_to: Vec<(String, String)>,
 }
 
@@ -80,16 +114,283 @@ struct General {
     next: Vec<String>,
     previous: Vec<String>,
     view_trash: Vec<String>,
+    #[serde(default)]
+    empty_trash: Vec<String>,
     toggle_hidden: Vec<String>,
     toggle_log: Vec<String>,
+    #[serde(default)]
+    toggle_pin: Vec<String>,
+    #[serde(default)]
+    export: Vec<String>,
+    #[serde(default)]
+    export_long: Vec<String>,
+    #[serde(default)]
+    bookmarks: Vec<String>,
+    #[serde(default)]
+    clear_cache: Vec<String>,
+    #[serde(default)]
+    help: Vec<String>,
+    #[serde(default)]
+    disk_usage: Vec<String>,
+    #[serde(default)]
+    devices: Vec<String>,
+    #[serde(default)]
+    mounts: Vec<String>,
+    #[serde(default)]
+    jobs: Vec<String>,
+    #[serde(default)]
+    subshell: Vec<String>,
+    #[serde(default)]
+    run_command: Vec<String>,
+    #[serde(default)]
+    copy_path: Vec<String>,
+    #[serde(default)]
+    copy_dir: Vec<String>,
+    #[serde(default)]
+    copy_name: Vec<String>,
     quit: Vec<String>,
 }
 
+#[derive(Deserialize, Debug, Default)]
+struct Layout {
+    #[serde(default)]
+    grow_center: Vec<String>,
+    #[serde(default)]
+    shrink_center: Vec<String>,
+    #[serde(default)]
+    toggle_left: Vec<String>,
+    #[serde(default)]
+    toggle_preview: Vec<String>,
+    #[serde(default)]
+    toggle_wrap: Vec<String>,
+}
+
+#[derive(Deserialize, Debug, Default)]
+struct Tabs {
+    #[serde(default)]
+    new: Vec<String>,
+    #[serde(default)]
+    close: Vec<String>,
+    #[serde(default)]
+    next: Vec<String>,
+    #[serde(default)]
+    previous: Vec<String>,
+    #[serde(default)]
+    open_marked: Vec<String>,
+}
+
 #[derive(Deserialize, Debug)]
 pub struct KeyConfig {
     general: General,
     movement: Movement,
     manipulation: Manipulation,
+    #[serde(default)]
+    tabs: Tabs,
+    #[serde(default)]
+    layout: Layout,
+    /// Additional names for existing commands (e.g. `md = "mkdir"`)
+    #[serde(default)]
+    aliases: BTreeMap<String, String>,
+    #[serde(default)]
+    leader: Leader,
+}
+
+/// Prefix for user commands, so they don't collide with the built-in bindings.
+///
+/// All bindings may use `<leader>` as a placeholder for the key.
+#[derive(Deserialize, Debug)]
+struct Leader {
+    #[serde(default = "default_leader")]
+    key: String,
+
+    /// Key-sequences (after the leader) mapped to shell commands,
+    /// that run in the current directory (e.g. `gs = "git status"`)
+    #[serde(default)]
+    commands: BTreeMap<String, String>,
+}
+
+impl Default for Leader {
+    fn default() -> Self {
+        Leader {
+            key: default_leader(),
+            commands: BTreeMap::new(),
+        }
+    }
+}
+
+fn default_leader() -> String {
+    ",".to_string()
+}
+
+#[test]
+fn test_aliases() {
+    let mut parser = CommandParser::new();
+    parser.insert(vec!["mkdir".to_string()], Command::Mkdir);
+    parser.insert(vec!["tn".to_string()], Command::NewTab);
+    let aliases = [
+        ("md", "mkdir"),
+        ("t", "touch"),
+        ("tnx", "mkdir"),
+        ("nd", "mkdir  src/"),
+        ("x", "tn docs"),
+        ("z", "!zip -r %f.zip %f"),
+    ]
+    .into_iter()
+    .map(|(a, t)| (a.to_string(), t.to_string()))
+    .collect();
+    parser.insert_aliases(aliases);
+    assert!(matches!(
+        parser.key_commands.get("md"),
+        Some(Command::Mkdir)
+    ));
+    // Unknown target
+    assert!(parser.key_commands.get("t").is_none());
+    // Shadowed by "tn"
+    assert!(parser.key_commands.get("tnx").is_none());
+    // Arguments fill in the prompt - commands without a prompt take none
+    assert!(matches!(
+        parser.key_commands.get("nd"),
+        Some(Command::Prefilled { command, input }) if matches!(**command, Command::Mkdir) && input == "src/"
+    ));
+    assert!(parser.key_commands.get("x").is_none());
+    assert!(matches!(
+        parser.key_commands.get("z"),
+        Some(Command::Template(template)) if template == "zip -r %f.zip %f"
+    ));
+}
+
+#[test]
+fn test_count() {
+    let mut parser = CommandParser::new();
+    parser.insert(vec!["j".to_string()], Command::Move(Move::Down));
+    parser.insert(vec!["dd".to_string()], Command::Cut);
+    parser.insert(vec!["1x".to_string()], Command::Quit);
+    let mut keys = |keys: &str| {
+        keys.chars()
+            .map(|c| parser.add_event(KeyEvent::new(KeyCode::Char(c), KeyModifiers::NONE)))
+            .last()
+            .map(|cmd| (cmd, parser.count(), parser.buffer()))
+            .unwrap()
+    };
+    assert!(matches!(
+        keys("5j"),
+        (Command::Move(Move::Down), Some(5), _)
+    ));
+    assert!(matches!(keys("j"), (Command::Move(Move::Down), None, _)));
+    assert!(matches!(keys("20d"), (Command::None, _, b) if b == "20d"));
+    assert!(matches!(keys("d"), (Command::Cut, Some(20), _)));
+    // A leading zero is no count, and "1" is the start of a binding
+    assert!(matches!(keys("0j"), (Command::Move(Move::Down), None, _)));
+    assert!(matches!(keys("1x"), (Command::Quit, None, _)));
+}
+
+#[test]
+fn test_bindings() {
+    let mut parser = CommandParser::new();
+    parser.insert(vec!["dd".to_string(), "ctrl-x".to_string()], Command::Cut);
+    let bindings: Vec<(String, &str)> = parser
+        .bindings()
+        .into_iter()
+        .map(|(keys, cmd)| (keys, cmd.describe().0))
+        .collect();
+    assert!(bindings.contains(&("dd".to_string(), "cut")));
+    assert!(bindings.contains(&("ctrl-x".to_string(), "cut")));
+    assert!(bindings.contains(&("up".to_string(), "up")));
+}
+
+#[test]
+fn test_goto_defaults() {
+    let home = std::env::var("HOME").unwrap_or_default();
+    let bindings: Vec<(String, String)> = CommandParser::default_bindings()
+        .bindings()
+        .into_iter()
+        .map(|(keys, cmd)| (keys, cmd.describe().1))
+        .collect();
+    assert!(bindings.contains(&("gh".to_string(), format!("Jump to {home}"))));
+    assert!(bindings.contains(&("gd".to_string(), format!("Jump to {home}/Downloads"))));
+    assert!(bindings.contains(&("gm".to_string(), "Jump to /mnt".to_string())));
+    assert!(bindings.contains(&("gt".to_string(), "Open the trash".to_string())));
+}
+
+#[test]
+fn test_completions() {
+    let mut parser = CommandParser::new();
+    parser.insert(vec!["gg".to_string()], Command::Move(Move::Top));
+    parser.insert(vec!["gf".to_string()], Command::Move(Move::FollowLink));
+    parser.insert(vec!["dd".to_string()], Command::Cut);
+    assert!(parser.completions().is_empty());
+    parser.add_event(KeyEvent::new(KeyCode::Char('g'), KeyModifiers::NONE));
+    assert!(parser.is_pending());
+    let keys: Vec<String> = parser.completions().into_iter().map(|(k, _)| k).collect();
+    assert_eq!(keys, ["gf", "gg"]);
+}
+
+#[test]
+fn test_project_commands() {
+    use crate::project::ProjectConfig;
+
+    let mut parser = CommandParser::new();
+    parser.insert(vec!["dd".to_string()], Command::Cut);
+    let commands = [("b", "cargo build"), ("d", "docker compose up")]
+        .into_iter()
+        .map(|(k, c)| (k.to_string(), c.to_string()))
+        .collect();
+    let project = Project {
+        root: PathBuf::from("/project"),
+        config: ProjectConfig { commands },
+        hash: String::new(),
+    };
+    parser.set_project(Some(&project));
+    assert!(matches!(
+        parser.key_commands.get("b"),
+        Some(Command::Shell { command, .. }) if command == "cargo build"
+    ));
+    // Shadowed by "dd"
+    assert!(parser.key_commands.get("d").is_none());
+    parser.set_project(None);
+    assert!(parser.key_commands.get("b").is_none());
+    assert!(parser.key_commands.get("dd").is_some());
+}
+
+#[test]
+fn test_leader() {
+    let mut parser = CommandParser::new();
+    parser.leader = "\\".to_string();
+    parser.insert(vec!["<leader>m".to_string()], Command::Mkdir);
+    let commands = [("gs", "git status"), ("m", "make")]
+        .into_iter()
+        .map(|(k, c)| (k.to_string(), c.to_string()))
+        .collect();
+    parser.insert_leader_commands(commands);
+    assert!(matches!(
+        parser.key_commands.get("\\m"),
+        Some(Command::Mkdir)
+    ));
+    assert!(matches!(
+        parser.key_commands.get("\\gs"),
+        Some(Command::Shell { dir: None, .. })
+    ));
+}
+
+#[test]
+fn test_plugin_bindings() {
+    let mut parser = CommandParser::new();
+    parser.insert(vec!["dd".to_string()], Command::Cut);
+    parser.insert_plugin_bindings(vec![
+        ("zz".to_string(), "Plugin".to_string()),
+        ("d".to_string(), "Shadowed".to_string()),
+    ]);
+    assert!(matches!(
+        parser.key_commands.get("zz"),
+        Some(Command::Plugin { index: 0, .. })
+    ));
+    assert!(parser.key_commands.get("d").is_none());
+    assert!(matches!(parser.command_by_name("cut"), Some(Command::Cut)));
+    assert!(matches!(
+        parser.command_by_name("up"),
+        Some(Command::Move(Move::Up))
+    ));
+    assert!(parser.command_by_name("mkdir").is_none());
 }
 
 #[test]
@@ -113,6 +414,48 @@ pub enum Move {
     HalfPageBackward,
     JumpTo(ExpandedPath),
     JumpPrevious,
+    FollowLink,
+    /// Jumps to the given percentage of the list (the count, or 50%)
+    Percent,
+}
+
+/// Runtime change of the column widths.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LayoutChange {
+    /// Moves one ratio step from the preview to the center column
+    GrowCenter,
+    /// Moves one ratio step from the center column to the preview
+    ShrinkCenter,
+    /// Collapses the left column, or restores it
+    ToggleLeft,
+    /// Hides the preview - or shows it next to the center panel in narrow terminals
+    TogglePreview,
+    /// Wraps or truncates long lines in text previews
+    ToggleWrap,
+}
+
+/// Where the cursor starts when a single item is renamed (like in ranger).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RenameCursor {
+    /// End of the name
+    End,
+    /// In front of the (last) extension
+    BeforeExtension,
+    /// Start of the name
+    Start,
+    /// Empty input, to type a completely new name
+    Replace,
+}
+
+/// Part of the path of the selected item, that is copied to the system clipboard.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PathPart {
+    /// Absolute path
+    Path,
+    /// Absolute path of the parent directory
+    Directory,
+    /// File name
+    Name,
 }
 
 #[derive(Debug, Clone)]
@@ -122,35 +465,283 @@ pub enum Command {
     Previous,
     ToggleHidden,
     ToggleLog,
+    TogglePin,
     ViewTrash,
+    EmptyTrash,
+    Bookmarks,
+    ClearCache,
+    FirstLetter,
+    FrecentJump,
+    SetMark,
+    JumpMark,
+    Help,
+    DiskUsage,
+    Devices,
+    Mounts,
+    Jobs,
+    Subshell,
+    Run,
+    CopyToClipboard(PathPart),
+    Export {
+        long: bool,
+    },
+    NewTab,
+    CloseTab,
+    NextTab,
+    PreviousTab,
+    OpenMarkedInTabs,
+    Layout(LayoutChange),
     Cd,
     Search,
-    Rename,
+    Rename(RenameCursor),
     Mkdir,
     Touch,
     Cut,
     Copy,
     Delete,
-    Paste { overwrite: bool },
+    DeletePermanently,
+    Shred,
+    Paste {
+        overwrite: bool,
+    },
+    PasteSymlink {
+        relative: bool,
+    },
+    PasteHardlink,
+    Chown {
+        group_only: bool,
+    },
     Mark,
+    /// Runs a shell command - of a `.rfm.toml` in its directory,
+    /// or of the leader commands in the current directory
+    Shell {
+        command: String,
+        dir: Option<PathBuf>,
+    },
+    /// Calls the function of a plugin keybinding
+    Plugin {
+        index: usize,
+        description: String,
+    },
+    /// Runs a command that opens a prompt, with the prompt already filled in (aliases with arguments)
+    Prefilled {
+        command: Box<Command>,
+        input: String,
+    },
+    /// Shell command template of an alias - its macros are expanded like in the run prompt
+    Template(String),
     Quit,
     None,
 }
 
+impl Command {
+    /// Returns the name of the command (as in `keys.toml`) and a short description.
+    pub fn describe(&self) -> (&'static str, String) {
+        let (name, description) = match self {
+            Command::Move(movement) => match movement {
+                Move::Up => ("up", "Move the selection up"),
+                Move::Down => ("down", "Move the selection down"),
+                Move::Left => ("left", "Go to the parent directory"),
+                Move::Right => ("right", "Enter the directory or open the file"),
+                Move::Top => ("top", "Select the first item"),
+                Move::Bottom => ("bottom", "Select the last item"),
+                Move::PageForward => ("page_forward", "Move one page down"),
+                Move::PageBackward => ("page_backward", "Move one page up"),
+                Move::HalfPageForward => ("half_page_forward", "Move half a page down"),
+                Move::HalfPageBackward => ("half_page_backward", "Move half a page up"),
+                Move::JumpTo(path) => {
+                    return ("jump_to", format!("Jump to {}", path.as_ref().display()))
+                }
+                Move::JumpPrevious => ("jump_previous", "Jump back to the previous directory"),
+                Move::FollowLink => ("follow_link", "Jump to the target of the symlink"),
+                Move::Percent => ("percent", "Jump to N% of the list"),
+            },
+            Command::Next => ("next", "Jump to the next marked item"),
+            Command::Previous => ("previous", "Jump to the previous marked item"),
+            Command::ToggleHidden => ("toggle_hidden", "Show or hide hidden files"),
+            Command::ToggleLog => ("toggle_log", "Show or hide the log"),
+            Command::TogglePin => ("toggle_pin", "Pin the left panel to this directory"),
+            Command::ViewTrash => ("view_trash", "Open the trash"),
+            Command::EmptyTrash => ("empty_trash", "Permanently delete everything in the trash"),
+            Command::Bookmarks => ("bookmarks", "Show all bookmarks"),
+            Command::ClearCache => ("clear_cache", "Drop all cached directories and previews"),
+            Command::FirstLetter => (
+                "first_letter",
+                "Jump to the next item starting with a letter",
+            ),
+            Command::FrecentJump => (
+                "frecent_jump",
+                "Jump to the most visited directory matching the input",
+            ),
+            Command::SetMark => ("set_mark", "Set a mark for this session"),
+            Command::JumpMark => ("jump_mark", "Jump to a mark of this session"),
+            Command::Help => ("help", "Show all keybindings"),
+            Command::DiskUsage => (
+                "disk_usage",
+                "Show the disk usage below the current directory",
+            ),
+            Command::Devices => ("devices", "Mount, unmount and eject removable media"),
+            Command::Mounts => (
+                "mounts",
+                "Show the mounted filesystems and their free space",
+            ),
+            Command::Jobs => (
+                "jobs",
+                "Show the running jobs - Enter shows the progress of one in the right column",
+            ),
+            Command::Subshell => ("subshell", "Start a shell in the current directory"),
+            Command::Run => (
+                "run_command",
+                "Run a shell command (%f: selected, %s: marked, %d: directory)",
+            ),
+            Command::CopyToClipboard(PathPart::Path) => (
+                "copy_path",
+                "Copy the absolute path to the system clipboard",
+            ),
+            Command::CopyToClipboard(PathPart::Directory) => {
+                ("copy_dir", "Copy the directory to the system clipboard")
+            }
+            Command::CopyToClipboard(PathPart::Name) => {
+                ("copy_name", "Copy the file name to the system clipboard")
+            }
+            Command::Export { long: false } => ("export", "Export the listing"),
+            Command::Export { long: true } => ("export_long", "Export the listing with details"),
+            Command::NewTab => ("tabs.new", "Open a new tab"),
+            Command::CloseTab => ("tabs.close", "Close the tab"),
+            Command::NextTab => ("tabs.next", "Switch to the next tab"),
+            Command::PreviousTab => ("tabs.previous", "Switch to the previous tab"),
+            Command::OpenMarkedInTabs => {
+                ("tabs.open_marked", "Open the marked directories in tabs")
+            }
+            Command::Layout(LayoutChange::GrowCenter) => {
+                ("layout.grow_center", "Widen the center column")
+            }
+            Command::Layout(LayoutChange::ShrinkCenter) => {
+                ("layout.shrink_center", "Narrow the center column")
+            }
+            Command::Layout(LayoutChange::ToggleLeft) => {
+                ("layout.toggle_left", "Hide or show the left column")
+            }
+            Command::Layout(LayoutChange::TogglePreview) => {
+                ("layout.toggle_preview", "Show or hide the preview")
+            }
+            Command::Layout(LayoutChange::ToggleWrap) => (
+                "layout.toggle_wrap",
+                "Wrap or truncate long lines in the preview",
+            ),
+            Command::Cd => ("change_directory", "Change the directory"),
+            Command::Search => ("search", "Search in the directory"),
+            Command::Rename(RenameCursor::End) => ("rename", "Rename the item"),
+            Command::Rename(RenameCursor::BeforeExtension) => (
+                "rename_before_extension",
+                "Rename the item, starting in front of the extension",
+            ),
+            Command::Rename(RenameCursor::Start) => {
+                ("rename_start", "Rename the item, starting at the beginning")
+            }
+            Command::Rename(RenameCursor::Replace) => (
+                "rename_replace",
+                "Rename the item, starting with an empty name",
+            ),
+            Command::Mkdir => ("mkdir", "Create a directory"),
+            Command::Touch => ("touch", "Create a file"),
+            Command::Cut => ("cut", "Cut the marked items"),
+            Command::Copy => ("copy", "Copy the marked items"),
+            Command::Delete => ("delete", "Move the marked items to the trash"),
+            Command::DeletePermanently => (
+                "delete_permanently",
+                "Delete the marked items without the trash",
+            ),
+            Command::Shred => (
+                "shred",
+                "Overwrite the marked items with random data and delete them",
+            ),
+            Command::Paste { overwrite: false } => ("paste", "Paste the clipboard"),
+            Command::Paste { overwrite: true } => (
+                "paste_overwrite",
+                "Paste the clipboard, overwriting existing files",
+            ),
+            Command::PasteSymlink { relative: false } => {
+                ("paste_symlink", "Paste the clipboard as symlinks")
+            }
+            Command::PasteSymlink { relative: true } => (
+                "paste_relative_symlink",
+                "Paste the clipboard as relative symlinks",
+            ),
+            Command::PasteHardlink => ("paste_hardlink", "Paste the clipboard as hardlinks"),
+            Command::Chown { group_only: false } => ("chown", "Change the owner"),
+            Command::Chown { group_only: true } => ("chgrp", "Change the group"),
+            Command::Mark => ("mark", "Mark or unmark the item"),
+            Command::Shell { command, .. } => return ("shell", command.clone()),
+            Command::Plugin { description, .. } => return ("plugin", description.clone()),
+            Command::Prefilled { command, input } => {
+                let (name, description) = command.describe();
+                return (name, format!("{description}: {input}"));
+            }
+            Command::Template(template) => return ("template", template.clone()),
+            Command::Quit => ("quit", "Quit rfm"),
+            Command::None => ("none", ""),
+        };
+        (name, description.to_string())
+    }
+
+    /// Whether the command opens a prompt, that an alias can fill in.
+    fn takes_input(&self) -> bool {
+        matches!(
+            self,
+            Command::Mkdir
+                | Command::Touch
+                | Command::Search
+                | Command::Run
+                | Command::Export { .. }
+                | Command::Chown { .. }
+        )
+    }
+}
+
+/// Returns the name of a key with modifier, in the notation of `keys.toml` (e.g. "ctrl-f").
+fn key_name(event: &KeyEvent) -> String {
+    let key = match event.code {
+        KeyCode::Char(c) => c.to_string(),
+        KeyCode::BackTab => "shift-tab".to_string(),
+        code => format!("{code:?}").to_lowercase(),
+    };
+    if event.modifiers.contains(KeyModifiers::CONTROL) {
+        format!("ctrl-{key}")
+    } else {
+        key
+    }
+}
+
 /// Takes the incoming key-events, and returns the corresponding command.
 ///
 /// Uses a `PatriciaMap` to match patterns of keystrokes,
 /// and a normal `HashMap` to match "oneshot"-commands,
 /// that don't require any key combinations but may require a modifier.
+///
+/// A command can be prefixed with a count (e.g. `5j`), unless a binding starts with that digit.
 pub struct CommandParser {
     key_commands: PatriciaMap<Command>,
     mod_commands: HashMap<KeyEvent, Command>,
     buffer: String,
+
+    /// Count that is typed in front of the next command
+    pending_count: Option<usize>,
+
+    /// Count of the last returned command
+    last_count: Option<usize>,
+
+    /// Key-sequences of the commands of the current `.rfm.toml`
+    project_keys: Vec<String>,
+
+    /// Replaces `<leader>` in all bindings
+    leader: String,
 }
 
 impl CommandParser {
     pub fn from_config(config: KeyConfig) -> Self {
         let mut parser = CommandParser::new();
+        parser.leader = config.leader.key;
         // General commands
         parser.insert(config.general.search, Command::Search);
         parser.insert(config.general.mark, Command::Mark);
@@ -159,7 +750,59 @@ impl CommandParser {
         parser.insert(config.general.quit, Command::Quit);
         parser.insert(config.general.toggle_hidden, Command::ToggleHidden);
         parser.insert(config.general.toggle_log, Command::ToggleLog);
+        parser.insert(config.general.toggle_pin, Command::TogglePin);
+        parser.insert(config.general.export, Command::Export { long: false });
+        parser.insert(config.general.export_long, Command::Export { long: true });
         parser.insert(config.general.view_trash, Command::ViewTrash);
+        parser.insert(config.general.empty_trash, Command::EmptyTrash);
+        parser.insert(config.general.bookmarks, Command::Bookmarks);
+        parser.insert(config.general.clear_cache, Command::ClearCache);
+        parser.insert(config.general.help, Command::Help);
+        parser.insert(config.general.disk_usage, Command::DiskUsage);
+        parser.insert(config.general.devices, Command::Devices);
+        parser.insert(config.general.mounts, Command::Mounts);
+        parser.insert(config.general.jobs, Command::Jobs);
+        parser.insert(config.general.subshell, Command::Subshell);
+        parser.insert(config.general.run_command, Command::Run);
+        parser.insert(
+            config.general.copy_path,
+            Command::CopyToClipboard(PathPart::Path),
+        );
+        parser.insert(
+            config.general.copy_dir,
+            Command::CopyToClipboard(PathPart::Directory),
+        );
+        parser.insert(
+            config.general.copy_name,
+            Command::CopyToClipboard(PathPart::Name),
+        );
+
+        // Tab commands
+        parser.insert(config.tabs.new, Command::NewTab);
+        parser.insert(config.tabs.close, Command::CloseTab);
+        parser.insert(config.tabs.next, Command::NextTab);
+        parser.insert(config.tabs.previous, Command::PreviousTab);
+        parser.insert(config.tabs.open_marked, Command::OpenMarkedInTabs);
+        parser.insert(
+            config.layout.grow_center,
+            Command::Layout(LayoutChange::GrowCenter),
+        );
+        parser.insert(
+            config.layout.shrink_center,
+            Command::Layout(LayoutChange::ShrinkCenter),
+        );
+        parser.insert(
+            config.layout.toggle_left,
+            Command::Layout(LayoutChange::ToggleLeft),
+        );
+        parser.insert(
+            config.layout.toggle_preview,
+            Command::Layout(LayoutChange::TogglePreview),
+        );
+        parser.insert(
+            config.layout.toggle_wrap,
+            Command::Layout(LayoutChange::ToggleWrap),
+        );
 
         // Movement commands
         parser.insert(config.movement.up, Command::Move(Move::Up));
@@ -188,19 +831,54 @@ impl CommandParser {
             config.movement.jump_previous,
             Command::Move(Move::JumpPrevious),
         );
+        parser.insert(config.movement.follow_link, Command::Move(Move::FollowLink));
+        parser.insert(config.movement.percent, Command::Move(Move::Percent));
+        parser.insert(config.movement.first_letter, Command::FirstLetter);
+        parser.insert(config.movement.frecent_jump, Command::FrecentJump);
+        parser.insert(config.movement.set_mark, Command::SetMark);
+        parser.insert(config.movement.jump_mark, Command::JumpMark);
         for (keys, path) in config.movement.jump_to {
+            let keys = parser.expand_leader(&keys);
             parser
                 .key_commands
                 .insert(keys, Command::Move(Move::JumpTo(path.into())));
         }
         // Manipulation commands
         parser.insert(config.manipulation.change_directory, Command::Cd);
-        parser.insert(config.manipulation.rename, Command::Rename);
+        parser.insert(
+            config.manipulation.rename,
+            Command::Rename(RenameCursor::End),
+        );
+        parser.insert(
+            config.manipulation.rename_before_extension,
+            Command::Rename(RenameCursor::BeforeExtension),
+        );
+        parser.insert(
+            config.manipulation.rename_start,
+            Command::Rename(RenameCursor::Start),
+        );
+        parser.insert(
+            config.manipulation.rename_replace,
+            Command::Rename(RenameCursor::Replace),
+        );
         parser.insert(config.manipulation.mkdir, Command::Mkdir);
         parser.insert(config.manipulation.touch, Command::Touch);
         parser.insert(config.manipulation.cut, Command::Cut);
         parser.insert(config.manipulation.copy, Command::Copy);
         parser.insert(config.manipulation.delete, Command::Delete);
+        parser.insert(
+            config.manipulation.delete_permanently,
+            Command::DeletePermanently,
+        );
+        parser.insert(config.manipulation.shred, Command::Shred);
+        parser.insert(
+            config.manipulation.chown,
+            Command::Chown { group_only: false },
+        );
+        parser.insert(
+            config.manipulation.chgrp,
+            Command::Chown { group_only: true },
+        );
         parser.insert(
             config.manipulation.paste,
             Command::Paste { overwrite: false },
@@ -209,10 +887,161 @@ impl CommandParser {
             config.manipulation.paste_overwrite,
             Command::Paste { overwrite: true },
         );
+        parser.insert(
+            config.manipulation.paste_symlink,
+            Command::PasteSymlink { relative: false },
+        );
+        parser.insert(
+            config.manipulation.paste_relative_symlink,
+            Command::PasteSymlink { relative: true },
+        );
+        parser.insert(config.manipulation.paste_hardlink, Command::PasteHardlink);
+
+        // Aliases must come last, so they can refer to all other commands
+        parser.insert_aliases(config.aliases);
+        parser.insert_leader_commands(config.leader.commands);
 
         parser
     }
 
+    /// Binds every alias to the command of its target key-sequence.
+    ///
+    /// Arguments after the key-sequence fill in the prompt of the command (e.g. `tp = "touch notes/"`),
+    /// and a target starting with `!` is a shell command template (e.g. `xz = "!tar czf %f.tar.gz %f"`).
+    /// Aliases that would shadow (or be shadowed by) another binding are ignored,
+    /// because the parser executes a command as soon as the typed keys match.
+    fn insert_aliases(&mut self, aliases: BTreeMap<String, String>) {
+        for (alias, target) in aliases {
+            let alias = self.expand_leader(&alias);
+            let Some(cmd) = self.alias_target(&alias, target.trim()) else {
+                continue;
+            };
+            match self.conflict(&alias) {
+                Some(keys) => warn!("Alias '{alias}' conflicts with the binding '{keys}'"),
+                None => {
+                    self.key_commands.insert(alias, cmd);
+                }
+            }
+        }
+    }
+
+    /// Returns the command of an alias target (see [`CommandParser::insert_aliases`]).
+    fn alias_target(&self, alias: &str, target: &str) -> Option<Command> {
+        if let Some(template) = target.strip_prefix('!') {
+            return Some(Command::Template(template.trim().to_string()));
+        }
+        let (keys, input) = target.split_once(' ').unwrap_or((target, ""));
+        let keys = self.expand_leader(keys);
+        let Some(cmd) = self.key_commands.get(&keys).cloned() else {
+            warn!("Alias '{alias}': there is no command '{keys}'");
+            return None;
+        };
+        match input.trim_start() {
+            "" => Some(cmd),
+            input if cmd.takes_input() => Some(Command::Prefilled {
+                command: Box::new(cmd),
+                input: input.to_string(),
+            }),
+            _ => {
+                warn!("Alias '{alias}': the command '{keys}' takes no input");
+                None
+            }
+        }
+    }
+
+    /// Binds the shell commands to `<leader>` followed by their key-sequence.
+    ///
+    /// Like aliases, they never shadow the regular bindings.
+    fn insert_leader_commands(&mut self, commands: BTreeMap<String, String>) {
+        for (keys, command) in commands {
+            let keys = format!("{}{keys}", self.leader);
+            match self.conflict(&keys) {
+                Some(existing) => {
+                    warn!("Leader command '{keys}' conflicts with the binding '{existing}'")
+                }
+                None => {
+                    self.key_commands
+                        .insert(keys, Command::Shell { command, dir: None });
+                }
+            }
+        }
+    }
+
+    /// Binds the keybindings of the plugins (see [`crate::plugin::PluginHost::bindings`]).
+    ///
+    /// Like aliases, they never shadow the regular bindings.
+    pub fn insert_plugin_bindings(&mut self, bindings: Vec<(String, String)>) {
+        for (index, (keys, description)) in bindings.into_iter().enumerate() {
+            let keys = self.expand_leader(&keys);
+            match self.conflict(&keys) {
+                Some(existing) => {
+                    warn!("Plugin binding '{keys}' conflicts with the binding '{existing}'")
+                }
+                None => {
+                    self.key_commands
+                        .insert(keys, Command::Plugin { index, description });
+                }
+            }
+        }
+    }
+
+    /// Returns the bound command with the given name (see [`Command::describe`]).
+    pub fn command_by_name(&self, name: &str) -> Option<Command> {
+        self.key_commands
+            .values()
+            .chain(self.mod_commands.values())
+            .find(|cmd| cmd.describe().0 == name)
+            .cloned()
+    }
+
+    /// Replaces the `<leader>` placeholder with the leader key.
+    fn expand_leader(&self, keys: &str) -> String {
+        keys.replace("<leader>", &self.leader)
+    }
+
+    /// Returns a binding that shadows (or is shadowed by) the key-sequence.
+    fn conflict(&self, keys: &str) -> Option<String> {
+        self.key_commands
+            .get_longest_common_prefix(keys.as_bytes())
+            .map(|(keys, _)| String::from_utf8_lossy(keys).to_string())
+            .or_else(|| {
+                self.key_commands
+                    .iter_prefix(keys.as_bytes())
+                    .next()
+                    .map(|(keys, _)| String::from_utf8_lossy(&keys).to_string())
+            })
+    }
+
+    /// Replaces the commands of the previous `.rfm.toml` with the ones of the project.
+    ///
+    /// Like aliases, project commands never shadow the regular bindings.
+    pub fn set_project(&mut self, project: Option<&Project>) {
+        for keys in self.project_keys.drain(..) {
+            self.key_commands.remove(keys);
+        }
+        let Some(project) = project else {
+            return;
+        };
+        for (keys, command) in project.config.commands.iter() {
+            let keys = &self.expand_leader(keys);
+            match self.conflict(keys) {
+                Some(existing) => {
+                    warn!("Project command '{keys}' conflicts with the binding '{existing}'")
+                }
+                None => {
+                    self.key_commands.insert(
+                        keys,
+                        Command::Shell {
+                            command: command.clone(),
+                            dir: Some(project.root.clone()),
+                        },
+                    );
+                    self.project_keys.push(keys.clone());
+                }
+            }
+        }
+    }
+
     pub fn new() -> Self {
         let mut mod_commands = HashMap::new();
         // Insert basic arrow key movement
@@ -240,13 +1069,74 @@ impl CommandParser {
             KeyEvent::new(KeyCode::PageDown, KeyModifiers::NONE),
             Command::Move(Move::PageForward),
         );
+        // Cycle through tabs
+        mod_commands.insert(
+            KeyEvent::new(KeyCode::Tab, KeyModifiers::NONE),
+            Command::NextTab,
+        );
+        mod_commands.insert(
+            KeyEvent::new(KeyCode::BackTab, KeyModifiers::SHIFT),
+            Command::PreviousTab,
+        );
         CommandParser {
             key_commands: PatriciaMap::new(),
             mod_commands,
             buffer: "".to_string(),
+            pending_count: None,
+            last_count: None,
+            project_keys: Vec::new(),
+            leader: default_leader(),
         }
     }
 
+    /// Returns the keys and directories of all jump-marks.
+    pub fn jump_marks(&self) -> Vec<(String, PathBuf)> {
+        self.key_commands
+            .iter()
+            .filter_map(|(keys, cmd)| match cmd {
+                Command::Move(Move::JumpTo(path)) => {
+                    Some((String::from_utf8(keys).ok()?, path.clone().into()))
+                }
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// Returns `true` if a key-sequence is partially typed.
+    pub fn is_pending(&self) -> bool {
+        !self.buffer.is_empty()
+    }
+
+    /// Returns all key-sequences (sorted) that complete the partially typed keys.
+    pub fn completions(&self) -> Vec<(String, Command)> {
+        if self.buffer.is_empty() {
+            return Vec::new();
+        }
+        let mut completions: Vec<(String, Command)> = self
+            .key_commands
+            .iter_prefix(self.buffer.as_bytes())
+            .map(|(keys, cmd)| (String::from_utf8_lossy(&keys).to_string(), cmd.clone()))
+            .collect();
+        completions.sort_by(|a, b| a.0.cmp(&b.0));
+        completions
+    }
+
+    /// Returns all key-sequences (sorted) together with their commands.
+    pub fn bindings(&self) -> Vec<(String, Command)> {
+        let mut bindings: Vec<(String, Command)> = self
+            .key_commands
+            .iter()
+            .map(|(keys, cmd)| (String::from_utf8_lossy(&keys).to_string(), cmd.clone()))
+            .chain(
+                self.mod_commands
+                    .iter()
+                    .map(|(event, cmd)| (key_name(event), cmd.clone())),
+            )
+            .collect();
+        bindings.sort_by(|a, b| a.0.cmp(&b.0));
+        bindings
+    }
+
     fn insert(&mut self, bindings: Vec<String>, cmd: Command) {
         for b in bindings {
             // Check if b starts with "ctrl"
@@ -263,7 +1153,8 @@ impl CommandParser {
                     cmd.clone(),
                 );
             } else {
-                self.key_commands.insert(b, cmd.clone());
+                self.key_commands
+                    .insert(self.expand_leader(&b), cmd.clone());
             }
         }
     }
@@ -297,7 +1188,7 @@ impl CommandParser {
             "gw",
             Command::Move(Move::JumpTo("~/Bilder/wallpapers".into())),
         );
-        key_commands.insert("gd", Command::Move(Move::JumpTo("~/Dokumente".into())));
+        key_commands.insert("gd", Command::Move(Move::JumpTo("~/Downloads".into())));
         key_commands.insert("gD", Command::Move(Move::JumpTo("~/Downloads".into())));
         key_commands.insert(
             "gl",
@@ -309,18 +1200,69 @@ impl CommandParser {
                 "~/Projekte/loadrunner-2021/lr-localization".into(),
             )),
         );
-        key_commands.insert("gm", Command::Move(Move::JumpTo("~/Musik".into())));
+        key_commands.insert("gm", Command::Move(Move::JumpTo("/mnt".into())));
         key_commands.insert("gN", Command::Move(Move::JumpTo("/nix/store".into())));
+        key_commands.insert("gt", Command::ViewTrash);
         key_commands.insert("gT", Command::ViewTrash);
+        key_commands.insert("emptytrash", Command::EmptyTrash);
+
+        // Show all bookmarks
+        key_commands.insert("B", Command::Bookmarks);
+
+        // Show all keybindings
+        key_commands.insert("?", Command::Help);
+
+        // Disk usage of the current directory
+        key_commands.insert("du", Command::DiskUsage);
+        key_commands.insert("mnt", Command::Devices);
+        key_commands.insert("df", Command::Mounts);
+        key_commands.insert("gj", Command::Jobs);
+        key_commands.insert("S", Command::Subshell);
+        key_commands.insert("!", Command::Run);
+
+        // Copy the path of the selected item to the system clipboard
+        key_commands.insert("yp", Command::CopyToClipboard(PathPart::Path));
+        key_commands.insert("yd", Command::CopyToClipboard(PathPart::Directory));
+        key_commands.insert("yn", Command::CopyToClipboard(PathPart::Name));
 
         // Toggle hidden files
         key_commands.insert("zh", Command::ToggleHidden);
 
+        // Pin the left panel to the current directory
+        key_commands.insert("zp", Command::TogglePin);
+
+        // Export the listing of the current directory
+        key_commands.insert("export", Command::Export { long: false });
+        key_commands.insert("Export", Command::Export { long: true });
+
         // Toggle log visibility
         key_commands.insert("devlog", Command::ToggleLog);
 
+        // Drop all cached panels
+        key_commands.insert("clearcache", Command::ClearCache);
+
+        // Tabs
+        key_commands.insert("tn", Command::NewTab);
+        key_commands.insert("tc", Command::CloseTab);
+        key_commands.insert("tt", Command::NextTab);
+        key_commands.insert("tT", Command::PreviousTab);
+        key_commands.insert("tm", Command::OpenMarkedInTabs);
+
+        // Column widths
+        key_commands.insert("z>", Command::Layout(LayoutChange::GrowCenter));
+        key_commands.insert("z<", Command::Layout(LayoutChange::ShrinkCenter));
+        key_commands.insert("zl", Command::Layout(LayoutChange::ToggleLeft));
+        key_commands.insert("zv", Command::Layout(LayoutChange::TogglePreview));
+        key_commands.insert("zw", Command::Layout(LayoutChange::ToggleWrap));
+
         // Jump to previous location
         key_commands.insert("\'\'", Command::Move(Move::JumpPrevious));
+        key_commands.insert("gf", Command::Move(Move::FollowLink));
+        key_commands.insert("%", Command::Move(Move::Percent));
+        key_commands.insert("F", Command::FirstLetter);
+        key_commands.insert("gz", Command::FrecentJump);
+        key_commands.insert("M", Command::SetMark);
+        key_commands.insert("`", Command::JumpMark);
 
         // Mark current file
         key_commands.insert(" ", Command::Mark);
@@ -333,7 +1275,12 @@ impl CommandParser {
         key_commands.insert("pp", Command::Paste { overwrite: false });
         key_commands.insert("paste", Command::Paste { overwrite: false });
         key_commands.insert("po", Command::Paste { overwrite: true });
+        key_commands.insert("pl", Command::PasteSymlink { relative: false });
+        key_commands.insert("pL", Command::PasteSymlink { relative: true });
+        key_commands.insert("ph", Command::PasteHardlink);
         key_commands.insert("delete", Command::Delete);
+        key_commands.insert("D", Command::DeletePermanently);
+        key_commands.insert("shred", Command::Shred);
 
         // Search
         key_commands.insert("/", Command::Search);
@@ -346,7 +1293,15 @@ impl CommandParser {
         key_commands.insert("touch", Command::Touch);
 
         // Rename
-        key_commands.insert("rename", Command::Rename);
+        key_commands.insert("rename", Command::Rename(RenameCursor::End));
+        key_commands.insert("A", Command::Rename(RenameCursor::End));
+        key_commands.insert("a", Command::Rename(RenameCursor::BeforeExtension));
+        key_commands.insert("i", Command::Rename(RenameCursor::Start));
+        key_commands.insert("I", Command::Rename(RenameCursor::Replace));
+
+        // Change owner and group
+        key_commands.insert("chown", Command::Chown { group_only: false });
+        key_commands.insert("chgrp", Command::Chown { group_only: true });
 
         // Quit
         key_commands.insert("q", Command::Quit);
@@ -384,6 +1339,16 @@ impl CommandParser {
             Command::Move(Move::HalfPageBackward),
         );
 
+        // Cycle through tabs
+        mod_commands.insert(
+            KeyEvent::new(KeyCode::Tab, KeyModifiers::NONE),
+            Command::NextTab,
+        );
+        mod_commands.insert(
+            KeyEvent::new(KeyCode::BackTab, KeyModifiers::SHIFT),
+            Command::PreviousTab,
+        );
+
         // Toggle hidden (backspace)
         // mod_commands.insert(
         //     KeyEvent::new(KeyCode::Backspace, KeyModifiers::NONE),
@@ -394,26 +1359,76 @@ impl CommandParser {
             key_commands,
             mod_commands,
             buffer: "".to_string(),
+            pending_count: None,
+            last_count: None,
+            project_keys: Vec::new(),
+            leader: default_leader(),
         }
     }
 
+    /// Returns the typed keys (including the count) of the command that is not complete yet.
     pub fn buffer(&self) -> String {
-        self.buffer.clone()
+        match self.pending_count {
+            Some(count) => format!("{count}{}", self.buffer),
+            None => self.buffer.clone(),
+        }
     }
 
     pub fn clear(&mut self) {
         self.buffer.clear();
+        self.pending_count = None;
+    }
+
+    /// Returns the count that was typed in front of the last command (if any).
+    pub fn count(&self) -> Option<usize> {
+        self.last_count
+    }
+
+    /// Returns the command, and remembers the count that was typed in front of it.
+    fn finish(&mut self, command: Command) -> Command {
+        self.buffer.clear();
+        self.last_count = self.pending_count.take();
+        trace!("Command: {:?} (count: {:?})", command, self.last_count);
+        command
+    }
+
+    /// Adds the digit to the count, if it cannot be the start of a binding.
+    fn push_count(&mut self, c: char) -> bool {
+        let Some(digit) = c.to_digit(10) else {
+            return false;
+        };
+        let mut prefix = [0; 4];
+        if !self.buffer.is_empty()
+            || (self.pending_count.is_none() && digit == 0)
+            || self
+                .key_commands
+                .iter_prefix(c.encode_utf8(&mut prefix).as_bytes())
+                .next()
+                .is_some()
+        {
+            return false;
+        }
+        let count = self.pending_count.unwrap_or(0);
+        self.pending_count = Some(count.saturating_mul(10).saturating_add(digit as usize));
+        true
     }
 
     /// Parse an event and return the command that is assigned to it
     pub fn add_event(&mut self, event: KeyEvent) -> Command {
         if let KeyCode::Backspace = event.code {
-            self.buffer.pop();
+            if self.buffer.pop().is_none() {
+                self.pending_count = self.pending_count.map(|c| c / 10).filter(|c| *c > 0);
+            }
             return Command::None;
         }
         match event.modifiers {
             // First parse for "normal" characters:
             KeyModifiers::NONE | KeyModifiers::SHIFT => {
+                if let KeyCode::Char(c) = event.code {
+                    if self.push_count(c) {
+                        return Command::None;
+                    }
+                }
                 // Put character into buffer
                 if let KeyCode::Char(c) = event.code {
                     if event.modifiers.contains(KeyModifiers::SHIFT) {
@@ -432,15 +1447,13 @@ impl CommandParser {
                     .count()
                     == 0
                 {
-                    self.buffer.clear();
+                    self.clear();
                     return Command::None;
                 }
 
                 // Check if we have a valid command
-                if let Some(command) = self.key_commands.get(self.buffer.as_bytes()) {
-                    self.buffer.clear();
-                    trace!("Command: {:?}", command);
-                    return command.clone();
+                if let Some(command) = self.key_commands.get(self.buffer.as_bytes()).cloned() {
+                    return self.finish(command);
                 }
             }
             _ => {}
@@ -448,10 +1461,8 @@ impl CommandParser {
         // If we have not returned yet,
         // always check if there is a oneshot command assigned to the
         // incoming event.
-        if let Some(command) = self.mod_commands.get(&event) {
-            self.buffer.clear();
-            trace!("Command: {:?}", command);
-            return command.clone();
+        if let Some(command) = self.mod_commands.get(&event).cloned() {
+            return self.finish(command);
         }
         Command::None
     }
@@ -0,0 +1,446 @@
+use std::path::{Path, PathBuf};
+
+use globset::{GlobBuilder, GlobSet, GlobSetBuilder};
+use log::{error, warn};
+use serde::{de::DeserializeOwned, Deserialize};
+
+use crate::hooks::HookEvent;
+
+/// General settings of rfm (`config.toml`).
+#[derive(Debug, Default, Clone, Deserialize)]
+pub struct Config {
+    #[serde(default)]
+    pub copy: CopyConfig,
+
+    #[serde(default)]
+    pub cache: CacheConfig,
+
+    #[serde(default)]
+    pub hidden: HiddenConfig,
+
+    #[serde(default)]
+    pub sort: SortConfig,
+
+    #[serde(default)]
+    pub hooks: HooksConfig,
+
+    #[serde(default)]
+    pub layout: LayoutConfig,
+
+    #[serde(default)]
+    pub footer: FooterConfig,
+
+    #[serde(default)]
+    pub terminal: TerminalConfig,
+
+    #[serde(default)]
+    pub log: LogConfig,
+}
+
+/// Settings for copying files and directories.
+#[derive(Debug, Clone, Copy, Deserialize)]
+pub struct CopyConfig {
+    /// Keep mode bits, timestamps, ownership (if permitted) and extended attributes,
+    /// like `cp -a` does.
+    #[serde(default)]
+    pub preserve: bool,
+
+    /// Use reflinks (copy-on-write clones) on filesystems that support them (e.g. btrfs, XFS).
+    ///
+    /// Falls back to a regular copy, if the filesystem does not support them.
+    #[serde(default = "enabled")]
+    pub reflink: bool,
+
+    /// How often a file is copied again after a transient error (e.g. a hiccup of a network share),
+    /// with a growing pause between the attempts.
+    #[serde(default = "default_retries")]
+    pub retries: u32,
+
+    /// Read every copied file back from the disk and compare its checksum with the source
+    /// (e.g. for flaky USB drives). Without it, only the sizes are compared.
+    #[serde(default)]
+    pub verify: bool,
+
+    /// Program that copies (and moves across filesystems)
+    #[serde(default)]
+    pub backend: CopyBackend,
+
+    /// Number of files that are copied at the same time
+    /// (faster for many small files, and on network filesystems)
+    #[serde(default = "default_parallel")]
+    pub parallel: usize,
+
+    /// Maximum speed of copies in bytes per second - unlimited if not set
+    #[serde(default)]
+    pub max_rate: Option<u64>,
+
+    /// Niceness of the background thread that copies and moves (0 keeps the priority of rfm)
+    #[serde(default)]
+    pub nice: i32,
+
+    /// I/O scheduling class of the background thread that copies and moves
+    #[serde(default)]
+    pub io_priority: IoPriority,
+}
+
+impl Default for CopyConfig {
+    fn default() -> Self {
+        CopyConfig {
+            preserve: false,
+            reflink: true,
+            retries: default_retries(),
+            verify: false,
+            backend: CopyBackend::default(),
+            parallel: default_parallel(),
+            max_rate: None,
+            nice: 0,
+            io_priority: IoPriority::default(),
+        }
+    }
+}
+
+/// How files are copied.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CopyBackend {
+    /// rfm copies the files itself
+    #[default]
+    Builtin,
+    /// rsync copies the files - interrupted transfers are resumed when they are retried
+    Rsync,
+}
+
+/// I/O priority of background file operations (like `ionice`).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum IoPriority {
+    /// Keep the priority of rfm
+    #[default]
+    Normal,
+    /// Lowest level of the best-effort class
+    Low,
+    /// Only use the disk when no other process needs it
+    Idle,
+}
+
+/// Capacity of the panel caches - the least recently used panels are evicted first.
+#[derive(Debug, Clone, Copy, Deserialize)]
+pub struct CacheConfig {
+    /// Maximum number of cached directory listings
+    #[serde(default = "default_directories")]
+    pub directories: usize,
+
+    /// Maximum number of cached previews (files and directories)
+    #[serde(default = "default_previews")]
+    pub previews: usize,
+}
+
+impl Default for CacheConfig {
+    fn default() -> Self {
+        CacheConfig {
+            directories: default_directories(),
+            previews: default_previews(),
+        }
+    }
+}
+
+/// Widths of the Miller columns.
+#[derive(Debug, Clone, Copy, Deserialize)]
+pub struct LayoutConfig {
+    /// Ratios of the left, center and right column - a left ratio of 0 collapses the left column
+    #[serde(default = "default_ratios")]
+    pub ratios: [u16; 3],
+
+    /// Terminals narrower than this (in columns) only show the center panel
+    #[serde(default = "default_narrow_width")]
+    pub narrow_width: u16,
+
+    /// Show the preview column - without it, no previews are generated at all
+    #[serde(default = "enabled")]
+    pub preview: bool,
+
+    /// Wrap long lines of text previews, instead of truncating them
+    #[serde(default)]
+    pub wrap: bool,
+}
+
+impl Default for LayoutConfig {
+    fn default() -> Self {
+        LayoutConfig {
+            ratios: default_ratios(),
+            narrow_width: default_narrow_width(),
+            preview: true,
+            wrap: false,
+        }
+    }
+}
+
+/// Persistent log in `~/.local/state/rfm/rfm.log`.
+#[derive(Debug, Clone, Deserialize)]
+pub struct LogConfig {
+    /// Write the log to the file
+    #[serde(default)]
+    pub file: bool,
+
+    /// Minimum level of the records in the file ("error", "warn", "info" or "debug")
+    #[serde(default = "default_log_level")]
+    pub level: String,
+
+    /// The file is rotated once it is larger than this (in bytes)
+    #[serde(default = "default_log_max_size")]
+    pub max_size: u64,
+
+    /// Number of rotated files that are kept
+    #[serde(default = "default_log_keep")]
+    pub keep: usize,
+}
+
+impl Default for LogConfig {
+    fn default() -> Self {
+        LogConfig {
+            file: false,
+            level: default_log_level(),
+            max_size: default_log_max_size(),
+            keep: default_log_keep(),
+        }
+    }
+}
+
+impl LogConfig {
+    pub fn level(&self) -> log::Level {
+        self.level.parse().unwrap_or_else(|_| {
+            warn!("Unknown log level '{}'. Using 'info'", self.level);
+            log::Level::Info
+        })
+    }
+}
+
+fn default_log_level() -> String {
+    "info".to_string()
+}
+
+fn default_log_max_size() -> u64 {
+    1024 * 1024
+}
+
+fn default_log_keep() -> usize {
+    3
+}
+
+/// Integration with the terminal emulator.
+#[derive(Debug, Clone, Copy, Deserialize)]
+pub struct TerminalConfig {
+    /// Sets the title of the terminal window to `rfm: <current directory>`
+    #[serde(default = "enabled")]
+    pub title: bool,
+}
+
+impl Default for TerminalConfig {
+    fn default() -> Self {
+        TerminalConfig { title: true }
+    }
+}
+
+/// Templates of the left and right side of the footer.
+///
+/// Placeholders: `{permissions}`, `{user}`, `{group}`, `{size}`, `{lines}`, `{words}`, `{modified}`,
+/// `{mime}`, `{image}`, `{link}`, `{free}`, `{marked}`, `{position}` and `{scroll}`.
+#[derive(Debug, Clone, Deserialize)]
+pub struct FooterConfig {
+    #[serde(default = "default_footer_left")]
+    pub left: String,
+
+    #[serde(default = "default_footer_right")]
+    pub right: String,
+}
+
+impl Default for FooterConfig {
+    fn default() -> Self {
+        FooterConfig {
+            left: default_footer_left(),
+            right: default_footer_right(),
+        }
+    }
+}
+
+pub fn default_footer_left() -> String {
+    "{permissions}   {user} {group} {size} {lines} {words} {modified} {mime} {image} {link}"
+        .to_string()
+}
+
+pub fn default_footer_right() -> String {
+    "{marked}  {scroll}  {position} ".to_string()
+}
+
+/// Files that are hidden like dotfiles - unless all files are shown.
+#[derive(Debug, Default, Clone, Deserialize)]
+pub struct HiddenConfig {
+    /// Globs that are matched against the file name (e.g. `*.o` or `__pycache__`)
+    #[serde(default)]
+    pub patterns: Vec<String>,
+}
+
+impl HiddenConfig {
+    /// Builds the set of all valid patterns - invalid ones are skipped with a warning.
+    pub fn globs(&self) -> GlobSet {
+        let mut builder = GlobSetBuilder::new();
+        for pattern in self.patterns.iter() {
+            match GlobBuilder::new(pattern).literal_separator(true).build() {
+                Ok(glob) => {
+                    builder.add(glob);
+                }
+                Err(e) => warn!("hidden: invalid pattern '{pattern}': {e}"),
+            }
+        }
+        builder.build().unwrap_or_else(|e| {
+            error!("hidden: {e}");
+            GlobSet::empty()
+        })
+    }
+}
+
+/// How the items of a directory are sorted (directories always come first).
+#[derive(Debug, Clone, Copy, Deserialize)]
+pub struct SortConfig {
+    /// Compare numbers in names by their value, so `file10` comes after `file2`
+    #[serde(default = "enabled")]
+    pub natural: bool,
+
+    /// Compare names by the collation of the locale (`LC_COLLATE`), so e.g. `ä` sorts next to `a`
+    #[serde(default = "enabled")]
+    pub locale: bool,
+}
+
+impl Default for SortConfig {
+    fn default() -> Self {
+        SortConfig {
+            natural: true,
+            locale: true,
+        }
+    }
+}
+
+/// Shell commands that run on events of rfm.
+///
+/// They receive the context in `RFM_EVENT`, `RFM_CWD`, `RFM_SELECTED`, `RFM_MARKED` and `RFM_FILES`.
+#[derive(Debug, Default, Clone, Deserialize)]
+pub struct HooksConfig {
+    /// Entered a directory
+    pub on_cd: Option<String>,
+    /// Selected another item
+    pub on_select: Option<String>,
+    /// Runs before the files are pasted - rfm waits for it
+    pub before_paste: Option<String>,
+    pub after_paste: Option<String>,
+    /// Runs before rfm exits - rfm waits for it
+    pub on_quit: Option<String>,
+}
+
+impl HooksConfig {
+    /// Shell command of the event.
+    pub fn command(&self, event: HookEvent) -> Option<&str> {
+        match event {
+            HookEvent::Cd => self.on_cd.as_deref(),
+            HookEvent::Select => self.on_select.as_deref(),
+            HookEvent::BeforePaste => self.before_paste.as_deref(),
+            HookEvent::AfterPaste => self.after_paste.as_deref(),
+            HookEvent::Quit => self.on_quit.as_deref(),
+        }
+    }
+}
+
+fn enabled() -> bool {
+    true
+}
+
+fn default_retries() -> u32 {
+    3
+}
+
+fn default_parallel() -> usize {
+    4
+}
+
+pub fn default_ratios() -> [u16; 3] {
+    [1, 3, 4]
+}
+
+fn default_narrow_width() -> u16 {
+    60
+}
+
+fn default_directories() -> usize {
+    16384
+}
+
+fn default_previews() -> usize {
+    4096
+}
+
+/// A config file that could not be parsed - rfm uses the defaults for it instead.
+#[derive(Debug, Clone)]
+pub struct ConfigError {
+    pub path: PathBuf,
+
+    /// Message of the parser, which names the line and the field
+    pub message: String,
+
+    /// Parses the content with the type of the config file
+    check: fn(&str) -> Result<(), String>,
+}
+
+impl ConfigError {
+    pub fn new<T: DeserializeOwned>(path: &Path, error: &toml::de::Error) -> Self {
+        ConfigError {
+            path: path.to_path_buf(),
+            message: error.to_string(),
+            check: check::<T>,
+        }
+    }
+
+    /// Parses the file again (e.g. after it was edited).
+    ///
+    /// Returns `true` if the file is valid now, otherwise the message is updated.
+    pub fn recheck(&mut self) -> bool {
+        let result = std::fs::read_to_string(&self.path)
+            .map_err(|e| e.to_string())
+            .and_then(|content| (self.check)(&content));
+        match result {
+            Ok(()) => true,
+            Err(message) => {
+                self.message = message;
+                false
+            }
+        }
+    }
+}
+
+fn check<T: DeserializeOwned>(content: &str) -> Result<(), String> {
+    toml::from_str::<T>(content)
+        .map(|_| ())
+        .map_err(|e| e.to_string())
+}
+
+#[test]
+fn test_config_error() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("config.toml");
+    std::fs::write(&path, "[copy]\npreserve = 1\n").unwrap();
+    let error = toml::from_str::<Config>(&std::fs::read_to_string(&path).unwrap()).unwrap_err();
+    let mut error = ConfigError::new::<Config>(&path, &error);
+    assert!(error.message.contains("line 2"));
+    assert!(!error.recheck());
+
+    std::fs::write(&path, "[copy]\npreserve = true\n").unwrap();
+    assert!(error.recheck());
+}
+
+#[test]
+fn test_hidden_patterns() {
+    let config: Config =
+        toml::from_str("[hidden]\npatterns = [\"*.o\", \"__pycache__\", \"[\"]\n").unwrap();
+    let globs = config.hidden.globs();
+    assert!(globs.is_match("main.o"));
+    assert!(globs.is_match("__pycache__"));
+    assert!(!globs.is_match("main.rs"));
+}
@@ -1,15 +1,28 @@
 use cached::{Cached, SizedCache};
+use ignore::WalkBuilder;
 use log::{debug, error};
 use parking_lot::Mutex;
-use std::{path::PathBuf, sync::Arc, time::SystemTime};
+use std::{
+    collections::HashSet,
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::SystemTime,
+};
 use tokio::{sync::mpsc, task::spawn_blocking};
 use walkdir::WalkDir;
 
-use crate::panel::{
-    DirElem, DirPanel, FilePreview, PanelContent, PanelState, PanelUpdate, PreviewPanel,
+use crate::{
+    panel::{DirElem, DirPanel, FilePreview, PanelContent, PanelState, PanelUpdate, PreviewPanel},
+    plugin::PluginHost,
 };
 
 /// Cache that is shared by the content-manager and the panel-manager.
+///
+/// The cache holds at most `capacity` panels - when it is full,
+/// the least recently used panel is evicted.
 #[derive(Clone)]
 pub struct PanelCache<Item: Clone> {
     inner: Arc<Mutex<SizedCache<PathBuf, Item>>>,
@@ -19,7 +32,8 @@ impl<Item: PanelContent> PanelCache<Item> {
     /// Creates a new cache with given size
     pub fn with_size(size: usize) -> Self {
         PanelCache {
-            inner: Arc::new(Mutex::new(SizedCache::with_size(size))),
+            // NOTE: A sized cache must hold at least one value
+            inner: Arc::new(Mutex::new(SizedCache::with_size(size.max(1)))),
         }
     }
 
@@ -33,6 +47,21 @@ impl<Item: PanelContent> PanelCache<Item> {
         self.inner.lock().cache_set(path, item)
     }
 
+    /// Removes the cached value of the path
+    pub fn remove(&self, path: &PathBuf) -> Option<Item> {
+        self.inner.lock().cache_remove(path)
+    }
+
+    /// Removes all cached values
+    pub fn clear(&self) {
+        self.inner.lock().cache_clear();
+    }
+
+    /// Returns the number of cached values
+    pub fn len(&self) -> usize {
+        self.inner.lock().cache_size()
+    }
+
     /// Returns the cache capacity
     pub fn capacity(&self) -> usize {
         self.inner.lock().cache_capacity().unwrap_or_default()
@@ -59,6 +88,7 @@ pub struct DirManager {
     rx: mpsc::UnboundedReceiver<PanelUpdate>,
     directory_cache: PanelCache<DirPanel>,
     preview_cache: PanelCache<PreviewPanel>,
+    plugins: Arc<PluginHost>,
 }
 
 /// Receives commands to parse the directory or generate a new preview.
@@ -66,32 +96,113 @@ pub struct PreviewManager {
     tx: mpsc::Sender<(PreviewPanel, PanelState)>,
     rx: mpsc::UnboundedReceiver<PanelUpdate>,
     preview_cache: PanelCache<PreviewPanel>,
+    plugins: Arc<PluginHost>,
+}
+
+/// Preview of the file - plugins may replace it with their own text.
+fn file_preview(path: PathBuf, plugins: &PluginHost) -> FilePreview {
+    match plugins.preview(&path) {
+        Some(lines) => FilePreview::text(path, lines),
+        None => FilePreview::new(path),
+    }
+}
+
+/// Number of entries after which a directory is sent to the panel for the first time,
+/// while it is still being read.
+const STREAM_BATCH: usize = 4096;
+
+/// Set while gitignored files are hidden - only then the gitignore status is worth computing.
+static TRACK_IGNORED: AtomicBool = AtomicBool::new(false);
+
+/// Enables or disables the gitignore status for the directories that are read from now on.
+pub fn track_ignored(enabled: bool) {
+    TRACK_IGNORED.store(enabled, Ordering::Relaxed);
+}
+
+/// Returns `true` if directories are read together with their gitignore status.
+pub fn ignored_tracked() -> bool {
+    TRACK_IGNORED.load(Ordering::Relaxed)
 }
 
 pub fn dir_content(path: PathBuf) -> Vec<DirElem> {
+    dir_content_streaming(path, |_| false)
+}
+
+/// Reads the directory and calls `on_batch` with all entries that were read so far,
+/// whenever their number reaches [`STREAM_BATCH`] (and again each time it doubled).
+///
+/// If `on_batch` returns `false`, the directory is read to the end without further calls.
+/// The gitignore status is only known for the final result (and only while it is tracked).
+pub fn dir_content_streaming(
+    path: PathBuf,
+    mut on_batch: impl FnMut(&[DirElem]) -> bool,
+) -> Vec<DirElem> {
     // read directory
-    match std::fs::read_dir(path) {
-        Ok(dir) => {
-            let mut out = Vec::new();
-            for item in dir.into_iter().flatten() {
-                out.push(DirElem::from(item.path()))
-            }
-            out
+    let dir = match std::fs::read_dir(&path) {
+        Ok(dir) => dir,
+        Err(_) => return Vec::new(),
+    };
+    let mut out = Vec::new();
+    let mut next_batch = STREAM_BATCH;
+    let mut streaming = true;
+    for item in dir.into_iter().flatten() {
+        out.push(DirElem::from(item.path()));
+        if streaming && out.len() == next_batch {
+            streaming = on_batch(&out);
+            next_batch *= 2;
+        }
+    }
+    if ignored_tracked() {
+        mark_ignored(&path, &mut out);
+    }
+    out
+}
+
+/// Sets the gitignore status of the entries of the directory.
+pub fn mark_ignored(path: &Path, elements: &mut [DirElem]) {
+    if let Some(not_ignored) = not_ignored(path) {
+        for elem in elements.iter_mut() {
+            elem.set_ignored(!not_ignored.contains(elem.path()));
         }
-        Err(_) => Vec::new(),
     }
 }
 
+/// Returns all entries of the directory that are *not* ignored by git.
+///
+/// The matching respects the `.gitignore` files of the directory and its parents,
+/// `.git/info/exclude` and the global gitignore.
+/// Returns `None` if the directory is not inside a git repository.
+fn not_ignored(path: &Path) -> Option<HashSet<PathBuf>> {
+    if !path.ancestors().any(|p| p.join(".git").exists()) {
+        return None;
+    }
+    let entries = WalkBuilder::new(path)
+        .max_depth(Some(1))
+        .hidden(false)
+        .ignore(false)
+        .build()
+        .flatten()
+        .filter(|entry| entry.depth() == 1)
+        .map(|entry| entry.into_path())
+        .collect();
+    Some(entries)
+}
+
 // TODO: Benchmark this guy
 async fn fill_cache(
     path: PathBuf,
     directory_cache: PanelCache<DirPanel>,
     preview_cache: PanelCache<PreviewPanel>,
+    plugins: Arc<PluginHost>,
 ) {
     if !path.is_dir() {
         return;
     }
-    let file_capacity = preview_cache.capacity() / 16;
+    // Plugin previews are only generated on request, so the Lua code does not block the other plugins
+    let file_capacity = match plugins.has_previewers() {
+        true => 0,
+        false => preview_cache.capacity() / 16,
+    };
     let dir_capacity = directory_cache.capacity() / 16;
     let mut dir_handles = Vec::new();
     let mut file_handles = Vec::new();
@@ -143,12 +254,14 @@ impl DirManager {
         preview_cache: PanelCache<PreviewPanel>,
         tx: mpsc::Sender<(DirPanel, PanelState)>,
         rx: mpsc::UnboundedReceiver<PanelUpdate>,
+        plugins: Arc<PluginHost>,
     ) -> Self {
         DirManager {
             tx,
             rx,
             directory_cache,
             preview_cache,
+            plugins,
         }
     }
 
@@ -160,15 +273,24 @@ impl DirManager {
             }
             let dir_path = update.state.path().clone();
             debug!("request new dir-panel for {}", dir_path.display());
-            let result = spawn_blocking(move || dir_content(dir_path)).await;
-            if let Ok(content) = result {
+            // Huge directories are sent in batches, so the panel is usable while they are read.
+            // Every batch gets a higher counter, so it replaces the previous one.
+            let tx = self.tx.clone();
+            let mut state = update.state.increased().increased();
+            let result = spawn_blocking(move || {
+                let content = dir_content_streaming(dir_path.clone(), |elements| {
+                    let panel = DirPanel::partial(elements.to_vec(), dir_path.clone());
+                    let sent = tx.blocking_send((panel, state.clone())).is_ok();
+                    state.increase();
+                    sent
+                });
+                (content, state)
+            })
+            .await;
+            if let Ok((content, state)) = result {
                 // Only update when the hash has changed
                 let panel = DirPanel::new(content, update.state.path().clone());
-                if let Err(e) = self
-                    .tx
-                    .send((panel.clone(), update.state.increased().increased()))
-                    .await
-                {
+                if let Err(e) = self.tx.send((panel.clone(), state)).await {
                     error!("Cannot send panel-update: {e}");
                     break;
                 };
@@ -183,6 +305,7 @@ impl DirManager {
                     update.state.path(),
                     self.directory_cache.clone(),
                     self.preview_cache.clone(),
+                    self.plugins.clone(),
                 ));
             }
         }
@@ -194,11 +317,13 @@ impl PreviewManager {
         preview_cache: PanelCache<PreviewPanel>,
         tx: mpsc::Sender<(PreviewPanel, PanelState)>,
         rx: mpsc::UnboundedReceiver<PanelUpdate>,
+        plugins: Arc<PluginHost>,
     ) -> Self {
         PreviewManager {
             tx,
             rx,
             preview_cache,
+            plugins,
         }
     }
 
@@ -223,7 +348,8 @@ impl PreviewManager {
             } else {
                 // Create preview
                 let file_path = update.state.path().clone();
-                let result = spawn_blocking(move || FilePreview::new(file_path)).await;
+                let plugins = self.plugins.clone();
+                let result = spawn_blocking(move || file_preview(file_path, &plugins)).await;
                 if let Ok(preview) = result {
                     let panel = PreviewPanel::File(preview);
                     if let Err(e) = self
@@ -0,0 +1,495 @@
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+    process::Command,
+    sync::mpsc,
+    time::{Duration, Instant},
+};
+
+use zbus::{
+    blocking::Connection,
+    zvariant::{OwnedObjectPath, OwnedValue, Value},
+};
+
+use crate::util::{exec_allowed, fs_space};
+
+const UDISKS: &str = "org.freedesktop.UDisks2";
+const BLOCK: &str = "org.freedesktop.UDisks2.Block";
+const FILESYSTEM: &str = "org.freedesktop.UDisks2.Filesystem";
+const DRIVE: &str = "org.freedesktop.UDisks2.Drive";
+
+/// Filesystems that do not report their size within this time (e.g. dead NFS or sshfs mounts)
+/// are listed without it.
+const MOUNT_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Objects of udisks2 with the properties of their interfaces.
+type Objects = HashMap<OwnedObjectPath, HashMap<String, HashMap<String, OwnedValue>>>;
+
+/// Where a device comes from - this decides how it is mounted.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Backend {
+    /// Block device of udisks2 - with its D-Bus object and the D-Bus object of its drive
+    Udisks {
+        object: String,
+        drive: Option<String>,
+    },
+    /// Phone or camera (MTP), which is mounted by gvfs
+    Mtp { uri: String },
+}
+
+/// Block device (or partition) with a filesystem, as reported by udisks2,
+/// or an MTP device (like an Android phone), as reported by gvfs.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Device {
+    backend: Backend,
+    /// Like `/dev/sdb1` - or the URI of an MTP device (`mtp://<name>/`)
+    pub device: PathBuf,
+    pub label: String,
+    /// Unknown (0) for MTP devices
+    pub size: u64,
+    /// Empty, if the filesystem is not mounted
+    pub mount_points: Vec<PathBuf>,
+    pub ejectable: bool,
+    /// Vendor and model of the drive
+    pub model: String,
+}
+
+/// Mounted filesystem, as listed in `/proc/self/mounts`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Mount {
+    pub device: String,
+    pub mount_point: PathBuf,
+    pub fs_type: String,
+    /// Size and free space for unprivileged users - `None` if the filesystem did not respond
+    pub space: Option<(u64, u64)>,
+}
+
+/// Lists the mounted filesystems - like `df`, pseudo filesystems without a size (proc, sysfs, ...) are skipped.
+///
+/// Every filesystem is queried on its own thread, so a hanging one only loses its size.
+/// This blocks for up to [`MOUNT_TIMEOUT`] - call it in the background.
+pub fn list_mounts() -> Vec<Mount> {
+    let content = std::fs::read_to_string("/proc/self/mounts").unwrap_or_default();
+    let mut mounts = parse_mounts(&content);
+    let (tx, rx) = mpsc::channel();
+    for (idx, mount) in mounts.iter().enumerate() {
+        let (tx, path) = (tx.clone(), mount.mount_point.clone());
+        std::thread::spawn(move || {
+            let _ = tx.send((idx, fs_space(&path)));
+        });
+    }
+    drop(tx);
+    let deadline = Instant::now() + MOUNT_TIMEOUT;
+    let mut unknown = vec![true; mounts.len()];
+    while let Ok((idx, space)) = rx.recv_timeout(deadline.saturating_duration_since(Instant::now()))
+    {
+        mounts[idx].space = space;
+        unknown[idx] = false;
+    }
+    mounts
+        .into_iter()
+        .zip(unknown)
+        .filter(|(mount, unknown)| *unknown || mount.space.is_some_and(|(size, _)| size > 0))
+        .map(|(mount, _)| mount)
+        .collect()
+}
+
+/// Parses the lines of `/proc/self/mounts` (`device mount_point fs_type options 0 0`) - without sizes.
+fn parse_mounts(content: &str) -> Vec<Mount> {
+    content
+        .lines()
+        .filter_map(|line| {
+            let mut fields = line.split_whitespace();
+            Some(Mount {
+                device: unescape_mount(fields.next()?),
+                mount_point: PathBuf::from(unescape_mount(fields.next()?)),
+                fs_type: fields.next()?.to_string(),
+                space: None,
+            })
+        })
+        .collect()
+}
+
+/// Spaces, tabs, newlines and backslashes are escaped as octal numbers (like `\040`).
+fn unescape_mount(field: &str) -> String {
+    let mut out = String::new();
+    let mut rest = field;
+    while let Some(idx) = rest.find('\\') {
+        out.push_str(&rest[..idx]);
+        let code = rest.get(idx + 1..idx + 4);
+        match code.and_then(|code| u8::from_str_radix(code, 8).ok()) {
+            Some(byte) => {
+                out.push(byte as char);
+                rest = &rest[idx + 4..];
+            }
+            None => {
+                out.push('\\');
+                rest = &rest[idx + 1..];
+            }
+        }
+    }
+    out.push_str(rest);
+    out
+}
+
+/// Messages of device operations that run in the background.
+#[derive(Debug)]
+pub enum DeviceUpdate {
+    /// The device was mounted at the path
+    Mounted(PathBuf),
+    Unmounted(PathBuf),
+    Ejected(PathBuf),
+    Failed(String),
+    /// Result of [`list_devices`]
+    Listed(Result<Vec<Device>, String>),
+}
+
+/// Operations on a [`Device`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DeviceAction {
+    Mount,
+    Unmount,
+    Eject,
+}
+
+impl DeviceAction {
+    /// Runs the operation - this blocks until udisks2 (or gvfs) is done.
+    pub fn run(self, device: &Device) -> DeviceUpdate {
+        let name = device.device.clone();
+        let result = match self {
+            DeviceAction::Mount => mount(device).map(DeviceUpdate::Mounted),
+            DeviceAction::Unmount => unmount(device).map(|_| DeviceUpdate::Unmounted(name)),
+            DeviceAction::Eject => eject(device).map(|_| DeviceUpdate::Ejected(name)),
+        };
+        result.unwrap_or_else(DeviceUpdate::Failed)
+    }
+}
+
+/// Lists all filesystems that udisks2 knows (except the ones it hides), sorted by their device,
+/// followed by the MTP devices.
+pub fn list_devices() -> Result<Vec<Device>, String> {
+    let mut devices = udisks_devices()?;
+    devices.extend(mtp_devices());
+    Ok(devices)
+}
+
+fn udisks_devices() -> Result<Vec<Device>, String> {
+    let reply = system_bus()?
+        .call_method(
+            Some(UDISKS),
+            "/org/freedesktop/UDisks2",
+            Some("org.freedesktop.DBus.ObjectManager"),
+            "GetManagedObjects",
+            &(),
+        )
+        .map_err(|e| e.to_string())?;
+    let objects: Objects = reply.body().map_err(|e| e.to_string())?;
+    Ok(parse_devices(&objects))
+}
+
+/// Mounts the filesystem (at the place udisks2 chooses, like `/run/media/<user>/<label>`).
+///
+/// MTP devices are mounted below `$XDG_RUNTIME_DIR/gvfs`.
+pub fn mount(device: &Device) -> Result<PathBuf, String> {
+    match &device.backend {
+        Backend::Udisks { object, .. } => {
+            let reply = call(object, FILESYSTEM, "Mount")?;
+            let path: String = reply.body().map_err(|e| e.to_string())?;
+            Ok(PathBuf::from(path))
+        }
+        Backend::Mtp { uri } => {
+            gio_mount(&[uri])?;
+            gvfs_path(uri).ok_or_else(|| "XDG_RUNTIME_DIR is not set".to_string())
+        }
+    }
+}
+
+pub fn unmount(device: &Device) -> Result<(), String> {
+    match &device.backend {
+        Backend::Udisks { object, .. } => call(object, FILESYSTEM, "Unmount").map(|_| ()),
+        Backend::Mtp { uri } => gio_mount(&["-u", uri]),
+    }
+}
+
+/// Unmounts the filesystem (if necessary) and ejects its drive.
+pub fn eject(device: &Device) -> Result<(), String> {
+    let drive = match &device.backend {
+        Backend::Udisks { drive, .. } => drive.as_deref().filter(|_| device.ejectable),
+        Backend::Mtp { uri } => return gio_mount(&["-e", uri]),
+    };
+    let Some(drive) = drive else {
+        return Err(format!("{} cannot be ejected", device.device.display()));
+    };
+    if !device.mount_points.is_empty() {
+        unmount(device)?;
+    }
+    call(drive, DRIVE, "Eject").map(|_| ())
+}
+
+/// Lists the MTP devices of gvfs (with `gio mount -li`) - empty, if gio is not installed.
+fn mtp_devices() -> Vec<Device> {
+    if !exec_allowed() {
+        return Vec::new();
+    }
+    Command::new("gio")
+        .args(["mount", "-li"])
+        .output()
+        .ok()
+        .filter(|out| out.status.success())
+        .map(|out| parse_gio_mounts(&String::from_utf8_lossy(&out.stdout)))
+        .unwrap_or_default()
+}
+
+fn gio_mount(args: &[&str]) -> Result<(), String> {
+    if !exec_allowed() {
+        return Err("external programs are disabled (--no-exec)".to_string());
+    }
+    let out = Command::new("gio")
+        .arg("mount")
+        .args(args)
+        .output()
+        .map_err(|e| format!("cannot run gio: {e}"))?;
+    if out.status.success() {
+        Ok(())
+    } else {
+        Err(String::from_utf8_lossy(&out.stderr).trim().to_string())
+    }
+}
+
+/// Directory where gvfs mounts the URI (like `$XDG_RUNTIME_DIR/gvfs/mtp:host=Pixel_7_123`).
+fn gvfs_path(uri: &str) -> Option<PathBuf> {
+    let host = uri.strip_prefix("mtp://")?.trim_end_matches('/');
+    let runtime_dir = std::env::var_os("XDG_RUNTIME_DIR").filter(|dir| !dir.is_empty())?;
+    Some(
+        Path::new(&runtime_dir)
+            .join("gvfs")
+            .join(format!("mtp:host={host}")),
+    )
+}
+
+/// Finds the MTP volumes (`activation_root=mtp://...`) in the output of `gio mount -li`,
+/// and whether they are mounted (`Mount(0): <name> -> mtp://...`).
+fn parse_gio_mounts(output: &str) -> Vec<Device> {
+    let mut devices: Vec<Device> = Vec::new();
+    let mut volume = String::new();
+    for line in output.lines().map(str::trim) {
+        if line.starts_with("Volume(") {
+            volume = line
+                .split_once(": ")
+                .map(|(_, name)| name.to_string())
+                .unwrap_or_default();
+        } else if let Some(uri) = line.strip_prefix("activation_root=") {
+            if uri.starts_with("mtp://") && !devices.iter().any(|d| d.device == Path::new(uri)) {
+                devices.push(Device {
+                    backend: Backend::Mtp {
+                        uri: uri.to_string(),
+                    },
+                    device: PathBuf::from(uri),
+                    label: volume.clone(),
+                    size: 0,
+                    mount_points: Vec::new(),
+                    ejectable: true,
+                    model: "MTP".to_string(),
+                });
+            }
+        } else if line.starts_with("Mount(") {
+            let Some((_, uri)) = line.split_once(" -> ") else {
+                continue;
+            };
+            if let Some(device) = devices.iter_mut().find(|d| d.device == Path::new(uri)) {
+                device.mount_points = gvfs_path(uri).into_iter().collect();
+            }
+        }
+    }
+    devices
+}
+
+fn system_bus() -> Result<Connection, String> {
+    Connection::system().map_err(|e| format!("cannot connect to the system bus: {e}"))
+}
+
+/// Calls the method (without options) on the object.
+fn call(
+    object: &str,
+    interface: &str,
+    method: &str,
+) -> Result<std::sync::Arc<zbus::Message>, String> {
+    let options: HashMap<&str, Value> = HashMap::new();
+    system_bus()?
+        .call_method(Some(UDISKS), object, Some(interface), method, &(options,))
+        .map_err(|e| match e {
+            zbus::Error::MethodError(_, Some(message), _) => message,
+            e => e.to_string(),
+        })
+}
+
+fn parse_devices(objects: &Objects) -> Vec<Device> {
+    let property =
+        |interface: &HashMap<String, OwnedValue>, name: &str| interface.get(name).cloned();
+    let mut devices: Vec<Device> = objects
+        .iter()
+        .filter_map(|(object, interfaces)| {
+            let block = interfaces.get(BLOCK)?;
+            let filesystem = interfaces.get(FILESYSTEM)?;
+            if property(block, "HintIgnore").and_then(|v| bool::try_from(v).ok()) == Some(true) {
+                return None;
+            }
+            let drive = property(block, "Drive")
+                .and_then(|v| OwnedObjectPath::try_from(v).ok())
+                .map(|path| path.as_str().to_string())
+                .filter(|path| path != "/");
+            let drive_properties = drive
+                .as_ref()
+                .and_then(|drive| objects.iter().find(|(o, _)| o.as_str() == drive))
+                .and_then(|(_, interfaces)| interfaces.get(DRIVE));
+            let string = |interface: Option<&HashMap<String, OwnedValue>>, name: &str| {
+                interface
+                    .and_then(|i| property(i, name))
+                    .and_then(|v| String::try_from(v).ok())
+                    .unwrap_or_default()
+            };
+            let model = format!(
+                "{} {}",
+                string(drive_properties, "Vendor"),
+                string(drive_properties, "Model")
+            );
+            Some(Device {
+                backend: Backend::Udisks {
+                    object: object.as_str().to_string(),
+                    drive,
+                },
+                device: property(block, "Device")
+                    .and_then(|v| Vec::<u8>::try_from(v).ok())
+                    .map(|bytes| bytes_to_path(&bytes))?,
+                label: string(Some(block), "IdLabel"),
+                size: property(block, "Size")
+                    .and_then(|v| u64::try_from(v).ok())
+                    .unwrap_or_default(),
+                mount_points: property(filesystem, "MountPoints")
+                    .and_then(|v| Vec::<Vec<u8>>::try_from(v).ok())
+                    .unwrap_or_default()
+                    .iter()
+                    .map(|bytes| bytes_to_path(bytes))
+                    .collect(),
+                ejectable: drive_properties
+                    .and_then(|i| property(i, "Ejectable"))
+                    .and_then(|v| bool::try_from(v).ok())
+                    .unwrap_or(false),
+                model: model.trim().to_string(),
+            })
+        })
+        .collect();
+    devices.sort_by(|a, b| a.device.cmp(&b.device));
+    devices
+}
+
+/// udisks2 reports paths as null-terminated byte strings.
+fn bytes_to_path(bytes: &[u8]) -> PathBuf {
+    use std::os::unix::ffi::OsStrExt;
+    let bytes = bytes.strip_suffix(&[0]).unwrap_or(bytes);
+    Path::new(std::ffi::OsStr::from_bytes(bytes)).to_path_buf()
+}
+
+#[test]
+fn test_parse_mounts() {
+    let mounts = parse_mounts(
+        "/dev/sda2 / ext4 rw,relatime 0 0\n\
+         proc /proc proc rw,nosuid 0 0\n\
+         /dev/sdb1 /run/media/me/MY\\040STICK vfat rw 0 0\n",
+    );
+    assert_eq!(mounts.len(), 3);
+    assert_eq!(mounts[0].device, "/dev/sda2");
+    assert_eq!(mounts[0].fs_type, "ext4");
+    assert_eq!(mounts[0].space, None);
+    assert_eq!(
+        mounts[2].mount_point,
+        PathBuf::from("/run/media/me/MY STICK")
+    );
+    assert_eq!(unescape_mount("a\\b"), "a\\b");
+}
+
+#[test]
+fn test_parse_devices() {
+    let value = |value: Value| OwnedValue::from(value);
+    let interface = |properties: Vec<(&str, OwnedValue)>| -> HashMap<String, OwnedValue> {
+        properties
+            .into_iter()
+            .map(|(name, value)| (name.to_string(), value))
+            .collect()
+    };
+    let path = |path: &str| OwnedObjectPath::try_from(path).unwrap();
+    let drive = "/org/freedesktop/UDisks2/drives/Stick";
+    let mut objects = Objects::new();
+    objects.insert(
+        path("/org/freedesktop/UDisks2/block_devices/sdb1"),
+        HashMap::from([
+            (
+                BLOCK.to_string(),
+                interface(vec![
+                    ("Device", value(Value::from(b"/dev/sdb1\0".to_vec()))),
+                    ("IdLabel", value(Value::from("STICK"))),
+                    ("Size", value(Value::from(1024u64))),
+                    ("Drive", value(Value::from(path(drive).into_inner()))),
+                ]),
+            ),
+            (
+                FILESYSTEM.to_string(),
+                interface(vec![(
+                    "MountPoints",
+                    value(Value::from(vec![b"/media/stick\0".to_vec()])),
+                )]),
+            ),
+        ]),
+    );
+    objects.insert(
+        path(drive),
+        HashMap::from([(
+            DRIVE.to_string(),
+            interface(vec![
+                ("Ejectable", value(Value::from(true))),
+                ("Vendor", value(Value::from("ACME"))),
+                ("Model", value(Value::from("Stick"))),
+            ]),
+        )]),
+    );
+    // Without a filesystem, there is nothing to mount
+    objects.insert(
+        path("/org/freedesktop/UDisks2/block_devices/sdb"),
+        HashMap::from([(
+            BLOCK.to_string(),
+            interface(vec![("Device", value(Value::from(b"/dev/sdb\0".to_vec())))]),
+        )]),
+    );
+
+    let devices = parse_devices(&objects);
+    assert_eq!(devices.len(), 1);
+    assert_eq!(devices[0].device, PathBuf::from("/dev/sdb1"));
+    assert_eq!(devices[0].label, "STICK");
+    assert_eq!(devices[0].size, 1024);
+    assert_eq!(devices[0].mount_points, vec![PathBuf::from("/media/stick")]);
+    assert!(devices[0].ejectable);
+    assert_eq!(devices[0].model, "ACME Stick");
+
+    let output = "\
+Drive(0): SAMSUNG SSD
+  Type: GProxyDrive (GProxyVolumeMonitorUDisks2)
+Volume(0): Pixel 7
+  Type: GProxyVolume (GProxyVolumeMonitorMTP)
+  activation_root=mtp://Google_Pixel_7_2A11/
+Mount(0): Pixel 7 -> mtp://Google_Pixel_7_2A11/
+  Type: GProxyMount (GProxyVolumeMonitorMTP)
+";
+    let devices = parse_gio_mounts(output);
+    assert_eq!(devices.len(), 1);
+    assert_eq!(devices[0].label, "Pixel 7");
+    assert_eq!(
+        devices[0].device,
+        PathBuf::from("mtp://Google_Pixel_7_2A11/")
+    );
+    assert_eq!(
+        devices[0].mount_points,
+        gvfs_path("mtp://Google_Pixel_7_2A11/")
+            .into_iter()
+            .collect::<Vec<_>>()
+    );
+}
@@ -0,0 +1,150 @@
+use std::{
+    fs::Metadata,
+    os::unix::fs::MetadataExt,
+    path::Path,
+    sync::atomic::{AtomicBool, Ordering},
+    time::{Duration, Instant},
+};
+
+/// Minimum time between two progress reports of [`scan`].
+const PROGRESS_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Disk usage of a file or of a whole directory tree.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DuEntry {
+    pub name: String,
+    /// Allocated size in bytes (like `du`, not the apparent size)
+    pub size: u64,
+    pub is_dir: bool,
+    /// Content of a directory - sorted by size, largest first
+    pub children: Vec<DuEntry>,
+}
+
+/// Messages of a running scan.
+#[derive(Debug)]
+pub enum DuUpdate {
+    /// Number of items and total size scanned so far
+    Progress {
+        items: u64,
+        size: u64,
+    },
+    Done(DuEntry),
+}
+
+struct ScanState<'a> {
+    device: u64,
+    items: u64,
+    size: u64,
+    last_report: Instant,
+    cancel: &'a AtomicBool,
+}
+
+/// Computes the disk usage of everything under the path.
+///
+/// Symlinks are not followed and other filesystems (e.g. mounts below the path) are skipped.
+/// `progress` is called periodically with the number of items and the size scanned so far.
+/// Returns `None` if the path cannot be read or the scan was cancelled.
+pub fn scan(
+    path: &Path,
+    cancel: &AtomicBool,
+    mut progress: impl FnMut(u64, u64),
+) -> Option<DuEntry> {
+    let metadata = std::fs::symlink_metadata(path).ok()?;
+    let mut state = ScanState {
+        device: metadata.dev(),
+        items: 0,
+        size: 0,
+        last_report: Instant::now(),
+        cancel,
+    };
+    let name = path.to_string_lossy().to_string();
+    scan_entry(path, name, &metadata, &mut state, &mut progress)
+}
+
+fn scan_entry(
+    path: &Path,
+    name: String,
+    metadata: &Metadata,
+    state: &mut ScanState,
+    progress: &mut impl FnMut(u64, u64),
+) -> Option<DuEntry> {
+    if state.cancel.load(Ordering::Relaxed) {
+        return None;
+    }
+    let mut entry = DuEntry {
+        name,
+        size: metadata.blocks() * 512,
+        is_dir: metadata.is_dir(),
+        children: Vec::new(),
+    };
+    state.items += 1;
+    state.size += entry.size;
+    if state.last_report.elapsed() >= PROGRESS_INTERVAL {
+        progress(state.items, state.size);
+        state.last_report = Instant::now();
+    }
+    if entry.is_dir && metadata.dev() == state.device {
+        // Unreadable directories only count with their own size
+        for child in std::fs::read_dir(path).into_iter().flatten().flatten() {
+            let Ok(child_metadata) = child.metadata() else {
+                continue;
+            };
+            let child_name = child.file_name().to_string_lossy().to_string();
+            let child = scan_entry(&child.path(), child_name, &child_metadata, state, progress)?;
+            entry.size += child.size;
+            entry.children.push(child);
+        }
+        entry
+            .children
+            .sort_by(|a, b| b.size.cmp(&a.size).then(a.name.cmp(&b.name)));
+    }
+    Some(entry)
+}
+
+impl DuEntry {
+    /// Returns the entry at the given child indices.
+    pub fn get(&self, indices: &[usize]) -> Option<&DuEntry> {
+        indices
+            .iter()
+            .try_fold(self, |entry, idx| entry.children.get(*idx))
+    }
+
+    /// Removes the entry at the given child indices and subtracts its size from all parents.
+    pub fn remove(&mut self, indices: &[usize]) -> Option<DuEntry> {
+        let (last, parents) = indices.split_last()?;
+        // Checks the indices, before any size is touched
+        let size = self.get(indices)?.size;
+        let mut entry = self;
+        for idx in parents {
+            entry.size -= size;
+            entry = &mut entry.children[*idx];
+        }
+        entry.size -= size;
+        Some(entry.children.remove(*last))
+    }
+}
+
+#[test]
+fn test_scan() {
+    let dir = tempfile::tempdir().unwrap();
+    std::fs::create_dir(dir.path().join("big")).unwrap();
+    std::fs::write(dir.path().join("big/data"), vec![1u8; 64 * 1024]).unwrap();
+    std::fs::write(dir.path().join("small"), "x").unwrap();
+
+    let mut tree = scan(dir.path(), &AtomicBool::new(false), |_, _| ()).unwrap();
+    let names: Vec<_> = tree.children.iter().map(|c| c.name.as_str()).collect();
+    assert_eq!(names, vec!["big", "small"]);
+    let sum: u64 = tree.children.iter().map(|c| c.size).sum();
+    let own_size = std::fs::metadata(dir.path()).unwrap().blocks() * 512;
+    assert_eq!(tree.size, own_size + sum);
+    assert!(tree.get(&[0, 0]).unwrap().size >= 64 * 1024);
+
+    let total = tree.size;
+    let removed = tree.remove(&[0, 0]).unwrap();
+    assert_eq!(removed.name, "data");
+    assert_eq!(tree.size, total - removed.size);
+    assert_eq!(tree.get(&[0]).unwrap().children.len(), 0);
+    assert_eq!(tree.remove(&[5]), None);
+
+    assert_eq!(scan(dir.path(), &AtomicBool::new(true), |_, _| ()), None);
+}
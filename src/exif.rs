@@ -0,0 +1,272 @@
+use std::{
+    fs::File,
+    io::{BufReader, Read},
+    path::Path,
+};
+
+/// Largest EXIF block that is read (a JPEG segment cannot be larger anyway).
+const MAX_EXIF_SIZE: usize = 64 * 1024;
+
+const TAG_MAKE: u16 = 0x010f;
+const TAG_MODEL: u16 = 0x0110;
+const TAG_DATE_TIME: u16 = 0x0132;
+const TAG_EXIF_IFD: u16 = 0x8769;
+const TAG_DATE_TIME_ORIGINAL: u16 = 0x9003;
+
+/// Dimensions of an image, and the camera and capture date from its EXIF data (JPEG and TIFF).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ImageInfo {
+    pub width: u32,
+    pub height: u32,
+    /// Make and model of the camera
+    pub camera: Option<String>,
+    /// Capture date (`YYYY-MM-DD hh:mm:ss`)
+    pub taken: Option<String>,
+}
+
+impl ImageInfo {
+    /// Returns `None` if the file is no image - only the headers are read, not the pixels.
+    pub fn read(path: &Path) -> Option<Self> {
+        let (width, height) = image::image_dimensions(path).ok()?;
+        let mut info = ImageInfo {
+            width,
+            height,
+            camera: None,
+            taken: None,
+        };
+        if let Some(tiff) = read_exif(path) {
+            let exif = Exif::parse(&tiff);
+            info.camera = exif.camera();
+            info.taken = exif.taken();
+        }
+        Some(info)
+    }
+
+    /// One line like `4000x3000  Canon EOS 80D  2021-06-01 12:30:00`.
+    pub fn summary(&self) -> String {
+        let mut parts = vec![format!("{}x{}", self.width, self.height)];
+        parts.extend(self.camera.clone());
+        parts.extend(self.taken.clone());
+        parts.join("  ")
+    }
+}
+
+/// Returns the TIFF structure that contains the EXIF data.
+fn read_exif(path: &Path) -> Option<Vec<u8>> {
+    let mut file = BufReader::new(File::open(path).ok()?);
+    let mut magic = [0; 4];
+    file.read_exact(&mut magic).ok()?;
+    match magic {
+        // TIFF files are one big EXIF structure
+        [b'I', b'I', 42, 0] | [b'M', b'M', 0, 42] => {
+            let mut tiff = magic.to_vec();
+            file.take(MAX_EXIF_SIZE as u64)
+                .read_to_end(&mut tiff)
+                .ok()?;
+            Some(tiff)
+        }
+        [0xff, 0xd8, 0xff, marker] => jpeg_exif(&mut file, marker),
+        _ => None,
+    }
+}
+
+/// Searches the segments of a JPEG for the APP1 segment with the EXIF data.
+fn jpeg_exif(file: &mut impl Read, mut marker: u8) -> Option<Vec<u8>> {
+    loop {
+        let mut length = [0; 2];
+        file.read_exact(&mut length).ok()?;
+        let length = (u16::from_be_bytes(length) as usize).checked_sub(2)?;
+        let mut segment = vec![0; length];
+        file.read_exact(&mut segment).ok()?;
+        if marker == 0xe1 && segment.starts_with(b"Exif\0\0") {
+            segment.drain(..6);
+            return Some(segment);
+        }
+        // The image data starts - there is no EXIF
+        if marker == 0xda {
+            return None;
+        }
+        let mut next = [0; 2];
+        file.read_exact(&mut next).ok()?;
+        if next[0] != 0xff {
+            return None;
+        }
+        marker = next[1];
+    }
+}
+
+/// The tags of IFD0 and the EXIF IFD, that we are interested in.
+#[derive(Debug, Default)]
+struct Exif {
+    make: Option<String>,
+    model: Option<String>,
+    date_time: Option<String>,
+    date_time_original: Option<String>,
+}
+
+impl Exif {
+    fn parse(tiff: &[u8]) -> Self {
+        let mut exif = Exif::default();
+        let Some(reader) = TiffReader::new(tiff) else {
+            return exif;
+        };
+        let Some(ifd0) = reader.u32(4) else {
+            return exif;
+        };
+        for (tag, value_type, count, offset) in reader.entries(ifd0 as usize) {
+            match tag {
+                TAG_MAKE => exif.make = reader.ascii(value_type, count, offset),
+                TAG_MODEL => exif.model = reader.ascii(value_type, count, offset),
+                TAG_DATE_TIME => exif.date_time = reader.ascii(value_type, count, offset),
+                TAG_EXIF_IFD => {
+                    let Some(exif_ifd) = reader.u32(offset) else {
+                        continue;
+                    };
+                    for (tag, value_type, count, offset) in reader.entries(exif_ifd as usize) {
+                        if tag == TAG_DATE_TIME_ORIGINAL {
+                            exif.date_time_original = reader.ascii(value_type, count, offset);
+                        }
+                    }
+                }
+                _ => (),
+            }
+        }
+        exif
+    }
+
+    fn camera(&self) -> Option<String> {
+        match (&self.make, &self.model) {
+            // Most models already contain the make (e.g. "Canon EOS 80D")
+            (Some(make), Some(model)) if !model.starts_with(make.as_str()) => {
+                Some(format!("{make} {model}"))
+            }
+            (_, Some(model)) => Some(model.clone()),
+            (make, None) => make.clone(),
+        }
+    }
+
+    fn taken(&self) -> Option<String> {
+        let date = self
+            .date_time_original
+            .as_ref()
+            .or(self.date_time.as_ref())?;
+        // EXIF dates look like "2021:06:01 12:30:00"
+        Some(match date.split_once(' ') {
+            Some((day, time)) => format!("{} {time}", day.replace(':', "-")),
+            None => date.clone(),
+        })
+    }
+}
+
+struct TiffReader<'a> {
+    data: &'a [u8],
+    little_endian: bool,
+}
+
+impl<'a> TiffReader<'a> {
+    fn new(data: &'a [u8]) -> Option<Self> {
+        let little_endian = match data.get(..2)? {
+            b"II" => true,
+            b"MM" => false,
+            _ => return None,
+        };
+        Some(TiffReader {
+            data,
+            little_endian,
+        })
+    }
+
+    fn u16(&self, offset: usize) -> Option<u16> {
+        let bytes = self.data.get(offset..offset + 2)?.try_into().ok()?;
+        Some(if self.little_endian {
+            u16::from_le_bytes(bytes)
+        } else {
+            u16::from_be_bytes(bytes)
+        })
+    }
+
+    fn u32(&self, offset: usize) -> Option<u32> {
+        let bytes = self.data.get(offset..offset + 4)?.try_into().ok()?;
+        Some(if self.little_endian {
+            u32::from_le_bytes(bytes)
+        } else {
+            u32::from_be_bytes(bytes)
+        })
+    }
+
+    /// Tag, type, count and the offset of the value (or of the field that holds it inline)
+    /// of every entry of the IFD.
+    fn entries(&self, ifd: usize) -> Vec<(u16, u16, u32, usize)> {
+        let count = self.u16(ifd).unwrap_or(0) as usize;
+        (0..count)
+            .filter_map(|idx| {
+                let entry = ifd + 2 + idx * 12;
+                Some((
+                    self.u16(entry)?,
+                    self.u16(entry + 2)?,
+                    self.u32(entry + 4)?,
+                    entry + 8,
+                ))
+            })
+            .collect()
+    }
+
+    /// Reads an ASCII value - values of up to four bytes are stored inside the entry itself.
+    fn ascii(&self, value_type: u16, count: u32, field: usize) -> Option<String> {
+        const ASCII: u16 = 2;
+        if value_type != ASCII {
+            return None;
+        }
+        let count = count as usize;
+        let start = if count <= 4 {
+            field
+        } else {
+            self.u32(field)? as usize
+        };
+        let bytes = self.data.get(start..start.checked_add(count)?)?;
+        let text = String::from_utf8_lossy(bytes);
+        let text = text.trim_end_matches('\0').trim();
+        (!text.is_empty()).then(|| text.to_string())
+    }
+}
+
+#[test]
+fn test_exif() {
+    // Big-endian TIFF with Make, Model and an EXIF IFD with DateTimeOriginal
+    let mut tiff = b"MM\0\x2a\0\0\0\x08".to_vec();
+    let entry = |tag: u16, value_type: u16, count: u32, value: u32| {
+        let mut bytes = tag.to_be_bytes().to_vec();
+        bytes.extend(value_type.to_be_bytes());
+        bytes.extend(count.to_be_bytes());
+        bytes.extend(value.to_be_bytes());
+        bytes
+    };
+    // IFD0 at 8 with 3 entries ends at 8 + 2 + 36 + 4 = 50
+    tiff.extend(3u16.to_be_bytes());
+    tiff.extend(entry(TAG_MAKE, 2, 6, 50));
+    tiff.extend(entry(TAG_MODEL, 2, 13, 56));
+    tiff.extend(entry(TAG_EXIF_IFD, 4, 1, 69));
+    tiff.extend(0u32.to_be_bytes());
+    tiff.extend(b"Canon\0");
+    tiff.extend(b"Canon EOS 5D\0");
+    // EXIF IFD at 69 with 1 entry ends at 69 + 2 + 12 + 4 = 87
+    tiff.extend(1u16.to_be_bytes());
+    tiff.extend(entry(TAG_DATE_TIME_ORIGINAL, 2, 20, 87));
+    tiff.extend(0u32.to_be_bytes());
+    tiff.extend(b"2021:06:01 12:30:00\0");
+
+    let exif = Exif::parse(&tiff);
+    assert_eq!(exif.camera().as_deref(), Some("Canon EOS 5D"));
+    assert_eq!(exif.taken().as_deref(), Some("2021-06-01 12:30:00"));
+
+    // The same block inside a JPEG
+    let dir = tempfile::tempdir().unwrap();
+    let jpeg = dir.path().join("photo.jpg");
+    let mut bytes = vec![0xff, 0xd8, 0xff, 0xe1];
+    bytes.extend((tiff.len() as u16 + 8).to_be_bytes());
+    bytes.extend(b"Exif\0\0");
+    bytes.extend(&tiff);
+    bytes.extend([0xff, 0xda, 0, 2]);
+    std::fs::write(&jpeg, bytes).unwrap();
+    assert_eq!(read_exif(&jpeg), Some(tiff));
+}
@@ -0,0 +1,231 @@
+use std::{
+    path::{Path, PathBuf},
+    process::{Command, Stdio},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use log::warn;
+
+use crate::util::{exec_allowed, xdg_state_home};
+
+/// Once the ranks add up to more than this, all of them are aged (like z and zoxide do).
+const MAX_TOTAL_RANK: f64 = 10_000.0;
+
+/// Directory that was visited, with the number of visits (decayed by aging) and the last visit.
+#[derive(Debug, Clone, PartialEq)]
+struct Entry {
+    path: PathBuf,
+    rank: f64,
+    /// Seconds since the epoch
+    time: u64,
+}
+
+/// Visited directories, ranked by frequency and recency ("frecency").
+///
+/// The database is stored in `~/.local/state/rfm/frecency` in the format of z (`path|rank|time`).
+#[derive(Debug, Default)]
+pub struct Frecency {
+    entries: Vec<Entry>,
+    /// Last visited directory, so staying in a directory does not count as a visit
+    last: PathBuf,
+    file: Option<PathBuf>,
+}
+
+impl Frecency {
+    pub fn open() -> Self {
+        let file = xdg_state_home().map(|dir| dir.join("rfm").join("frecency"));
+        Frecency::from_file(file)
+    }
+
+    fn from_file(file: Option<PathBuf>) -> Self {
+        let entries = file
+            .as_ref()
+            .and_then(|file| std::fs::read_to_string(file).ok())
+            .map(|content| content.lines().filter_map(parse_entry).collect())
+            .unwrap_or_default();
+        Frecency {
+            entries,
+            file,
+            ..Default::default()
+        }
+    }
+
+    /// Counts a visit of the directory, if it is not the last visited one.
+    pub fn visit(&mut self, path: &Path) {
+        if path == self.last {
+            return;
+        }
+        self.last = path.to_path_buf();
+        self.add(path, now());
+        if let Err(e) = self.save() {
+            warn!("Cannot save the frecency database: {e}");
+        }
+    }
+
+    fn add(&mut self, path: &Path, time: u64) {
+        match self.entries.iter_mut().find(|e| e.path == path) {
+            Some(entry) => {
+                entry.rank += 1.0;
+                entry.time = time;
+            }
+            None => self.entries.push(Entry {
+                path: path.to_path_buf(),
+                rank: 1.0,
+                time,
+            }),
+        }
+        if self.entries.iter().map(|e| e.rank).sum::<f64>() > MAX_TOTAL_RANK {
+            for entry in self.entries.iter_mut() {
+                entry.rank *= 0.9;
+            }
+            self.entries.retain(|e| e.rank >= 1.0);
+        }
+    }
+
+    /// Best-ranked directory that matches all keywords (except the current directory).
+    ///
+    /// Like in zoxide, the keywords must appear in order (case-insensitive)
+    /// and the last one must be part of the last component.
+    /// If no directory matches, zoxide is asked.
+    pub fn query(&self, keywords: &str, current: &Path) -> Option<PathBuf> {
+        let keywords: Vec<String> = keywords.split_whitespace().map(str::to_lowercase).collect();
+        if keywords.is_empty() {
+            return None;
+        }
+        self.best(&keywords, current)
+            .or_else(|| query_zoxide(&keywords, current))
+    }
+
+    fn best(&self, keywords: &[String], current: &Path) -> Option<PathBuf> {
+        let now = now();
+        self.entries
+            .iter()
+            .filter(|e| e.path != current && matches(&e.path, keywords))
+            .filter(|e| e.path.is_dir())
+            .max_by(|a, b| score(a, now).total_cmp(&score(b, now)))
+            .map(|e| e.path.clone())
+    }
+
+    fn save(&self) -> std::io::Result<()> {
+        let Some(file) = &self.file else {
+            return Ok(());
+        };
+        if let Some(dir) = file.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        let content: String = self
+            .entries
+            .iter()
+            .map(|e| format!("{}|{}|{}\n", e.path.display(), e.rank, e.time))
+            .collect();
+        std::fs::write(file, content)
+    }
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default()
+}
+
+fn parse_entry(line: &str) -> Option<Entry> {
+    let mut fields = line.rsplitn(3, '|');
+    let time = fields.next()?.parse().ok()?;
+    let rank = fields.next()?.parse().ok()?;
+    Some(Entry {
+        path: PathBuf::from(fields.next()?),
+        rank,
+        time,
+    })
+}
+
+/// Rank weighted by the age of the last visit.
+fn score(entry: &Entry, now: u64) -> f64 {
+    let age = now.saturating_sub(entry.time);
+    let factor = match age {
+        a if a < 60 * 60 => 4.0,
+        a if a < 24 * 60 * 60 => 2.0,
+        a if a < 7 * 24 * 60 * 60 => 0.5,
+        _ => 0.25,
+    };
+    entry.rank * factor
+}
+
+fn matches(path: &Path, keywords: &[String]) -> bool {
+    let path = path.to_string_lossy().to_lowercase();
+    let last_component = path.rsplit('/').next().unwrap_or_default();
+    if !keywords
+        .last()
+        .is_some_and(|last| last_component.contains(last.as_str()))
+    {
+        return false;
+    }
+    let mut rest = path.as_str();
+    keywords
+        .iter()
+        .all(|keyword| match rest.find(keyword.as_str()) {
+            Some(idx) => {
+                rest = &rest[idx + keyword.len()..];
+                true
+            }
+            None => false,
+        })
+}
+
+fn query_zoxide(keywords: &[String], current: &Path) -> Option<PathBuf> {
+    if !exec_allowed() {
+        return None;
+    }
+    let out = Command::new("zoxide")
+        .arg("query")
+        .arg("--exclude")
+        .arg(current)
+        .arg("--")
+        .args(keywords)
+        .stderr(Stdio::null())
+        .output()
+        .ok()
+        .filter(|out| out.status.success())?;
+    let path = String::from_utf8_lossy(&out.stdout).trim().to_string();
+    (!path.is_empty()).then(|| PathBuf::from(path))
+}
+
+#[test]
+fn test_frecency() {
+    let dir = tempfile::tempdir().unwrap();
+    let (src, rfm) = (dir.path().join("src"), dir.path().join("src/rfm"));
+    let old = dir.path().join("old/rfm");
+    std::fs::create_dir_all(&rfm).unwrap();
+    std::fs::create_dir_all(&old).unwrap();
+    let mut frecency = Frecency::default();
+    let now = now();
+    frecency.add(&src, now);
+    frecency.add(&src, now);
+    frecency.add(&rfm, now);
+    // Visited a long time ago, so it ranks below the recent visit
+    for _ in 0..5 {
+        frecency.add(&old, 0);
+    }
+
+    let keywords = |k: &str| -> Vec<String> { k.split(' ').map(String::from).collect() };
+    assert_eq!(frecency.best(&keywords("src"), dir.path()), Some(src));
+    assert_eq!(
+        frecency.best(&keywords("src rfm"), dir.path()),
+        Some(rfm.clone())
+    );
+    assert_eq!(frecency.best(&keywords("rfm src"), dir.path()), None);
+    assert_eq!(
+        frecency.best(&keywords("rfm"), dir.path()),
+        Some(rfm.clone())
+    );
+    assert_eq!(frecency.best(&keywords("rfm"), &rfm), Some(old));
+    assert_eq!(
+        parse_entry("/home/a|b|2.5|1700000000"),
+        Some(Entry {
+            path: PathBuf::from("/home/a|b"),
+            rank: 2.5,
+            time: 1700000000,
+        })
+    );
+}
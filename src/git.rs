@@ -0,0 +1,147 @@
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+    process::{Command, Stdio},
+};
+
+use crate::util::exec_allowed;
+
+/// Status of a file (or directory) inside a git repository.
+///
+/// The variants are ordered by importance - if a directory contains
+/// files with different states, the directory shows the most important one.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum GitStatus {
+    Untracked,
+    Staged,
+    Modified,
+    Conflict,
+}
+
+impl GitStatus {
+    /// Single character that is displayed in front of the element.
+    pub fn marker(&self) -> char {
+        match self {
+            GitStatus::Untracked => '?',
+            GitStatus::Staged => '+',
+            GitStatus::Modified => '~',
+            GitStatus::Conflict => '!',
+        }
+    }
+
+    /// Parses the two-letter status code of `git status --porcelain`.
+    fn from_code(code: &[u8]) -> Option<Self> {
+        match code {
+            b"??" => Some(GitStatus::Untracked),
+            b"!!" => None,
+            [b'U', _] | [_, b'U'] | b"AA" | b"DD" => Some(GitStatus::Conflict),
+            [_, y] if *y != b' ' => Some(GitStatus::Modified),
+            [x, _] if *x != b' ' => Some(GitStatus::Staged),
+            _ => None,
+        }
+    }
+}
+
+/// Git status of all entries of a directory.
+#[derive(Debug, Clone, Default)]
+pub struct GitStatusMap {
+    /// Directory that the status belongs to
+    pub dir: PathBuf,
+
+    /// Status of all direct children of the directory that are not clean.
+    pub entries: HashMap<PathBuf, GitStatus>,
+
+    /// Increased by the panel-manager whenever a new status arrives,
+    /// so the panels know when they have to re-apply it.
+    pub generation: u64,
+}
+
+/// Runs `git status` for the given directory.
+///
+/// Returns `None` if the directory is not inside a git repository
+/// (or if git is not installed, or external programs are disabled).
+///
+/// NOTE: This is a blocking call, so better run it with `spawn_blocking`.
+pub fn git_status(dir: &Path) -> Option<GitStatusMap> {
+    // NOTE: git may run commands from the repository config (e.g. core.fsmonitor)
+    if !exec_allowed() {
+        return None;
+    }
+    let root = Command::new("git")
+        .arg("-C")
+        .arg(dir)
+        .args(["rev-parse", "--show-toplevel"])
+        .stderr(Stdio::null())
+        .output()
+        .ok()
+        .filter(|out| out.status.success())?;
+    let root = PathBuf::from(String::from_utf8_lossy(&root.stdout).trim_end());
+
+    let output = Command::new("git")
+        .arg("-C")
+        .arg(dir)
+        .args([
+            "status",
+            "--porcelain",
+            "-z",
+            "--untracked-files=normal",
+            ".",
+        ])
+        .stderr(Stdio::null())
+        .output()
+        .ok()
+        .filter(|out| out.status.success())?;
+
+    Some(GitStatusMap {
+        dir: dir.to_path_buf(),
+        entries: parse_porcelain(&output.stdout, &root, dir),
+        generation: 0,
+    })
+}
+
+/// Parses the output of `git status --porcelain -z`.
+///
+/// The paths in the output are relative to the repository root.
+/// Every path is mapped to the direct child of `dir` that contains it.
+fn parse_porcelain(output: &[u8], root: &Path, dir: &Path) -> HashMap<PathBuf, GitStatus> {
+    let mut entries = HashMap::new();
+    let mut records = output.split(|b| *b == 0);
+    while let Some(record) = records.next() {
+        if record.len() < 4 {
+            continue;
+        }
+        let (code, path) = (&record[..2], &record[3..]);
+        // Renames and copies are followed by the original path, which we skip
+        if code[0] == b'R' || code[0] == b'C' {
+            records.next();
+        }
+        let Some(status) = GitStatus::from_code(code) else {
+            continue;
+        };
+        let path = root.join(String::from_utf8_lossy(path).trim_end_matches('/'));
+        let Some(child) = path
+            .strip_prefix(dir)
+            .ok()
+            .and_then(|rel| rel.components().next())
+        else {
+            continue;
+        };
+        let entry = entries.entry(dir.join(child)).or_insert(status);
+        *entry = (*entry).max(status);
+    }
+    entries
+}
+
+#[test]
+fn test_parse_porcelain() {
+    let root = Path::new("/repo");
+    let dir = Path::new("/repo/src");
+    let output =
+        b" M src/main.rs\0A  src/new.rs\0?? src/panel/\0R  src/b.rs\0src/a.rs\0UU README.md\0";
+    let entries = parse_porcelain(output, root, dir);
+    assert_eq!(entries.len(), 4);
+    assert_eq!(entries[Path::new("/repo/src/main.rs")], GitStatus::Modified);
+    assert_eq!(entries[Path::new("/repo/src/new.rs")], GitStatus::Staged);
+    assert_eq!(entries[Path::new("/repo/src/panel")], GitStatus::Untracked);
+    assert_eq!(entries[Path::new("/repo/src/b.rs")], GitStatus::Staged);
+}
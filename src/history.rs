@@ -0,0 +1,126 @@
+use std::path::PathBuf;
+
+use log::warn;
+
+use crate::util::xdg_state_home;
+
+/// Maximum number of entries that are kept.
+const MAX_HISTORY: usize = 500;
+
+/// Previous inputs of a prompt, which can be recalled with Up and Down.
+///
+/// The entries are stored in `~/.local/state/rfm/<name>_history`, one per line.
+#[derive(Debug, Default)]
+pub struct History {
+    /// Oldest entry first
+    entries: Vec<String>,
+
+    /// Entry that is currently recalled - `None` while editing a new input
+    position: Option<usize>,
+
+    /// Input before the recall started - restored when going past the newest entry
+    draft: String,
+
+    file: Option<PathBuf>,
+}
+
+impl History {
+    /// Loads the history with the given name from the state directory.
+    pub fn open(name: &str) -> Self {
+        let file = xdg_state_home().map(|dir| dir.join("rfm").join(format!("{name}_history")));
+        History::from_file(file)
+    }
+
+    fn from_file(file: Option<PathBuf>) -> Self {
+        let entries = file
+            .as_ref()
+            .and_then(|file| std::fs::read_to_string(file).ok())
+            .map(|content| content.lines().map(String::from).collect())
+            .unwrap_or_default();
+        History {
+            entries,
+            file,
+            ..Default::default()
+        }
+    }
+
+    /// Recalls the previous (older) entry - `current` is kept as draft when the recall starts.
+    pub fn prev(&mut self, current: &str) -> Option<&str> {
+        let position = match self.position {
+            None if self.entries.is_empty() => return None,
+            None => {
+                self.draft = current.to_string();
+                self.entries.len() - 1
+            }
+            Some(0) => return None,
+            Some(position) => position - 1,
+        };
+        self.position = Some(position);
+        Some(&self.entries[position])
+    }
+
+    /// Recalls the next (newer) entry, or the draft after the newest one.
+    pub fn next(&mut self) -> Option<&str> {
+        let position = self.position? + 1;
+        if position < self.entries.len() {
+            self.position = Some(position);
+            Some(&self.entries[position])
+        } else {
+            self.position = None;
+            Some(&self.draft)
+        }
+    }
+
+    /// Stops recalling, so the next [`History::prev`] starts at the newest entry again.
+    pub fn reset(&mut self) {
+        self.position = None;
+        self.draft.clear();
+    }
+
+    /// Adds an entry as the newest one (removing older duplicates) and saves the history.
+    pub fn push(&mut self, entry: &str) {
+        self.reset();
+        let entry = entry.trim();
+        if entry.is_empty() || entry.contains('\n') {
+            return;
+        }
+        self.entries.retain(|e| e != entry);
+        self.entries.push(entry.to_string());
+        let excess = self.entries.len().saturating_sub(MAX_HISTORY);
+        self.entries.drain(..excess);
+        if let Err(e) = self.save() {
+            warn!("Cannot save the history: {e}");
+        }
+    }
+
+    fn save(&self) -> std::io::Result<()> {
+        let Some(file) = &self.file else {
+            return Ok(());
+        };
+        if let Some(dir) = file.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        let mut content = self.entries.join("\n");
+        content.push('\n');
+        std::fs::write(file, content)
+    }
+}
+
+#[test]
+fn test_history() {
+    let dir = tempfile::tempdir().unwrap();
+    let file = dir.path().join("rfm").join("search_history");
+    let mut history = History::from_file(Some(file.clone()));
+    assert_eq!(history.prev("draft"), None);
+    history.push("foo");
+    history.push("bar");
+    history.push("foo");
+
+    let mut history = History::from_file(Some(file));
+    assert_eq!(history.prev("draft"), Some("foo"));
+    assert_eq!(history.prev("ignored"), Some("bar"));
+    assert_eq!(history.prev("ignored"), None);
+    assert_eq!(history.next(), Some("foo"));
+    assert_eq!(history.next(), Some("draft"));
+    assert_eq!(history.next(), None);
+}
@@ -0,0 +1,138 @@
+use std::{
+    path::PathBuf,
+    process::{Command, Stdio},
+};
+
+use log::{error, info};
+
+use crate::{opener::capture_output, remote::socket_env, util::exec_allowed};
+
+/// Events of the lifecycle of rfm, which can trigger hooks.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HookEvent {
+    /// Entered a directory
+    Cd,
+    /// Selected another item
+    Select,
+    BeforePaste,
+    AfterPaste,
+    Quit,
+}
+
+impl HookEvent {
+    /// Name of the event, as it is used by the plugins and `RFM_EVENT`.
+    pub fn name(&self) -> &'static str {
+        match self {
+            HookEvent::Cd => "cd",
+            HookEvent::Select => "select",
+            HookEvent::BeforePaste => "before_paste",
+            HookEvent::AfterPaste => "after_paste",
+            HookEvent::Quit => "quit",
+        }
+    }
+
+    /// Whether rfm waits for the shell hook, before it continues.
+    ///
+    /// All other hooks run in the background.
+    fn waits(&self) -> bool {
+        matches!(self, HookEvent::BeforePaste | HookEvent::Quit)
+    }
+}
+
+/// State of rfm when the event happened.
+#[derive(Debug, Clone, Default)]
+pub struct HookContext {
+    pub cwd: PathBuf,
+    pub selected: Option<PathBuf>,
+    pub marked: Vec<PathBuf>,
+    /// Files of the operation (e.g. the pasted files)
+    pub files: Vec<PathBuf>,
+}
+
+impl HookContext {
+    /// Environment variables that describe the context (lists are separated by newlines).
+    pub fn env(&self, event: HookEvent) -> Vec<(&'static str, String)> {
+        let join = |paths: &[PathBuf]| {
+            paths
+                .iter()
+                .map(|p| p.to_string_lossy())
+                .collect::<Vec<_>>()
+                .join("\n")
+        };
+        vec![
+            ("RFM_EVENT", event.name().to_string()),
+            ("RFM_CWD", self.cwd.to_string_lossy().to_string()),
+            (
+                "RFM_SELECTED",
+                self.selected
+                    .as_ref()
+                    .map(|p| p.to_string_lossy().to_string())
+                    .unwrap_or_default(),
+            ),
+            ("RFM_MARKED", join(&self.marked)),
+            ("RFM_FILES", join(&self.files)),
+        ]
+    }
+}
+
+/// Runs the shell command of a hook in the current directory, without any terminal in- or output.
+///
+/// Its output is written into the log.
+pub fn run_hook(command: &str, event: HookEvent, context: &HookContext) {
+    if !exec_allowed() {
+        return;
+    }
+    let child = Command::new("sh")
+        .arg("-c")
+        .arg(command)
+        .current_dir(&context.cwd)
+        .envs(context.env(event))
+        .envs(socket_env())
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn();
+    let mut child = match child {
+        Ok(child) => child,
+        Err(e) => {
+            error!("Hook {}: {e}", event.name());
+            return;
+        }
+    };
+    capture_output(&format!("Hook {}", event.name()), &mut child);
+    let mut wait = move || match child.wait() {
+        Ok(status) if !status.success() => info!("Hook {} exited with {status}", event.name()),
+        Ok(_) => (),
+        Err(e) => error!("Hook {}: {e}", event.name()),
+    };
+    if event.waits() {
+        wait();
+    } else {
+        std::thread::spawn(wait);
+    }
+}
+
+#[test]
+fn test_hook_env() {
+    let context = HookContext {
+        cwd: "/home".into(),
+        selected: Some("/home/a".into()),
+        marked: vec!["/home/a".into(), "/home/b".into()],
+        files: Vec::new(),
+    };
+    let env = context.env(HookEvent::BeforePaste);
+    assert!(env.contains(&("RFM_EVENT", "before_paste".to_string())));
+    assert!(env.contains(&("RFM_SELECTED", "/home/a".to_string())));
+    assert!(env.contains(&("RFM_MARKED", "/home/a\n/home/b".to_string())));
+    assert!(env.contains(&("RFM_FILES", String::new())));
+
+    let dir = tempfile::tempdir().unwrap();
+    let context = HookContext {
+        cwd: dir.path().to_path_buf(),
+        ..Default::default()
+    };
+    // Quit hooks finish before rfm exits
+    run_hook("echo $RFM_EVENT > out", HookEvent::Quit, &context);
+    let out = std::fs::read_to_string(dir.path().join("out")).unwrap();
+    assert_eq!(out, "quit\n");
+}
@@ -0,0 +1,183 @@
+use std::{
+    collections::{BTreeMap, VecDeque},
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+    time::Instant,
+};
+
+use parking_lot::Mutex;
+use tokio::sync::mpsc;
+
+/// Number of progress reports that are kept for the throughput graph.
+const MAX_SAMPLES: usize = 64;
+
+/// File operations that run in the background (pastes, shredding, emptying the trash).
+///
+/// Every job is registered with [`Jobs::start`] and removed once its [`JobGuard`] is dropped,
+/// which also wakes up the main loop, so the status is published again.
+#[derive(Debug, Clone)]
+pub struct Jobs {
+    running: Arc<Mutex<BTreeMap<u64, JobInfo>>>,
+    next_id: Arc<AtomicU64>,
+    finished_tx: mpsc::UnboundedSender<()>,
+}
+
+/// State of a running job, as reported by the thread that does the work.
+#[derive(Debug, Clone)]
+pub struct JobInfo {
+    pub id: u64,
+    pub description: String,
+    pub started: Instant,
+    /// Item that is processed right now
+    pub current: Option<PathBuf>,
+    /// Number of bytes that are done, and of all bytes (0 if unknown)
+    pub done: u64,
+    pub total: u64,
+    /// Time and done bytes of the last progress reports
+    samples: VecDeque<(Instant, u64)>,
+}
+
+impl JobInfo {
+    /// Bytes per second between the progress reports, oldest first.
+    pub fn throughput(&self) -> Vec<u64> {
+        self.samples
+            .iter()
+            .zip(self.samples.iter().skip(1))
+            .map(|((t0, b0), (t1, b1))| {
+                let secs = t1.duration_since(*t0).as_secs_f64().max(0.001);
+                (b1.saturating_sub(*b0) as f64 / secs) as u64
+            })
+            .collect()
+    }
+
+    /// Bytes per second since the job started.
+    pub fn average(&self) -> u64 {
+        let secs = self.started.elapsed().as_secs_f64().max(0.001);
+        (self.done as f64 / secs) as u64
+    }
+}
+
+impl Jobs {
+    /// Returns the registry and the receiver that is notified whenever a job ends.
+    pub fn new() -> (Self, mpsc::UnboundedReceiver<()>) {
+        let (finished_tx, finished_rx) = mpsc::unbounded_channel();
+        let jobs = Jobs {
+            running: Arc::default(),
+            next_id: Arc::default(),
+            finished_tx,
+        };
+        (jobs, finished_rx)
+    }
+
+    /// Registers a job - it runs until the returned guard is dropped.
+    pub fn start(&self, description: String) -> JobGuard {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        let started = Instant::now();
+        self.running.lock().insert(
+            id,
+            JobInfo {
+                id,
+                description,
+                started,
+                current: None,
+                done: 0,
+                total: 0,
+                samples: VecDeque::from([(started, 0)]),
+            },
+        );
+        JobGuard {
+            id,
+            jobs: self.clone(),
+        }
+    }
+
+    /// Descriptions of the running jobs, oldest first.
+    pub fn list(&self) -> Vec<String> {
+        self.running
+            .lock()
+            .values()
+            .map(|job| job.description.clone())
+            .collect()
+    }
+
+    /// State of all running jobs, oldest first.
+    pub fn infos(&self) -> Vec<JobInfo> {
+        self.running.lock().values().cloned().collect()
+    }
+
+    /// State of the job - `None` once it has ended.
+    pub fn info(&self, id: u64) -> Option<JobInfo> {
+        self.running.lock().get(&id).cloned()
+    }
+}
+
+/// Keeps a job registered - move it into the thread that does the work.
+#[derive(Debug)]
+pub struct JobGuard {
+    id: u64,
+    jobs: Jobs,
+}
+
+impl JobGuard {
+    /// Sets the number of bytes that the job processes.
+    pub fn set_total(&self, total: u64) {
+        if let Some(job) = self.jobs.running.lock().get_mut(&self.id) {
+            job.total = total;
+        }
+    }
+
+    /// Reports the item that is processed now, and how many bytes are done before it.
+    pub fn progress(&self, current: &Path, done: u64) {
+        if let Some(job) = self.jobs.running.lock().get_mut(&self.id) {
+            job.current = Some(current.to_path_buf());
+            job.done = done;
+            if job.samples.len() >= MAX_SAMPLES {
+                job.samples.pop_front();
+            }
+            job.samples.push_back((Instant::now(), done));
+        }
+    }
+}
+
+impl Drop for JobGuard {
+    fn drop(&mut self) {
+        self.jobs.running.lock().remove(&self.id);
+        let _ = self.jobs.finished_tx.send(());
+    }
+}
+
+/// Number of bytes of the item - directories are summed up (without following symlinks).
+pub fn item_size(path: &Path) -> u64 {
+    walkdir::WalkDir::new(path)
+        .into_iter()
+        .flatten()
+        .filter_map(|entry| entry.metadata().ok())
+        .filter(|metadata| metadata.is_file())
+        .map(|metadata| metadata.len())
+        .sum()
+}
+
+#[test]
+fn test_jobs() {
+    let (jobs, mut finished_rx) = Jobs::new();
+    let paste = jobs.start("paste 2 items".to_string());
+    let shred = jobs.start("shred 1 item".to_string());
+    assert_eq!(jobs.list(), ["paste 2 items", "shred 1 item"]);
+
+    paste.set_total(300);
+    paste.progress(Path::new("/tmp/a"), 100);
+    let info = jobs.infos().remove(0);
+    assert_eq!(info.current.as_deref(), Some(Path::new("/tmp/a")));
+    assert_eq!((info.done, info.total), (100, 300));
+    assert_eq!(info.throughput().len(), 1);
+
+    drop(paste);
+    assert_eq!(jobs.list(), ["shred 1 item"]);
+    assert!(jobs.info(info.id).is_none());
+    assert!(finished_rx.try_recv().is_ok());
+    std::thread::spawn(move || drop(shred)).join().unwrap();
+    assert!(jobs.list().is_empty());
+}
@@ -0,0 +1,308 @@
+use std::fmt::Display;
+
+use log::{debug, error};
+use once_cell::sync::OnceCell;
+
+pub static LOCALE: OnceCell<Language> = OnceCell::new();
+
+/// Languages of the message catalog.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum Language {
+    #[default]
+    English,
+    German,
+}
+
+impl Language {
+    /// Selects the language from the environment (`LC_ALL`, `LC_MESSAGES` and `LANG`, in that order).
+    ///
+    /// Unknown languages fall back to english.
+    pub fn from_env() -> Self {
+        let locale = ["LC_ALL", "LC_MESSAGES", "LANG"]
+            .into_iter()
+            .filter_map(|var| std::env::var(var).ok())
+            .find(|value| !value.is_empty())
+            .unwrap_or_default();
+        Language::from_locale(&locale)
+    }
+
+    /// Parses a locale like `de_DE.UTF-8`.
+    fn from_locale(locale: &str) -> Self {
+        let code = locale
+            .split(['_', '.', '@'])
+            .next()
+            .unwrap_or_default()
+            .to_ascii_lowercase();
+        match code.as_str() {
+            "de" => Language::German,
+            _ => Language::English,
+        }
+    }
+
+    pub fn init() {
+        let language = Language::from_env();
+        debug!("Language: {language:?}");
+        if LOCALE.set(language).is_err() {
+            error!("Locale was already initialized.");
+        }
+    }
+}
+
+/// User-facing messages.
+///
+/// Messages with arguments contain `{}` placeholders, which are filled by [`tr_fmt`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Msg {
+    Search,
+    RunCommand,
+    Rename,
+    MakeDirectory,
+    Touch,
+    Export,
+    Chown,
+    Bookmarks,
+    NoBookmarks,
+    NoMatch,
+    Marked,
+    ImageFailed,
+    ChownDone,
+    DirectoryVanished,
+    PinVanished,
+    BulkrenameHeader,
+    BulkrenameFailed,
+    BulkrenameKept,
+    BulkrenameCount,
+    BulkrenameSlash,
+    BulkrenameExists,
+    BulkrenameDuplicate,
+    BulkrenameRolledBack,
+    ConfigErrors,
+    ConfigErrorsHint,
+    ConfigValid,
+    FirstLetter,
+    FrecentJump,
+    SetMark,
+    JumpMark,
+    NoSuchMark,
+    Help,
+    Log,
+    ConfirmDelete,
+    ConfirmOverwrite,
+    Cancelled,
+    PressEnter,
+    NoTrash,
+    NoTrashView,
+    TrashSummary,
+    TrashIsEmpty,
+    ConfirmEmptyTrash,
+    EmptyingTrash,
+    EmptiedTrash,
+    ConfirmDeletePermanently,
+    ConfirmBypassTrash,
+    ConfirmShred,
+    ConfirmProjectCommand,
+    ConfirmShredCopyOnWrite,
+    Shredded,
+    DiskUsage,
+    DiskUsageScanning,
+    ConfirmDeleteItem,
+    Devices,
+    DevicesKeys,
+    Mounted,
+    Unmounted,
+    Ejected,
+    Mounts,
+    Jobs,
+    NoJobs,
+    JobCurrent,
+    JobThroughput,
+}
+
+/// Returns the message in the language of the user.
+pub fn tr(msg: Msg) -> &'static str {
+    let language = LOCALE.get().copied().unwrap_or_default();
+    match language {
+        Language::English => english(msg),
+        Language::German => german(msg),
+    }
+}
+
+/// Returns the message with its placeholders replaced by the arguments.
+pub fn tr_fmt(msg: Msg, args: &[&dyn Display]) -> String {
+    let mut parts = tr(msg).split("{}");
+    let mut out = parts.next().unwrap_or_default().to_string();
+    for (idx, part) in parts.enumerate() {
+        if let Some(arg) = args.get(idx) {
+            out.push_str(&arg.to_string());
+        }
+        out.push_str(part);
+    }
+    out
+}
+
+fn english(msg: Msg) -> &'static str {
+    match msg {
+        Msg::Search => "Search:",
+        Msg::RunCommand => "Run:",
+        Msg::Rename => "Rename:",
+        Msg::MakeDirectory => "Make Directory:",
+        Msg::Touch => "Touch:",
+        Msg::Export => "Export to (empty = clipboard):",
+        Msg::Chown => "Chown (user:group):",
+        Msg::Bookmarks => "Bookmarks:",
+        Msg::NoBookmarks => "(no bookmarks)",
+        Msg::NoMatch => "(no match)",
+        Msg::Marked => "marked",
+        Msg::ImageFailed => "Failed to load image '{}'",
+        Msg::ChownDone => "Changed ownership of {} items",
+        Msg::DirectoryVanished => "'{}' no longer exists - moved to '{}'",
+        Msg::PinVanished => "'{}' no longer exists - unpinned the left panel",
+        Msg::BulkrenameHeader => {
+            "\
+# Edit the names below - one line per file, without changing the order.
+# Lines starting with '#' are comments. Names starting with '#' or '\\' are escaped with a '\\'.
+"
+        }
+        Msg::BulkrenameFailed => "--- Bulkrename failed ---",
+        Msg::BulkrenameKept => "Bulkrename failed - your edits are kept in '{}'",
+        Msg::BulkrenameCount => "The file has {} names, but {} files should be renamed.",
+        Msg::BulkrenameSlash => "'{}' must not contain a '/'.",
+        Msg::BulkrenameExists => "Cannot rename '{}' to '{}': '{}' already exists.",
+        Msg::BulkrenameDuplicate => "'{}' is used for more than one file.",
+        Msg::BulkrenameRolledBack => "Bulkrename failed, no file was renamed: {}",
+        Msg::ConfigErrors => "Configuration errors - using the defaults for these files:",
+        Msg::ConfigErrorsHint => "e: edit selected file   Esc: dismiss",
+        Msg::ConfigValid => "'{}' is valid now - restart rfm to apply it",
+        Msg::FirstLetter => "Jump to letter:",
+        Msg::FrecentJump => "Jump to:",
+        Msg::SetMark => "Set mark:",
+        Msg::JumpMark => "Jump to mark:",
+        Msg::NoSuchMark => "There is no mark '{}'",
+        Msg::Help => "Keybindings:",
+        Msg::Log => "Log",
+        Msg::ConfirmDelete => "ROOT: Delete {} items including directories? (y/N)",
+        Msg::ConfirmOverwrite => "ROOT: Paste and overwrite existing files? (y/N)",
+        Msg::Cancelled => "Cancelled",
+        Msg::PressEnter => "Press Enter to continue",
+        Msg::NoTrash => "Cannot create a trash ({}) - deleted items are removed permanently",
+        Msg::NoTrashView => "There is no trash - deleted items are removed permanently",
+        Msg::TrashSummary => "trash: {} items, {}",
+        Msg::TrashIsEmpty => "The trash is empty",
+        Msg::ConfirmEmptyTrash => "Empty the trash - delete {} items permanently? (y/N)",
+        Msg::EmptyingTrash => "Emptying the trash: {}/{} items",
+        Msg::EmptiedTrash => "Emptied the trash ({} items could not be removed)",
+        Msg::ConfirmDeletePermanently => "No trash: Delete {} items permanently? (y/N)",
+        Msg::ConfirmBypassTrash => "Delete {} items permanently, without the trash? (y/N)",
+        Msg::ConfirmShred => "Shred {} items - overwrite and delete them permanently? (y/N)",
+        Msg::ConfirmProjectCommand => "Allow the commands of {} and run '{}'? (y/N)",
+        Msg::ConfirmShredCopyOnWrite => {
+            "Copy-on-write filesystem: the old content will survive! Shred {} items anyway? (y/N)"
+        }
+        Msg::Shredded => "Shredded {} items",
+        Msg::DiskUsage => "Disk usage:",
+        Msg::DiskUsageScanning => "Scanning... {} items, {}",
+        Msg::ConfirmDeleteItem => "Delete '{}' ({})? (y/N)",
+        Msg::Devices => "Devices:",
+        Msg::DevicesKeys => "Enter: open  m: mount  u: unmount  e: eject  r: refresh",
+        Msg::Mounted => "Mounted {}",
+        Msg::Unmounted => "Unmounted {}",
+        Msg::Ejected => "Ejected {} - the device can be removed",
+        Msg::Mounts => "Mounted filesystems:  device / type / size / used / free",
+        Msg::Jobs => "Running jobs:  progress / description",
+        Msg::NoJobs => "(no running jobs)",
+        Msg::JobCurrent => "Current: {}",
+        Msg::JobThroughput => "Throughput: {}",
+    }
+}
+
+fn german(msg: Msg) -> &'static str {
+    match msg {
+        Msg::Search => "Suchen:",
+        Msg::RunCommand => "Ausführen:",
+        Msg::Rename => "Umbenennen:",
+        Msg::MakeDirectory => "Verzeichnis erstellen:",
+        Msg::Touch => "Datei erstellen:",
+        Msg::Export => "Exportieren nach (leer = Zwischenablage):",
+        Msg::Chown => "Besitzer (Benutzer:Gruppe):",
+        Msg::Bookmarks => "Lesezeichen:",
+        Msg::NoBookmarks => "(keine Lesezeichen)",
+        Msg::NoMatch => "(kein Treffer)",
+        Msg::Marked => "markiert",
+        Msg::ImageFailed => "Bild '{}' konnte nicht geladen werden",
+        Msg::ChownDone => "Besitzer von {} Elementen geändert",
+        Msg::DirectoryVanished => "'{}' existiert nicht mehr - wechsle nach '{}'",
+        Msg::PinVanished => "'{}' existiert nicht mehr - linkes Panel gelöst",
+        Msg::BulkrenameHeader => "\
+# Bearbeite die Namen unten - eine Zeile pro Datei, ohne die Reihenfolge zu ändern.
+# Zeilen, die mit '#' beginnen, sind Kommentare. Namen, die mit '#' oder '\\' beginnen, werden mit '\\' maskiert.
+",
+        Msg::BulkrenameFailed => "--- Umbenennen fehlgeschlagen ---",
+        Msg::BulkrenameKept => {
+            "Umbenennen fehlgeschlagen - deine Änderungen sind in '{}' gespeichert"
+        }
+        Msg::BulkrenameCount => "Die Datei enthält {} Namen, aber {} Dateien sollen umbenannt werden.",
+        Msg::BulkrenameSlash => "'{}' darf kein '/' enthalten.",
+        Msg::BulkrenameExists => {
+            "'{}' kann nicht in '{}' umbenannt werden: '{}' existiert bereits."
+        }
+        Msg::BulkrenameDuplicate => "'{}' wird für mehrere Dateien verwendet.",
+        Msg::BulkrenameRolledBack => {
+            "Umbenennen fehlgeschlagen, keine Datei wurde umbenannt: {}"
+        }
+        Msg::ConfigErrors => "Konfigurationsfehler - für diese Dateien gelten die Standardwerte:",
+        Msg::ConfigErrorsHint => "e: ausgewählte Datei bearbeiten   Esc: schließen",
+        Msg::ConfigValid => "'{}' ist jetzt gültig - starte rfm neu, um sie zu übernehmen",
+        Msg::FirstLetter => "Springe zu Buchstabe:",
+        Msg::FrecentJump => "Springe zu:",
+        Msg::SetMark => "Markierung setzen:",
+        Msg::JumpMark => "Springe zu Markierung:",
+        Msg::NoSuchMark => "Es gibt keine Markierung '{}'",
+        Msg::Help => "Tastenbelegung:",
+        Msg::Log => "Protokoll",
+        Msg::ConfirmDelete => "ROOT: {} Elemente inklusive Verzeichnissen löschen? (y/N)",
+        Msg::ConfirmOverwrite => "ROOT: Einfügen und vorhandene Dateien überschreiben? (y/N)",
+        Msg::Cancelled => "Abgebrochen",
+        Msg::PressEnter => "Weiter mit Enter",
+        Msg::NoTrash => {
+            "Papierkorb kann nicht erstellt werden ({}) - gelöschte Elemente werden endgültig entfernt"
+        }
+        Msg::NoTrashView => "Kein Papierkorb - gelöschte Elemente werden endgültig entfernt",
+        Msg::TrashSummary => "Papierkorb: {} Elemente, {}",
+        Msg::TrashIsEmpty => "Der Papierkorb ist leer",
+        Msg::ConfirmEmptyTrash => "Papierkorb leeren - {} Elemente endgültig löschen? (y/N)",
+        Msg::EmptyingTrash => "Papierkorb wird geleert: {}/{} Elemente",
+        Msg::EmptiedTrash => "Papierkorb geleert ({} Elemente konnten nicht entfernt werden)",
+        Msg::ConfirmDeletePermanently => "Kein Papierkorb: {} Elemente endgültig löschen? (y/N)",
+        Msg::ConfirmBypassTrash => "{} Elemente endgültig löschen, ohne Papierkorb? (y/N)",
+        Msg::ConfirmShred => "{} Elemente schreddern - überschreiben und endgültig löschen? (y/N)",
+        Msg::ConfirmProjectCommand => "Befehle von {} erlauben und '{}' ausführen? (y/N)",
+        Msg::ConfirmShredCopyOnWrite => {
+            "Copy-on-Write-Dateisystem: der alte Inhalt bleibt erhalten! {} Elemente trotzdem schreddern? (y/N)"
+        }
+        Msg::Shredded => "{} Elemente geschreddert",
+        Msg::DiskUsage => "Speicherbelegung:",
+        Msg::DiskUsageScanning => "Durchsuche... {} Elemente, {}",
+        Msg::ConfirmDeleteItem => "'{}' ({}) löschen? (y/N)",
+        Msg::Devices => "Geräte:",
+        Msg::DevicesKeys => "Enter: öffnen  m: einhängen  u: aushängen  e: auswerfen  r: aktualisieren",
+        Msg::Mounted => "{} eingehängt",
+        Msg::Unmounted => "{} ausgehängt",
+        Msg::Ejected => "{} ausgeworfen - das Gerät kann entfernt werden",
+        Msg::Mounts => "Eingehängte Dateisysteme:  Gerät / Typ / Größe / belegt / frei",
+        Msg::Jobs => "Laufende Aufgaben:  Fortschritt / Beschreibung",
+        Msg::NoJobs => "(keine laufenden Aufgaben)",
+        Msg::JobCurrent => "Aktuell: {}",
+        Msg::JobThroughput => "Durchsatz: {}",
+    }
+}
+
+#[test]
+fn test_locale() {
+    assert_eq!(Language::from_locale("de_DE.UTF-8"), Language::German);
+    assert_eq!(Language::from_locale("C"), Language::English);
+    assert_eq!(Language::from_locale(""), Language::English);
+    assert_eq!(
+        tr_fmt(Msg::BulkrenameExists, &[&"a", &"b", &"b"]),
+        "Cannot rename 'a' to 'b': 'b' already exists."
+    );
+}
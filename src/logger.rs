@@ -1,34 +1,35 @@
-use std::{collections::VecDeque, sync::Arc};
+use std::{
+    collections::VecDeque,
+    fs::{File, OpenOptions},
+    io::{self, Write},
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::SystemTime,
+};
 
 use log::Level;
 use parking_lot::Mutex;
 use tokio::sync::Notify;
 
+use crate::util::format_time;
+
 #[derive(Clone)]
 pub struct LogBuffer {
     buffer: Arc<Mutex<VecDeque<(Level, String)>>>,
     notify: Arc<Notify>,
     capacity: usize,
     level: Level,
+    /// Persists the log (see [`LogBuffer::set_file`])
+    file: Arc<Mutex<Option<LogFile>>>,
 }
 
 impl LogBuffer {
     pub fn with_level(self, level: Level) -> Self {
-        Self {
-            buffer: self.buffer,
-            notify: self.notify,
-            capacity: self.capacity,
-            level,
-        }
+        Self { level, ..self }
     }
 
     pub fn with_capacity(self, capacity: usize) -> Self {
-        Self {
-            buffer: self.buffer,
-            notify: self.notify,
-            capacity,
-            level: self.level,
-        }
+        Self { capacity, ..self }
     }
 
     pub fn capacity(&self) -> usize {
@@ -42,6 +43,14 @@ impl LogBuffer {
     pub async fn update(&self) {
         self.notify.notified().await
     }
+
+    /// Additionally writes all records to the file - starting with those that are still buffered.
+    pub fn set_file(&self, mut file: LogFile) {
+        for (level, line) in self.buffer.lock().iter() {
+            let _ = file.write(*level, line);
+        }
+        *self.file.lock() = Some(file);
+    }
 }
 
 impl log::Log for LogBuffer {
@@ -51,6 +60,10 @@ impl log::Log for LogBuffer {
 
     fn log(&self, record: &log::Record) {
         let line = format!("{}", record.args());
+        if let Some(file) = self.file.lock().as_mut() {
+            // NOTE: There is no place to report errors of the log itself
+            let _ = file.write(record.level(), &line);
+        }
         let mut inner = self.buffer.lock();
         inner.push_back((record.level(), line));
         if inner.len() > self.capacity {
@@ -70,6 +83,92 @@ impl Default for LogBuffer {
             notify: Default::default(),
             capacity: 10,
             level: Level::Info,
+            file: Default::default(),
+        }
+    }
+}
+
+/// Log file that is rotated once it exceeds its maximum size.
+///
+/// The rotated files are named `<name>.1` (the newest) up to `<name>.<keep>`.
+pub struct LogFile {
+    path: PathBuf,
+    file: File,
+    /// Current size of the file
+    size: u64,
+    /// Records below this level are not written
+    level: Level,
+    max_size: u64,
+    keep: usize,
+}
+
+impl LogFile {
+    pub fn open(path: &Path, level: Level, max_size: u64, keep: usize) -> io::Result<Self> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+        let size = file.metadata()?.len();
+        Ok(LogFile {
+            path: path.to_path_buf(),
+            file,
+            size,
+            level,
+            max_size,
+            keep,
+        })
+    }
+
+    fn write(&mut self, level: Level, line: &str) -> io::Result<()> {
+        if level > self.level {
+            return Ok(());
         }
+        if self.size >= self.max_size {
+            self.rotate()?;
+        }
+        let record = format!("{} {level:<5} {line}\n", format_time(SystemTime::now()));
+        self.file.write_all(record.as_bytes())?;
+        self.size += record.len() as u64;
+        Ok(())
+    }
+
+    fn rotate(&mut self) -> io::Result<()> {
+        let rotated = |n: usize| {
+            let mut name = self.path.as_os_str().to_owned();
+            name.push(format!(".{n}"));
+            PathBuf::from(name)
+        };
+        if self.keep == 0 {
+            std::fs::remove_file(&self.path)?;
+        } else {
+            for n in (1..self.keep).rev() {
+                let _ = std::fs::rename(rotated(n), rotated(n + 1));
+            }
+            std::fs::rename(&self.path, rotated(1))?;
+        }
+        self.file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)?;
+        self.size = 0;
+        Ok(())
+    }
+}
+
+#[test]
+fn test_log_file() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("rfm.log");
+    let mut file = LogFile::open(&path, Level::Info, 10, 2).unwrap();
+    file.write(Level::Debug, "skipped").unwrap();
+    for n in 0..4 {
+        file.write(Level::Error, &format!("record {n}")).unwrap();
     }
+    let read = |name: &str| std::fs::read_to_string(dir.path().join(name)).unwrap();
+    // Every record exceeds the maximum size
+    assert!(read("rfm.log").ends_with("ERROR record 3\n"));
+    assert!(read("rfm.log.1").ends_with("ERROR record 2\n"));
+    assert!(read("rfm.log.2").ends_with("ERROR record 1\n"));
+    assert!(!dir.path().join("rfm.log.3").exists());
+    assert!(!read("rfm.log.2").contains("skipped"));
 }
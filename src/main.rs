@@ -1,51 +1,139 @@
 #![allow(dead_code)]
 use clap::Parser;
-use commands::CommandParser;
+use commands::{CommandParser, KeyConfig};
+use config::{Config, ConfigError};
 use content::PanelCache;
 use crossterm::{
     cursor,
-    event::DisableMouseCapture,
+    event::{DisableMouseCapture, EnableMouseCapture},
     terminal::{
         disable_raw_mode, enable_raw_mode, Clear, ClearType, DisableLineWrap, EnableLineWrap,
         EnterAlternateScreen, LeaveAlternateScreen,
     },
     QueueableCommand,
 };
+use locale::Language;
 use log::{info, warn};
-use logger::LogBuffer;
+use logger::{LogBuffer, LogFile};
+use notifier::DirNotifier;
 use notify_rust::Notification;
-use opener::OpenEngine;
-use panel::manager::PanelManager;
+use opener::{OpenEngine, OpenerConfig};
+use panel::{init_hidden_patterns, init_sorting, manager::PanelManager};
+use plugin::PluginHost;
 use std::{
     error::Error,
     fs::OpenOptions,
-    io::{stdout, Write},
+    io::{self, stdout, Write},
     path::PathBuf,
+    sync::Arc,
 };
 use symbols::SymbolEngine;
+use theme::{ThemeConfig, ThemeEngine};
 use tokio::sync::mpsc;
-use util::xdg_config_home;
+use util::{
+    disable_exec, exec_allowed, start_location, xdg_config_home, xdg_state_home, StartupProfile,
+};
 
+mod bookmarks;
 mod commands;
+mod config;
 mod content;
+mod devices;
+mod disk_usage;
+mod exif;
+mod frecency;
+mod git;
+mod history;
+mod hooks;
+mod jobs;
+mod locale;
 mod logger;
+mod notifier;
 mod opener;
+mod ownership;
 mod panel;
+mod plugin;
+mod project;
+mod remote;
+mod status;
+mod structured;
 mod symbols;
+mod theme;
+mod trash;
 mod util;
+mod vfs;
 
 #[derive(Parser, Debug)]
 #[command(author, version, about, long_about = None)]
 struct Args {
+    /// Directories to start in - or files, which are then selected in their directory.
+    /// Every path is opened in its own tab
+    paths: Vec<PathBuf>,
+
+    /// Starts in the directory of PATH, with the cursor on it
+    #[arg(long, value_name = "PATH", conflicts_with = "paths")]
+    select: Option<PathBuf>,
+
     /// Makes rfm act like a diretory chooser. Upon quitting
     /// it will write the full path of the last visited directory to CHOOSEDIR
     #[arg(long)]
     choosedir: Option<PathBuf>,
+
+    /// Logs how long the individual startup phases take (see "devlog")
+    #[arg(long)]
+    profile_startup: bool,
+
+    /// Safe mode: Never run external programs (opener, editor, git, mediainfo).
+    /// Only the built-in viewing and navigation works.
+    #[arg(long)]
+    no_exec: bool,
+
+    /// Writes every directory change as a line to DIR_NOTIFY (a FIFO or a regular file),
+    /// so that other programs can follow rfm
+    #[arg(long)]
+    dir_notify: Option<PathBuf>,
+
+    /// Mouse mode: Click to select, drag to mark a range and ctrl-click to toggle marks
+    #[arg(long)]
+    mouse: bool,
+
+    /// Sends a command ("jump DIR", "select PATH", "mark PATH" or "quit") to a running rfm
+    /// and exits. The instance is taken from $RFM_SOCKET, or else the most recently started one
+    #[arg(long, num_args = 1.., value_name = "COMMAND")]
+    remote: Option<Vec<String>>,
 }
 
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn Error>> {
     let args = Args::parse();
+    if let Some(command) = args.remote {
+        let Some(socket) = remote::find_socket() else {
+            eprintln!("Error: cannot find a running rfm");
+            std::process::exit(1);
+        };
+        let answer = remote::send(&socket, &command.join(" "))?;
+        if answer != "ok" {
+            eprintln!("{answer}");
+            std::process::exit(1);
+        }
+        return Ok(());
+    }
+    let mut locations = Vec::new();
+    for path in args.select.iter().chain(args.paths.iter()) {
+        match start_location(path) {
+            Ok(location) => locations.push(location),
+            Err(e) => {
+                eprintln!("Error: {}: {e}", path.display());
+                std::process::exit(1);
+            }
+        }
+    }
+    let mut locations = locations.into_iter();
+    let (start_dir, start_selected) = locations.next().unwrap_or_else(|| (".".into(), None));
+    let mut profile = StartupProfile::new(args.profile_startup);
+    if args.no_exec {
+        disable_exec();
+    }
 
     std::panic::set_hook(Box::new(|panic_info| {
         let body = if let Some(s) = panic_info.payload().downcast_ref::<&str>() {
@@ -75,9 +163,10 @@ async fn main() -> Result<(), Box<dyn Error>> {
     // Initialize logger
     let logger = LogBuffer::default()
         .with_level(log::Level::Debug)
-        .with_capacity(15);
+        .with_capacity(1000);
     log::set_boxed_logger(Box::new(logger.clone())).expect("failed to initialize logger");
     log::set_max_level(log::LevelFilter::Debug);
+    profile.phase("logger init");
 
     enable_raw_mode()?;
 
@@ -93,35 +182,69 @@ async fn main() -> Result<(), Box<dyn Error>> {
         .queue(cursor::Hide)?
         .queue(Clear(ClearType::All))?
         .queue(cursor::MoveTo(0, 0))?;
+    if args.mouse {
+        stdout.queue(EnableMouseCapture)?;
+    }
+    profile.phase("terminal setup");
 
     SymbolEngine::init();
+    profile.phase("symbol engine init");
 
-    let directory_cache = PanelCache::with_size(16384);
-    let preview_cache = PanelCache::with_size(4096);
+    Language::init();
 
-    let (dir_tx, dir_rx) = mpsc::channel(32);
-    let (prev_tx, prev_rx) = mpsc::channel(32);
+    let config_dir = xdg_config_home()?.join("rfm");
+    // Broken config files are reported in an overlay after startup
+    let mut config_errors = Vec::new();
 
-    let (preview_tx, preview_rx) = mpsc::unbounded_channel();
-    let (directory_tx, directory_rx) = mpsc::unbounded_channel();
+    // Read general config
+    let general_config_file = config_dir.join("config.toml");
 
-    let dir_manager = content::DirManager::new(
-        directory_cache.clone(),
-        preview_cache.clone(),
-        dir_tx,
-        directory_rx,
-    );
+    let config = if let Ok(content) = std::fs::read_to_string(&general_config_file) {
+        match toml::from_str(&content) {
+            Ok(config) => {
+                info!("Using config: {}", general_config_file.display());
+                config
+            }
+            Err(e) => {
+                warn!("Configuration error: {e}. Using default config");
+                config_errors.push(ConfigError::new::<Config>(&general_config_file, &e));
+                Config::default()
+            }
+        }
+    } else {
+        info!("Using default config");
+        Config::default()
+    };
 
-    let preview_manager = content::PreviewManager::new(preview_cache.clone(), prev_tx, preview_rx);
+    init_hidden_patterns(config.hidden.globs());
+    init_sorting(config.sort);
 
-    let dir_mngr_handle = tokio::spawn(dir_manager.run());
-    let prev_mngr_handle = tokio::spawn(preview_manager.run());
+    if config.log.file {
+        let log_file = xdg_state_home()
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "HOME is not set"))
+            .map(|dir| dir.join("rfm").join("rfm.log"))
+            .and_then(|path| {
+                LogFile::open(
+                    &path,
+                    config.log.level(),
+                    config.log.max_size,
+                    config.log.keep,
+                )
+            });
+        match log_file {
+            Ok(log_file) => logger.set_file(log_file),
+            Err(e) => warn!("Cannot open the log file: {e}"),
+        }
+    }
+
+    let directory_cache = PanelCache::with_size(config.cache.directories);
+    let preview_cache = PanelCache::with_size(config.cache.previews);
+    profile.phase("cache init");
 
     // Read keybinding config
-    let config_dir = xdg_config_home()?.join("rfm");
     let key_config_file = config_dir.join("keys.toml");
 
-    let parser = if let Ok(content) = std::fs::read_to_string(&key_config_file) {
+    let mut parser = if let Ok(content) = std::fs::read_to_string(&key_config_file) {
         match toml::from_str(&content) {
             Ok(key_config) => {
                 info!("Using keyboard config: {}", key_config_file.display());
@@ -129,6 +252,7 @@ async fn main() -> Result<(), Box<dyn Error>> {
             }
             Err(e) => {
                 warn!("Configuration error: {e}. Using default keyboard bindings");
+                config_errors.push(ConfigError::new::<KeyConfig>(&key_config_file, &e));
                 CommandParser::default_bindings()
             }
         }
@@ -140,6 +264,35 @@ async fn main() -> Result<(), Box<dyn Error>> {
         CommandParser::default_bindings()
     };
 
+    // Plugins can run arbitrary code, so they are not loaded in safe mode
+    let plugins = Arc::new(PluginHost::new());
+    if exec_allowed() {
+        plugins.load_dir(&config_dir.join("plugins"));
+        parser.insert_plugin_bindings(plugins.bindings());
+    }
+    profile.phase("plugins");
+
+    let (dir_tx, dir_rx) = mpsc::channel(32);
+    let (prev_tx, prev_rx) = mpsc::channel(32);
+
+    let (preview_tx, preview_rx) = mpsc::unbounded_channel();
+    let (directory_tx, directory_rx) = mpsc::unbounded_channel();
+
+    let dir_manager = content::DirManager::new(
+        directory_cache.clone(),
+        preview_cache.clone(),
+        dir_tx,
+        directory_rx,
+        plugins.clone(),
+    );
+
+    let preview_manager =
+        content::PreviewManager::new(preview_cache.clone(), prev_tx, preview_rx, plugins.clone());
+
+    let dir_mngr_handle = tokio::spawn(dir_manager.run());
+    let prev_mngr_handle = tokio::spawn(preview_manager.run());
+    profile.phase("content manager init");
+
     // Read opener config
     let open_config_file = config_dir.join("open.toml");
 
@@ -151,6 +304,7 @@ async fn main() -> Result<(), Box<dyn Error>> {
             }
             Err(e) => {
                 warn!("Configuration error: {e}. Using default open engine");
+                config_errors.push(ConfigError::new::<OpenerConfig>(&open_config_file, &e));
                 OpenEngine::default()
             }
         }
@@ -159,6 +313,29 @@ async fn main() -> Result<(), Box<dyn Error>> {
         OpenEngine::default()
     };
 
+    // Read theme config
+    let theme_config_file = config_dir.join("theme.toml");
+
+    let theme_config = if let Ok(content) = std::fs::read_to_string(&theme_config_file) {
+        match toml::from_str(&content) {
+            Ok(theme_config) => {
+                info!("Using theme config: {}", theme_config_file.display());
+                theme_config
+            }
+            Err(e) => {
+                warn!("Configuration error: {e}. Using default theme");
+                config_errors.push(ConfigError::new::<ThemeConfig>(&theme_config_file, &e));
+                ThemeConfig::default()
+            }
+        }
+    } else {
+        info!("Using default theme");
+        ThemeConfig::default()
+    };
+    ThemeEngine::init(theme_config);
+
+    profile.phase("config parsing");
+
     let panel_manager = PanelManager::new(
         parser,
         directory_cache,
@@ -169,7 +346,15 @@ async fn main() -> Result<(), Box<dyn Error>> {
         preview_tx,
         logger,
         opener,
-    )?;
+        &start_dir,
+        start_selected.as_deref(),
+        &mut profile,
+    )?
+    .with_dir_notifier(args.dir_notify.clone().map(DirNotifier::new))
+    .with_config(config)
+    .with_plugins(plugins)
+    .with_tabs(locations.collect())
+    .with_config_errors(config_errors);
     let panel_handle = tokio::spawn(panel_manager.run());
 
     let panel_result = panel_handle.await;
@@ -178,6 +363,7 @@ async fn main() -> Result<(), Box<dyn Error>> {
 
     // Be a good citizen, cleanup
     stdout
+        .queue(DisableMouseCapture)?
         .queue(EnableLineWrap)?
         .queue(Clear(ClearType::Purge))?
         .queue(LeaveAlternateScreen)?
@@ -0,0 +1,73 @@
+use std::{
+    fs::OpenOptions,
+    io::{self, Write},
+    os::unix::fs::{FileTypeExt, OpenOptionsExt},
+    path::{Path, PathBuf},
+};
+
+use log::{debug, warn};
+
+/// Writes every directory change to a FIFO or file (see `--dir-notify`),
+/// so external programs (prompts, status bars, terminal panes) can follow rfm.
+///
+/// Every change is written as a single line that contains the full path.
+#[derive(Debug, Clone)]
+pub struct DirNotifier {
+    target: PathBuf,
+
+    /// Last directory that was written to the target
+    last: PathBuf,
+}
+
+impl DirNotifier {
+    pub fn new(target: PathBuf) -> Self {
+        DirNotifier {
+            target,
+            last: PathBuf::new(),
+        }
+    }
+
+    /// Notifies the target, if the directory has changed since the last call.
+    pub fn update(&mut self, dir: &Path) {
+        if dir == self.last {
+            return;
+        }
+        self.last = dir.to_path_buf();
+        match self.write(dir) {
+            Ok(()) => (),
+            // Nobody is reading from the FIFO right now
+            Err(e) if e.raw_os_error() == Some(libc::ENXIO) => {
+                debug!("dir-notify: no reader on {}", self.target.display())
+            }
+            Err(e) => warn!("dir-notify: cannot write to {}: {e}", self.target.display()),
+        }
+    }
+
+    fn write(&self, dir: &Path) -> io::Result<()> {
+        let is_fifo = self
+            .target
+            .metadata()
+            .map(|m| m.file_type().is_fifo())
+            .unwrap_or(false);
+        let mut options = OpenOptions::new();
+        if is_fifo {
+            // Never block the UI - if there is no reader, opening fails immediately.
+            options.write(true).custom_flags(libc::O_NONBLOCK);
+        } else {
+            options.append(true).create(true);
+        }
+        let mut file = options.open(&self.target)?;
+        file.write_all(format!("{}\n", dir.display()).as_bytes())
+    }
+}
+
+#[test]
+fn test_dir_notifier() {
+    let dir = tempfile::tempdir().unwrap();
+    let target = dir.path().join("dirs");
+    let mut notifier = DirNotifier::new(target.clone());
+    notifier.update(Path::new("/tmp"));
+    notifier.update(Path::new("/tmp"));
+    notifier.update(Path::new("/home"));
+    assert_eq!(std::fs::read_to_string(target).unwrap(), "/tmp\n/home\n");
+}
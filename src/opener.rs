@@ -1,18 +1,26 @@
 use std::{
-    io::{stdout, Write},
+    io::{self, stdout, BufRead, BufReader, Read, Write},
+    os::unix::process::CommandExt,
     path::{Path, PathBuf},
-    process::Command,
+    process::{Child, Command, ExitStatus, Stdio},
 };
 
 use crossterm::{
     cursor,
-    terminal::{self, Clear, ClearType},
+    terminal::{self, Clear, ClearType, EnterAlternateScreen, LeaveAlternateScreen},
     QueueableCommand, Result,
 };
-use log::{debug, error, info};
+use log::{debug, error, info, warn};
 use mime::Mime;
 use serde::{Deserialize, Serialize};
 
+use crate::{
+    locale::{tr, Msg},
+    panel::escape_control,
+    remote::socket_env,
+    util::exec_allowed,
+};
+
 /// Uses mime_guess to extract the mime-type.
 ///
 /// However: There are a few exceptions,
@@ -32,24 +40,293 @@ pub fn get_mime_type<P: AsRef<Path>>(path: P) -> Mime {
 #[derive(Debug, Default, Clone, Serialize, Deserialize)]
 pub struct Application {
     name: String,
+    /// If set, the application runs inside the terminal and rfm is suspended until it exits.
+    ///
+    /// Otherwise the application runs in the background and rfm continues immediately.
+    #[serde(default)]
     terminal: bool,
+    /// If set, the application is detached into its own process group,
+    /// so it is not affected by signals that are sent to rfm (e.g. Ctrl-C in the terminal).
+    ///
+    /// Ignored for terminal applications.
+    #[serde(default)]
+    fork: bool,
+    #[serde(default)]
     args: Vec<String>,
 }
 
 impl Application {
-    pub fn open<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+    /// Opens the file with the application.
+    ///
+    /// Terminal applications always block until they exit.
+    /// For all other applications we only wait, if `wait` is set.
+    /// If we wait, a nonzero exit status is an error.
+    pub fn open<P: AsRef<Path>>(&self, path: P, wait: bool) -> Result<()> {
         info!("Opening '{}' with '{}'", path.as_ref().display(), self.name);
-        let mut handle = Command::new(&self.name)
+        let mut command = Command::new(&self.name);
+        command
             .args(&self.args)
             .arg(path.as_ref())
-            .spawn()?;
+            .envs(socket_env());
         if self.terminal {
-            handle.wait()?;
+            // Suspend the TUI while the application is running
+            terminal::disable_raw_mode()?;
+            let mut stdout = stdout();
+            stdout
+                .queue(Clear(ClearType::All))?
+                .queue(cursor::MoveTo(0, 0))?;
+            stdout.flush()?;
+            let result = command.spawn().and_then(|mut handle| handle.wait());
+            terminal::enable_raw_mode()?;
+            check_status(result?)?;
+        } else {
+            // GUI applications must not write into our terminal
+            command
+                .stdin(Stdio::null())
+                .stdout(Stdio::null())
+                .stderr(Stdio::null());
+            if self.fork {
+                command.process_group(0);
+            }
+            let mut handle = command.spawn()?;
+            if wait {
+                check_status(handle.wait()?)?;
+            } else {
+                // Reap the process in the background, so it does not become a zombie
+                let name = self.name.clone();
+                std::thread::spawn(move || match handle.wait() {
+                    Ok(status) => debug!("'{name}' exited with {status}"),
+                    Err(e) => error!("'{name}': {e}"),
+                });
+            }
         }
         Ok(())
     }
 }
 
+/// Runs the shell command in the directory, while the TUI is suspended.
+///
+/// Waits for `Enter` afterwards, so the output can be read.
+pub fn run_shell(command: &str, dir: &Path) -> Result<()> {
+    if !exec_allowed() {
+        return Err(io::Error::other(
+            "external programs are disabled (--no-exec)",
+        ));
+    }
+    info!("Running '{command}' in {}", dir.display());
+    terminal::disable_raw_mode()?;
+    let mut stdout = stdout();
+    stdout
+        .queue(Clear(ClearType::All))?
+        .queue(cursor::MoveTo(0, 0))?
+        .queue(cursor::Show)?;
+    writeln!(stdout, "$ {command}")?;
+    stdout.flush()?;
+    let result = Command::new("sh")
+        .arg("-c")
+        .arg(command)
+        .current_dir(dir)
+        .envs(socket_env())
+        .status();
+    write!(stdout, "\n{}", tr(Msg::PressEnter))?;
+    stdout.flush()?;
+    let mut line = String::new();
+    let _ = io::stdin().read_line(&mut line);
+    terminal::enable_raw_mode()?;
+    check_status(result?)
+}
+
+/// Starts an interactive `$SHELL` in the directory, while the TUI is suspended.
+///
+/// `RFM_LEVEL` tells the shell how deeply it is nested in rfm sessions (e.g. for the prompt).
+pub fn run_subshell(dir: &Path) -> Result<()> {
+    if !exec_allowed() {
+        return Err(io::Error::other(
+            "external programs are disabled (--no-exec)",
+        ));
+    }
+    let shell = std::env::var_os("SHELL")
+        .filter(|shell| !shell.is_empty())
+        .unwrap_or_else(|| "sh".into());
+    let level = std::env::var("RFM_LEVEL")
+        .ok()
+        .and_then(|level| level.parse::<u32>().ok())
+        .unwrap_or(0)
+        + 1;
+    info!("Starting {} in {}", shell.to_string_lossy(), dir.display());
+    // The exit status of an interactive shell is the one of its last command
+    run_interactive(
+        Command::new(shell)
+            .current_dir(dir)
+            .env("RFM_LEVEL", level.to_string())
+            .envs(socket_env()),
+    )
+    .map(|_| ())
+}
+
+/// Runs the program on the normal screen of the terminal, so it can interact with the user
+/// (e.g. to ask for a password).
+pub fn run_interactive(command: &mut Command) -> Result<ExitStatus> {
+    terminal::disable_raw_mode()?;
+    let mut stdout = stdout();
+    stdout
+        .queue(LeaveAlternateScreen)?
+        .queue(cursor::Show)?
+        .flush()?;
+    let result = command.status();
+    stdout
+        .queue(EnterAlternateScreen)?
+        .queue(cursor::Hide)?
+        .queue(Clear(ClearType::All))?
+        .flush()?;
+    terminal::enable_raw_mode()?;
+    result
+}
+
+/// Runs the shell command in the background, without access to the terminal.
+///
+/// Its output ends up in the log (see [`capture_output`]).
+pub fn spawn_shell(command: &str, dir: &Path) -> Result<()> {
+    if !exec_allowed() {
+        return Err(io::Error::other(
+            "external programs are disabled (--no-exec)",
+        ));
+    }
+    info!("Running '{command}' detached in {}", dir.display());
+    let mut handle = Command::new("sh")
+        .arg("-c")
+        .arg(command)
+        .current_dir(dir)
+        .envs(socket_env())
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .process_group(0)
+        .spawn()?;
+    capture_output(command, &mut handle);
+    // Reap the process in the background, so it does not become a zombie
+    let command = command.to_string();
+    std::thread::spawn(move || match handle.wait() {
+        Ok(status) if !status.success() => warn!("'{command}' exited with {status}"),
+        Ok(status) => debug!("'{command}' exited with {status}"),
+        Err(e) => error!("'{command}': {e}"),
+    });
+    Ok(())
+}
+
+/// Writes the output of a background process into the log, so it can be read in the log panel
+/// (instead of garbling the TUI): stdout as info, stderr as warnings.
+///
+/// The process must have been spawned with piped stdout and stderr.
+pub fn capture_output(name: &str, child: &mut Child) {
+    if let Some(stdout) = child.stdout.take() {
+        let name = name.to_string();
+        std::thread::spawn(move || forward_lines(stdout, |line| info!("{name}: {line}")));
+    }
+    if let Some(stderr) = child.stderr.take() {
+        let name = name.to_string();
+        std::thread::spawn(move || forward_lines(stderr, |line| warn!("{name}: {line}")));
+    }
+}
+
+/// Calls `sink` for every line of the reader (lossy UTF-8, without the line break), until it ends.
+///
+/// Control characters are escaped, so colored output cannot garble the log view.
+fn forward_lines(reader: impl Read, sink: impl Fn(&str)) {
+    let mut reader = BufReader::new(reader);
+    let mut line = Vec::new();
+    while matches!(reader.read_until(b'\n', &mut line), Ok(n) if n > 0) {
+        let text = String::from_utf8_lossy(&line);
+        sink(&escape_control(text.trim_end_matches(['\n', '\r'])));
+        line.clear();
+    }
+}
+
+/// Replaces the macros of a typed command:
+/// `%f` the selected item, `%s` the marked items (or the selected one), `%d` the directory
+/// and `%%` a literal `%`. All paths are quoted for the shell.
+pub fn expand_macros(
+    command: &str,
+    selected: Option<&Path>,
+    marked: &[PathBuf],
+    dir: &Path,
+) -> String {
+    let mut expanded = String::new();
+    let mut chars = command.chars();
+    while let Some(c) = chars.next() {
+        if c != '%' {
+            expanded.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('f') => expanded.push_str(&selected.map(shell_quote).unwrap_or_default()),
+            Some('s') => {
+                let files: Vec<String> = if marked.is_empty() {
+                    selected.into_iter().map(shell_quote).collect()
+                } else {
+                    marked.iter().map(|p| shell_quote(p)).collect()
+                };
+                expanded.push_str(&files.join(" "));
+            }
+            Some('d') => expanded.push_str(&shell_quote(dir)),
+            Some('%') => expanded.push('%'),
+            Some(other) => {
+                expanded.push('%');
+                expanded.push(other);
+            }
+            None => expanded.push('%'),
+        }
+    }
+    expanded
+}
+
+/// Quotes the path for `sh`, with single quotes.
+fn shell_quote(path: &Path) -> String {
+    format!("'{}'", path.to_string_lossy().replace('\'', "'\\''"))
+}
+
+fn check_status(status: ExitStatus) -> Result<()> {
+    if status.success() {
+        Ok(())
+    } else {
+        Err(io::Error::other(format!("exited with {status}")))
+    }
+}
+
+/// A single rule of the opener config.
+///
+/// All conditions that are set must match, unset conditions match every file.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OpenRule {
+    /// Mime-type (e.g. "image/png") or only its top-level type (e.g. "image")
+    #[serde(default)]
+    mime: Option<String>,
+
+    /// List of file extensions (case-insensitive)
+    #[serde(default)]
+    ext: Vec<String>,
+
+    /// Application that opens the file
+    #[serde(flatten)]
+    application: Application,
+}
+
+impl OpenRule {
+    fn matches(&self, mime_type: &Mime, extension: Option<&str>) -> bool {
+        let mime_matches = match self.mime.as_deref() {
+            Some(mime) if mime.contains('/') => {
+                mime == mime_type.essence_str()
+                    || mime.strip_suffix("/*") == Some(mime_type.type_().as_str())
+            }
+            Some(mime) => mime == mime_type.type_().as_str(),
+            None => true,
+        };
+        let ext_matches = self.ext.is_empty()
+            || extension.is_some_and(|e| self.ext.iter().any(|x| x.eq_ignore_ascii_case(e)));
+        mime_matches && ext_matches
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct OpenOptions {
     default: Application,
@@ -57,25 +334,41 @@ pub struct OpenOptions {
 }
 
 impl OpenOptions {
-    pub fn open(&self, absolute: PathBuf) -> Result<()> {
-        if let Some(ext_list) = &self.extensions {
-            info!("checking extensions: {:?}", ext_list);
-            let path_extension = absolute.extension().and_then(|s| s.to_str());
-            for (ext, application) in ext_list.iter() {
-                if Some(ext.as_str()) == path_extension {
-                    return application.open(&absolute);
-                }
-            }
+    /// Converts the (legacy) section of a mime-type to a list of rules.
+    fn into_rules(self, mime: &str) -> Vec<OpenRule> {
+        let mut rules = Vec::new();
+        for (ext, application) in self.extensions.unwrap_or_default() {
+            rules.push(OpenRule {
+                mime: Some(mime.to_string()),
+                ext: vec![ext],
+                application: legacy_application(application),
+            });
         }
-        self.default.open(absolute)
+        rules.push(OpenRule {
+            mime: Some(mime.to_string()),
+            ext: Vec::new(),
+            application: legacy_application(self.default),
+        });
+        rules
     }
 }
 
+/// Non-terminal applications of the old config format never blocked rfm.
+fn legacy_application(mut application: Application) -> Application {
+    application.fork = !application.terminal;
+    application
+}
+
 // #[derive(Debug, Default, Clone, Serialize, Deserialize)]
 // pub struct Applications(HashMap<String, Application>);
 
 #[derive(Debug, Default, Clone, Serialize, Deserialize)]
 pub struct OpenerConfig {
+    /// Rules that are evaluated from top to bottom - the first matching rule wins.
+    #[serde(default)]
+    rule: Vec<OpenRule>,
+
+    // Sections of the old config format, which are checked after the rules.
     application: Option<OpenOptions>,
     audio: Option<OpenOptions>,
     video: Option<OpenOptions>,
@@ -85,102 +378,193 @@ pub struct OpenerConfig {
 
 #[derive(Default)]
 pub struct OpenEngine {
-    config: OpenerConfig,
+    rules: Vec<OpenRule>,
 }
 
 impl OpenEngine {
     pub fn with_config(config: OpenerConfig) -> Self {
-        OpenEngine { config }
+        let mut rules = config.rule;
+        for (mime, section) in [
+            ("text", config.text),
+            ("image", config.image),
+            ("audio", config.audio),
+            ("video", config.video),
+            ("application", config.application),
+        ] {
+            if let Some(section) = section {
+                rules.extend(section.into_rules(mime));
+            }
+        }
+        OpenEngine { rules }
+    }
+
+    /// Returns the first rule that matches the given file.
+    fn find_rule(&self, path: &Path) -> Option<&OpenRule> {
+        self.find_rules(path).into_iter().next()
+    }
+
+    /// Returns all rules that match the given file, in the order of the config.
+    fn find_rules(&self, path: &Path) -> Vec<&OpenRule> {
+        let mime_type = get_mime_type(path);
+        debug!("MIME-Type: {mime_type}");
+        let extension = path.extension().and_then(|e| e.to_str());
+        self.rules
+            .iter()
+            .filter(|rule| rule.matches(&mime_type, extension))
+            .collect()
     }
 
+    /// Returns `true` if the file is opened by a terminal application,
+    /// which means that the TUI is suspended while the application is running.
+    pub fn is_terminal(&self, path: &Path) -> bool {
+        self.find_rule(path)
+            .is_some_and(|rule| rule.application.terminal)
+    }
+
+    /// Opens the file - only terminal applications block until they exit.
     pub fn open(&self, path: PathBuf) -> Result<()> {
+        self.open_with(path, false)
+    }
+
+    /// Opens the file and waits for the application to exit (even if it is not a terminal application).
+    pub fn open_and_wait(&self, path: PathBuf) -> Result<()> {
+        self.open_with(path, true)
+    }
+
+    /// Opens the file in `$EDITOR` (or `vi`, if it is not set) - independent of the rules.
+    pub fn edit(&self, path: PathBuf) -> Result<()> {
+        if !exec_allowed() {
+            return Err(io::Error::other(
+                "external programs are disabled (--no-exec)",
+            ));
+        }
+        let editor = Application {
+            name: std::env::var("EDITOR")
+                .ok()
+                .filter(|e| !e.is_empty())
+                .unwrap_or_else(|| "vi".to_string()),
+            terminal: true,
+            ..Default::default()
+        };
+        editor.open(path, true)
+    }
+
+    fn open_with(&self, path: PathBuf, wait: bool) -> Result<()> {
+        if !exec_allowed() {
+            return Err(io::Error::other(
+                "external programs are disabled (--no-exec)",
+            ));
+        }
         let absolute = if path.is_absolute() {
             path
         } else {
             path.canonicalize().unwrap_or_default()
         };
-        terminal::disable_raw_mode()?;
-        let mut stdout = stdout();
-        stdout
-            .queue(Clear(ClearType::All))?
-            .queue(cursor::MoveTo(0, 0))?;
-        stdout.flush()?;
-
-        // Check mime-type
-        let mime_type = get_mime_type(&absolute);
-        match mime_type.type_().as_str() {
-            "text" => {
-                debug!("MIME-Type: Text");
-                if let Some(engine) = &self.config.text {
-                    engine.open(absolute)?;
-                } else {
-                    error!("Unset config value for mime-type 'text'");
-                }
-            }
-            "image" => {
-                debug!("MIME-Type: Image");
-                if let Some(engine) = &self.config.image {
-                    engine.open(absolute)?;
-                } else {
-                    error!("Unset config value for mime-type 'image'");
-                }
-            }
-            "audio" => {
-                debug!("MIME-Type: Audio");
-                if let Some(engine) = &self.config.audio {
-                    engine.open(absolute)?;
-                } else {
-                    error!("Unset config value for mime-type 'audio'");
-                }
-            }
-            "video" => {
-                debug!("MIME-Type: Video");
-                if let Some(engine) = &self.config.video {
-                    engine.open(absolute)?;
-                } else {
-                    error!("Unset config value for mime-type 'video'");
+        let rules = self.find_rules(&absolute);
+        if rules.is_empty() {
+            error!("Cannot open '{}' - no matching rule", absolute.display());
+            return Ok(());
+        }
+        // If an application cannot be started (or fails), the next matching rule is tried.
+        let mut failed = Vec::new();
+        for rule in rules {
+            let name = &rule.application.name;
+            match rule.application.open(&absolute, wait) {
+                Ok(()) => {
+                    if !failed.is_empty() {
+                        info!("Opened with '{name}' after: {}", failed.join(", "));
+                    }
+                    return Ok(());
                 }
-            }
-            "application" => {
-                debug!("MIME-Type: Application");
-                if let Some(app) = &self.config.application {
-                    app.open(absolute)?
-                } else {
-                    error!("Unset config value for mime-type 'application'");
+                Err(e) => {
+                    warn!("Opening with '{name}' failed: {e}");
+                    failed.push(format!("'{name}' ({e})"));
                 }
             }
-            _ => {
-                // Otherwise print error
-                error!("Cannot open '{}' - unknown mime-type", absolute.display());
-            }
         }
-
-        // if let Some(ext) = absolute.extension().and_then(|ext| ext.to_str()) {
-        //     match ext {
-        //         "png" | "bmp" | "jpg" | "jpeg" | "svg" => {
-        //             Command::new("sxiv")
-        //                 .stderr(Stdio::null())
-        //                 .stdin(Stdio::null())
-        //                 .stdout(Stdio::null())
-        //                 .arg(absolute.clone())
-        //                 .spawn()?;
-        //         }
-        //         "wav" | "aiff" | "au" | "flac" | "m4a" | "mp3" | "opus" => {
-        //             Command::new("mpv").arg(absolute).spawn()?.wait()?;
-        //         }
-        //         "pdf" => {
-        //             Command::new("zathura").arg(absolute).spawn()?;
-        //         }
-        //         _ => {
-        //             // Everything else with vim
-        //             Command::new("nvim").arg(absolute).spawn()?.wait()?;
-        //         }
-        //     }
-        // } else {
-        //     // Try to open things without extensions with vim
-        //     Command::new("nvim").arg(absolute).spawn()?.wait()?;
-        // }
-        terminal::enable_raw_mode()?;
-        Ok(())
+        Err(io::Error::other(format!(
+            "no application could open the file: {}",
+            failed.join(", ")
+        )))
     }
 }
+
+#[test]
+fn test_rule_order() {
+    let config: OpenerConfig = toml::from_str(
+        r#"
+        [[rule]]
+        ext = ["PDF"]
+        name = "zathura"
+        fork = true
+
+        [[rule]]
+        mime = "text/*"
+        name = "nvim"
+        terminal = true
+
+        [image]
+        default = { name = "sxiv", args = [], terminal = false }
+        "#,
+    )
+    .unwrap();
+    let engine = OpenEngine::with_config(config);
+    let name = |path: &str| {
+        engine
+            .find_rule(Path::new(path))
+            .map(|r| r.application.name.clone())
+    };
+    assert_eq!(name("/tmp/paper.pdf").as_deref(), Some("zathura"));
+    assert_eq!(name("/tmp/notes.txt").as_deref(), Some("nvim"));
+    assert_eq!(name("/tmp/photo.png").as_deref(), Some("sxiv"));
+    assert_eq!(name("/tmp/song.mp3"), None);
+}
+
+#[test]
+fn test_expand_macros() {
+    let dir = Path::new("/home/me");
+    let selected = Some(Path::new("/home/me/it's.txt"));
+    let marked = [PathBuf::from("/a b"), PathBuf::from("/c")];
+    assert_eq!(
+        expand_macros("mv %s %d/old # 100%%", selected, &marked, dir),
+        "mv '/a b' '/c' '/home/me'/old # 100%"
+    );
+    assert_eq!(
+        expand_macros("cat %f %s %x", selected, &[], dir),
+        r"cat '/home/me/it'\''s.txt' '/home/me/it'\''s.txt' %x"
+    );
+}
+
+#[test]
+fn test_forward_lines() {
+    let lines = std::cell::RefCell::new(Vec::new());
+    forward_lines(&b"one\r\n\x1b[1mtwo\x1b[0m\n\xfflast"[..], |line| {
+        lines.borrow_mut().push(line.to_string())
+    });
+    assert_eq!(
+        lines.into_inner(),
+        ["one", "\\u{1b}[1mtwo\\u{1b}[0m", "\u{fffd}last"]
+    );
+}
+
+#[test]
+fn test_fallback_chain() {
+    let engine = |second: &str| {
+        let config: OpenerConfig = toml::from_str(&format!(
+            r#"
+            [[rule]]
+            ext = ["txt"]
+            name = "rfm-nonexistent-opener"
+
+            [[rule]]
+            ext = ["txt"]
+            name = "{second}"
+            "#
+        ))
+        .unwrap();
+        OpenEngine::with_config(config)
+    };
+    let path = PathBuf::from("/tmp/notes.txt");
+    assert!(engine("true").open_and_wait(path.clone()).is_ok());
+    assert!(engine("false").open_and_wait(path).is_err());
+}
@@ -0,0 +1,110 @@
+use std::{collections::BTreeSet, io, path::Path};
+
+use users::{get_group_by_gid, get_group_by_name, get_user_by_name, group_access_list};
+
+use crate::util::common_prefix;
+
+/// Names of all users of the system (sorted).
+pub fn user_names() -> Vec<String> {
+    // SAFETY: `all_users` is not thread-safe, because it iterates with `getpwent`.
+    // It is only ever called from the panel-manager, so there are no concurrent iterations.
+    let users = unsafe { users::all_users() };
+    let names: BTreeSet<String> = users
+        .filter_map(|u| u.name().to_str().map(String::from))
+        .collect();
+    names.into_iter().collect()
+}
+
+/// Names of all groups that are relevant for `chgrp` (sorted):
+/// the groups of the current user, and the primary groups of all users.
+pub fn group_names() -> Vec<String> {
+    let mut names = BTreeSet::new();
+    for group in group_access_list().unwrap_or_default() {
+        if let Some(name) = group.name().to_str() {
+            names.insert(name.to_string());
+        }
+    }
+    // SAFETY: see `user_names`
+    for user in unsafe { users::all_users() } {
+        if let Some(name) = get_group_by_gid(user.primary_group_id())
+            .and_then(|g| g.name().to_str().map(String::from))
+        {
+            names.insert(name);
+        }
+    }
+    names.into_iter().collect()
+}
+
+/// Completes the part of a `user:group` specification that is currently typed.
+///
+/// Returns the longest common prefix of all matching candidates,
+/// or `None` if nothing matches.
+pub fn complete(input: &str, users: &[String], groups: &[String]) -> Option<String> {
+    let (head, part, candidates) = match input.split_once(':') {
+        Some((user, group)) => (format!("{user}:"), group, groups),
+        None => (String::new(), input, users),
+    };
+    let matches = candidates
+        .iter()
+        .map(String::as_str)
+        .filter(|c| c.starts_with(part));
+    let prefix = common_prefix(matches)?;
+    Some(format!("{head}{prefix}"))
+}
+
+/// Resolves a specification of the form `user`, `:group` or `user:group` to ids.
+pub fn parse_owner(spec: &str) -> Result<(Option<u32>, Option<u32>), String> {
+    let (user, group) = spec.split_once(':').unwrap_or((spec, ""));
+    let uid = match user {
+        "" => None,
+        name => Some(
+            get_user_by_name(name)
+                .ok_or_else(|| format!("unknown user '{name}'"))?
+                .uid(),
+        ),
+    };
+    let gid = match group {
+        "" => None,
+        name => Some(
+            get_group_by_name(name)
+                .ok_or_else(|| format!("unknown group '{name}'"))?
+                .gid(),
+        ),
+    };
+    if uid.is_none() && gid.is_none() {
+        return Err("neither user nor group given".to_string());
+    }
+    Ok((uid, gid))
+}
+
+/// Changes owner and/or group of the path.
+///
+/// Permission errors are turned into a message that explains what is missing.
+pub fn change_owner(path: &Path, uid: Option<u32>, gid: Option<u32>) -> Result<(), String> {
+    std::os::unix::fs::chown(path, uid, gid).map_err(|e| {
+        if e.kind() == io::ErrorKind::PermissionDenied {
+            let reason = if uid.is_some() {
+                "changing the owner requires root privileges"
+            } else {
+                "you must own the file and be a member of the group"
+            };
+            format!("Cannot change ownership of '{}': {reason}", path.display())
+        } else {
+            format!("Cannot change ownership of '{}': {e}", path.display())
+        }
+    })
+}
+
+#[test]
+fn test_complete() {
+    let users = vec!["alice".to_string(), "albert".to_string(), "bob".to_string()];
+    let groups = vec!["wheel".to_string(), "users".to_string()];
+    assert_eq!(complete("a", &users, &groups).as_deref(), Some("al"));
+    assert_eq!(complete("bo", &users, &groups).as_deref(), Some("bob"));
+    assert_eq!(
+        complete("bob:wh", &users, &groups).as_deref(),
+        Some("bob:wheel")
+    );
+    assert_eq!(complete(":u", &users, &groups).as_deref(), Some(":users"));
+    assert_eq!(complete("zed", &users, &groups), None);
+}
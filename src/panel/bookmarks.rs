@@ -0,0 +1,129 @@
+use crate::{
+    bookmarks::Bookmark,
+    locale::{tr, Msg},
+    util::ExactWidth,
+};
+
+use super::*;
+
+/// Overlay that lists all bookmarks and lets the user filter them by typing.
+pub struct BookmarkOverlay {
+    bookmarks: Vec<Bookmark>,
+
+    /// Filter for the bookmarks (case-insensitive)
+    input: String,
+
+    /// Index of the selection in the filtered list
+    selected: usize,
+}
+
+impl Draw for BookmarkOverlay {
+    fn draw(
+        &mut self,
+        stdout: &mut Stdout,
+        x_range: Range<u16>,
+        y_range: Range<u16>,
+    ) -> Result<()> {
+        let width = x_range.end.saturating_sub(x_range.start);
+        let height = y_range.end.saturating_sub(y_range.start);
+
+        let box_width = width.saturating_sub(4).min(100);
+        let x_start = x_range.start + width.saturating_sub(box_width) / 2;
+        let filtered = self.filtered();
+        // One line for the input and one for the border
+        let n_rows = (height.saturating_sub(3) as usize).min(filtered.len().max(1));
+        let y_start = y_range.start + 1;
+
+        // Keep the selection visible
+        let scroll = self.selected.saturating_sub(n_rows.saturating_sub(1));
+
+        queue!(
+            stdout,
+            cursor::MoveTo(x_start, y_start),
+            PrintStyledContent(
+                format!(" {} {}", tr(Msg::Bookmarks), self.input)
+                    .exact_width(box_width as usize)
+                    .bold()
+                    .dark_green()
+                    .reverse()
+            ),
+        )?;
+        for row in 0..n_rows {
+            let y = y_start + 1 + row as u16;
+            let line = match filtered.get(scroll + row) {
+                Some(bookmark) => {
+                    let source = format!(" {} ", bookmark.source);
+                    let path = format!(" {}", bookmark.path.display())
+                        .exact_width((box_width as usize).saturating_sub(source.chars().count()));
+                    format!("{path}{source}")
+                }
+                None => format!(" {}", tr(Msg::NoBookmarks)).exact_width(box_width as usize),
+            };
+            if scroll + row == self.selected {
+                queue!(
+                    stdout,
+                    cursor::MoveTo(x_start, y),
+                    PrintStyledContent(line.bold().negative())
+                )?;
+            } else {
+                queue!(stdout, cursor::MoveTo(x_start, y), Print(line))?;
+            }
+        }
+        queue!(
+            stdout,
+            cursor::MoveTo(x_start, y_start + 1 + n_rows as u16),
+            PrintStyledContent("―".repeat(box_width as usize).dark_green().bold()),
+        )?;
+        Ok(())
+    }
+}
+
+impl BookmarkOverlay {
+    pub fn new(bookmarks: Vec<Bookmark>) -> Self {
+        BookmarkOverlay {
+            bookmarks,
+            input: String::new(),
+            selected: 0,
+        }
+    }
+
+    /// Replaces the bookmarks (e.g. once the import has finished) - they must start with the old ones,
+    /// so the selection stays the same.
+    pub fn set_bookmarks(&mut self, bookmarks: Vec<Bookmark>) {
+        self.bookmarks = bookmarks;
+    }
+
+    /// Returns all bookmarks whose path contains the input.
+    fn filtered(&self) -> Vec<&Bookmark> {
+        let pattern = self.input.to_lowercase();
+        self.bookmarks
+            .iter()
+            .filter(|b| b.path.to_string_lossy().to_lowercase().contains(&pattern))
+            .collect()
+    }
+
+    pub fn insert(&mut self, character: char) {
+        self.input.push(character);
+        self.selected = 0;
+    }
+
+    pub fn del(&mut self) {
+        self.input.pop();
+        self.selected = 0;
+    }
+
+    pub fn up(&mut self) {
+        self.selected = self.selected.saturating_sub(1);
+    }
+
+    pub fn down(&mut self) {
+        if self.selected + 1 < self.filtered().len() {
+            self.selected += 1;
+        }
+    }
+
+    /// Returns the path of the selected bookmark.
+    pub fn selected_path(&self) -> Option<PathBuf> {
+        self.filtered().get(self.selected).map(|b| b.path.clone())
+    }
+}
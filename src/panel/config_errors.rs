@@ -0,0 +1,124 @@
+use crate::{
+    config::ConfigError,
+    locale::{tr, tr_fmt, Msg},
+    util::ExactWidth,
+};
+
+use super::*;
+
+/// Overlay that lists the config files that could not be parsed, together with their errors.
+pub struct ConfigErrorOverlay {
+    errors: Vec<ConfigError>,
+
+    /// Index of the selected file
+    selected: usize,
+}
+
+impl Draw for ConfigErrorOverlay {
+    fn draw(
+        &mut self,
+        stdout: &mut Stdout,
+        x_range: Range<u16>,
+        y_range: Range<u16>,
+    ) -> Result<()> {
+        let width = x_range.end.saturating_sub(x_range.start);
+        let box_width = width.saturating_sub(4).min(100);
+        let x_start = x_range.start + width.saturating_sub(box_width) / 2;
+        // Keep one line for the border
+        let y_end = y_range.end.saturating_sub(1);
+        let mut y = y_range.start + 1;
+
+        queue!(
+            stdout,
+            cursor::MoveTo(x_start, y),
+            PrintStyledContent(
+                format!(" {}", tr(Msg::ConfigErrors))
+                    .exact_width(box_width as usize)
+                    .bold()
+                    .red()
+                    .reverse()
+            ),
+        )?;
+        y += 1;
+        for (idx, error) in self.errors.iter().enumerate() {
+            if y >= y_end {
+                break;
+            }
+            let path = format!(" {}", error.path.display()).exact_width(box_width as usize);
+            if idx == self.selected {
+                queue!(
+                    stdout,
+                    cursor::MoveTo(x_start, y),
+                    PrintStyledContent(path.bold().negative())
+                )?;
+            } else {
+                queue!(
+                    stdout,
+                    cursor::MoveTo(x_start, y),
+                    PrintStyledContent(path.bold())
+                )?;
+            }
+            y += 1;
+            for line in error.message.lines() {
+                if y >= y_end {
+                    break;
+                }
+                queue!(
+                    stdout,
+                    cursor::MoveTo(x_start, y),
+                    Print(format!("   {line}").exact_width(box_width as usize))
+                )?;
+                y += 1;
+            }
+        }
+        queue!(
+            stdout,
+            cursor::MoveTo(x_start, y),
+            PrintStyledContent(
+                format!(" {}", tr(Msg::ConfigErrorsHint))
+                    .exact_width(box_width as usize)
+                    .red()
+                    .reverse()
+            ),
+        )?;
+        Ok(())
+    }
+}
+
+impl ConfigErrorOverlay {
+    pub fn new(errors: Vec<ConfigError>) -> Self {
+        ConfigErrorOverlay {
+            errors,
+            selected: 0,
+        }
+    }
+
+    pub fn up(&mut self) {
+        self.selected = self.selected.saturating_sub(1);
+    }
+
+    pub fn down(&mut self) {
+        if self.selected + 1 < self.errors.len() {
+            self.selected += 1;
+        }
+    }
+
+    /// Returns the path of the selected config file.
+    pub fn selected_path(&self) -> Option<PathBuf> {
+        self.errors.get(self.selected).map(|e| e.path.clone())
+    }
+
+    /// Parses the selected file again and removes it from the list, if it is valid now.
+    ///
+    /// Returns `true` if there are no errors left.
+    pub fn recheck_selected(&mut self) -> bool {
+        if let Some(error) = self.errors.get_mut(self.selected) {
+            if error.recheck() {
+                let error = self.errors.remove(self.selected);
+                info!("{}", tr_fmt(Msg::ConfigValid, &[&error.path.display()]));
+                self.selected = self.selected.min(self.errors.len().saturating_sub(1));
+            }
+        }
+        self.errors.is_empty()
+    }
+}
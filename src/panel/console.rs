@@ -1,7 +1,7 @@
 use patricia_tree::{PatriciaMap, PatriciaSet};
 
 use super::*;
-use crate::content::dir_content;
+use crate::{content::dir_content, history::History, vfs::RemoteUrl};
 
 #[derive(Default)]
 pub struct DirConsole {
@@ -33,7 +33,7 @@ impl Draw for DirConsole {
         let path_len = path.len() as u16;
 
         let text_len = path_len + self.input.len() as u16;
-        let offset = if text_len < (width / 2).into() {
+        let offset = if text_len < (width / 2) {
             width / 4
         } else if text_len < width {
             ((width - text_len).saturating_sub(1) / 2) as u16
@@ -106,7 +106,7 @@ impl DirConsole {
         let path = panel.path().to_path_buf();
         let mut recommendations = PatriciaSet::new();
         for item in panel.elements() {
-            if item.path().is_dir() && (panel.show_hidden() || !item.is_hidden()) {
+            if item.path().is_dir() && panel.visibility().shows(item) {
                 recommendations.insert(item.name());
             }
         }
@@ -121,6 +121,38 @@ impl DirConsole {
         }
     }
 
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Changes to the previous (`older`) or next directory of the history.
+    ///
+    /// Directories that do not exist anymore are skipped.
+    pub fn recall(&mut self, history: &mut History, older: bool) -> Option<PathBuf> {
+        let current = self.path.to_string_lossy().to_string();
+        loop {
+            let entry = if older {
+                history.prev(&current)
+            } else {
+                history.next()
+            };
+            let path = PathBuf::from(entry?);
+            if path.is_dir() {
+                self.change_dir(path.clone());
+                return Some(path);
+            }
+        }
+    }
+
+    /// Remote location that was typed (like `sftp://host/path`) - it is opened with Enter.
+    pub fn url(&self) -> Option<RemoteUrl> {
+        RemoteUrl::parse(&self.input)
+    }
+
+    fn is_url(&self) -> bool {
+        self.input.starts_with("sftp:")
+    }
+
     pub fn joined_input(&self) -> PathBuf {
         self.path.join(&self.input)
     }
@@ -175,6 +207,18 @@ impl DirConsole {
     }
 
     pub fn insert(&mut self, character: char) -> Option<PathBuf> {
+        // URLs are typed verbatim, including their slashes
+        self.input.push(character);
+        if self.is_url() {
+            self.tmp_input = self.input.clone();
+            return None;
+        }
+        self.input.pop();
+        // Absolute paths, "~" and environment variables (like "$HOME/") start from their own directory
+        if let Some(path) = self.start_of_path(character) {
+            self.change_dir(path.clone());
+            return Some(path);
+        }
         // If we entered "..", we want to go up by one directory
         if self.input == ".." {
             self.clear();
@@ -232,6 +276,21 @@ impl DirConsole {
         }
     }
 
+    /// Directory that the input jumps to, if the character starts or completes a path of its own.
+    fn start_of_path(&self, character: char) -> Option<PathBuf> {
+        let path = match character {
+            '/' if self.input.is_empty() => Some(PathBuf::from("/")),
+            '~' if self.input.is_empty() => std::env::var_os("HOME").map(PathBuf::from),
+            '/' => {
+                let name = self.input.strip_prefix('$')?;
+                let name = name.trim_start_matches('{').trim_end_matches('}');
+                std::env::var_os(name).map(PathBuf::from)
+            }
+            _ => None,
+        };
+        path.filter(|path| path.is_absolute() && path.is_dir())
+    }
+
     pub fn tab(&mut self) -> Option<PathBuf> {
         self.input = self.recommendation();
         self.rec_idx = self.rec_idx.saturating_add(1);
@@ -302,7 +361,11 @@ impl DirConsole {
     }
 
     pub fn del(&mut self) -> Option<&Path> {
-        if self.input.is_empty() {
+        if self.is_url() {
+            self.input.pop();
+            self.tmp_input = self.input.clone();
+            None
+        } else if self.input.is_empty() {
             if let Some(parent) = self.path.parent().map(|p| p.to_path_buf()) {
                 self.change_dir(parent);
                 Some(self.path.as_path())
@@ -505,3 +568,47 @@ impl SearchConsole {
         }
     }
 }
+
+#[test]
+fn test_console_recall() {
+    let dir = tempfile::tempdir().unwrap();
+    let (first, gone) = (dir.path().join("first"), dir.path().join("gone"));
+    std::fs::create_dir(&first).unwrap();
+    let mut history = History::default();
+    history.push(&first.to_string_lossy());
+    history.push(&gone.to_string_lossy());
+
+    let mut console = DirConsole::default();
+    console.change_dir(dir.path().to_path_buf());
+    assert_eq!(console.recall(&mut history, true), Some(first.clone()));
+    assert_eq!(console.path(), first);
+    assert_eq!(console.recall(&mut history, true), None);
+    // Going forward skips the missing directory and returns to the start
+    assert_eq!(
+        console.recall(&mut history, false),
+        Some(dir.path().to_path_buf())
+    );
+}
+
+#[test]
+fn test_console_paths() {
+    // The environment is only read - other tests run in parallel
+    let home = PathBuf::from(std::env::var_os("HOME").expect("HOME is not set"));
+    let dir = tempfile::tempdir().unwrap();
+    let mut console = DirConsole::default();
+    console.change_dir(dir.path().to_path_buf());
+    assert_eq!(console.insert('/'), Some(PathBuf::from("/")));
+    for c in "${HOME}".chars() {
+        console.insert(c);
+    }
+    assert_eq!(console.insert('/'), Some(home.clone()));
+    assert_eq!(console.path(), home);
+    for c in "sftp://host/srv".chars() {
+        console.insert(c);
+    }
+    assert_eq!(
+        console.url().map(|url| url.path),
+        Some(PathBuf::from("/srv"))
+    );
+    assert_eq!(console.path(), home);
+}
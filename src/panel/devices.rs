@@ -0,0 +1,101 @@
+use crate::{
+    devices::Device,
+    locale::{tr, Msg},
+    util::{file_size_str, ExactWidth},
+};
+
+use super::*;
+
+/// Full-screen list of the block devices and partitions, which can be mounted, unmounted and ejected.
+pub struct DeviceOverlay {
+    devices: Vec<Device>,
+    selected: usize,
+}
+
+impl Draw for DeviceOverlay {
+    fn draw(
+        &mut self,
+        stdout: &mut Stdout,
+        x_range: Range<u16>,
+        y_range: Range<u16>,
+    ) -> Result<()> {
+        let width = x_range.end.saturating_sub(x_range.start) as usize;
+        let height = y_range.end.saturating_sub(y_range.start) as usize;
+        // One line for the header
+        let n_rows = height.saturating_sub(1);
+
+        let header = format!(" {}  {}", tr(Msg::Devices), tr(Msg::DevicesKeys));
+        queue!(
+            stdout,
+            cursor::MoveTo(x_range.start, y_range.start),
+            PrintStyledContent(header.exact_width(width).bold().dark_green().reverse()),
+        )?;
+
+        // Keep the selection visible
+        let scroll = self.selected.saturating_sub(n_rows.saturating_sub(1));
+        for row in 0..n_rows {
+            let y = y_range.start + 1 + row as u16;
+            queue!(stdout, cursor::MoveTo(x_range.start, y))?;
+            let Some(device) = self.devices.get(scroll + row) else {
+                queue!(stdout, Print(" ".repeat(width)))?;
+                continue;
+            };
+            let mount_point = device
+                .mount_points
+                .first()
+                .map(|path| format!("-> {}", path.display()))
+                .unwrap_or_default();
+            // The size of MTP devices is unknown
+            let size = match device.size {
+                0 => String::new(),
+                size => file_size_str(size),
+            };
+            let line = format!(
+                " {:<14} {:>10}  {:<16} {:<24} {mount_point}",
+                device.device.display(),
+                size,
+                device.label,
+                device.model,
+            )
+            .exact_width(width);
+            if scroll + row == self.selected {
+                queue!(stdout, PrintStyledContent(line.bold().negative()))?;
+            } else if !device.mount_points.is_empty() {
+                queue!(stdout, PrintStyledContent(line.dark_green().bold()))?;
+            } else {
+                queue!(stdout, Print(line))?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl DeviceOverlay {
+    pub fn new(devices: Vec<Device>) -> Self {
+        DeviceOverlay {
+            devices,
+            selected: 0,
+        }
+    }
+
+    /// Replaces the list (e.g. after a device was mounted), keeping the selected device selected.
+    pub fn update(&mut self, devices: Vec<Device>) {
+        let selected = self.selected().map(|d| d.device.clone());
+        self.devices = devices;
+        self.selected = selected
+            .and_then(|path| self.devices.iter().position(|d| d.device == path))
+            .unwrap_or(0);
+    }
+
+    pub fn selected(&self) -> Option<&Device> {
+        self.devices.get(self.selected)
+    }
+
+    pub fn up(&mut self) {
+        self.selected = self.selected.saturating_sub(1);
+    }
+
+    pub fn down(&mut self) {
+        self.selected = (self.selected + 1).min(self.devices.len().saturating_sub(1));
+    }
+}
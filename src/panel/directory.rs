@@ -1,4 +1,5 @@
 use std::{
+    ffi::CString,
     fs::read_dir,
     os::unix::prelude::MetadataExt,
     slice::{Iter, IterMut},
@@ -6,15 +7,116 @@ use std::{
 };
 
 use crossterm::style::{ContentStyle, StyledContent};
+use globset::GlobSet;
+use once_cell::sync::OnceCell;
 use unix_mode::is_allowed;
 
 use crate::{
-    content::dir_content,
+    config::SortConfig,
+    content::{dir_content, ignored_tracked, mark_ignored},
+    git::{GitStatus, GitStatusMap},
+    locale::{tr, Msg},
     symbols::SymbolEngine,
-    util::{file_size_str, ExactWidth},
+    theme::ThemeEngine,
+    trash::{TrashEntry, TrashListing},
+    util::{file_size_str, format_time, ExactWidth},
 };
 
 use super::*;
+
+/// Additional patterns for hidden files from the config (besides dotfiles).
+static HIDDEN_PATTERNS: OnceCell<GlobSet> = OnceCell::new();
+
+/// Hides all files whose name matches one of the globs - must be called before reading directories.
+pub fn init_hidden_patterns(globs: GlobSet) {
+    if HIDDEN_PATTERNS.set(globs).is_err() {
+        error!("Hidden patterns were already initialized.");
+    }
+}
+
+/// Sort order from the config.
+static SORT: OnceCell<SortConfig> = OnceCell::new();
+
+/// Sets the sort order - must be called before reading directories.
+pub fn init_sorting(config: SortConfig) {
+    if config.locale {
+        // Rust programs start with the "C" locale - use the one of the environment for collation
+        unsafe { libc::setlocale(libc::LC_COLLATE, c"".as_ptr()) };
+    }
+    if SORT.set(config).is_err() {
+        error!("Sorting was already initialized.");
+    }
+}
+
+/// Part of a name, for sorting.
+///
+/// Numbers come before text, like digits come before letters.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+enum SortChunk {
+    /// Number of digits (without leading zeros) and the digits - so longer numbers are larger
+    Number(usize, String),
+    /// Collation key of the lowercase text
+    Text(Vec<u8>),
+}
+
+/// Sort order from the config, or natural sorting without the locale before it is initialized.
+fn sort_config() -> SortConfig {
+    SORT.get().copied().unwrap_or(SortConfig {
+        natural: true,
+        locale: false,
+    })
+}
+
+/// Key that sorts the names case-insensitively - with numbers by their value and by the locale,
+/// if the config says so.
+fn sort_key(lowercase: &str) -> Vec<SortChunk> {
+    let config = sort_config();
+    let text = |text: &str| {
+        SortChunk::Text(match config.locale {
+            true => collation_key(text),
+            false => text.as_bytes().to_vec(),
+        })
+    };
+    if !config.natural {
+        return vec![text(lowercase)];
+    }
+    let mut chunks = Vec::new();
+    let mut rest = lowercase;
+    while let Some(first) = rest.chars().next() {
+        let is_digit = first.is_ascii_digit();
+        let end = rest
+            .find(|c: char| c.is_ascii_digit() != is_digit)
+            .unwrap_or(rest.len());
+        let (chunk, tail) = rest.split_at(end);
+        if is_digit {
+            let digits = chunk.trim_start_matches('0');
+            chunks.push(SortChunk::Number(digits.len(), digits.to_string()));
+        } else {
+            chunks.push(text(chunk));
+        }
+        rest = tail;
+    }
+    chunks
+}
+
+/// Transforms the text with `strxfrm`, so comparing the bytes follows the collation of the locale.
+fn collation_key(text: &str) -> Vec<u8> {
+    let Ok(source) = CString::new(text) else {
+        return text.as_bytes().to_vec();
+    };
+    let len = unsafe { libc::strxfrm(std::ptr::null_mut(), source.as_ptr(), 0) };
+    let mut key = vec![0u8; len + 1];
+    unsafe {
+        libc::strxfrm(
+            key.as_mut_ptr() as *mut libc::c_char,
+            source.as_ptr(),
+            key.len(),
+        )
+    };
+    key.truncate(len);
+    key
+}
+
 /// An element of a directory.
 ///
 /// Shorthand for saving a path together whith what we want to display.
@@ -42,6 +144,9 @@ pub struct DirElem {
     /// True if element is a hidden file or directory.
     is_hidden: bool,
 
+    /// True if element is ignored by git (e.g. a build artifact).
+    is_ignored: bool,
+
     /// True if the element is marked.
     ///
     /// Users can mark a selected item to perform operations on them.
@@ -49,6 +154,18 @@ pub struct DirElem {
 
     /// Weather or not we have calculated all values for that panel
     is_normalized: bool,
+
+    /// Status of the element, if it lives inside a git repository and is not clean.
+    git_status: Option<GitStatus>,
+
+    /// Original location and deletion date, if the element lives inside the trash.
+    trash_entry: Option<TrashEntry>,
+
+    /// Target of the element, if it is a symlink
+    link_target: Option<PathBuf>,
+
+    /// True if the element is a symlink whose target does not exist.
+    is_broken_link: bool,
 }
 
 impl DirElem {
@@ -68,39 +185,112 @@ impl DirElem {
         self.is_hidden
     }
 
-    pub fn is_marked(&self) -> bool {
-        self.is_marked
+    pub fn is_ignored(&self) -> bool {
+        self.is_ignored
     }
 
-    pub fn unmark(&mut self) {
-        self.is_marked = false;
+    pub fn set_ignored(&mut self, is_ignored: bool) {
+        self.is_ignored = is_ignored;
+    }
+
+    pub fn is_marked(&self) -> bool {
+        self.is_marked
     }
 
     /// Creates a [`PrintStyledContent`] from the `DirElem` itself.
     ///
     /// If the element has not been normalized yet, we do so before we create the styled content.
     pub fn print_styled(&mut self, selected: bool, max_len: u16) -> PrintStyledContent<String> {
+        let (style, string, _) = self.styled(selected, max_len);
+        PrintStyledContent(StyledContent::new(style, string))
+    }
+
+    /// Like [`DirElem::print_styled`], but highlights the first match of the (lowercase) pattern in the name.
+    pub fn print_highlighted(
+        &mut self,
+        selected: bool,
+        max_len: u16,
+        pattern: &str,
+    ) -> Vec<PrintStyledContent<String>> {
+        let (style, string, name) = self.styled(selected, max_len);
+        let range = match_range(&self.name, pattern)
+            .map(|m| name.start + m.start..(name.start + m.end).min(name.end))
+            .filter(|m| !m.is_empty());
+        let Some(range) = range else {
+            return vec![PrintStyledContent(StyledContent::new(style, string))];
+        };
+        let chars: Vec<char> = string.chars().collect();
+        let part = |range: Range<usize>| chars[range].iter().collect::<String>();
+        vec![
+            PrintStyledContent(StyledContent::new(style, part(0..range.start))),
+            PrintStyledContent(StyledContent::new(
+                style.red().bold().underlined(),
+                part(range.clone()),
+            )),
+            PrintStyledContent(StyledContent::new(style, part(range.end..chars.len()))),
+        ]
+    }
+
+    /// Style and text of the element, and the range of characters that shows its name.
+    fn styled(&mut self, selected: bool, max_len: u16) -> (ContentStyle, String, Range<usize>) {
         // Only print normalized items
         self.normalize();
         // Prepare output
+        let suffix = match &self.trash_entry {
+            // Trashed items show where they came from and when they were deleted
+            Some(entry) => {
+                let original = entry
+                    .original
+                    .display()
+                    .to_string()
+                    .exact_width(usize::from(max_len) / 3);
+                format!(
+                    "{original} {} {}",
+                    entry.deleted.replace('T', " "),
+                    self.suffix
+                )
+            }
+            None => self.suffix.clone(),
+        };
         let name_len = usize::from(max_len)
-            .saturating_sub(self.suffix.len())
+            .saturating_sub(suffix.chars().count())
             .saturating_sub(6);
-        let name = self.name.exact_width(name_len);
+        let name = match &self.link_target {
+            Some(target) => format!("{} -> {}", self.name, target.display()),
+            None => self.name.clone(),
+        }
+        .exact_width(name_len);
+        let git = self.git_status.map(|s| s.marker()).unwrap_or(' ');
 
-        let string: String;
+        let prefix: String;
         let mut style = ContentStyle::new();
         if self.path.is_dir() {
             style = style.dark_green().bold();
-            string = format!(" \u{1F4C1}{name} {} ", self.suffix);
+            prefix = format!("{git}\u{1F4C1}");
         } else if self.is_executable {
             style = style.green().bold();
             let symbol = SymbolEngine::get_symbol(self.path());
-            string = format!(" {symbol} {name} {} ", self.suffix);
+            prefix = format!("{git}{symbol} ");
         } else {
             style = style.grey();
             let symbol = SymbolEngine::get_symbol(self.path());
-            string = format!(" {symbol} {name} {} ", self.suffix);
+            prefix = format!("{git}{symbol} ");
+        }
+        let name_start = prefix.chars().count();
+        let name_range = name_start..name_start + name.chars().count();
+        let string = format!("{prefix}{name} {suffix} ");
+        if let Some(file_style) = ThemeEngine::get_style(&self.name) {
+            style = file_style.apply(style);
+        }
+        if self.is_broken_link {
+            style = style.red();
+        }
+        match self.git_status {
+            Some(GitStatus::Untracked) => style = style.dark_magenta(),
+            Some(GitStatus::Staged) => style = style.cyan(),
+            Some(GitStatus::Modified) => style = style.yellow(),
+            Some(GitStatus::Conflict) => style = style.red(),
+            None => (),
         }
         if self.is_marked {
             style = style.dark_yellow();
@@ -108,7 +298,7 @@ impl DirElem {
         if selected {
             style = style.negative().bold();
         }
-        PrintStyledContent(StyledContent::new(style, string))
+        (style, string, name_range)
     }
 
     /// Normalizes the `DirElem` to make it viewable by the user.
@@ -130,6 +320,9 @@ impl DirElem {
         // Always use an absolute pathhere
         self.path.canonicalize().unwrap_or_default();
 
+        self.link_target = self.path.read_link().ok();
+        self.is_broken_link = self.link_target.is_some() && !self.path.exists();
+
         let (mode, size) = self
             .path
             .metadata()
@@ -163,7 +356,12 @@ impl<P: AsRef<Path>> From<P> for DirElem {
             .unwrap_or_default();
 
         let lowercase = name.to_lowercase();
-        let is_hidden = name.starts_with('.') || name.starts_with("__") || name.ends_with(".swp");
+        let is_hidden = name.starts_with('.')
+            || name.starts_with("__")
+            || name.ends_with(".swp")
+            || HIDDEN_PATTERNS
+                .get()
+                .is_some_and(|globs| globs.is_match(&name));
 
         // NOTE: We don't fully create the DirElem here with all of its information,
         // as this would take too much time.
@@ -177,10 +375,15 @@ impl<P: AsRef<Path>> From<P> for DirElem {
             lowercase,
             path,
             is_hidden,
+            is_ignored: false,
             suffix,
             is_executable,
             is_marked: false,
             is_normalized: false,
+            git_status: None,
+            trash_entry: None,
+            link_target: None,
+            is_broken_link: false,
         }
     }
 }
@@ -195,20 +398,59 @@ impl PartialOrd for DirElem {
     fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
         if self.path.is_dir() {
             if other.path.is_dir() {
-                return self
-                    .name()
+                self.name()
                     .to_lowercase()
-                    .partial_cmp(&other.name().to_lowercase());
+                    .partial_cmp(&other.name().to_lowercase())
             } else {
                 Some(Ordering::Less)
             }
         } else if other.path.is_dir() {
             Some(Ordering::Greater)
         } else {
-            return self
-                .name()
+            self.name()
                 .to_lowercase()
-                .partial_cmp(&other.name().to_lowercase());
+                .partial_cmp(&other.name().to_lowercase())
+        }
+    }
+}
+
+/// Which elements of a directory are displayed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Visibility {
+    /// Hide dotfiles (and other hidden files)
+    #[default]
+    HideDotfiles,
+    /// Show everything
+    ShowAll,
+    /// Hide dotfiles and everything that is ignored by git
+    HideIgnored,
+}
+
+impl Visibility {
+    /// Returns `true` if the element is displayed.
+    pub fn shows(&self, elem: &DirElem) -> bool {
+        match self {
+            Visibility::HideDotfiles => !elem.is_hidden,
+            Visibility::ShowAll => true,
+            Visibility::HideIgnored => !elem.is_hidden && !elem.is_ignored,
+        }
+    }
+
+    /// Short description for the view indicator of the panel.
+    pub fn label(&self) -> &'static str {
+        match self {
+            Visibility::HideDotfiles => "dotfiles hidden",
+            Visibility::ShowAll => "all shown",
+            Visibility::HideIgnored => "ignored hidden",
+        }
+    }
+
+    /// Returns the next state when cycling through the visibility states.
+    pub fn next(self) -> Self {
+        match self {
+            Visibility::HideDotfiles => Visibility::ShowAll,
+            Visibility::ShowAll => Visibility::HideIgnored,
+            Visibility::HideIgnored => Visibility::HideDotfiles,
         }
     }
 }
@@ -220,6 +462,8 @@ pub struct DirPanel {
 
     /// Non-hidden elements (saved by their index)
     ///
+    /// Depends on the visibility - it is re-calculated, if the visibility changes.
+    ///
     /// NOTE: The elements vector *must not change* over the lifetime of the panel.
     /// Otherwise the indizes in this vector would be invalid
     non_hidden: Vec<usize>,
@@ -227,6 +471,12 @@ pub struct DirPanel {
     /// Active search term
     search: Option<String>,
 
+    /// Selection before the search started - restored, if the search is aborted
+    search_origin: Option<usize>,
+
+    /// Pattern of the last finished search, which is highlighted in the marked matches
+    highlight: Option<String>,
+
     /// Selected element
     selected_idx: usize,
 
@@ -242,8 +492,29 @@ pub struct DirPanel {
     /// Weather or not the panel is still loading some data
     loading: bool,
 
-    /// Weather or not to show hidden files
-    show_hidden: bool,
+    /// The directory is still being read - the panel only contains the entries read so far
+    partial: bool,
+
+    /// Which files are shown
+    visibility: Visibility,
+
+    /// Generation of the [`Marks`] registry that was last applied to the elements.
+    marks_generation: u64,
+
+    /// Scroll offset of the last drawn frame
+    scroll: usize,
+
+    /// Number of rows of the last drawn frame
+    view_height: usize,
+
+    /// Generation of the [`GitStatusMap`] that was last applied to the elements.
+    git_generation: u64,
+
+    /// Generation of the [`TrashListing`] that was last applied to the elements.
+    trash_generation: u64,
+
+    /// Whether the gitignore status of the elements was computed (see [`Visibility::HideIgnored`])
+    ignored_known: bool,
 }
 
 impl Draw for DirPanel {
@@ -258,7 +529,7 @@ impl Draw for DirPanel {
 
         // Calculate page-scroll
         let h = (height.saturating_add(1)) as usize / 2;
-        let bot = if self.show_hidden {
+        let bot = if self.show_all() {
             self.elements.len().min(self.selected_idx.saturating_add(h))
         } else {
             self.non_hidden
@@ -272,37 +543,36 @@ impl Draw for DirPanel {
             bot.saturating_sub(height as usize)
         };
 
+        let total = if self.show_all() {
+            self.elements.len()
+        } else {
+            self.non_hidden.len()
+        };
+        self.scroll = scroll;
+        self.view_height = height as usize;
+
         // Then print new buffer
         let mut y_offset = 0_u16;
 
         if let Some(pattern) = &self.search {
-            for entry in self
+            for (idx, entry) in self
                 .elements
                 .iter_mut()
-                .filter(|elem| self.show_hidden || !elem.is_hidden)
-                .filter(|elem| elem.name_lowercase().contains(pattern))
+                .enumerate()
+                .filter(|(_, elem)| self.visibility.shows(elem))
+                .filter(|(_, elem)| elem.name_lowercase().contains(pattern))
             {
                 let y = y_range.start + y_offset;
                 if y > height {
                     break;
                 }
-                if let Some(offset) = entry.name_lowercase().find(pattern) {
-                    queue!(
-                        stdout,
-                        cursor::MoveTo(x_range.start, y),
-                        PrintStyledContent("│".dark_green().bold()),
-                        entry.print_styled(false, width),
-                    )?;
-                    let pattern_x = x_range.start + 4 + offset as u16;
-                    if pattern_x <= width {
-                        queue!(
-                            stdout,
-                            cursor::MoveTo(pattern_x, y),
-                            PrintStyledContent(pattern.clone().red().bold())
-                        )?;
-                    }
-                } else {
-                    continue;
+                queue!(
+                    stdout,
+                    cursor::MoveTo(x_range.start, y),
+                    PrintStyledContent("│".dark_green().bold()),
+                )?;
+                for part in entry.print_highlighted(self.selected_idx == idx, width, pattern) {
+                    queue!(stdout, part)?;
                 }
                 y_offset += 1;
             }
@@ -312,7 +582,7 @@ impl Draw for DirPanel {
                     cursor::MoveTo(x_range.start, y_range.start),
                     PrintStyledContent("│".dark_green().bold()),
                     PrintStyledContent(
-                        " (no match)"
+                        format!(" {}", tr(Msg::NoMatch))
                             .exact_width(width.saturating_sub(2) as usize)
                             .red()
                             .italic()
@@ -326,7 +596,7 @@ impl Draw for DirPanel {
                 .elements
                 .iter_mut()
                 .enumerate()
-                .filter(|(_, elem)| self.show_hidden || !elem.is_hidden)
+                .filter(|(_, elem)| self.visibility.shows(elem))
                 .skip(scroll)
                 .take(height as usize)
             {
@@ -335,8 +605,18 @@ impl Draw for DirPanel {
                     stdout,
                     cursor::MoveTo(x_range.start, y),
                     PrintStyledContent("│".dark_green().bold()),
-                    entry.print_styled(self.selected_idx == idx, width),
                 )?;
+                // Matches of the last search stay highlighted while they are marked
+                match &self.highlight {
+                    Some(pattern) if entry.is_marked() => {
+                        for part in
+                            entry.print_highlighted(self.selected_idx == idx, width, pattern)
+                        {
+                            queue!(stdout, part)?;
+                        }
+                    }
+                    _ => queue!(stdout, entry.print_styled(self.selected_idx == idx, width))?,
+                }
                 y_offset += 1;
             }
         }
@@ -352,6 +632,30 @@ impl Draw for DirPanel {
             }
         }
 
+        // Draw a scrollbar, if not all elements fit on the screen
+        if self.search.is_none() && total > height as usize && width > 4 {
+            let height = height as usize;
+            let thumb_len = (height * height / total).max(1);
+            let thumb_start = (scroll * height / total).min(height - thumb_len);
+            let x = x_range.end.saturating_sub(1);
+            for row in 0..height {
+                let y = y_range.start + row as u16;
+                if (thumb_start..thumb_start + thumb_len).contains(&row) {
+                    queue!(
+                        stdout,
+                        cursor::MoveTo(x, y),
+                        PrintStyledContent("▐".dark_green())
+                    )?;
+                } else {
+                    queue!(
+                        stdout,
+                        cursor::MoveTo(x, y),
+                        PrintStyledContent("▕".dark_grey())
+                    )?;
+                }
+            }
+        }
+
         // Check if we are loading or not
         if self.loading {
             queue!(
@@ -388,7 +692,7 @@ impl PanelContent for DirPanel {
 
     fn update_content(&mut self, mut content: Self) {
         // Keep "hidden" state
-        content.show_hidden = self.show_hidden;
+        content.set_visibility(self.visibility);
         // If the content is for the same directory
         if content.path == self.path {
             // Set the selection accordingly
@@ -418,8 +722,7 @@ impl BasePanel for DirPanel {
 impl DirPanel {
     pub fn new(mut elements: Vec<DirElem>, path: PathBuf) -> Self {
         // Sort the elements before you use them
-        elements.sort_by_cached_key(|a| a.name_lowercase().clone());
-        elements.sort_by_cached_key(|a| !a.path().is_dir());
+        elements.sort_by_cached_key(|a| (!a.path().is_dir(), sort_key(a.name_lowercase())));
         // Normalize the first elements, so the first drawing is still really quick
         elements.iter_mut().take(128).for_each(|e| e.normalize());
 
@@ -444,50 +747,246 @@ impl DirPanel {
             selected_idx: selected,
             non_hidden_idx: 0,
             search: None,
+            search_origin: None,
+            highlight: None,
             path,
             modified,
             loading: false,
-            show_hidden: false,
+            partial: false,
+            visibility: Visibility::default(),
+            marks_generation: 0,
+            scroll: 0,
+            view_height: 0,
+            git_generation: 0,
+            trash_generation: 0,
+            ignored_known: ignored_tracked(),
         }
     }
 
+    /// Creates a panel from the entries of a directory that is still being read.
+    pub fn partial(elements: Vec<DirElem>, path: PathBuf) -> Self {
+        DirPanel {
+            partial: true,
+            ignored_known: false,
+            ..DirPanel::new(elements, path)
+        }
+    }
+
+    pub fn is_partial(&self) -> bool {
+        self.partial
+    }
+
+    /// Describes how the panel is sorted and filtered, so no view state is hidden from the user.
+    ///
+    /// Directories always come first, the names are sorted ascending (`name↑`),
+    /// with numbers by their value (`natural`) and by the collation of the locale (`locale`).
+    pub fn view_indicator(&self) -> String {
+        let config = sort_config();
+        let mut indicator = "name\u{2191}".to_string();
+        if config.natural {
+            indicator.push_str(" natural");
+        }
+        if config.locale {
+            indicator.push_str(" locale");
+        }
+        indicator.push_str(&format!("  {}", self.visibility.label()));
+        if let Some(pattern) = &self.search {
+            indicator.push_str(&format!("  filter: {pattern}"));
+        }
+        if self.partial {
+            indicator.push_str(&format!("  loading ({})", self.elements.len()));
+        }
+        indicator
+    }
+
+    /// Updates the search term and selects the first match while typing.
+    ///
+    /// Matches are searched from the selection before the search started, wrapping around at the end.
     pub fn update_search(&mut self, pattern: String) {
-        self.search = Some(pattern.to_lowercase());
+        let pattern = pattern.to_lowercase();
+        let origin = *self.search_origin.get_or_insert(self.selected_idx);
+        let visible = |elem: &&DirElem| self.visibility.shows(elem);
+        let first_match = self
+            .elements
+            .iter()
+            .enumerate()
+            .skip(origin)
+            .chain(self.elements.iter().enumerate().take(origin))
+            .filter(|(_, elem)| visible(elem))
+            .find(|(_, elem)| elem.name_lowercase().contains(&pattern))
+            .map(|(idx, _)| idx);
+        self.selected_idx = first_match.unwrap_or(origin);
+        if !self.show_all() {
+            self.set_non_hidden_idx();
+        }
+        self.search = Some(pattern);
     }
 
-    /// Mark all items that contain the search pattern and clear the search afterwards.
-    pub fn finish_search(&mut self, pattern: &str) {
-        let pat = pattern.to_lowercase();
-        for elem in self.elements.iter_mut() {
-            if elem.name_lowercase().contains(&pat) {
-                elem.is_marked = true;
-            } else {
-                elem.is_marked = false;
+    /// Selects the next (or previous) match of the active search, wrapping around at the end.
+    pub fn cycle_search(&mut self, forward: bool) {
+        let Some(pattern) = &self.search else {
+            return;
+        };
+        let len = self.elements.len();
+        let next = (1..len)
+            .map(|offset| {
+                if forward {
+                    (self.selected_idx + offset) % len
+                } else {
+                    (self.selected_idx + len - offset) % len
+                }
+            })
+            .find(|idx| {
+                let elem = &self.elements[*idx];
+                self.visibility.shows(elem) && elem.name_lowercase().contains(pattern.as_str())
+            });
+        if let Some(idx) = next {
+            self.selected_idx = idx;
+            if !self.show_all() {
+                self.set_non_hidden_idx();
             }
         }
+    }
+
+    /// Returns all items that contain the search pattern and clears the search afterwards.
+    ///
+    /// The selection stays on the match that was selected while typing.
+    pub fn finish_search(&mut self, pattern: &str) -> Vec<PathBuf> {
+        let pat = pattern.to_lowercase();
         self.search = None;
+        self.search_origin = None;
+        self.highlight = (!pat.is_empty()).then(|| pat.clone());
+        self.elements
+            .iter()
+            .filter(|elem| elem.name_lowercase().contains(&pat))
+            .map(|elem| elem.path.clone())
+            .collect()
     }
 
+    /// Aborts the search and restores the selection from before the search.
     pub fn clear_search(&mut self) {
         self.search = None;
+        self.highlight = None;
+        if let Some(origin) = self.search_origin.take() {
+            self.selected_idx = origin;
+            if !self.show_all() {
+                self.set_non_hidden_idx();
+            }
+        }
     }
 
-    pub fn elements(&self) -> Iter<DirElem> {
+    pub fn elements(&self) -> Iter<'_, DirElem> {
         self.elements.iter()
     }
 
-    pub fn elements_mut(&mut self) -> IterMut<DirElem> {
+    pub fn elements_mut(&mut self) -> IterMut<'_, DirElem> {
         self.elements.iter_mut()
     }
 
     pub fn show_hidden(&self) -> bool {
-        self.show_hidden
+        self.show_all()
+    }
+
+    pub fn visibility(&self) -> Visibility {
+        self.visibility
+    }
+
+    fn show_all(&self) -> bool {
+        self.visibility == Visibility::ShowAll
     }
 
-    pub fn mark_selected_item(&mut self) {
-        if let Some(elem) = self.elements.get_mut(self.selected_idx) {
-            elem.is_marked = !elem.is_marked;
+    /// Returns all elements that are currently visible,
+    /// respecting the hidden-files setting and an active search.
+    pub fn visible_elements(&self) -> impl Iterator<Item = &DirElem> {
+        self.elements
+            .iter()
+            .filter(|elem| self.visibility.shows(elem))
+            .filter(|elem| match &self.search {
+                Some(pattern) => elem.name_lowercase().contains(pattern),
+                None => true,
+            })
+    }
+
+    /// Renders the visible elements as a listing with one element per line.
+    ///
+    /// If `long` is set, the size and modification time are added as extra columns.
+    /// The columns are separated by tabs, or - if `csv` is set - by commas (including a header).
+    pub fn listing(&self, long: bool, csv: bool) -> String {
+        let mut out = String::new();
+        if csv {
+            out.push_str(if long {
+                "name,size,modified\n"
+            } else {
+                "name\n"
+            });
         }
+        for elem in self.visible_elements() {
+            let name = if csv {
+                csv_field(elem.name())
+            } else {
+                elem.name().clone()
+            };
+            out.push_str(&name);
+            if long {
+                let metadata = elem.path().metadata().ok();
+                let size = metadata.as_ref().map(|m| m.size()).unwrap_or_default();
+                let modified = metadata
+                    .and_then(|m| m.modified().ok())
+                    .map(format_time)
+                    .unwrap_or_default();
+                if csv {
+                    out.push_str(&format!(",{size},{modified}"));
+                } else {
+                    out.push_str(&format!("\t{}\t{modified}", file_size_str(size)));
+                }
+            }
+            out.push('\n');
+        }
+        out
+    }
+
+    /// Applies the marks of the registry to the elements of the panel.
+    ///
+    /// This is a no-op, if the registry has not changed since the last call.
+    pub fn sync_marks(&mut self, marks: &Marks) {
+        if self.marks_generation == marks.generation() {
+            return;
+        }
+        for elem in self.elements.iter_mut() {
+            elem.is_marked = !marks.is_empty() && marks.contains(&elem.path);
+        }
+        self.marks_generation = marks.generation();
+    }
+
+    /// Applies the git status to the elements of the panel.
+    ///
+    /// If the status belongs to another directory, all markers are removed.
+    pub fn sync_git_status(&mut self, status: &GitStatusMap) {
+        if self.git_generation == status.generation {
+            return;
+        }
+        let matches = status.dir == self.path;
+        for elem in self.elements.iter_mut() {
+            elem.git_status = if matches {
+                status.entries.get(&elem.path).copied()
+            } else {
+                None
+            };
+        }
+        self.git_generation = status.generation;
+    }
+
+    /// Applies the metadata of the trash to the elements of the panel.
+    ///
+    /// Elements that are not inside the trash are not affected.
+    pub fn sync_trash(&mut self, listing: &TrashListing) {
+        if self.trash_generation == listing.generation {
+            return;
+        }
+        for elem in self.elements.iter_mut() {
+            elem.trash_entry = listing.entries.get(&elem.path).cloned();
+        }
+        self.trash_generation = listing.generation;
     }
 
     /// Changes the selection to the given path.
@@ -502,15 +1001,59 @@ impl DirPanel {
             .elements
             .iter()
             .enumerate()
-            .filter(|(_, elem)| self.show_hidden || !elem.is_hidden)
+            .filter(|(_, elem)| self.visibility.shows(elem))
             .find(|(_, elem)| elem.path() == selection)
             .map(|(idx, _)| idx)
             .unwrap_or(self.selected_idx);
-        if !self.show_hidden {
+        if !self.show_all() {
             self.set_non_hidden_idx();
         }
     }
 
+    /// Returns the path of the element that was drawn in the given row of the panel.
+    pub fn path_at_row(&self, row: usize) -> Option<PathBuf> {
+        if row >= self.view_height {
+            return None;
+        }
+        self.elements
+            .iter()
+            .filter(|elem| self.visibility.shows(elem))
+            .nth(self.scroll + row)
+            .map(|elem| elem.path.clone())
+    }
+
+    /// Returns the paths of all visible elements from `first` to `last` (both inclusive),
+    /// in the order they are shown - no matter which of both comes first.
+    pub fn paths_between(&self, first: &Path, last: &Path) -> Vec<PathBuf> {
+        let mut inside = false;
+        let mut paths = Vec::new();
+        for elem in self.elements.iter().filter(|e| self.visibility.shows(e)) {
+            let is_bound = elem.path() == first || elem.path() == last;
+            if is_bound || inside {
+                paths.push(elem.path.clone());
+            }
+            if is_bound && (inside || first == last) {
+                break;
+            }
+            if is_bound {
+                inside = true;
+            }
+        }
+        paths
+    }
+
+    /// Returns the paths of up to `count` visible items, starting at the selection.
+    pub fn paths_from_selected(&self, count: usize) -> Vec<PathBuf> {
+        let Some(selected) = self.selected_path() else {
+            return Vec::new();
+        };
+        self.visible_elements()
+            .skip_while(|elem| elem.path() != selected)
+            .take(count)
+            .map(|elem| elem.path.clone())
+            .collect()
+    }
+
     /// Selects the next marked item
     pub fn select_next_marked(&mut self) {
         // Search from selected-idx to end
@@ -519,7 +1062,7 @@ impl DirPanel {
             .iter()
             .enumerate()
             .skip(self.selected_idx + 1)
-            .filter(|(_, elem)| self.show_hidden || !elem.is_hidden)
+            .filter(|(_, elem)| self.visibility.shows(elem))
             .find(|(_, elem)| elem.is_marked)
             .map(|(idx, _)| idx)
         {
@@ -530,12 +1073,12 @@ impl DirPanel {
                 .elements
                 .iter()
                 .enumerate()
-                .filter(|(_, elem)| self.show_hidden || !elem.is_hidden)
+                .filter(|(_, elem)| self.visibility.shows(elem))
                 .find(|(_, elem)| elem.is_marked)
                 .map(|(idx, _)| idx)
                 .unwrap_or(self.selected_idx);
         }
-        if !self.show_hidden {
+        if !self.show_all() {
             self.set_non_hidden_idx();
         }
     }
@@ -549,7 +1092,7 @@ impl DirPanel {
             .enumerate()
             .rev()
             .filter(|(idx, _)| idx < &self.selected_idx)
-            .filter(|(_, elem)| self.show_hidden || !elem.is_hidden)
+            .filter(|(_, elem)| self.visibility.shows(elem))
             .find(|(_, elem)| elem.is_marked)
             .map(|(idx, _)| idx)
         {
@@ -561,12 +1104,12 @@ impl DirPanel {
                 .iter()
                 .enumerate()
                 .rev()
-                .filter(|(_, elem)| self.show_hidden || !elem.is_hidden)
+                .filter(|(_, elem)| self.visibility.shows(elem))
                 .find(|(_, elem)| elem.is_marked)
                 .map(|(idx, _)| idx)
                 .unwrap_or(self.selected_idx);
         }
-        if !self.show_hidden {
+        if !self.show_all() {
             self.set_non_hidden_idx();
         }
     }
@@ -581,20 +1124,32 @@ impl DirPanel {
         }
     }
 
-    pub fn set_hidden(&mut self, show_hidden: bool) {
-        if self.show_hidden == show_hidden {
+    pub fn set_visibility(&mut self, visibility: Visibility) {
+        if self.visibility == visibility {
             // Nothing to do
             return;
         }
-        if self.show_hidden && !show_hidden {
-            // Currently we show hidden files, but we should stop that
+        self.visibility = visibility;
+        // Panels that were read while gitignored files were shown lack the status
+        if visibility == Visibility::HideIgnored && !self.ignored_known && !self.partial {
+            mark_ignored(&self.path, &mut self.elements);
+            self.ignored_known = true;
+        }
+        self.non_hidden = self
+            .elements
+            .iter()
+            .enumerate()
+            .filter(|(_, elem)| visibility.shows(elem))
+            .map(|(idx, _)| idx)
+            .collect();
+        if !self.show_all() {
+            // We hide some files now
             // -> non-hidden-idx needs to be updated to the value closest to selection
+            self.non_hidden_idx = 0;
             self.set_non_hidden_idx();
             // Update selection accordingly for the next time we toggle hidden files
             self.selected_idx = *self.non_hidden.get(self.non_hidden_idx).unwrap_or(&0);
         }
-        // Save value and change selection accordingly
-        self.show_hidden = show_hidden;
     }
 
     pub fn loading(path: PathBuf) -> Self {
@@ -604,10 +1159,19 @@ impl DirPanel {
             selected_idx: 0,
             non_hidden_idx: 0,
             search: None,
+            search_origin: None,
+            highlight: None,
             path,
             modified: SystemTime::now(),
             loading: true,
-            show_hidden: false,
+            partial: false,
+            visibility: Visibility::default(),
+            marks_generation: 0,
+            scroll: 0,
+            view_height: 0,
+            git_generation: 0,
+            trash_generation: 0,
+            ignored_known: true,
         }
     }
 
@@ -621,10 +1185,19 @@ impl DirPanel {
             selected_idx: 0,
             non_hidden_idx: 0,
             search: None,
+            search_origin: None,
+            highlight: None,
             modified: SystemTime::now(),
             path: "path-of-empty-panel".into(),
             loading: false,
-            show_hidden: false,
+            partial: false,
+            visibility: Visibility::default(),
+            marks_generation: 0,
+            scroll: 0,
+            view_height: 0,
+            git_generation: 0,
+            trash_generation: 0,
+            ignored_known: true,
         }
     }
 
@@ -633,7 +1206,7 @@ impl DirPanel {
     /// Returns true if the panel has changed and
     /// requires a redraw.
     pub fn up(&mut self, step: usize) -> bool {
-        if self.show_hidden {
+        if self.show_all() {
             if self.selected_idx == 0 {
                 return false;
             }
@@ -653,7 +1226,7 @@ impl DirPanel {
     /// Returns true if the panel has changed and
     /// requires a redraw.
     pub fn down(&mut self, step: usize) -> bool {
-        if self.show_hidden {
+        if self.show_all() {
             // If we are already at the end, do nothing and return
             if self.selected_idx.saturating_add(1) == self.elements.len() {
                 return false;
@@ -692,7 +1265,7 @@ impl DirPanel {
     /// Returns either the selected-idx or non-hidden-idx,
     /// depending on weather or not we display hidden files.
     pub fn index(&self) -> usize {
-        if self.show_hidden {
+        if self.show_all() {
             self.selected_idx
         } else {
             self.non_hidden_idx
@@ -713,12 +1286,140 @@ impl DirPanel {
         self.elements.get(self.selected_idx)
     }
 
+    /// Returns a vim-like indicator for the scroll position of the last drawn frame:
+    /// "All" if everything fits on the screen, "Top", "Bot" or the percentage otherwise.
+    pub fn scroll_indicator(&self) -> String {
+        let (_, total) = self.index_vs_total();
+        if total <= self.view_height {
+            "All".to_string()
+        } else if self.scroll == 0 {
+            "Top".to_string()
+        } else if self.scroll + self.view_height >= total {
+            "Bot".to_string()
+        } else {
+            format!("{}%", self.scroll * 100 / (total - self.view_height))
+        }
+    }
+
+    /// Returns the position (like [`DirPanel::index_vs_total`]) of the next item
+    /// whose name starts with the letter (case-insensitive).
+    ///
+    /// The search starts after the selection and wraps around,
+    /// so repeating it cycles through all matching items.
+    pub fn next_starting_with(&self, letter: char) -> Option<usize> {
+        let letter = letter.to_lowercase().to_string();
+        let names: Vec<&String> = self
+            .elements
+            .iter()
+            .filter(|elem| self.visibility.shows(elem))
+            .map(|elem| elem.name_lowercase())
+            .collect();
+        let (current, _) = self.index_vs_total();
+        (0..names.len())
+            .map(|offset| (current + offset) % names.len())
+            .find(|idx| names[*idx].starts_with(&letter))
+            .map(|idx| idx + 1)
+    }
+
     /// Returns the selected index (starting at 1) and the total number of items.
     pub fn index_vs_total(&self) -> (usize, usize) {
-        if self.show_hidden {
+        if self.show_all() {
             (self.selected_idx.saturating_add(1), self.elements.len())
         } else {
             (self.non_hidden_idx.saturating_add(1), self.non_hidden.len())
         }
     }
 }
+
+/// Range of characters of the first case-insensitive match of the (lowercase) pattern in the name.
+fn match_range(name: &str, pattern: &str) -> Option<Range<usize>> {
+    let name: Vec<char> = name.chars().collect();
+    let pattern: Vec<char> = pattern.chars().collect();
+    if pattern.is_empty() || pattern.len() > name.len() {
+        return None;
+    }
+    (0..=name.len() - pattern.len())
+        .find(|start| {
+            name[*start..]
+                .iter()
+                .zip(pattern.iter())
+                .all(|(c, p)| c.to_lowercase().eq(p.to_lowercase()))
+        })
+        .map(|start| start..start + pattern.len())
+}
+
+/// Quotes a field for a CSV file, if necessary.
+fn csv_field(field: &str) -> String {
+    if field.contains([',', '"', '\n']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+#[test]
+fn test_csv_field() {
+    assert_eq!(csv_field("plain.txt"), "plain.txt");
+    assert_eq!(csv_field("a,b.txt"), "\"a,b.txt\"");
+    assert_eq!(csv_field("say \"hi\""), "\"say \"\"hi\"\"\"");
+}
+
+#[test]
+fn test_progressive_search() {
+    let names = ["alpha", "beta", "gamma", "delta"];
+    let elements = names
+        .iter()
+        .map(|n| DirElem::from(format!("/nonexistent/{n}")))
+        .collect();
+    let mut panel = DirPanel::new(elements, PathBuf::from("/nonexistent"));
+    let selected = |panel: &DirPanel| panel.selected_path().map(|p| p.to_path_buf());
+
+    panel.update_search("ta".to_string());
+    assert_eq!(selected(&panel), Some(PathBuf::from("/nonexistent/beta")));
+    panel.update_search("del".to_string());
+    assert_eq!(selected(&panel), Some(PathBuf::from("/nonexistent/delta")));
+    assert_eq!(
+        panel.view_indicator(),
+        "name\u{2191} natural  dotfiles hidden  filter: del"
+    );
+    panel.update_search("a".to_string());
+    panel.cycle_search(true);
+    assert_eq!(selected(&panel), Some(PathBuf::from("/nonexistent/beta")));
+    panel.cycle_search(false);
+    panel.cycle_search(false);
+    assert_eq!(selected(&panel), Some(PathBuf::from("/nonexistent/gamma")));
+    panel.clear_search();
+    assert_eq!(selected(&panel), Some(PathBuf::from("/nonexistent/alpha")));
+}
+
+#[test]
+fn test_next_starting_with() {
+    let names = ["Apple", "banana", "avocado", "cherry"];
+    let elements = names
+        .iter()
+        .map(|n| DirElem::from(format!("/nonexistent/{n}")))
+        .collect();
+    // Sorted: apple, avocado, banana, cherry
+    let panel = DirPanel::new(elements, PathBuf::from("/nonexistent"));
+    assert_eq!(panel.next_starting_with('a'), Some(2));
+    assert_eq!(panel.next_starting_with('C'), Some(4));
+    assert_eq!(panel.next_starting_with('z'), None);
+}
+
+#[test]
+fn test_sort_key() {
+    let mut names = vec!["file10", "File2", "file1", "file02b", "notes", "2023"];
+    names.sort_by_cached_key(|name| sort_key(&name.to_lowercase()));
+    assert_eq!(
+        names,
+        ["2023", "file1", "File2", "file02b", "file10", "notes"]
+    );
+}
+
+#[test]
+fn test_match_range() {
+    assert_eq!(match_range("ReadMe.md", "adm"), Some(2..5));
+    assert_eq!(match_range("Ünïcode-Ärger", "är"), Some(8..10));
+    assert_eq!(match_range("alpha", "beta"), None);
+    assert_eq!(match_range("alpha", ""), None);
+}
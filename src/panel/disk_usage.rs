@@ -0,0 +1,240 @@
+use std::sync::atomic::AtomicBool;
+
+use crate::{
+    disk_usage::{DuEntry, DuUpdate},
+    locale::{tr, tr_fmt, Msg},
+    util::{file_size_str, ExactWidth},
+};
+
+use super::*;
+
+/// Width of the percentage bar
+const BAR_WIDTH: usize = 20;
+
+/// Full-screen overview of the disk usage below a directory (like `ncdu`).
+///
+/// The sizes are computed in the background, until then the progress of the scan is shown.
+pub struct DiskUsageOverlay {
+    root: PathBuf,
+
+    /// Result of the scan, `None` while the scan is running
+    tree: Option<DuEntry>,
+
+    /// Number of items and size scanned so far
+    progress: (u64, u64),
+
+    /// Child indices from the root to the shown directory
+    dir: Vec<usize>,
+
+    /// Index of the selection in the shown directory
+    selected: usize,
+
+    /// Set while the deletion of the selected item waits for its confirmation
+    confirm_delete: bool,
+
+    /// Stops the scan once the overlay is closed
+    cancel: Arc<AtomicBool>,
+}
+
+impl Draw for DiskUsageOverlay {
+    fn draw(
+        &mut self,
+        stdout: &mut Stdout,
+        x_range: Range<u16>,
+        y_range: Range<u16>,
+    ) -> Result<()> {
+        let width = x_range.end.saturating_sub(x_range.start) as usize;
+        let height = y_range.end.saturating_sub(y_range.start) as usize;
+        // One line for the header
+        let n_rows = height.saturating_sub(1);
+
+        let header = match (self.shown(), self.selected_entry()) {
+            (Some(_), Some(entry)) if self.confirm_delete => tr_fmt(
+                Msg::ConfirmDeleteItem,
+                &[&entry.name, &file_size_str(entry.size)],
+            ),
+            (Some(shown), _) => format!(
+                "{} {}  {}",
+                tr(Msg::DiskUsage),
+                self.shown_path().display(),
+                file_size_str(shown.size)
+            ),
+            (None, _) => format!("{} {}", tr(Msg::DiskUsage), self.root.display()),
+        };
+        let header = format!(" {header}").exact_width(width).bold();
+        let header = if self.confirm_delete {
+            header.red().reverse()
+        } else {
+            header.dark_green().reverse()
+        };
+        queue!(
+            stdout,
+            cursor::MoveTo(x_range.start, y_range.start),
+            PrintStyledContent(header),
+        )?;
+
+        let Some(shown) = self.shown() else {
+            let (items, size) = self.progress;
+            let line = tr_fmt(Msg::DiskUsageScanning, &[&items, &file_size_str(size)]);
+            for row in 0..n_rows {
+                let y = y_range.start + 1 + row as u16;
+                let line = if row == 0 { line.as_str() } else { "" };
+                queue!(
+                    stdout,
+                    cursor::MoveTo(x_range.start, y),
+                    Print(format!(" {line}").exact_width(width)),
+                )?;
+            }
+            return Ok(());
+        };
+
+        // Keep the selection visible
+        let scroll = self.selected.saturating_sub(n_rows.saturating_sub(1));
+        for row in 0..n_rows {
+            let y = y_range.start + 1 + row as u16;
+            queue!(stdout, cursor::MoveTo(x_range.start, y))?;
+            let Some(entry) = shown.children.get(scroll + row) else {
+                queue!(stdout, Print(" ".repeat(width)))?;
+                continue;
+            };
+            let ratio = if shown.size > 0 {
+                entry.size as f64 / shown.size as f64
+            } else {
+                0.0
+            };
+            let filled = (ratio * BAR_WIDTH as f64).round() as usize;
+            let name = if entry.is_dir {
+                format!("{}/", entry.name)
+            } else {
+                entry.name.clone()
+            };
+            let line = format!(
+                " {:>10} {:>5.1}% [{}{}] {name}",
+                file_size_str(entry.size),
+                ratio * 100.0,
+                "#".repeat(filled),
+                " ".repeat(BAR_WIDTH - filled)
+            )
+            .exact_width(width);
+            if scroll + row == self.selected {
+                queue!(stdout, PrintStyledContent(line.bold().negative()))?;
+            } else if entry.is_dir {
+                queue!(stdout, PrintStyledContent(line.dark_green().bold()))?;
+            } else {
+                queue!(stdout, Print(line))?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Drop for DiskUsageOverlay {
+    fn drop(&mut self) {
+        self.cancel
+            .store(true, std::sync::atomic::Ordering::Relaxed);
+    }
+}
+
+impl DiskUsageOverlay {
+    /// Creates the overlay - the scan must be started with the returned cancel flag.
+    pub fn new(root: PathBuf) -> (Self, Arc<AtomicBool>) {
+        let cancel = Arc::new(AtomicBool::new(false));
+        let overlay = DiskUsageOverlay {
+            root,
+            tree: None,
+            progress: (0, 0),
+            dir: Vec::new(),
+            selected: 0,
+            confirm_delete: false,
+            cancel: cancel.clone(),
+        };
+        (overlay, cancel)
+    }
+
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    pub fn update(&mut self, update: DuUpdate) {
+        match update {
+            DuUpdate::Progress { items, size } => self.progress = (items, size),
+            DuUpdate::Done(tree) => self.tree = Some(tree),
+        }
+    }
+
+    /// Directory whose content is shown.
+    fn shown(&self) -> Option<&DuEntry> {
+        self.tree.as_ref()?.get(&self.dir)
+    }
+
+    fn shown_path(&self) -> PathBuf {
+        let mut path = self.root.clone();
+        let mut entry = self.tree.as_ref();
+        for idx in self.dir.iter() {
+            entry = entry.and_then(|e| e.children.get(*idx));
+            if let Some(entry) = entry {
+                path.push(&entry.name);
+            }
+        }
+        path
+    }
+
+    fn selected_entry(&self) -> Option<&DuEntry> {
+        self.shown()?.children.get(self.selected)
+    }
+
+    /// Path of the selected item.
+    pub fn selected_path(&self) -> Option<PathBuf> {
+        let entry = self.selected_entry()?;
+        Some(self.shown_path().join(&entry.name))
+    }
+
+    pub fn up(&mut self) {
+        self.selected = self.selected.saturating_sub(1);
+    }
+
+    pub fn down(&mut self) {
+        let len = self.shown().map(|s| s.children.len()).unwrap_or(0);
+        self.selected = (self.selected + 1).min(len.saturating_sub(1));
+    }
+
+    /// Shows the content of the selected directory.
+    pub fn enter(&mut self) {
+        if self.selected_entry().is_some_and(|e| e.is_dir) {
+            self.dir.push(self.selected);
+            self.selected = 0;
+        }
+    }
+
+    /// Goes back to the parent directory (but not above the root).
+    pub fn leave(&mut self) {
+        if let Some(idx) = self.dir.pop() {
+            self.selected = idx;
+        }
+    }
+
+    pub fn is_confirming(&self) -> bool {
+        self.confirm_delete
+    }
+
+    /// Asks for the confirmation to delete the selected item.
+    pub fn request_delete(&mut self) {
+        self.confirm_delete = self.selected_entry().is_some();
+    }
+
+    pub fn cancel_delete(&mut self) {
+        self.confirm_delete = false;
+    }
+
+    /// Removes the (deleted) selected item from the overview.
+    pub fn remove_selected(&mut self) {
+        self.confirm_delete = false;
+        let mut indices = self.dir.clone();
+        indices.push(self.selected);
+        if let Some(tree) = self.tree.as_mut() {
+            tree.remove(&indices);
+        }
+        let len = self.shown().map(|s| s.children.len()).unwrap_or(0);
+        self.selected = self.selected.min(len.saturating_sub(1));
+    }
+}
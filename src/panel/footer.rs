@@ -0,0 +1,253 @@
+use std::{os::unix::prelude::MetadataExt, time::Instant};
+
+use users::{get_group_by_gid, get_user_by_uid};
+
+use crate::{
+    exif::ImageInfo,
+    opener::get_mime_type,
+    util::{file_size_str, format_time, free_space, text_stats},
+};
+
+use super::*;
+
+/// Text files up to this size show their line and word count in the footer.
+const TEXT_STATS_MAX_SIZE: u64 = 1024 * 1024;
+
+/// Metadata of an item, as it is shown in the footer.
+///
+/// It is read in the background, because `metadata()` can hang (e.g. on a dead NFS mount).
+#[derive(Debug, Clone)]
+pub struct FooterInfo {
+    pub permissions: String,
+    pub user: String,
+    pub group: String,
+    pub size: String,
+    pub modified: String,
+    pub mime: String,
+
+    /// Line and word count of small text files
+    pub text_stats: Option<(usize, usize)>,
+
+    /// Dimensions, camera and capture date of images
+    pub image: Option<ImageInfo>,
+
+    /// Target of a symlink, and whether the target exists
+    pub link: Option<(PathBuf, bool)>,
+
+    /// Free space of the filesystem
+    pub free: Option<u64>,
+
+    /// When the metadata was read
+    pub read_at: Instant,
+}
+
+impl FooterInfo {
+    pub fn read(path: &Path) -> Self {
+        let mut info = FooterInfo {
+            permissions: String::from("------------"),
+            user: String::new(),
+            group: String::new(),
+            size: String::new(),
+            modified: String::new(),
+            mime: String::new(),
+            text_stats: None,
+            image: None,
+            link: path.read_link().ok().map(|target| (target, path.exists())),
+            free: free_space(path),
+            read_at: Instant::now(),
+        };
+        let Ok(metadata) = path.metadata() else {
+            return info;
+        };
+        info.permissions = unix_mode::to_string(metadata.permissions().mode());
+        info.modified = metadata
+            .modified()
+            .map(format_time)
+            .unwrap_or_else(|_| String::from("cannot read timestamp"));
+        info.user = get_user_by_uid(metadata.uid())
+            .and_then(|u| u.name().to_str().map(String::from))
+            .unwrap_or_default();
+        info.group = get_group_by_gid(metadata.gid())
+            .and_then(|g| g.name().to_str().map(String::from))
+            .unwrap_or_default();
+        info.size = file_size_str(metadata.size());
+        info.mime = mime_guess::from_path(path)
+            .first_raw()
+            .unwrap_or_default()
+            .to_string();
+        let is_text = metadata.is_file()
+            && metadata.len() <= TEXT_STATS_MAX_SIZE
+            && get_mime_type(path).type_() == mime::TEXT;
+        info.text_stats = is_text.then(|| text_stats(path)).flatten();
+        let is_image = metadata.is_file() && get_mime_type(path).type_() == mime::IMAGE;
+        info.image = is_image.then(|| ImageInfo::read(path)).flatten();
+        info
+    }
+}
+
+/// Placeholders of a [`FooterFormat`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Field {
+    Permissions,
+    User,
+    Group,
+    Size,
+    Lines,
+    Words,
+    Modified,
+    Mime,
+    /// Dimensions, camera and capture date of an image
+    Image,
+    /// `-> target` of a symlink
+    Link,
+    /// Free space of the filesystem
+    Free,
+    /// Number of marked items
+    Marked,
+    /// Position of the selection (`n/m`)
+    Position,
+    /// Scroll indicator of the center panel (`Top`, `Bot`, `42%`)
+    Scroll,
+}
+
+impl Field {
+    const ALL: [(&'static str, Field); 14] = [
+        ("permissions", Field::Permissions),
+        ("user", Field::User),
+        ("group", Field::Group),
+        ("size", Field::Size),
+        ("lines", Field::Lines),
+        ("words", Field::Words),
+        ("modified", Field::Modified),
+        ("mime", Field::Mime),
+        ("image", Field::Image),
+        ("link", Field::Link),
+        ("free", Field::Free),
+        ("marked", Field::Marked),
+        ("position", Field::Position),
+        ("scroll", Field::Scroll),
+    ];
+
+    /// Value of the field for the footer metadata - `None` if the field does not depend on it.
+    pub fn of_info(&self, info: &FooterInfo) -> Option<String> {
+        let value = match self {
+            Field::Permissions => info.permissions.clone(),
+            Field::User => info.user.clone(),
+            Field::Group => info.group.clone(),
+            Field::Size => info.size.clone(),
+            Field::Lines => info
+                .text_stats
+                .map(|(lines, _)| format!("{lines}L"))
+                .unwrap_or_default(),
+            Field::Words => info
+                .text_stats
+                .map(|(_, words)| format!("{words}W"))
+                .unwrap_or_default(),
+            Field::Modified => info.modified.clone(),
+            Field::Mime => info.mime.clone(),
+            Field::Image => info
+                .image
+                .as_ref()
+                .map(ImageInfo::summary)
+                .unwrap_or_default(),
+            Field::Link => info
+                .link
+                .as_ref()
+                .map(|(target, _)| format!("-> {}", target.display()))
+                .unwrap_or_default(),
+            Field::Free => info.free.map(file_size_str).unwrap_or_default(),
+            Field::Marked | Field::Position | Field::Scroll => return None,
+        };
+        Some(value)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Part {
+    Text(String),
+    Field(Field),
+}
+
+/// Template of one side of the footer, e.g. `"{permissions}   {user} {size}"`.
+///
+/// Placeholders in braces are replaced by their value (see [`Field`]), `{{` and `}}` are literal braces.
+/// If a placeholder is empty, the whitespace after it is dropped as well.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FooterFormat {
+    parts: Vec<Part>,
+}
+
+impl FooterFormat {
+    pub fn parse(template: &str) -> std::result::Result<Self, String> {
+        let mut parts = Vec::new();
+        let mut text = String::new();
+        let mut chars = template.chars().peekable();
+        while let Some(c) = chars.next() {
+            match c {
+                '{' if chars.peek() == Some(&'{') => {
+                    chars.next();
+                    text.push('{');
+                }
+                '}' if chars.peek() == Some(&'}') => {
+                    chars.next();
+                    text.push('}');
+                }
+                '{' => {
+                    let name: String = chars.by_ref().take_while(|c| *c != '}').collect();
+                    let field = Field::ALL
+                        .iter()
+                        .find(|(n, _)| *n == name)
+                        .map(|(_, field)| *field)
+                        .ok_or_else(|| format!("unknown placeholder '{{{name}}}'"))?;
+                    if !text.is_empty() {
+                        parts.push(Part::Text(std::mem::take(&mut text)));
+                    }
+                    parts.push(Part::Field(field));
+                }
+                c => text.push(c),
+            }
+        }
+        if !text.is_empty() {
+            parts.push(Part::Text(text));
+        }
+        Ok(FooterFormat { parts })
+    }
+
+    /// Replaces the placeholders - the segments keep the field they belong to, so they can be styled.
+    pub fn render(&self, value: impl Fn(Field) -> String) -> Vec<(Option<Field>, String)> {
+        let mut segments = Vec::new();
+        let mut skip_space = false;
+        for part in self.parts.iter() {
+            match part {
+                Part::Text(text) => {
+                    let text = if skip_space { text.trim_start() } else { text };
+                    segments.push((None, text.to_string()));
+                    skip_space = false;
+                }
+                Part::Field(field) => {
+                    let text = value(*field);
+                    skip_space = text.is_empty();
+                    segments.push((Some(*field), text));
+                }
+            }
+        }
+        segments
+    }
+}
+
+#[test]
+fn test_footer_format() {
+    let format = FooterFormat::parse("{permissions}   {lines} {words} {size} {{x}}").unwrap();
+    let value = |field| match field {
+        Field::Permissions => "drwxr-xr-x".to_string(),
+        Field::Size => "4 KiB".to_string(),
+        _ => String::new(),
+    };
+    let rendered: String = format
+        .render(value)
+        .into_iter()
+        .map(|(_, text)| text)
+        .collect();
+    assert_eq!(rendered, "drwxr-xr-x   4 KiB {x}");
+    assert!(FooterFormat::parse("{owner}").is_err());
+}
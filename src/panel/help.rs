@@ -0,0 +1,150 @@
+use crate::{
+    commands::CommandParser,
+    locale::{tr, Msg},
+    util::ExactWidth,
+};
+
+use super::*;
+
+/// Full-screen list of all keybindings, generated from the active [`CommandParser`].
+///
+/// Typing filters the list by keys, command name and description.
+pub struct HelpOverlay {
+    /// Keys, command name and description of every binding
+    entries: Vec<(String, &'static str, String)>,
+
+    /// Filter for the entries (case-insensitive)
+    input: String,
+
+    /// Index of the first visible entry in the filtered list
+    scroll: usize,
+
+    /// Number of rows of the last draw call
+    page: usize,
+}
+
+impl Draw for HelpOverlay {
+    fn draw(
+        &mut self,
+        stdout: &mut Stdout,
+        x_range: Range<u16>,
+        y_range: Range<u16>,
+    ) -> Result<()> {
+        let width = x_range.end.saturating_sub(x_range.start) as usize;
+        let height = y_range.end.saturating_sub(y_range.start) as usize;
+        // One line for the input
+        self.page = height.saturating_sub(1).max(1);
+        let n_filtered = self.filtered().len();
+        self.scroll = self.scroll.min(n_filtered.saturating_sub(self.page));
+        let filtered = self.filtered();
+
+        queue!(
+            stdout,
+            cursor::MoveTo(x_range.start, y_range.start),
+            PrintStyledContent(
+                format!(" {} {}", tr(Msg::Help), self.input)
+                    .exact_width(width)
+                    .bold()
+                    .dark_green()
+                    .reverse()
+            ),
+        )?;
+        let keys_width = filtered
+            .iter()
+            .map(|(keys, _, _)| keys.chars().count())
+            .max()
+            .unwrap_or(0)
+            .min(width / 4)
+            + 2;
+        let name_width = 24.min(width / 4);
+        for row in 0..self.page {
+            let y = y_range.start + 1 + row as u16;
+            queue!(stdout, cursor::MoveTo(x_range.start, y))?;
+            match filtered.get(self.scroll + row) {
+                Some((keys, name, description)) => {
+                    let rest = width.saturating_sub(keys_width + name_width);
+                    queue!(
+                        stdout,
+                        PrintStyledContent(format!(" {keys}").exact_width(keys_width).bold()),
+                        PrintStyledContent(name.exact_width(name_width).dark_green()),
+                        Print(description.exact_width(rest)),
+                    )?;
+                }
+                None => queue!(stdout, Print(" ".repeat(width)))?,
+            }
+        }
+        Ok(())
+    }
+}
+
+impl HelpOverlay {
+    pub fn new(parser: &CommandParser) -> Self {
+        let entries = parser
+            .bindings()
+            .into_iter()
+            .map(|(keys, cmd)| {
+                let (name, description) = cmd.describe();
+                // Show whitespace bindings, e.g. " " for mark
+                let keys = keys.replace(' ', "<space>");
+                (keys, name, description)
+            })
+            .collect();
+        HelpOverlay {
+            entries,
+            input: String::new(),
+            scroll: 0,
+            page: 1,
+        }
+    }
+
+    /// Returns all entries that contain the input.
+    fn filtered(&self) -> Vec<&(String, &'static str, String)> {
+        let pattern = self.input.to_lowercase();
+        self.entries
+            .iter()
+            .filter(|(keys, name, description)| {
+                keys.to_lowercase().contains(&pattern)
+                    || name.contains(&pattern)
+                    || description.to_lowercase().contains(&pattern)
+            })
+            .collect()
+    }
+
+    pub fn insert(&mut self, character: char) {
+        self.input.push(character);
+        self.scroll = 0;
+    }
+
+    pub fn del(&mut self) {
+        self.input.pop();
+        self.scroll = 0;
+    }
+
+    pub fn up(&mut self, lines: usize) {
+        self.scroll = self.scroll.saturating_sub(lines);
+    }
+
+    pub fn down(&mut self, lines: usize) {
+        // Clamped to the last page while drawing
+        self.scroll = self.scroll.saturating_add(lines);
+    }
+
+    /// Number of entries that fit on the screen.
+    pub fn page(&self) -> usize {
+        self.page
+    }
+}
+
+#[test]
+fn test_help_filter() {
+    let mut help = HelpOverlay::new(&CommandParser::new());
+    help.insert('u');
+    help.insert('p');
+    assert!(help.filtered().iter().any(|(keys, _, _)| keys == "up"));
+    help.del();
+    help.del();
+    for c in "tabs.next".chars() {
+        help.insert(c);
+    }
+    assert_eq!(help.filtered().len(), 1);
+}
@@ -0,0 +1,186 @@
+use crate::{
+    jobs::{JobInfo, Jobs},
+    locale::{tr, tr_fmt, Msg},
+    util::{file_size_str, ExactWidth},
+};
+
+use super::*;
+
+/// Bars of the throughput graph, from low to high
+const GRAPH_BARS: [char; 8] = ['▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+
+/// Share of the job that is done, in percent (`None` if the size is unknown).
+fn percent(job: &JobInfo) -> Option<u64> {
+    (job.total > 0).then(|| (job.done * 100 / job.total).min(100))
+}
+
+/// Full-screen list of the running jobs - the selected one can be shown in the right column.
+pub struct JobsOverlay {
+    jobs: Jobs,
+    selected: usize,
+}
+
+impl Draw for JobsOverlay {
+    fn draw(
+        &mut self,
+        stdout: &mut Stdout,
+        x_range: Range<u16>,
+        y_range: Range<u16>,
+    ) -> Result<()> {
+        let width = x_range.end.saturating_sub(x_range.start) as usize;
+        let height = y_range.end.saturating_sub(y_range.start) as usize;
+        // One line for the header
+        let n_rows = height.saturating_sub(1);
+        let jobs = self.jobs.infos();
+        self.selected = self.selected.min(jobs.len().saturating_sub(1));
+
+        queue!(
+            stdout,
+            cursor::MoveTo(x_range.start, y_range.start),
+            PrintStyledContent(
+                format!(" {}", tr(Msg::Jobs))
+                    .exact_width(width)
+                    .bold()
+                    .dark_green()
+                    .reverse()
+            ),
+        )?;
+
+        let scroll = self.selected.saturating_sub(n_rows.saturating_sub(1));
+        for row in 0..n_rows {
+            let y = y_range.start + 1 + row as u16;
+            queue!(stdout, cursor::MoveTo(x_range.start, y))?;
+            let Some(job) = jobs.get(scroll + row) else {
+                let line = match row {
+                    0 if jobs.is_empty() => format!(" {}", tr(Msg::NoJobs)),
+                    _ => String::new(),
+                };
+                queue!(stdout, Print(line.exact_width(width)))?;
+                continue;
+            };
+            let progress = percent(job).map_or("?".to_string(), |p| format!("{p}%"));
+            let line = format!(" {progress:>4}  {}", job.description).exact_width(width);
+            if scroll + row == self.selected {
+                queue!(stdout, PrintStyledContent(line.bold().negative()))?;
+            } else {
+                queue!(stdout, Print(line))?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl JobsOverlay {
+    pub fn new(jobs: Jobs) -> Self {
+        JobsOverlay { jobs, selected: 0 }
+    }
+
+    pub fn selected_id(&self) -> Option<u64> {
+        self.jobs.infos().get(self.selected).map(|job| job.id)
+    }
+
+    pub fn up(&mut self) {
+        self.selected = self.selected.saturating_sub(1);
+    }
+
+    pub fn down(&mut self) {
+        self.selected = (self.selected + 1).min(self.jobs.infos().len().saturating_sub(1));
+    }
+}
+
+/// Live progress of a job, shown in the right column instead of the preview.
+pub struct JobPreview {
+    jobs: Jobs,
+    id: u64,
+}
+
+impl Draw for JobPreview {
+    fn draw(
+        &mut self,
+        stdout: &mut Stdout,
+        x_range: Range<u16>,
+        y_range: Range<u16>,
+    ) -> Result<()> {
+        let width = x_range.end.saturating_sub(x_range.start).saturating_sub(2) as usize;
+        let mut lines: Vec<style::StyledContent<String>> = Vec::new();
+        if let Some(job) = self.jobs.info(self.id) {
+            lines.push(job.description.exact_width(width).bold());
+            let current = job
+                .current
+                .as_ref()
+                .map_or(String::new(), |p| p.display().to_string());
+            lines.push(
+                tr_fmt(Msg::JobCurrent, &[&current])
+                    .exact_width(width)
+                    .reset(),
+            );
+            lines.push(String::new().reset());
+            let done = file_size_str(job.done);
+            let progress = match percent(&job) {
+                Some(percent) => {
+                    let total = file_size_str(job.total);
+                    let bar_width = width.saturating_sub(2);
+                    let filled = bar_width * percent as usize / 100;
+                    lines.push(
+                        format!("[{}{}]", "#".repeat(filled), " ".repeat(bar_width - filled))
+                            .dark_green(),
+                    );
+                    format!("{done} / {total}  ({percent}%)")
+                }
+                None => done,
+            };
+            lines.push(progress.exact_width(width).reset());
+            let average = format!("{}/s", file_size_str(job.average()));
+            lines.push(
+                tr_fmt(Msg::JobThroughput, &[&average])
+                    .exact_width(width)
+                    .reset(),
+            );
+            lines.push(String::new().reset());
+            lines.push(graph(&job.throughput(), width).dark_cyan());
+        }
+        for (row, y) in y_range.enumerate() {
+            let line = lines
+                .get(row)
+                .cloned()
+                .unwrap_or_else(|| " ".repeat(width).reset());
+            queue!(
+                stdout,
+                cursor::MoveTo(x_range.start, y),
+                PrintStyledContent("│".dark_green().bold()),
+                Print(" "),
+                PrintStyledContent(line),
+            )?;
+        }
+        Ok(())
+    }
+}
+
+impl JobPreview {
+    pub fn new(jobs: Jobs, id: u64) -> Self {
+        JobPreview { jobs, id }
+    }
+
+    pub fn id(&self) -> u64 {
+        self.id
+    }
+}
+
+/// Bar graph of the last values that fit into the width, scaled to the largest one.
+fn graph(values: &[u64], width: usize) -> String {
+    let values = &values[values.len().saturating_sub(width)..];
+    let max = values.iter().copied().max().unwrap_or(0).max(1);
+    let bars: String = values
+        .iter()
+        .map(|v| GRAPH_BARS[(v * (GRAPH_BARS.len() as u64 - 1) / max) as usize])
+        .collect();
+    // Padded by hand, because the bars take more than one byte
+    format!("{bars:width$}")
+}
+
+#[test]
+fn test_graph() {
+    assert_eq!(graph(&[0, 50, 100], 3), "▁▄█");
+    assert_eq!(graph(&[100, 0, 100], 2), "▁█");
+    assert_eq!(graph(&[], 2), "  ");
+}
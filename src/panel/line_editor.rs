@@ -0,0 +1,184 @@
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+
+use crate::commands::RenameCursor;
+
+/// Single line of text input with a cursor - shared by all input prompts.
+///
+/// Supports the usual readline keys: arrows, Home/End (ctrl-a/ctrl-e), word jumps
+/// (ctrl-left/right, alt-b/alt-f), delete-word (ctrl-w, alt-backspace) and ctrl-u/ctrl-k.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct LineEditor {
+    text: String,
+    /// Byte offset of the cursor (always on a char boundary)
+    cursor: usize,
+}
+
+impl LineEditor {
+    /// Creates an editor with the given text and the cursor at its end.
+    pub fn new(text: impl Into<String>) -> Self {
+        let text = text.into();
+        let cursor = text.len();
+        LineEditor { text, cursor }
+    }
+
+    /// Creates an editor to rename the item with the given name.
+    pub fn rename(name: &str, cursor: RenameCursor) -> Self {
+        let cursor = match cursor {
+            RenameCursor::End => name.len(),
+            // Dotfiles like ".bashrc" have no extension
+            RenameCursor::BeforeExtension => {
+                name.rfind('.').filter(|idx| *idx > 0).unwrap_or(name.len())
+            }
+            RenameCursor::Start => 0,
+            RenameCursor::Replace => return LineEditor::default(),
+        };
+        LineEditor {
+            text: name.to_string(),
+            cursor,
+        }
+    }
+
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    /// Replaces the text and puts the cursor at its end.
+    pub fn set(&mut self, text: impl Into<String>) {
+        *self = LineEditor::new(text);
+    }
+
+    /// Column of the cursor, in characters from the start of the text.
+    pub fn cursor_column(&self) -> usize {
+        self.text[..self.cursor].chars().count()
+    }
+
+    pub fn insert(&mut self, c: char) {
+        self.text.insert(self.cursor, c);
+        self.cursor += c.len_utf8();
+    }
+
+    /// Applies the key event to the text.
+    ///
+    /// Returns `false` if the key has no meaning for the editor (e.g. `Enter`).
+    pub fn handle(&mut self, key_event: KeyEvent) -> bool {
+        let ctrl = key_event.modifiers.contains(KeyModifiers::CONTROL);
+        let alt = key_event.modifiers.contains(KeyModifiers::ALT);
+        match key_event.code {
+            KeyCode::Char('a') if ctrl => self.cursor = 0,
+            KeyCode::Char('e') if ctrl => self.cursor = self.text.len(),
+            KeyCode::Char('u') if ctrl => {
+                self.text.drain(..self.cursor);
+                self.cursor = 0;
+            }
+            KeyCode::Char('k') if ctrl => self.text.truncate(self.cursor),
+            KeyCode::Char('w') if ctrl => self.delete_word(),
+            KeyCode::Char('b') if alt => self.cursor = self.word_left(),
+            KeyCode::Char('f') if alt => self.cursor = self.word_right(),
+            KeyCode::Char(_) if ctrl || alt => return false,
+            KeyCode::Char(c) => self.insert(c),
+            KeyCode::Backspace if alt || ctrl => self.delete_word(),
+            KeyCode::Backspace => {
+                if let Some(c) = self.text[..self.cursor].chars().next_back() {
+                    self.cursor -= c.len_utf8();
+                    self.text.remove(self.cursor);
+                }
+            }
+            KeyCode::Delete => {
+                if self.cursor < self.text.len() {
+                    self.text.remove(self.cursor);
+                }
+            }
+            KeyCode::Left if ctrl || alt => self.cursor = self.word_left(),
+            KeyCode::Right if ctrl || alt => self.cursor = self.word_right(),
+            KeyCode::Left => {
+                if let Some(c) = self.text[..self.cursor].chars().next_back() {
+                    self.cursor -= c.len_utf8();
+                }
+            }
+            KeyCode::Right => {
+                if let Some(c) = self.text[self.cursor..].chars().next() {
+                    self.cursor += c.len_utf8();
+                }
+            }
+            KeyCode::Home => self.cursor = 0,
+            KeyCode::End => self.cursor = self.text.len(),
+            _ => return false,
+        }
+        true
+    }
+
+    fn delete_word(&mut self) {
+        let start = self.word_left();
+        self.text.drain(start..self.cursor);
+        self.cursor = start;
+    }
+
+    /// Start of the word left of the cursor.
+    fn word_left(&self) -> usize {
+        let before = &self.text[..self.cursor];
+        let end = before
+            .trim_end_matches(|c: char| !c.is_alphanumeric())
+            .len();
+        before[..end].trim_end_matches(char::is_alphanumeric).len()
+    }
+
+    /// End of the word right of the cursor.
+    fn word_right(&self) -> usize {
+        let after = &self.text[self.cursor..];
+        let rest = after.trim_start_matches(|c: char| !c.is_alphanumeric());
+        let rest = rest.trim_start_matches(char::is_alphanumeric);
+        self.text.len() - rest.len()
+    }
+}
+
+#[test]
+fn test_line_editor() {
+    let key = |code| KeyEvent::new(code, KeyModifiers::NONE);
+    let ctrl = |c| KeyEvent::new(KeyCode::Char(c), KeyModifiers::CONTROL);
+
+    let mut editor = LineEditor::new("report.txt");
+    assert_eq!(editor.cursor_column(), 10);
+    editor.handle(KeyEvent::new(KeyCode::Left, KeyModifiers::CONTROL));
+    assert_eq!(editor.cursor_column(), 7);
+    editor.handle(key(KeyCode::Left));
+    "-ä".chars().for_each(|c| editor.insert(c));
+    assert_eq!(editor.text(), "report-ä.txt");
+    assert_eq!(editor.cursor_column(), 8);
+
+    editor.handle(ctrl('w'));
+    assert_eq!(editor.text(), "report-.txt");
+    editor.handle(key(KeyCode::Delete));
+    editor.handle(key(KeyCode::Backspace));
+    assert_eq!(editor.text(), "reporttxt");
+    editor.handle(key(KeyCode::Home));
+    editor.handle(KeyEvent::new(KeyCode::Char('f'), KeyModifiers::ALT));
+    assert_eq!(editor.cursor_column(), 9);
+
+    editor.handle(key(KeyCode::Left));
+    editor.handle(key(KeyCode::Left));
+    editor.handle(ctrl('u'));
+    assert_eq!(editor.text(), "xt");
+    assert_eq!(editor.cursor_column(), 0);
+    assert!(!editor.handle(key(KeyCode::Enter)));
+    assert!(!editor.handle(ctrl('x')));
+    assert_eq!(editor.text(), "xt");
+
+    let rename = |name, cursor| {
+        let editor = LineEditor::rename(name, cursor);
+        (editor.text().to_string(), editor.cursor_column())
+    };
+    assert_eq!(
+        rename("a.tar.gz", RenameCursor::BeforeExtension),
+        ("a.tar.gz".to_string(), 5)
+    );
+    assert_eq!(
+        rename(".bashrc", RenameCursor::BeforeExtension),
+        (".bashrc".to_string(), 7)
+    );
+    assert_eq!(rename("ä.txt", RenameCursor::End), ("ä.txt".to_string(), 5));
+    assert_eq!(
+        rename("ä.txt", RenameCursor::Start),
+        ("ä.txt".to_string(), 0)
+    );
+    assert_eq!(rename("ä.txt", RenameCursor::Replace), (String::new(), 0));
+}
@@ -0,0 +1,232 @@
+use log::Level;
+
+use crate::{
+    locale::{tr, Msg},
+    util::ExactWidth,
+};
+
+use super::*;
+
+/// Full-screen view of the log history.
+///
+/// The entries can be filtered by their level and searched, and new entries are appended
+/// while the overlay is open.
+pub struct LogOverlay {
+    entries: Vec<(Level, String)>,
+
+    /// Only entries of this level or more severe ones are shown
+    level: Level,
+
+    /// Search pattern (case-insensitive)
+    search: String,
+
+    /// Set while the search pattern is typed
+    searching: bool,
+
+    /// Index of the selection in the filtered list
+    selected: usize,
+
+    /// Index of the first visible entry in the filtered list
+    scroll: usize,
+
+    /// Number of rows of the last draw call
+    page: usize,
+}
+
+impl Draw for LogOverlay {
+    fn draw(
+        &mut self,
+        stdout: &mut Stdout,
+        x_range: Range<u16>,
+        y_range: Range<u16>,
+    ) -> Result<()> {
+        let width = x_range.end.saturating_sub(x_range.start) as usize;
+        let height = y_range.end.saturating_sub(y_range.start) as usize;
+        // One line for the header
+        self.page = height.saturating_sub(1).max(1);
+        // Keep the selection visible
+        if self.selected < self.scroll {
+            self.scroll = self.selected;
+        } else if self.selected >= self.scroll + self.page {
+            self.scroll = self.selected + 1 - self.page;
+        }
+        let filtered = self.filtered();
+
+        let search = if self.searching || !self.search.is_empty() {
+            format!("  /{}", self.search)
+        } else {
+            String::new()
+        };
+        let header = format!(
+            " {} [{}]  {}/{}{search}",
+            tr(Msg::Log),
+            self.level.as_str().to_lowercase(),
+            filtered.len(),
+            self.entries.len()
+        );
+        queue!(
+            stdout,
+            cursor::MoveTo(x_range.start, y_range.start),
+            PrintStyledContent(header.exact_width(width).bold().dark_green().reverse()),
+        )?;
+        for row in 0..self.page {
+            let y = y_range.start + 1 + row as u16;
+            queue!(stdout, cursor::MoveTo(x_range.start, y))?;
+            let Some((level, line)) = filtered.get(self.scroll + row) else {
+                queue!(stdout, Print(" ".repeat(width)))?;
+                continue;
+            };
+            let label = format!(" {:<6}", level.as_str().to_lowercase());
+            let label = match level {
+                Level::Error => label.red().bold(),
+                Level::Warn => label.yellow().bold(),
+                Level::Info => label.dark_green().bold(),
+                Level::Debug => label.dark_blue(),
+                Level::Trace => label.grey(),
+            };
+            let line = line.exact_width(width.saturating_sub(7));
+            if self.scroll + row == self.selected {
+                queue!(
+                    stdout,
+                    PrintStyledContent(label.negative()),
+                    PrintStyledContent(line.negative())
+                )?;
+            } else {
+                queue!(stdout, PrintStyledContent(label), Print(line))?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl LogOverlay {
+    /// Shows the entries with the newest one selected.
+    pub fn new(entries: Vec<(Level, String)>) -> Self {
+        let mut overlay = LogOverlay {
+            entries,
+            level: Level::Debug,
+            search: String::new(),
+            searching: false,
+            selected: 0,
+            scroll: 0,
+            page: 1,
+        };
+        overlay.bottom();
+        overlay
+    }
+
+    /// Replaces the entries - the selection follows new entries, if the newest one was selected.
+    pub fn update(&mut self, entries: Vec<(Level, String)>) {
+        let at_bottom = self.selected + 1 >= self.filtered().len();
+        self.entries = entries;
+        if at_bottom {
+            self.bottom();
+        } else {
+            self.clamp();
+        }
+    }
+
+    fn filtered(&self) -> Vec<&(Level, String)> {
+        let pattern = self.search.to_lowercase();
+        self.entries
+            .iter()
+            .filter(|(level, line)| *level <= self.level && line.to_lowercase().contains(&pattern))
+            .collect()
+    }
+
+    fn clamp(&mut self) {
+        self.selected = self.selected.min(self.filtered().len().saturating_sub(1));
+    }
+
+    /// Text of the selected entry.
+    pub fn selected_line(&self) -> Option<String> {
+        self.filtered()
+            .get(self.selected)
+            .map(|(_, line)| line.clone())
+    }
+
+    /// Shows only the entries of the level and the more severe ones.
+    pub fn set_level(&mut self, level: Level) {
+        self.level = level;
+        self.bottom();
+    }
+
+    pub fn is_searching(&self) -> bool {
+        self.searching
+    }
+
+    pub fn start_search(&mut self) {
+        self.searching = true;
+        self.search.clear();
+        self.bottom();
+    }
+
+    /// Finishes typing the search pattern, which stays active.
+    pub fn end_search(&mut self) {
+        self.searching = false;
+    }
+
+    pub fn insert(&mut self, c: char) {
+        self.search.push(c);
+        self.bottom();
+    }
+
+    pub fn del(&mut self) {
+        if self.search.pop().is_none() {
+            self.searching = false;
+        }
+        self.bottom();
+    }
+
+    pub fn up(&mut self, lines: usize) {
+        self.selected = self.selected.saturating_sub(lines);
+    }
+
+    pub fn down(&mut self, lines: usize) {
+        self.selected = self.selected.saturating_add(lines);
+        self.clamp();
+    }
+
+    pub fn top(&mut self) {
+        self.selected = 0;
+    }
+
+    pub fn bottom(&mut self) {
+        self.selected = self.filtered().len().saturating_sub(1);
+    }
+
+    /// Number of entries that fit on the screen.
+    pub fn page(&self) -> usize {
+        self.page
+    }
+}
+
+#[test]
+fn test_log_overlay() {
+    let entries = vec![
+        (Level::Info, "Using config".to_string()),
+        (Level::Error, "Cannot open file".to_string()),
+        (Level::Debug, "watching /tmp".to_string()),
+    ];
+    let mut overlay = LogOverlay::new(entries.clone());
+    assert_eq!(overlay.selected_line().as_deref(), Some("watching /tmp"));
+
+    overlay.set_level(Level::Info);
+    assert_eq!(overlay.selected_line().as_deref(), Some("Cannot open file"));
+    overlay.up(5);
+    assert_eq!(overlay.selected_line().as_deref(), Some("Using config"));
+
+    overlay.start_search();
+    "CONFIG".chars().for_each(|c| overlay.insert(c));
+    assert_eq!(overlay.filtered().len(), 1);
+    overlay.del();
+    overlay.end_search();
+
+    // Follows new entries at the bottom
+    overlay.search.clear();
+    overlay.set_level(Level::Debug);
+    let mut more = entries;
+    more.push((Level::Warn, "Low disk space".to_string()));
+    overlay.update(more);
+    assert_eq!(overlay.selected_line().as_deref(), Some("Low disk space"));
+}
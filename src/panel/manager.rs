@@ -1,49 +1,229 @@
-use std::{fs::OpenOptions, os::unix::prelude::MetadataExt};
+use std::{
+    collections::{HashMap, HashSet},
+    os::unix::prelude::MetadataExt,
+    sync::Arc,
+    time::{Duration, Instant},
+};
 
-use crossterm::event::{Event, EventStream, KeyCode};
+use crossterm::event::{
+    Event, EventStream, KeyCode, KeyModifiers, MouseButton, MouseEvent, MouseEventKind,
+};
 use futures::{FutureExt, StreamExt};
-use log::{debug, error, info, trace};
-use tempfile::TempDir;
-use time::OffsetDateTime;
-use users::{get_group_by_gid, get_user_by_uid};
+use log::{debug, error, info, trace, warn};
+use users::get_effective_uid;
 
 use crate::{
-    commands::{Command, CommandParser},
+    bookmarks::{import_bookmarks, Bookmark},
+    commands::{Command, CommandParser, LayoutChange, PathPart},
+    config::{default_footer_left, default_footer_right, default_ratios, Config, ConfigError},
+    content::track_ignored,
+    devices::{list_devices, list_mounts, DeviceAction, DeviceUpdate, Mount},
+    disk_usage::{scan, DuUpdate},
+    frecency::Frecency,
+    git::{git_status, GitStatusMap},
+    history::History,
+    hooks::{run_hook, HookContext, HookEvent},
+    jobs::{item_size, Jobs},
+    locale::{tr, tr_fmt, Msg},
     logger::LogBuffer,
-    opener::OpenEngine,
-    util::{copy_item, file_size_str, get_destination, move_item},
+    notifier::DirNotifier,
+    opener::{expand_macros, run_shell, run_subshell, spawn_shell, OpenEngine},
+    ownership::{change_owner, complete, group_names, parse_owner, user_names},
+    plugin::{PluginAction, PluginHost, PluginState},
+    project::{Project, ProjectTracker, PROJECT_FILE},
+    remote::{RemoteCommand, RemoteServer},
+    status::{Status, StatusFile},
+    trash::{self, Trash, TrashListing},
+    util::{
+        complete_path, copy_item, create_item, expand_braces, file_size_str, get_clipboard_files,
+        hardlink_item, is_copy_on_write, link_item, lower_priority, move_item, rename_all,
+        set_clipboard_files, set_system_clipboard, shred, ExactWidth, StartupProfile,
+    },
+    vfs::{RemoteUrl, Vfs},
+};
+
+use super::{
+    bookmarks::BookmarkOverlay,
+    config_errors::ConfigErrorOverlay,
+    console::DirConsole,
+    devices::DeviceOverlay,
+    disk_usage::DiskUsageOverlay,
+    footer::{Field, FooterFormat, FooterInfo},
+    help::HelpOverlay,
+    jobs::{JobPreview, JobsOverlay},
+    line_editor::LineEditor,
+    log_view::LogOverlay,
+    mounts::MountsOverlay,
+    session_marks::SessionMarks,
+    which_key::WhichKey,
+    *,
 };
 
-use super::{console::DirConsole, *};
+/// Movements that follow each other faster than this are treated as a held key.
+///
+/// While a key is held, we skip the preview requests and the footer metadata,
+/// and only catch up once the stream of events pauses.
+const KEY_REPEAT_PAUSE: Duration = Duration::from_millis(50);
+
+/// A partially typed key sequence shows its possible completions after this delay.
+const WHICH_KEY_DELAY: Duration = Duration::from_millis(500);
+
+/// Footer metadata older than this is read again (but shown until the new one arrives).
+const FOOTER_INFO_TTL: Duration = Duration::from_secs(2);
+
+/// Changes of the trash are collected for this long, before it is listed again.
+const TRASH_DEBOUNCE: Duration = Duration::from_millis(250);
+
+/// Minimum time between two progress messages while the trash is emptied.
+const TRASH_PROGRESS_INTERVAL: Duration = Duration::from_secs(1);
+
+/// The progress of a job is drawn again after this long, while it is shown.
+const JOB_REFRESH: Duration = Duration::from_millis(500);
+
+/// Maximum number of items whose footer metadata is cached.
+const FOOTER_INFO_CAPACITY: usize = 1024;
 
 struct Redraw {
     left: bool,
     center: bool,
     right: bool,
     console: bool,
-    log: bool,
     header: bool,
     footer: bool,
 }
 
 impl Redraw {
     fn any(&self) -> bool {
-        self.left
-            || self.center
-            || self.right
-            || self.console
-            || self.header
-            || self.footer
-            || self.log
+        self.left || self.center || self.right || self.console || self.header || self.footer
     }
 }
 
 enum Mode {
     Normal,
-    Console { console: DirConsole },
-    CreateItem { input: String, is_dir: bool },
-    Search { input: String },
-    Rename { input: String },
+    Console {
+        console: DirConsole,
+    },
+    CreateItem {
+        input: LineEditor,
+        is_dir: bool,
+    },
+    Search {
+        input: LineEditor,
+    },
+    /// Shell command with macros - `-d` in front runs it detached
+    RunCommand {
+        input: LineEditor,
+    },
+    Rename {
+        input: LineEditor,
+    },
+    Export {
+        input: LineEditor,
+        long: bool,
+    },
+    Bookmarks {
+        overlay: BookmarkOverlay,
+    },
+    /// Waits for the letter to jump to
+    FirstLetter,
+    /// Waits for the key of the mark to set
+    SetMark,
+    /// Waits for the key of the mark to jump to (while the marks are listed)
+    JumpMark,
+    /// Keywords of a jump to a visited directory, and the best match for them
+    FrecentJump {
+        input: LineEditor,
+        best: Option<PathBuf>,
+    },
+    Help {
+        overlay: HelpOverlay,
+    },
+    Log {
+        overlay: LogOverlay,
+    },
+    DiskUsage {
+        overlay: DiskUsageOverlay,
+    },
+    Devices {
+        overlay: DeviceOverlay,
+    },
+    Mounts {
+        overlay: MountsOverlay,
+    },
+    Jobs {
+        overlay: JobsOverlay,
+    },
+    ConfigErrors {
+        overlay: ConfigErrorOverlay,
+    },
+    Chown {
+        input: LineEditor,
+        paths: Vec<PathBuf>,
+        users: Vec<String>,
+        groups: Vec<String>,
+    },
+    /// Waits for 'y' before a dangerous operation is executed as root
+    Confirm {
+        operation: Dangerous,
+    },
+}
+
+impl Mode {
+    /// Returns the prompt and the editor of the input modes.
+    fn prompt(&self) -> Option<(&'static str, &LineEditor)> {
+        match self {
+            Mode::CreateItem {
+                input,
+                is_dir: true,
+            } => Some((tr(Msg::MakeDirectory), input)),
+            Mode::CreateItem {
+                input,
+                is_dir: false,
+            } => Some((tr(Msg::Touch), input)),
+            Mode::Search { input } => Some((tr(Msg::Search), input)),
+            Mode::RunCommand { input } => Some((tr(Msg::RunCommand), input)),
+            Mode::FrecentJump { input, .. } => Some((tr(Msg::FrecentJump), input)),
+            Mode::Rename { input } => Some((tr(Msg::Rename), input)),
+            Mode::Export { input, .. } => Some((tr(Msg::Export), input)),
+            Mode::Chown { input, .. } => Some((tr(Msg::Chown), input)),
+            _ => None,
+        }
+    }
+
+    /// Returns the editor of the input modes.
+    fn input_mut(&mut self) -> Option<&mut LineEditor> {
+        match self {
+            Mode::CreateItem { input, .. }
+            | Mode::Search { input }
+            | Mode::RunCommand { input }
+            | Mode::FrecentJump { input, .. }
+            | Mode::Rename { input }
+            | Mode::Export { input, .. }
+            | Mode::Chown { input, .. } => Some(input),
+            _ => None,
+        }
+    }
+}
+
+/// Operations that cannot be undone easily, and must be confirmed when running as root.
+enum Dangerous {
+    /// Deletes the items - including at least one directory
+    Delete(Vec<PathBuf>),
+    /// Deletes the items without the trash (always confirmed)
+    DeletePermanently(Vec<PathBuf>),
+    /// Overwrites and deletes the items (always confirmed, with a warning on copy-on-write filesystems)
+    Shred {
+        files: Vec<PathBuf>,
+        copy_on_write: bool,
+    },
+    /// Removes the given number of items from the trash (always confirmed)
+    EmptyTrash(usize),
+    /// Runs a command of a `.rfm.toml` that was not allowed yet (always confirmed)
+    ProjectCommand {
+        command: String,
+        root: PathBuf,
+    },
+    PasteOverwrite,
 }
 
 struct Clipboard {
@@ -56,6 +236,57 @@ struct Clipboard {
     cut: bool,
 }
 
+/// Publishes the files on the system clipboard (in the background), so other applications can paste them.
+fn publish_clipboard(files: &[PathBuf]) {
+    if files.is_empty() {
+        return;
+    }
+    let files = files.to_vec();
+    tokio::task::spawn_blocking(move || {
+        if let Err(e) = set_clipboard_files(&files) {
+            debug!("Cannot publish the files on the system clipboard: {e}");
+        }
+    });
+}
+
+/// Draws a directory panel, with its view indicator (see [`DirPanel::view_indicator`])
+/// right-aligned in the last row.
+fn draw_with_indicator(
+    stdout: &mut Stdout,
+    panel: &mut DirPanel,
+    x_range: Range<u16>,
+    y_range: Range<u16>,
+) -> Result<()> {
+    // Panels with very few rows only show their items
+    if y_range.len() < 3 {
+        return panel.draw(stdout, x_range, y_range);
+    }
+    let last = y_range.end - 1;
+    panel.draw(stdout, x_range.clone(), y_range.start..last)?;
+    let width = x_range.len().saturating_sub(1);
+    let indicator = format!("{} ", panel.view_indicator());
+    let indicator = if indicator.chars().count() <= width {
+        format!("{indicator:>width$}")
+    } else {
+        indicator.exact_width(width)
+    };
+    queue!(
+        stdout,
+        cursor::MoveTo(x_range.start, last),
+        style::PrintStyledContent("│".dark_green().bold()),
+        style::PrintStyledContent(indicator.dark_grey()),
+    )
+}
+
+/// A tab only remembers its location - the panels are shared between all tabs.
+#[derive(Debug, Clone)]
+struct Tab {
+    /// Directory of the center panel
+    path: PathBuf,
+    /// Selected item of the center panel
+    selected: Option<PathBuf>,
+}
+
 // enum Operation {
 //     MoveItems { from: Vec<PathBuf>, to: PathBuf },
 //     CopyItems { from: Vec<PathBuf>, to: PathBuf },
@@ -81,20 +312,68 @@ pub struct PanelManager {
     /// Clipboard
     clipboard: Option<Clipboard>,
 
+    /// Files that we published on the system clipboard - they are never pasted from there again
+    published: Vec<PathBuf>,
+
+    /// Files of the system clipboard, read for a paste into the directory (with `overwrite`)
+    import_tx: mpsc::UnboundedSender<(PathBuf, bool, Vec<PathBuf>)>,
+    import_rx: mpsc::UnboundedReceiver<(PathBuf, bool, Vec<PathBuf>)>,
+
     // /// Undo/Redo stack
     // stack: Vec<Operation>,
     /// Miller-Columns layout
     layout: MillerColumns,
 
-    /// Show hidden files
-    show_hidden: bool,
+    /// Ratios of the column widths (changed at runtime by the layout commands)
+    ratios: [u16; 3],
+
+    /// Whether the preview is shown (the second flag applies to narrow terminals)
+    show_preview: bool,
+    narrow_preview: bool,
 
-    /// Show log
-    show_log: bool,
+    /// Whether long lines of text previews are wrapped
+    wrap: bool,
+
+    /// Which files are shown (hidden files, gitignored files)
+    visibility: Visibility,
+
+    /// Marked items of all directories
+    marks: Marks,
+
+    /// Jump-marks and bookmarks imported from the shell
+    bookmarks: Vec<Bookmark>,
+    /// Bookmarks of the shell, once they are imported in the background
+    bookmarks_rx: mpsc::UnboundedReceiver<Vec<Bookmark>>,
+
+    /// Open tabs
+    tabs: Vec<Tab>,
+
+    /// Index of the active tab
+    active_tab: usize,
+
+    /// Symlinked directories that we entered, mapped to the symlink we came from.
+    ///
+    /// Moving left out of such a directory leads back to the directory of the symlink,
+    /// instead of the parent of the canonicalized target.
+    link_origins: HashMap<PathBuf, PathBuf>,
+
+    /// Directory the left panel is pinned to.
+    ///
+    /// If set, the left panel no longer follows the parent of the center panel,
+    /// which gives us a "source | working dir | preview" layout.
+    pinned: Option<PathBuf>,
 
     /// Elements that needs to be redrawn
     redraw: Redraw,
 
+    /// Time of the last up- or down-movement
+    last_move: Instant,
+
+    /// Set while a movement key is held down.
+    ///
+    /// The preview of the selected item is requested once the key is released.
+    preview_pending: bool,
+
     /// Event-stream from the terminal
     event_reader: EventStream,
 
@@ -102,7 +381,24 @@ pub struct PanelManager {
     /// Previous path
     previous: PathBuf,
     pre_console_path: PathBuf,
-    trash_dir: TempDir,
+    trash: Option<Trash>,
+
+    /// Previous search patterns
+    search_history: History,
+
+    /// Directories that were opened with the console
+    console_history: History,
+
+    /// Commands of [`Command::Run`]
+    command_history: History,
+
+    /// Visited directories, for [`Command::FrecentJump`]
+    frecency: Frecency,
+    /// Connections to remote locations
+    vfs: Vfs,
+
+    /// Marks of [`Command::SetMark`]
+    session_marks: SessionMarks,
 
     /// command-parser
     parser: CommandParser,
@@ -115,9 +411,117 @@ pub struct PanelManager {
 
     /// Receiver for incoming preview-panels
     prev_rx: mpsc::Receiver<(PreviewPanel, PanelState)>,
+
+    /// Git status of the center panel
+    git_status: GitStatusMap,
+
+    /// Directory for which we requested the git status last
+    git_requested: PathBuf,
+
+    /// Sender and receiver for the asynchronously generated git status
+    git_tx: mpsc::UnboundedSender<GitStatusMap>,
+    git_rx: mpsc::UnboundedReceiver<GitStatusMap>,
+
+    /// Metadata of the trashed items, shown as extra columns in the trash view
+    trash_listing: TrashListing,
+
+    /// Dedicated watcher of the trash - keeps the trash view up to date
+    trash_watcher: Option<RecommendedWatcher>,
+
+    /// Receives a notification whenever the content of the trash changes
+    trash_rx: mpsc::UnboundedReceiver<()>,
+
+    /// Listings of the trash, which are read in the background
+    trash_listing_tx: mpsc::UnboundedSender<TrashListing>,
+    trash_listing_rx: mpsc::UnboundedReceiver<TrashListing>,
+
+    /// Whether a listing of the trash is running, and whether the trash changed since it started
+    trash_listing_state: (bool, bool),
+
+    /// Reports directory changes to other programs (see `--dir-notify`)
+    dir_notifier: Option<DirNotifier>,
+
+    /// Publishes the state of rfm for status bars
+    status_file: Option<StatusFile>,
+
+    /// Accepts commands of other programs (see `--remote`)
+    _remote: Option<RemoteServer>,
+    remote_rx: mpsc::UnboundedReceiver<RemoteCommand>,
+
+    /// General settings
+    config: Config,
+
+    /// Whether rfm runs as root - dangerous operations must be confirmed then
+    is_root: bool,
+
+    /// `.rfm.toml` of the current directory tree
+    project: ProjectTracker,
+
+    /// Projects that were looked up in the background, together with the directory
+    project_tx: mpsc::UnboundedSender<(PathBuf, Option<Project>)>,
+    project_rx: mpsc::UnboundedReceiver<(PathBuf, Option<Project>)>,
+
+    /// Since when a key sequence is partially typed
+    pending_since: Option<Instant>,
+
+    /// Completions of the partially typed key sequence (shown after [`WHICH_KEY_DELAY`])
+    which_key: Option<WhichKey>,
+
+    /// Anchor of the current mouse drag, and the paths that were marked by it
+    drag: Option<(PathBuf, Vec<PathBuf>)>,
+
+    /// Footer metadata of the recently selected items
+    footer_info: HashMap<PathBuf, FooterInfo>,
+
+    /// Templates of the left and right side of the footer
+    footer_format: (FooterFormat, FooterFormat),
+
+    /// Items whose footer metadata is currently read
+    footer_pending: HashSet<PathBuf>,
+
+    footer_tx: mpsc::UnboundedSender<(PathBuf, FooterInfo)>,
+    footer_rx: mpsc::UnboundedReceiver<(PathBuf, FooterInfo)>,
+
+    /// Progress and result of the disk usage scans, together with the scanned directory
+    du_tx: mpsc::UnboundedSender<(PathBuf, DuUpdate)>,
+    du_rx: mpsc::UnboundedReceiver<(PathBuf, DuUpdate)>,
+
+    /// File operations that run in the background - notifies `jobs_rx` when one ends
+    jobs: Jobs,
+    jobs_rx: mpsc::UnboundedReceiver<()>,
+    /// Progress of the job that is shown in the right column (instead of the preview)
+    shown_job: Option<JobPreview>,
+
+    /// Mounted filesystems for the overview, which are listed in the background
+    mounts_tx: mpsc::UnboundedSender<Vec<Mount>>,
+    mounts_rx: mpsc::UnboundedReceiver<Vec<Mount>>,
+
+    /// Results of mounting, unmounting and ejecting devices
+    device_tx: mpsc::UnboundedSender<DeviceUpdate>,
+    device_rx: mpsc::UnboundedReceiver<DeviceUpdate>,
+
+    /// Lua plugins
+    plugins: Arc<PluginHost>,
+    /// Text of the plugin status functions, and the state it was computed for
+    plugin_status: (Option<PluginState>, Option<String>),
+
+    /// Directory that is shown in the title of the terminal
+    title_path: Option<PathBuf>,
+
+    /// Directory and selection of the last `cd` and `select` hooks
+    hook_cwd: PathBuf,
+    hook_selected: Option<PathBuf>,
+
+    /// Requests of the plugin hooks - executed once the current event is handled
+    plugin_actions: Vec<PluginAction>,
+
+    /// Hooks of background operations (e.g. after a paste), together with their files
+    hook_tx: mpsc::UnboundedSender<(HookEvent, Vec<PathBuf>)>,
+    hook_rx: mpsc::UnboundedReceiver<(HookEvent, Vec<PathBuf>)>,
 }
 
 impl PanelManager {
+    #[allow(clippy::too_many_arguments)]
     pub fn new(
         parser: CommandParser,
         directory_cache: PanelCache<DirPanel>,
@@ -128,28 +532,91 @@ impl PanelManager {
         preview_tx: mpsc::UnboundedSender<PanelUpdate>,
         logger: LogBuffer,
         opener: OpenEngine,
+        start_dir: &Path,
+        start_selected: Option<&Path>,
+        profile: &mut StartupProfile,
     ) -> Result<Self> {
         // Prepare terminal
         let stdout = stdout();
         let event_reader = EventStream::new();
         let terminal_size = terminal::size()?;
-        let layout = MillerColumns::from_size(terminal_size);
+        let layout = MillerColumns::from_size(terminal_size, default_ratios());
 
         // Create three panels
         let mut left = ManagedPanel::new(directory_cache.clone(), directory_tx.clone(), false);
         let mut center = ManagedPanel::new(directory_cache, directory_tx, false);
         let mut right = ManagedPanel::new(preview_cache, preview_tx, true);
 
+        profile.phase("panel creation");
+
         // Set the directories accordingly
-        left.new_panel_instant(Some(".."));
-        center.new_panel_instant(Some("."));
+        left.new_panel_instant(Some(start_dir.join("..")));
+        profile.phase("initial read of left panel");
+        center.new_panel_instant(Some(start_dir));
+        if let Some(selected) = start_selected {
+            center.panel_mut().select_path(selected);
+        }
+        profile.phase("initial read of center panel");
         right.new_panel_instant(center.panel().selected_path());
+        profile.phase("initial read of right panel");
 
         // select the correct directory for the left panel
         left.panel_mut().select_path(center.panel().path());
 
-        let trash_dir = tempfile::tempdir()?;
-        debug!("Using {} as temporary trash", trash_dir.path().display());
+        // Without a trash, rfm still starts - but deleted items are removed permanently
+        let trash = match Trash::temporary_with_fallback() {
+            Ok(trash) => {
+                debug!("Using {} as temporary trash", trash.files_dir().display());
+                Some(trash)
+            }
+            Err(e) => {
+                error!("{}", tr_fmt(Msg::NoTrash, &[&e]));
+                None
+            }
+        };
+        let (trash_tx, trash_rx) = mpsc::unbounded_channel();
+        // Without a watcher (e.g. no inotify watches left), the trash is only listed after deletions
+        let trash_watcher = trash
+            .as_ref()
+            .and_then(|trash| match trash.watch(trash_tx.clone()) {
+                Ok(watcher) => Some(watcher),
+                Err(e) => {
+                    error!("Cannot watch the trash: {e}");
+                    None
+                }
+            });
+        // The trash may already contain items - list them in the background
+        let _ = trash_tx.send(());
+        let (trash_listing_tx, trash_listing_rx) = mpsc::unbounded_channel();
+        profile.phase("trash init");
+
+        let (git_tx, git_rx) = mpsc::unbounded_channel();
+        let (footer_tx, footer_rx) = mpsc::unbounded_channel();
+        let (du_tx, du_rx) = mpsc::unbounded_channel();
+        let (device_tx, device_rx) = mpsc::unbounded_channel();
+        let (project_tx, project_rx) = mpsc::unbounded_channel();
+        let (import_tx, import_rx) = mpsc::unbounded_channel();
+        let (bookmarks_tx, bookmarks_rx) = mpsc::unbounded_channel();
+        let (jobs, jobs_rx) = Jobs::new();
+        let (mounts_tx, mounts_rx) = mpsc::unbounded_channel();
+        let (hook_tx, hook_rx) = mpsc::unbounded_channel();
+        let (remote_tx, remote_rx) = mpsc::unbounded_channel();
+
+        let mut bookmarks: Vec<Bookmark> = parser
+            .jump_marks()
+            .into_iter()
+            .map(|(keys, path)| Bookmark { path, source: keys })
+            .collect();
+        bookmarks.sort_by(|a, b| a.source.cmp(&b.source));
+        // Asking zoxide and checking the directories may take a while
+        tokio::task::spawn_blocking(move || {
+            let _ = bookmarks_tx.send(import_bookmarks());
+        });
+
+        let tabs = vec![Tab {
+            path: center.panel().path().to_path_buf(),
+            selected: center.panel().selected_path_owned(),
+        }];
 
         Ok(PanelManager {
             left,
@@ -158,31 +625,150 @@ impl PanelManager {
             mode: Mode::Normal,
             logger,
             clipboard: None,
+            published: Vec::new(),
+            import_tx,
+            import_rx,
             layout,
+            ratios: default_ratios(),
+            show_preview: true,
+            narrow_preview: false,
+            wrap: false,
             opener,
             // stack: Vec::new(),
-            show_hidden: false,
-            show_log: false,
+            visibility: Visibility::default(),
+            marks: Marks::default(),
+            bookmarks,
+            bookmarks_rx,
+            tabs,
+            active_tab: 0,
+            pinned: None,
+            link_origins: HashMap::new(),
             redraw: Redraw {
                 left: true,
                 center: true,
                 right: true,
-                log: true,
                 console: true,
                 header: true,
                 footer: true,
             },
+            last_move: Instant::now(),
+            preview_pending: false,
             event_reader,
             previous: ".".into(),
             pre_console_path: ".".into(),
-            trash_dir,
+            trash,
+            search_history: History::open("search"),
+            console_history: History::open("console"),
+            command_history: History::open("command"),
+            frecency: Frecency::open(),
+            vfs: Vfs::default(),
+            session_marks: SessionMarks::default(),
             parser,
             stdout,
             dir_rx,
             prev_rx,
+            git_status: GitStatusMap::default(),
+            git_requested: PathBuf::default(),
+            git_tx,
+            git_rx,
+            trash_listing: TrashListing::default(),
+            trash_listing_tx,
+            trash_listing_rx,
+            trash_listing_state: (false, false),
+            trash_watcher,
+            trash_rx,
+            dir_notifier: None,
+            status_file: StatusFile::new(),
+            _remote: RemoteServer::new(remote_tx),
+            remote_rx,
+            footer_info: HashMap::new(),
+            footer_format: (
+                FooterFormat::parse(&default_footer_left()).expect("invalid default footer"),
+                FooterFormat::parse(&default_footer_right()).expect("invalid default footer"),
+            ),
+            footer_pending: HashSet::new(),
+            footer_tx,
+            footer_rx,
+            du_tx,
+            du_rx,
+            jobs,
+            jobs_rx,
+            shown_job: None,
+            mounts_tx,
+            mounts_rx,
+            device_tx,
+            device_rx,
+            plugins: Arc::default(),
+            plugin_status: (None, None),
+            title_path: None,
+            hook_cwd: PathBuf::new(),
+            hook_selected: None,
+            plugin_actions: Vec::new(),
+            hook_tx,
+            hook_rx,
+            drag: None,
+            config: Config::default(),
+            is_root: get_effective_uid() == 0,
+            project: ProjectTracker::open(),
+            project_tx,
+            project_rx,
+            pending_since: None,
+            which_key: None,
         })
     }
 
+    /// Uses the general settings of the config file.
+    pub fn with_config(mut self, config: Config) -> Self {
+        self.ratios = config.layout.ratios;
+        self.show_preview = config.layout.preview;
+        self.wrap = config.layout.wrap;
+        let parse = |template: &str, default: String| {
+            FooterFormat::parse(template).unwrap_or_else(|e| {
+                warn!("Footer template: {e}. Using the default");
+                FooterFormat::parse(&default).expect("invalid default footer")
+            })
+        };
+        self.footer_format = (
+            parse(&config.footer.left, default_footer_left()),
+            parse(&config.footer.right, default_footer_right()),
+        );
+        self.config = config;
+        self.relayout(self.layout.size());
+        self
+    }
+
+    /// Opens additional tabs (behind the start directory) with their directory and selection.
+    pub fn with_tabs(mut self, tabs: Vec<(PathBuf, Option<PathBuf>)>) -> Self {
+        self.tabs.extend(
+            tabs.into_iter()
+                .map(|(path, selected)| Tab { path, selected }),
+        );
+        self
+    }
+
+    /// Uses the loaded plugins (their keybindings must be part of the parser).
+    pub fn with_plugins(mut self, plugins: Arc<PluginHost>) -> Self {
+        self.plugins = plugins;
+        self.refresh_plugin_status();
+        self
+    }
+
+    /// Starts with an overlay that lists the config files that could not be parsed.
+    pub fn with_config_errors(mut self, errors: Vec<ConfigError>) -> Self {
+        if !errors.is_empty() {
+            self.mode = Mode::ConfigErrors {
+                overlay: ConfigErrorOverlay::new(errors),
+            };
+        }
+        self
+    }
+
+    /// Reports every directory change to the notifier.
+    pub fn with_dir_notifier(mut self, dir_notifier: Option<DirNotifier>) -> Self {
+        self.dir_notifier = dir_notifier;
+        self
+    }
+
     fn redraw_header(&mut self) {
         self.redraw.header = true;
     }
@@ -201,7 +787,6 @@ impl PanelManager {
 
     fn redraw_left(&mut self) {
         self.redraw.left = true;
-        self.redraw.log = true;
     }
 
     fn redraw_center(&mut self) {
@@ -210,12 +795,10 @@ impl PanelManager {
         // also redraw header and footer
         self.redraw.footer = true;
         self.redraw.header = true;
-        self.redraw.log = true;
     }
 
     fn redraw_right(&mut self) {
         self.redraw.right = true;
-        self.redraw.log = true;
     }
 
     fn redraw_console(&mut self) {
@@ -231,40 +814,6 @@ impl PanelManager {
         self.redraw.console = true;
     }
 
-    fn redraw_log(&mut self) {
-        self.redraw.log = true;
-    }
-
-    fn draw_log(&mut self) -> Result<()> {
-        if !self.redraw.log || !self.show_log {
-            return Ok(());
-        }
-
-        let mut y = self.layout.footer().saturating_sub(2);
-
-        for (level, line) in self.logger.get().into_iter().rev() {
-            let content = match level {
-                log::Level::Error => style::PrintStyledContent("error".red().bold()),
-                log::Level::Warn => style::PrintStyledContent("warn".yellow().bold()),
-                log::Level::Info => style::PrintStyledContent("info".dark_green().bold()),
-                log::Level::Debug => style::PrintStyledContent("debug".dark_blue()),
-                log::Level::Trace => style::PrintStyledContent("trace".grey()),
-            };
-            queue!(
-                self.stdout,
-                cursor::MoveTo(0, y),
-                Clear(ClearType::CurrentLine),
-                content,
-                style::Print(": "),
-                style::PrintStyledContent(line.grey()),
-                style::Print("  "),
-            )?;
-            y = y.saturating_sub(1);
-        }
-        self.redraw.log = false;
-        Ok(())
-    }
-
     // Prints our header
     fn draw_header(&mut self) -> Result<()> {
         if !self.redraw.header {
@@ -282,19 +831,82 @@ impl PanelManager {
             .unwrap_or_default()
             .to_str()
             .unwrap_or_default();
-        let absolute = absolute.to_str().unwrap_or_default();
+        // Remote paths are shown as URL
+        let url = self.vfs.url(&absolute);
+        let absolute = url.as_deref().or(absolute.to_str()).unwrap_or_default();
+        let file_name = if absolute.ends_with(file_name) {
+            file_name
+        } else {
+            ""
+        };
 
         let (prefix, suffix) = absolute.split_at(absolute.len() - file_name.len());
+        let plugin_status = self.plugin_status.1.clone();
+        // Inside the trash, show how much it contains
+        let trash_summary = self
+            .trash
+            .as_ref()
+            .filter(|trash| self.center.panel().path().starts_with(trash.files_dir()))
+            .map(|_| {
+                let (items, size) = self.trash_listing.summary;
+                format!(
+                    "  ({})",
+                    tr_fmt(Msg::TrashSummary, &[&items, &file_size_str(size)])
+                )
+            });
 
         queue!(
             self.stdout,
             cursor::MoveTo(0, 0),
             Clear(ClearType::CurrentLine),
+        )?;
+        if self.is_root {
+            queue!(
+                self.stdout,
+                style::PrintStyledContent(" ROOT ".bold().red().reverse()),
+                style::Print(" "),
+            )?;
+        }
+        queue!(
+            self.stdout,
             style::PrintStyledContent(prompt.dark_green().bold()),
             style::Print(" "),
             style::PrintStyledContent(prefix.to_string().dark_blue().bold()),
             style::PrintStyledContent(suffix.to_string().white().bold()),
         )?;
+        if let Some(pinned) = &self.pinned {
+            queue!(
+                self.stdout,
+                style::PrintStyledContent(format!("  (pinned: {})", pinned.display()).dark_grey()),
+            )?;
+        }
+        if let Some(status) = plugin_status {
+            queue!(
+                self.stdout,
+                style::PrintStyledContent(format!("  {status}").dark_cyan()),
+            )?;
+        }
+        if let Some(summary) = trash_summary {
+            queue!(self.stdout, style::PrintStyledContent(summary.dark_grey()))?;
+        }
+        if self.tabs.len() > 1 {
+            let tab_width = self.tabs.len() as u16 * 3;
+            queue!(
+                self.stdout,
+                cursor::MoveTo(self.layout.width().saturating_sub(tab_width), 0)
+            )?;
+            for idx in 0..self.tabs.len() {
+                let label = format!(" {} ", idx + 1);
+                if idx == self.active_tab {
+                    queue!(
+                        self.stdout,
+                        style::PrintStyledContent(label.dark_green().bold().reverse())
+                    )?;
+                } else {
+                    queue!(self.stdout, style::PrintStyledContent(label.grey()))?;
+                }
+            }
+        }
         self.redraw.header = false;
         Ok(())
     }
@@ -311,27 +923,124 @@ impl PanelManager {
             Clear(ClearType::CurrentLine),
         )?;
 
+        if let Mode::Confirm { operation } = &self.mode {
+            let question = match operation {
+                Dangerous::Delete(files) => tr_fmt(Msg::ConfirmDelete, &[&files.len()]),
+                Dangerous::DeletePermanently(files) if self.trash.is_none() => {
+                    tr_fmt(Msg::ConfirmDeletePermanently, &[&files.len()])
+                }
+                Dangerous::DeletePermanently(files) => {
+                    tr_fmt(Msg::ConfirmBypassTrash, &[&files.len()])
+                }
+                Dangerous::Shred {
+                    files,
+                    copy_on_write: false,
+                } => tr_fmt(Msg::ConfirmShred, &[&files.len()]),
+                Dangerous::Shred { files, .. } => {
+                    tr_fmt(Msg::ConfirmShredCopyOnWrite, &[&files.len()])
+                }
+                Dangerous::EmptyTrash(items) => tr_fmt(Msg::ConfirmEmptyTrash, &[items]),
+                Dangerous::ProjectCommand { command, root } => tr_fmt(
+                    Msg::ConfirmProjectCommand,
+                    &[&root.join(PROJECT_FILE).display(), command],
+                ),
+                Dangerous::PasteOverwrite => tr(Msg::ConfirmOverwrite).to_string(),
+            };
+            queue!(
+                self.stdout,
+                style::PrintStyledContent(question.bold().red().reverse()),
+            )?;
+            return Ok(());
+        }
+        if let Mode::FirstLetter = self.mode {
+            queue!(
+                self.stdout,
+                style::PrintStyledContent(tr(Msg::FirstLetter).bold().dark_green().reverse()),
+            )?;
+            return Ok(());
+        }
         if let Mode::Search { input } = &self.mode {
             queue!(
                 self.stdout,
-                style::PrintStyledContent("Search:".bold().dark_green().reverse()),
-                style::PrintStyledContent(format!(" {input}").bold().red()),
+                style::PrintStyledContent(tr(Msg::Search).bold().dark_green().reverse()),
+                style::PrintStyledContent(format!(" {}", input.text()).bold().red()),
+            )?;
+            return Ok(());
+        }
+        if let Mode::SetMark | Mode::JumpMark = self.mode {
+            let prompt = match self.mode {
+                Mode::SetMark => tr(Msg::SetMark),
+                _ => tr(Msg::JumpMark),
+            };
+            queue!(
+                self.stdout,
+                style::PrintStyledContent(prompt.bold().dark_green().reverse()),
+            )?;
+            return Ok(());
+        }
+        if let Mode::FrecentJump { input, best } = &self.mode {
+            let best = best
+                .as_ref()
+                .map(|path| format!("  -> {}", path.display()))
+                .unwrap_or_default();
+            queue!(
+                self.stdout,
+                style::PrintStyledContent(tr(Msg::FrecentJump).bold().dark_green().reverse()),
+                style::PrintStyledContent(format!(" {}", input.text()).bold()),
+                style::PrintStyledContent(best.dark_grey()),
+            )?;
+            return Ok(());
+        }
+        if let Mode::RunCommand { input } = &self.mode {
+            queue!(
+                self.stdout,
+                style::PrintStyledContent(tr(Msg::RunCommand).bold().dark_green().reverse()),
+                style::PrintStyledContent(format!(" {}", input.text()).bold()),
             )?;
             return Ok(());
         }
         if let Mode::Rename { input } = &self.mode {
             queue!(
                 self.stdout,
-                style::PrintStyledContent("Rename:".bold().dark_green().reverse()),
-                style::PrintStyledContent(format!(" {input}").bold().yellow()),
+                style::PrintStyledContent(tr(Msg::Rename).bold().dark_green().reverse()),
+                style::PrintStyledContent(format!(" {}", input.text()).bold().yellow()),
+            )?;
+            return Ok(());
+        }
+        if let Mode::Export { input, .. } = &self.mode {
+            queue!(
+                self.stdout,
+                style::PrintStyledContent(tr(Msg::Export).bold().dark_green().reverse()),
+                style::PrintStyledContent(format!(" {}", input.text()).grey()),
+            )?;
+            return Ok(());
+        }
+        if let Mode::Chown {
+            input,
+            users,
+            groups,
+            ..
+        } = &self.mode
+        {
+            let completion = complete(input.text(), users, groups)
+                .and_then(|c| c.strip_prefix(input.text()).map(String::from))
+                .unwrap_or_default();
+            queue!(
+                self.stdout,
+                style::PrintStyledContent(tr(Msg::Chown).bold().dark_green().reverse()),
+                style::PrintStyledContent(format!(" {}", input.text()).bold().yellow()),
+                style::PrintStyledContent(completion.dark_grey()),
             )?;
             return Ok(());
         }
         if let Mode::CreateItem { input, is_dir } = &self.mode {
             let (prompt, item) = if *is_dir {
-                ("Make Directory:", format!(" {input}").dark_green().bold())
+                (
+                    tr(Msg::MakeDirectory),
+                    format!(" {}", input.text()).dark_green().bold(),
+                )
             } else {
-                ("Touch:", format!(" {input}").grey())
+                (tr(Msg::Touch), format!(" {}", input.text()).grey())
             };
             queue!(
                 self.stdout,
@@ -340,48 +1049,49 @@ impl PanelManager {
             )?;
             return Ok(());
         }
-        if let Some(selection) = self.center.panel().selected() {
-            let path = selection.path();
-            let permissions;
-            let other;
-            // TODO: Maybe we can put all of this into the DirElem and be done with it.
-            if let Ok(metadata) = path.metadata() {
-                permissions = unix_mode::to_string(metadata.permissions().mode());
-                let modified = metadata
-                    .modified()
-                    .map(OffsetDateTime::from)
-                    .map(|t| {
-                        format!(
-                            "{}-{:02}-{:02} {:02}:{:02}:{:02}",
-                            t.year(),
-                            u8::from(t.month()),
-                            t.day(),
-                            t.hour(),
-                            t.minute(),
-                            t.second()
-                        )
-                    })
-                    .unwrap_or_else(|_| String::from("cannot read timestamp"));
-                let user = get_user_by_uid(metadata.uid())
-                    .and_then(|u| u.name().to_str().map(String::from))
-                    .unwrap_or_default();
-                let group = get_group_by_gid(metadata.gid())
-                    .and_then(|g| g.name().to_str().map(String::from))
-                    .unwrap_or_default();
-                let size_str = file_size_str(metadata.size());
-                let mime_type = mime_guess::from_path(path).first_raw().unwrap_or_default();
-                other = format!("{user} {group} {size_str} {modified} {mime_type}");
-            } else {
-                permissions = String::from("------------");
-                other = String::from("");
-            }
-
+        let mut info = None;
+        if self.preview_pending {
+            // Skip the (expensive) metadata lookup while a movement key is held down
             queue!(
                 self.stdout,
-                style::PrintStyledContent(permissions.dark_cyan()),
-                Print("   "),
-                Print(other)
+                style::PrintStyledContent("------------".dark_grey()),
             )?;
+        } else if let Some(path) = self.center.panel().selected_path_owned() {
+            info = self.footer_info.get(&path).cloned();
+            if info
+                .as_ref()
+                .is_none_or(|info| info.read_at.elapsed() > FOOTER_INFO_TTL)
+            {
+                self.request_footer_info(path);
+            }
+            match info.as_ref() {
+                Some(info) => {
+                    let segments = self
+                        .footer_format
+                        .0
+                        .render(|field| self.footer_value(field, Some(info)));
+                    let link_exists = info.link.as_ref().is_some_and(|(_, exists)| *exists);
+                    for (field, text) in segments {
+                        match field {
+                            Some(Field::Permissions) => {
+                                queue!(self.stdout, style::PrintStyledContent(text.dark_cyan()))?
+                            }
+                            Some(Field::Link) if link_exists => {
+                                queue!(self.stdout, style::PrintStyledContent(text.cyan()))?
+                            }
+                            Some(Field::Link) => {
+                                queue!(self.stdout, style::PrintStyledContent(text.red()))?
+                            }
+                            _ => queue!(self.stdout, Print(text))?,
+                        }
+                    }
+                }
+                // Still reading the metadata
+                None => queue!(
+                    self.stdout,
+                    style::PrintStyledContent("------------   ...".dark_grey()),
+                )?,
+            }
         } else {
             queue!(
                 self.stdout,
@@ -390,8 +1100,13 @@ impl PanelManager {
         }
 
         let key_buffer = self.parser.buffer();
-        let (n, m) = self.center.panel().index_vs_total();
-        let n_files_string = format!("{n}/{m} ");
+        let right: String = self
+            .footer_format
+            .1
+            .render(|field| self.footer_value(field, info.as_ref()))
+            .into_iter()
+            .map(|(_, text)| text)
+            .collect();
 
         queue!(
             self.stdout,
@@ -403,58 +1118,102 @@ impl PanelManager {
             cursor::MoveTo(
                 self.layout
                     .width()
-                    .saturating_sub(n_files_string.len() as u16),
+                    .saturating_sub(right.chars().count() as u16),
                 self.layout.footer(),
             ),
-            style::PrintStyledContent(n_files_string.white()),
+            style::PrintStyledContent(right.white()),
         )?;
         self.redraw.footer = false;
         Ok(())
     }
 
+    /// Value of a placeholder of the footer templates.
+    fn footer_value(&self, field: Field, info: Option<&FooterInfo>) -> String {
+        if let Some(value) = info.and_then(|info| field.of_info(info)) {
+            return value;
+        }
+        match field {
+            Field::Marked if self.marks.is_empty() => String::new(),
+            Field::Marked => format!("{} {}", self.marks.len(), tr(Msg::Marked)),
+            Field::Position => {
+                let (n, m) = self.center.panel().index_vs_total();
+                format!("{n}/{m}")
+            }
+            Field::Scroll => self.center.panel().scroll_indicator().to_string(),
+            _ => String::new(),
+        }
+    }
+
     fn draw(&mut self) -> Result<()> {
         if !self.redraw.any() {
             return Ok(());
         }
         self.stdout.queue(cursor::Hide)?;
-        self.draw_footer()?;
+        // The popup is drawn on top of the panels
+        if self.which_key.is_some() && (self.redraw.left || self.redraw.center || self.redraw.right)
+        {
+            self.redraw.console = true;
+        }
         self.draw_header()?;
+        // NOTE: The panels must be drawn before the footer,
+        // because the scroll-indicator depends on the last drawn frame.
         self.draw_panels()?;
+        self.draw_footer()?;
         self.draw_console()?;
-        self.draw_log()?;
+        if let Some((prompt, input)) = self.mode.prompt() {
+            let column = prompt.chars().count() + 1 + input.cursor_column();
+            queue!(
+                self.stdout,
+                cursor::MoveTo(column as u16, self.layout.footer()),
+                cursor::Show,
+            )?;
+        }
         self.stdout.flush()
     }
 
     fn draw_panels(&mut self) -> Result<()> {
-        let (start, end) = (self.layout.y_range.start, self.layout.y_range.end);
-        let height = if self.show_log {
-            let cap = self.logger.capacity();
-            start..end.saturating_sub(cap as u16)
-        } else {
-            start..end
-        };
+        self.sync_marks();
+        self.center.panel_mut().sync_git_status(&self.git_status);
+        self.left.panel_mut().sync_trash(&self.trash_listing);
+        self.center.panel_mut().sync_trash(&self.trash_listing);
+        let height = self.layout.y_range.clone();
         if self.redraw.left {
-            self.left.panel_mut().draw(
-                &mut self.stdout,
-                self.layout.left_x_range.clone(),
-                height.clone(),
-            )?;
+            // The left column may be collapsed
+            if !self.layout.left_x_range.is_empty() {
+                draw_with_indicator(
+                    &mut self.stdout,
+                    self.left.panel_mut(),
+                    self.layout.left_x_range.clone(),
+                    height.clone(),
+                )?;
+            }
             self.redraw.left = false;
         }
         if self.redraw.center {
-            self.center.panel_mut().draw(
+            draw_with_indicator(
                 &mut self.stdout,
+                self.center.panel_mut(),
                 self.layout.center_x_range.clone(),
                 height.clone(),
             )?;
             self.redraw.center = false;
         }
+        if self.redraw.right && self.layout.right_x_range.is_empty() {
+            self.redraw.right = false;
+        }
         if self.redraw.right {
-            self.right.panel_mut().draw(
-                &mut self.stdout,
-                self.layout.right_x_range.clone(),
-                height,
-            )?;
+            if let Some(job) = self.shown_job.as_mut() {
+                job.draw(&mut self.stdout, self.layout.right_x_range.clone(), height)?;
+            } else {
+                if let PreviewPanel::File(file) = self.right.panel_mut() {
+                    file.set_wrap(self.wrap);
+                }
+                self.right.panel_mut().draw(
+                    &mut self.stdout,
+                    self.layout.right_x_range.clone(),
+                    height,
+                )?;
+            }
             self.redraw.right = false;
         }
         Ok(())
@@ -462,12 +1221,46 @@ impl PanelManager {
 
     fn draw_console(&mut self) -> Result<()> {
         if self.redraw.console {
-            if let Mode::Console { console } = &mut self.mode {
-                console.draw(
+            let x_range = self.layout.left_x_range.start..self.layout.right_x_range.end;
+            match &mut self.mode {
+                Mode::Console { console } => {
+                    console.draw(&mut self.stdout, x_range, self.layout.y_range.clone())?
+                }
+                Mode::Bookmarks { overlay } => {
+                    overlay.draw(&mut self.stdout, x_range, self.layout.y_range.clone())?
+                }
+                Mode::ConfigErrors { overlay } => {
+                    overlay.draw(&mut self.stdout, x_range, self.layout.y_range.clone())?
+                }
+                Mode::Help { overlay } => {
+                    overlay.draw(&mut self.stdout, x_range, self.layout.y_range.clone())?
+                }
+                Mode::Log { overlay } => {
+                    overlay.draw(&mut self.stdout, x_range, self.layout.y_range.clone())?
+                }
+                Mode::JumpMark => self.session_marks.draw(
                     &mut self.stdout,
-                    self.layout.left_x_range.start..self.layout.right_x_range.end,
+                    x_range,
                     self.layout.y_range.clone(),
-                )?;
+                )?,
+                Mode::DiskUsage { overlay } => {
+                    overlay.draw(&mut self.stdout, x_range, self.layout.y_range.clone())?
+                }
+                Mode::Devices { overlay } => {
+                    overlay.draw(&mut self.stdout, x_range, self.layout.y_range.clone())?
+                }
+                Mode::Mounts { overlay } => {
+                    overlay.draw(&mut self.stdout, x_range, self.layout.y_range.clone())?
+                }
+                Mode::Jobs { overlay } => {
+                    overlay.draw(&mut self.stdout, x_range, self.layout.y_range.clone())?
+                }
+                Mode::Normal => {
+                    if let Some(which_key) = self.which_key.as_mut() {
+                        which_key.draw(&mut self.stdout, x_range, self.layout.y_range.clone())?
+                    }
+                }
+                _ => (),
             }
             self.redraw.console = false;
         }
@@ -508,49 +1301,212 @@ impl PanelManager {
     //     }
     // }
 
+    /// Cycles through the visibility states:
+    /// hide dotfiles -> show everything -> hide dotfiles and gitignored files.
     fn toggle_hidden(&mut self) {
-        self.show_hidden = !self.show_hidden;
-        self.left.panel_mut().set_hidden(self.show_hidden);
-        self.center.panel_mut().set_hidden(self.show_hidden);
+        self.visibility = self.visibility.next();
+        info!("Visibility: {:?}", self.visibility);
+        track_ignored(self.visibility == Visibility::HideIgnored);
+        self.left.panel_mut().set_visibility(self.visibility);
+        self.center.panel_mut().set_visibility(self.visibility);
         if let PreviewPanel::Dir(panel) = self.right.panel_mut() {
-            panel.set_hidden(self.show_hidden);
+            panel.set_visibility(self.visibility);
         };
         // FIX: Re-selecting path. If we are in a hidden directory, we want to re-select the
         // correct path in the left panel.
-        self.left
-            .panel_mut()
-            .select_path(self.center.panel().path());
+        if self.pinned.is_none() {
+            self.left
+                .panel_mut()
+                .select_path(self.center.panel().path());
+        }
         self.redraw_everything();
     }
 
-    fn toggle_log(&mut self) {
-        self.show_log = !self.show_log;
-        if self.show_log {
-            self.redraw_log();
+    /// Pins the left panel to the current directory - or releases the pin,
+    /// so that the left panel shows the parent directory again.
+    fn toggle_pin(&mut self) {
+        if self.pinned.take().is_some() {
+            info!("Unpinned left panel");
+            self.left
+                .new_panel_instant(self.center.panel().path().parent());
+            self.left
+                .panel_mut()
+                .select_path(self.center.panel().path());
         } else {
-            // Redraw everything, so that the current log gets overdrawn by the panels
-            self.redraw_everything();
+            let path = self.center.panel().path().to_path_buf();
+            info!("Pinned left panel to {}", path.display());
+            self.left.update_panel(self.center.panel().clone());
+            self.pinned = Some(path);
         }
+        self.redraw_panels();
     }
 
-    fn select(&mut self, path: &Path) {
-        if self.center.panel().selected_path() == Some(path) {
-            return;
+    fn toggle_log(&mut self) {
+        if let Mode::Log { .. } = self.mode {
+            self.mode = Mode::Normal;
+            self.redraw_panels();
+        } else {
+            self.mode = Mode::Log {
+                overlay: LogOverlay::new(self.logger.get().into()),
+            };
+            self.redraw_console();
         }
-        self.center.panel_mut().select_path(path);
-        self.right
-            .new_panel_delayed(self.center.panel().selected_path());
-        self.redraw_center();
-        self.redraw_right();
     }
 
-    fn move_up(&mut self, step: usize) {
-        trace!("move-up");
-        if self.center.panel_mut().up(step) {
-            self.right
+    /// Remembers the location of the active tab.
+    fn save_tab(&mut self) {
+        self.tabs[self.active_tab] = Tab {
+            path: self.center.panel().path().to_path_buf(),
+            selected: self.center.panel().selected_path_owned(),
+        };
+    }
+
+    /// Opens a new tab right after the active one and switches to it.
+    fn open_tab(&mut self, path: PathBuf) {
+        self.save_tab();
+        self.tabs.insert(
+            self.active_tab + 1,
+            Tab {
+                path,
+                selected: None,
+            },
+        );
+        self.switch_tab(self.active_tab + 1);
+    }
+
+    /// Switches to the tab with the given index (wrapping around at the end).
+    fn switch_tab(&mut self, idx: usize) {
+        self.save_tab();
+        self.active_tab = idx % self.tabs.len();
+        let tab = self.tabs[self.active_tab].clone();
+        self.jump(tab.path);
+        if let Some(selected) = tab.selected {
+            self.select(&selected);
+        }
+        self.redraw_header();
+    }
+
+    /// Changes the ratios of the column widths.
+    fn change_layout(&mut self, change: LayoutChange) {
+        let [left, center, right] = &mut self.ratios;
+        match change {
+            LayoutChange::GrowCenter if *right > 1 => {
+                *center += 1;
+                *right -= 1;
+            }
+            LayoutChange::ShrinkCenter if *center > 1 => {
+                *center -= 1;
+                *right += 1;
+            }
+            LayoutChange::ToggleLeft => {
+                *left = if *left == 0 {
+                    self.config.layout.ratios[0].max(1)
+                } else {
+                    0
+                };
+            }
+            LayoutChange::TogglePreview if self.is_narrow() => {
+                self.narrow_preview = !self.narrow_preview
+            }
+            LayoutChange::TogglePreview => self.show_preview = !self.show_preview,
+            LayoutChange::ToggleWrap => {
+                self.wrap = !self.wrap;
+                self.redraw_right();
+                return;
+            }
+            _ => return,
+        }
+        self.relayout(self.layout.size());
+        self.redraw_everything();
+    }
+
+    fn is_narrow(&self) -> bool {
+        self.layout.width() < self.config.layout.narrow_width
+    }
+
+    /// Computes the layout for the terminal size.
+    ///
+    /// Terminals narrower than `narrow_width` only show the center panel (and the preview on demand).
+    /// A hidden preview is not generated at all.
+    fn relayout(&mut self, terminal_size: (u16, u16)) {
+        let [left, center, right] = self.ratios;
+        let ratios = if terminal_size.0 < self.config.layout.narrow_width {
+            [0, 1, self.narrow_preview as u16]
+        } else if self.show_preview {
+            self.ratios
+        } else {
+            [left, center + right, 0]
+        };
+        self.layout = MillerColumns::from_size(terminal_size, ratios);
+
+        let preview = !self.layout.right_x_range.is_empty();
+        if preview != self.right.is_enabled() {
+            self.right.set_enabled(preview);
+            self.right
                 .new_panel_delayed(self.center.panel().selected_path());
+        }
+    }
+
+    /// Closes the active tab - unless it is the last one.
+    fn close_tab(&mut self) {
+        if self.tabs.len() == 1 {
+            info!("Cannot close the last tab");
+            return;
+        }
+        self.tabs.remove(self.active_tab);
+        // NOTE: Don't save the closed tab, when we switch to the next one
+        let idx = self.active_tab.min(self.tabs.len() - 1);
+        self.active_tab = idx;
+        let tab = self.tabs[idx].clone();
+        self.jump(tab.path);
+        if let Some(selected) = tab.selected {
+            self.select(&selected);
+        }
+        self.redraw_everything();
+    }
+
+    /// Opens every marked directory in its own tab.
+    fn open_marked_in_tabs(&mut self) {
+        let dirs: Vec<PathBuf> = self
+            .marked_items()
+            .into_iter()
+            .filter(|p| p.is_dir())
+            .collect();
+        if dirs.is_empty() {
+            info!("No marked directories to open");
+            return;
+        }
+        self.save_tab();
+        let first = self.active_tab + 1;
+        for (offset, path) in dirs.into_iter().enumerate() {
+            self.tabs.insert(
+                first + offset,
+                Tab {
+                    path,
+                    selected: None,
+                },
+            );
+        }
+        self.unmark_all_items();
+        self.switch_tab(first);
+    }
+
+    fn select(&mut self, path: &Path) {
+        if self.center.panel().selected_path() == Some(path) {
+            return;
+        }
+        self.center.panel_mut().select_path(path);
+        self.right
+            .new_panel_delayed(self.center.panel().selected_path());
+        self.redraw_center();
+        self.redraw_right();
+    }
+
+    fn move_up(&mut self, step: usize) {
+        trace!("move-up");
+        if self.center.panel_mut().up(step) {
+            self.update_preview();
             self.redraw_center();
-            self.redraw_right();
             // self.stack.push(Operation::Move(Movement::Up));
         }
     }
@@ -558,11 +1514,27 @@ impl PanelManager {
     fn move_down(&mut self, step: usize) {
         trace!("move-down");
         if self.center.panel_mut().down(step) {
+            self.update_preview();
+            self.redraw_center();
+            // self.stack.push(Operation::Move(Movement::Down));
+        }
+    }
+
+    /// Requests the preview for the selected item after an up- or down-movement.
+    ///
+    /// If the movements arrive in quick succession (because the key is held down),
+    /// the request is deferred until the stream of events pauses.
+    fn update_preview(&mut self) {
+        let now = Instant::now();
+        let repeated = now.duration_since(self.last_move) < KEY_REPEAT_PAUSE;
+        self.last_move = now;
+        if repeated {
+            self.preview_pending = true;
+        } else {
+            self.preview_pending = false;
             self.right
                 .new_panel_delayed(self.center.panel().selected_path());
-            self.redraw_center();
             self.redraw_right();
-            // self.stack.push(Operation::Move(Movement::Down));
         }
     }
 
@@ -572,8 +1544,15 @@ impl PanelManager {
         if let Some(selected) = self.center.panel().selected_path().map(|p| p.to_path_buf()) {
             // If the selected item is a directory, all panels will shift to the left
             if selected.is_dir() {
+                if selected.is_symlink() {
+                    if let Ok(target) = selected.canonicalize() {
+                        self.link_origins.insert(target, selected.clone());
+                    }
+                }
                 self.previous = self.center.panel().path().to_path_buf();
-                self.left.update_panel(self.center.panel().clone());
+                if self.pinned.is_none() {
+                    self.left.update_panel(self.center.panel().clone());
+                }
                 self.center
                     .new_panel_instant(self.right.panel().maybe_path());
                 self.right
@@ -591,24 +1570,32 @@ impl PanelManager {
                 //
                 // Solution:
                 // "Freeze" the panel and deactivate the watchers while the open function is blocked.
+                //
+                // GUI applications run in the background, so there is nothing to freeze.
                 info!("Opening '{}'", selected.display());
-                self.center.freeze();
-                if let Err(e) = self.opener.open(selected) {
-                    /* failed to open selected */
+                if self.opener.is_terminal(&selected) {
+                    self.center.freeze();
+                    if let Err(e) = self.opener.open(selected) {
+                        /* failed to open selected */
+                        error!("Opening failed: {e}");
+                    }
+                    self.center.unfreeze();
+                    self.redraw_everything();
+                } else if let Err(e) = self.opener.open(selected) {
                     error!("Opening failed: {e}");
                 }
-                self.center.unfreeze();
-                self.redraw_everything();
             }
             // self.stack.push(Operation::Move(Movement::Right));
-            //
-            self.unmark_left_right();
         }
     }
 
     // TODO: Make this more efficient - the swapping was too nice to give it up
     fn move_left(&mut self) {
         trace!("move-left");
+        if self.pinned.is_some() {
+            self.move_left_pinned();
+            return;
+        }
         // If the left panel is empty, we cannot move left:
         if self.left.panel().selected_path().is_none() {
             return;
@@ -618,20 +1605,73 @@ impl PanelManager {
             .update_panel(PreviewPanel::Dir(self.center.panel().clone()));
         self.center.update_panel(self.left.panel().clone());
         // | m | l | m |
-        // TODO: When we followed some symlink we don't want to take the parent here.
-        self.left
-            .new_panel_instant(self.center.panel().path().parent());
-        self.left
-            .panel_mut()
-            .select_path(self.center.panel().path());
-
-        self.unmark_left_right();
+        // When we followed a symlink, we go back to the directory of the link.
+        let current = self.logical_path(self.center.panel().path());
+        self.left.new_panel_instant(current.parent());
+        self.left.panel_mut().select_path(&current);
 
         // All panels needs to be redrawn
         self.redraw_panels();
         // self.stack.push(Operation::Move(Movement::Left));
     }
 
+    /// Moves the center panel to its parent directory, without touching the pinned left panel.
+    fn move_left_pinned(&mut self) {
+        let current = self.logical_path(self.center.panel().path());
+        let Some(parent) = current.parent().map(|p| p.to_path_buf()) else {
+            return;
+        };
+        self.previous = self.center.panel().path().to_path_buf();
+        self.right
+            .update_panel(PreviewPanel::Dir(self.center.panel().clone()));
+        self.center.new_panel_instant(Some(&parent));
+        self.center.panel_mut().select_path(&current);
+        self.redraw_panels();
+    }
+
+    /// Reads the footer metadata of the item in the background.
+    fn request_footer_info(&mut self, path: PathBuf) {
+        // A hanging request (e.g. on a dead mount) is not repeated
+        if !self.footer_pending.insert(path.clone()) {
+            return;
+        }
+        let tx = self.footer_tx.clone();
+        tokio::task::spawn_blocking(move || {
+            let info = FooterInfo::read(&path);
+            let _ = tx.send((path, info));
+        });
+    }
+
+    /// Returns the symlink we entered the directory through - or the directory itself.
+    fn logical_path(&self, path: &Path) -> PathBuf {
+        self.link_origins
+            .get(path)
+            .cloned()
+            .unwrap_or_else(|| path.to_path_buf())
+    }
+
+    /// Jumps to the directory of the target of the selected symlink, and selects the target.
+    fn follow_link(&mut self) {
+        let Some(selected) = self.center.panel().selected_path_owned() else {
+            return;
+        };
+        if !selected.is_symlink() {
+            return;
+        }
+        match selected.canonicalize() {
+            Ok(target) => {
+                if let Some(parent) = target.parent() {
+                    self.jump(parent.to_path_buf());
+                    self.center.panel_mut().select_path(&target);
+                    self.right
+                        .new_panel_delayed(self.center.panel().selected_path());
+                    self.redraw_panels();
+                }
+            }
+            Err(e) => error!("Cannot follow {}: {e}", selected.display()),
+        }
+    }
+
     fn jump(&mut self, path: PathBuf) {
         trace!("jump-to {}", path.display());
         // Don't do anything, if the path hasn't changed
@@ -640,8 +1680,10 @@ impl PanelManager {
         }
         if path.exists() {
             self.previous = self.center.panel().path().to_path_buf();
-            self.left.new_panel_instant(path.parent());
-            self.left.panel_mut().select_path(&path);
+            if self.pinned.is_none() {
+                self.left.new_panel_instant(path.parent());
+                self.left.panel_mut().select_path(&path);
+            }
             self.center.new_panel_instant(Some(&path));
             self.right
                 .new_panel_delayed(self.center.panel().selected_path());
@@ -649,6 +1691,50 @@ impl PanelManager {
         }
     }
 
+    /// Connects to the remote location (if necessary) and jumps into it.
+    fn open_remote(&mut self, url: &RemoteUrl) {
+        self.center.freeze();
+        let result = self.vfs.open(url);
+        self.center.unfreeze();
+        match result {
+            Ok(path) if path.is_dir() => self.jump(path),
+            Ok(path) => error!("No such directory: {}", path.display()),
+            Err(e) => error!("Cannot connect to {}: {e}", url.host),
+        }
+        self.redraw_everything();
+    }
+
+    /// Falls back to the nearest existing ancestor, if the directory of the center panel
+    /// has vanished (e.g. because it was deleted or unmounted).
+    ///
+    /// A pinned directory that has vanished is unpinned.
+    fn check_vanished(&mut self) {
+        if let Some(pinned) = self.pinned.as_ref().filter(|p| !p.is_dir()) {
+            warn!("{}", tr_fmt(Msg::PinVanished, &[&pinned.display()]));
+            self.pinned = None;
+            self.left
+                .new_panel_instant(self.center.panel().path().parent());
+            self.left
+                .panel_mut()
+                .select_path(self.center.panel().path());
+            self.redraw_left();
+        }
+        let current = self.center.panel().path().to_path_buf();
+        if current.is_dir() {
+            return;
+        }
+        if let Some(ancestor) = current.ancestors().skip(1).find(|p| p.is_dir()) {
+            warn!(
+                "{}",
+                tr_fmt(
+                    Msg::DirectoryVanished,
+                    &[&current.display(), &ancestor.display()]
+                )
+            );
+            self.jump(ancestor.to_path_buf());
+        }
+    }
+
     fn move_cursor(&mut self, movement: Move) {
         // NOTE: Movement functions needs to determine which panels require a redraw.
         match movement {
@@ -664,288 +1750,1284 @@ impl PanelManager {
             Move::PageBackward => self.move_up(self.layout.height() as usize),
             Move::JumpTo(path) => self.jump(path.into()),
             Move::JumpPrevious => self.jump(self.previous.clone()),
+            Move::FollowLink => self.follow_link(),
+            Move::Percent => self.move_cursor_counted(Move::Percent, 1),
         };
     }
 
-    /// Returns a reference to all marked items.
-    fn marked_items(&self) -> Vec<&DirElem> {
-        let mut out = Vec::new();
-        out.extend(self.left.panel().elements().filter(|e| e.is_marked()));
-        out.extend(self.center.panel().elements().filter(|e| e.is_marked()));
-        if let PreviewPanel::Dir(panel) = self.right.panel() {
-            out.extend(panel.elements().filter(|e| e.is_marked()))
+    /// Moves the cursor `count` times (e.g. `5j` or `2h`).
+    fn move_cursor_counted(&mut self, movement: Move, count: usize) {
+        let height = self.layout.height() as usize;
+        match movement {
+            Move::Up => self.move_up(count),
+            Move::Down => self.move_down(count),
+            Move::HalfPageForward => self.move_down((height / 2).saturating_mul(count)),
+            Move::HalfPageBackward => self.move_up((height / 2).saturating_mul(count)),
+            Move::PageForward => self.move_down(height.saturating_mul(count)),
+            Move::PageBackward => self.move_up(height.saturating_mul(count)),
+            Move::Percent => {
+                // Without a count, "%" jumps to the middle of the list
+                let percent = if count == 1 && self.parser.count().is_none() {
+                    50
+                } else {
+                    count.min(100)
+                };
+                let (_, total) = self.center.panel().index_vs_total();
+                self.select_position(total.saturating_sub(1) * percent / 100 + 1);
+            }
+            Move::Left | Move::Right => {
+                for _ in 0..count {
+                    let before = self.center.panel().path().to_path_buf();
+                    self.move_cursor(movement.clone());
+                    // Stop at the root, or when a file was opened
+                    if self.center.panel().path() == before {
+                        break;
+                    }
+                }
+            }
+            _ => self.move_cursor(movement),
         }
-        out
     }
 
-    /// Unmarks all items in all panels
-    fn unmark_all_items(&mut self) {
-        self.center
-            .panel_mut()
-            .elements_mut()
-            .for_each(|item| item.unmark());
-        self.unmark_left_right();
+    /// Starts the delay for the completion popup when a key sequence is partially typed,
+    /// and updates or hides the popup otherwise.
+    fn update_which_key(&mut self) {
+        if matches!(self.mode, Mode::Normal) && self.parser.is_pending() {
+            if self.pending_since.is_none() {
+                self.pending_since = Some(Instant::now());
+            }
+            if self.which_key.is_some() {
+                self.which_key = Some(WhichKey::new(&self.parser));
+                // Erase the rows of the previous completions
+                self.redraw_panels();
+            }
+        } else {
+            self.pending_since = None;
+            if self.which_key.take().is_some() {
+                self.redraw_panels();
+            }
+        }
     }
 
-    /// Unmarks all items in the left and right panels.
-    fn unmark_left_right(&mut self) {
-        self.left
-            .panel_mut()
-            .elements_mut()
-            .for_each(|item| item.unmark());
-
-        if let PreviewPanel::Dir(panel) = self.right.panel_mut() {
-            panel.elements_mut().for_each(|item| item.unmark());
+    /// Copies the path, directory or name of the selected item to the system clipboard.
+    fn copy_to_clipboard(&self, part: PathPart) {
+        let Some(selected) = self.center.panel().selected_path() else {
+            return;
+        };
+        let absolute = std::path::absolute(selected).unwrap_or_else(|_| selected.to_path_buf());
+        let text = match part {
+            PathPart::Path => absolute.display().to_string(),
+            PathPart::Directory => absolute.parent().unwrap_or(&absolute).display().to_string(),
+            PathPart::Name => absolute
+                .file_name()
+                .map(|n| n.to_string_lossy().to_string())
+                .unwrap_or_default(),
+        };
+        match set_system_clipboard(&text) {
+            Ok(_) => info!("Copied '{text}' to clipboard"),
+            Err(e) => error!("Cannot copy '{text}' to clipboard: {e}"),
         }
-        self.redraw_panels();
     }
 
-    /// Returns all marked paths *or* the selected path.
+    /// Moves the items to the trash - or removes them, if there is no trash.
     ///
-    /// Note: This is an exclusive or - the selected path is not
-    /// returned, when there are marked paths.
-    /// If there are no marked paths, the selected path is automatically
-    /// marked - and therefore it is returned by this function.
-    fn marked_or_selected(&mut self) -> Vec<PathBuf> {
-        let files: Vec<PathBuf> = self
-            .marked_items()
-            .iter()
-            .map(|item| item.path().to_path_buf())
-            .collect();
-        // If we have nothing marked, take the current selection
-        if files.is_empty() {
-            self.center.panel_mut().mark_selected_item();
-            if let Some(path) = self.center.panel().selected_path() {
-                vec![path.to_path_buf()]
-            } else {
-                Vec::new()
+    /// With `permanently`, the items are removed even if there is a trash.
+    fn delete_items(&mut self, files: Vec<PathBuf>, permanently: bool) {
+        info!("Deleted {} items", files.len());
+        self.unmark_all_items();
+        // self.stack.push(Operation::MoveItems { from: files.clone(), to: trash_dir.path().to_path_buf() });
+        let trash = self.trash.as_ref().filter(|_| !permanently);
+        let unwatched = trash.is_some() && self.trash_watcher.is_none();
+        for file in files {
+            let result = match trash {
+                Some(trash) => trash.put(&file).map(|_| ()),
+                None if file.is_dir() && !file.is_symlink() => std::fs::remove_dir_all(&file),
+                None => std::fs::remove_file(&file),
+            };
+            if let Err(e) = result {
+                error!("Cannot delete {}: {e}", file.display());
             }
-        } else {
-            files
         }
+        if unwatched {
+            self.list_trash();
+        }
+        self.left.reload();
+        self.center.reload();
+        self.right.reload();
     }
 
-    pub async fn run(mut self) -> Result<PathBuf> {
-        // Initial draw
-        self.redraw_everything();
-        self.draw()?;
-
-        loop {
-            let event_reader = self.event_reader.next().fuse();
-            tokio::select! {
-                // Check incoming new logs
-                () = self.logger.update() => {
-                    self.redraw_log();
+    /// Overwrites and removes the items (in the background).
+    fn shred_items(&mut self, files: Vec<PathBuf>) {
+        self.unmark_all_items();
+        let job = self.jobs.start(format!("shred {} items", files.len()));
+        tokio::task::spawn_blocking(move || {
+            let _job = job;
+            let mut shredded = 0;
+            for file in files {
+                match shred(&file) {
+                    Ok(()) => shredded += 1,
+                    Err(e) => error!("Cannot shred {}: {e}", file.display()),
                 }
-                // Check incoming new dir-panels
-                result = self.dir_rx.recv() => {
-                    // Shutdown if sender has been dropped
-                    if result.is_none() {
-                        break;
-                    }
-                    let (panel, state) = result.unwrap();
+            }
+            info!("{}", tr_fmt(Msg::Shredded, &[&shredded]));
+        });
+    }
 
-                    // Find panel and update it
-                    if self.center.check_update(&state) {
-                        // Notification::new().summary("update-center").body(&format!("{:?}", state)).show().unwrap();
-                        self.center.update_panel(panel);
-                        // update preview (if necessary)
-                        self.right.new_panel_delayed(self.center.panel().selected_path());
-                        self.redraw_center();
-                        self.redraw_right();
-                        self.redraw_console();
-                    } else if self.left.check_update(&state) {
-                        // Notification::new().summary("update-left").body(&format!("{:?}", state)).show().unwrap();
-                        self.left.update_panel(panel);
-                        self.left.panel_mut().select_path(self.center.panel().path());
-                        self.redraw_left();
-                        self.redraw_console();
-                    } else {
-                        error!("unknown panel update: {:?}", state);
-                    }
+    /// Lists the trash in the background - if a listing is already running,
+    /// another one follows once it is done.
+    fn list_trash(&mut self) {
+        let Some(trash) = self.trash.as_ref().map(Trash::handle) else {
+            return;
+        };
+        let (running, changed) = &mut self.trash_listing_state;
+        if *running {
+            *changed = true;
+            return;
+        }
+        *running = true;
+        let tx = self.trash_listing_tx.clone();
+        tokio::task::spawn_blocking(move || {
+            std::thread::sleep(TRASH_DEBOUNCE);
+            let _ = tx.send(TrashListing {
+                entries: trash.entries(),
+                summary: trash.summary(),
+                generation: 0,
+            });
+        });
+    }
+
+    /// Permanently removes everything in the trash (in the background).
+    ///
+    /// The trash-watcher updates the listing, once the items are gone.
+    fn empty_trash(&mut self) {
+        let Some(trash) = self.trash.as_ref() else {
+            return;
+        };
+        let (files_dir, info_dir) = (trash.files_dir(), trash.info_dir());
+        let job = self.jobs.start("empty the trash".to_string());
+        tokio::task::spawn_blocking(move || {
+            let _job = job;
+            let mut last_report = Instant::now();
+            let progress = |done, total| {
+                if last_report.elapsed() >= TRASH_PROGRESS_INTERVAL {
+                    info!("{}", tr_fmt(Msg::EmptyingTrash, &[&done, &total]));
+                    last_report = Instant::now();
                 }
-                // Check incoming new preview-panels
-                result = self.prev_rx.recv() => {
-                    // Shutdown if sender has been dropped
-                    if result.is_none() {
-                        break;
-                    }
-                    let (panel, state) = result.unwrap();
+            };
+            let failed = trash::empty(&files_dir, &info_dir, progress);
+            if failed == 0 {
+                info!("{}", tr(Msg::TrashIsEmpty));
+            } else {
+                warn!("{}", tr_fmt(Msg::EmptiedTrash, &[&failed]));
+            }
+        });
+    }
 
-                    if self.right.check_update(&state) {
-                        self.right.update_panel(panel);
-                        self.redraw_right();
-                        self.redraw_console();
-                    }
+    /// Copies or moves the items of the clipboard into the current directory (in the background).
+    ///
+    /// Without own clipboard, the files copied in another application are pasted.
+    fn paste(&mut self, overwrite: bool) {
+        self.unmark_all_items();
+        let current_path = self.center.panel().path().to_path_buf();
+        let Some(clipboard) = self.clipboard.take() else {
+            let published = self.published.clone();
+            let tx = self.import_tx.clone();
+            tokio::task::spawn_blocking(move || {
+                let files = get_clipboard_files();
+                // Our own files were already pasted (or moved away)
+                if !files.is_empty() && files != published {
+                    let _ = tx.send((current_path, overwrite, files));
                 }
-                // Check incoming new events
-                result = event_reader => {
-                    // Shutdown if reader has been dropped
-                    match result {
-                        Some(event) => {
-                            if self.handle_event(event?)? {
-                                break;
-                            }
-                        }
-                        None => break,
-                    }
+            });
+            return;
+        };
+        self.paste_into(clipboard, current_path, overwrite);
+    }
+
+    /// Copies or moves the items of the clipboard into the directory (in the background).
+    fn paste_into(&mut self, clipboard: Clipboard, target: PathBuf, overwrite: bool) {
+        let copy_config = self.config.copy;
+        self.fire_hook(HookEvent::BeforePaste, clipboard.files.clone());
+        let hook_tx = self.hook_tx.clone();
+        let job = self.jobs.start(format!(
+            "paste {} items into {}",
+            clipboard.files.len(),
+            target.display()
+        ));
+        // A thread of its own, because its priority may be lowered (which cannot be undone)
+        std::thread::spawn(move || {
+            lower_priority(copy_config.nice, copy_config.io_priority);
+            info!(
+                "paste {} items, overwrite = {}",
+                clipboard.files.len(),
+                overwrite
+            );
+            let sizes: Vec<u64> = clipboard.files.iter().map(|file| item_size(file)).collect();
+            job.set_total(sizes.iter().sum());
+            let mut done = 0;
+            for (file, size) in clipboard.files.iter().zip(sizes) {
+                job.progress(file, done);
+                done += size;
+                let result = if clipboard.cut {
+                    move_item(file, &target, copy_config)
+                } else {
+                    copy_item(file, &target, copy_config)
+                };
+                if let Err(e) = result {
+                    error!("{e}");
                 }
             }
-            // Always redraw what needs to be redrawn
-            self.draw()?;
+            let pasted = clipboard
+                .files
+                .iter()
+                .filter_map(|file| Some(target.join(file.file_name()?)))
+                .collect();
+            let _ = hook_tx.send((HookEvent::AfterPaste, pasted));
+        });
+        self.left.reload();
+        self.center.reload();
+        self.right.reload();
+        self.redraw_panels();
+    }
+
+    /// Selects the item at the position (starting at 1, like the footer shows it).
+    fn select_position(&mut self, position: usize) {
+        let (current, _) = self.center.panel().index_vs_total();
+        if position > current {
+            self.move_down(position - current);
+        } else if position < current {
+            self.move_up(current - position);
         }
-        // Cleanup after leaving this function
-        self.stdout
-            .queue(Clear(ClearType::All))?
-            .queue(cursor::MoveTo(0, 0))?
-            .queue(cursor::Show)?
-            .flush()?;
-        Ok(self.center.panel().path().to_path_buf())
     }
 
-    /// Handles the terminal events.
+    /// Returns all marked paths - across all directories.
+    fn marked_items(&self) -> Vec<PathBuf> {
+        self.marks.paths()
+    }
+
+    /// Runs a command of the prompt, after its macros are expanded.
     ///
-    /// Returns Ok(true) if the application needs to shut down.
-    fn handle_event(&mut self, event: Event) -> Result<bool> {
-        if let Event::Key(key_event) = event {
-            // If we hit escape - go back to normal mode.
-            if let KeyCode::Esc = key_event.code {
-                if let Mode::Console { .. } = self.mode {
-                    self.jump(self.pre_console_path.clone());
-                }
-                self.mode = Mode::Normal;
-                self.parser.clear();
-                self.center.panel_mut().clear_search();
-                self.redraw_panels();
-                self.redraw_footer();
-                self.unmark_all_items();
+    /// With `-d` in front, the command runs detached - otherwise the TUI is suspended until it exits.
+    fn run_command(&mut self, command: &str) {
+        let (detached, command) = match command.strip_prefix("-d ") {
+            Some(command) => (true, command.trim_start()),
+            None => (false, command),
+        };
+        if command.is_empty() {
+            return;
+        }
+        let dir = self.center.panel().path().to_path_buf();
+        let command = expand_macros(
+            command,
+            self.center.panel().selected_path(),
+            &self.marked_items(),
+            &dir,
+        );
+        let result = if detached {
+            spawn_shell(&command, &dir)
+        } else {
+            self.center.freeze();
+            let result = run_shell(&command, &dir);
+            self.center.unfreeze();
+            result
+        };
+        if let Err(e) = result {
+            error!("'{command}' failed: {e}");
+        }
+    }
+
+    /// Unmarks all items in all directories
+    fn unmark_all_items(&mut self) {
+        self.marks.clear();
+        self.redraw_panels();
+    }
+
+    /// Applies the mark-registry to all panels.
+    fn sync_marks(&mut self) {
+        self.left.panel_mut().sync_marks(&self.marks);
+        self.center.panel_mut().sync_marks(&self.marks);
+        if let PreviewPanel::Dir(panel) = self.right.panel_mut() {
+            panel.sync_marks(&self.marks);
+        }
+    }
+
+    /// Requests the git status of the center panel in the background.
+    ///
+    /// Unless `force` is set, this only happens when the directory of the center panel has changed.
+    fn refresh_git_status(&mut self, force: bool) {
+        let dir = self.center.panel().path().to_path_buf();
+        if !force && dir == self.git_requested {
+            return;
+        }
+        self.git_requested = dir.clone();
+        let tx = self.git_tx.clone();
+        tokio::task::spawn_blocking(move || {
+            let status = git_status(&dir).unwrap_or(GitStatusMap {
+                dir,
+                ..Default::default()
+            });
+            let _ = tx.send(status);
+        });
+    }
+
+    /// State that the plugins can query.
+    fn plugin_state(&self) -> PluginState {
+        PluginState {
+            cwd: self.center.panel().path().to_path_buf(),
+            selected: self.center.panel().selected_path_owned(),
+            marked: self.marks.paths(),
+        }
+    }
+
+    /// Adds the imported bookmarks behind the jump-marks (skipping their directories).
+    fn add_bookmarks(&mut self, imported: Vec<Bookmark>) {
+        for bookmark in imported {
+            if !self.bookmarks.iter().any(|b| b.path == bookmark.path) {
+                self.bookmarks.push(bookmark);
             }
-            match &mut self.mode {
-                Mode::Normal => {
-                    match self.parser.add_event(key_event) {
-                        Command::Move(direction) => {
-                            self.move_cursor(direction);
+        }
+        if let Mode::Bookmarks { overlay } = &mut self.mode {
+            overlay.set_bookmarks(self.bookmarks.clone());
+            self.redraw_console();
+        }
+    }
+
+    /// Calls the status functions of the plugins again, if the state they can query has changed.
+    ///
+    /// The header only shows the cached text, so redrawing it never runs Lua code.
+    fn refresh_plugin_status(&mut self) {
+        let state = self.plugin_state();
+        if self.plugin_status.0.as_ref() == Some(&state) {
+            return;
+        }
+        let status = self.plugins.status(state.clone());
+        if status != self.plugin_status.1 {
+            self.redraw_header();
+        }
+        self.plugin_status = (Some(state), status);
+    }
+
+    /// Executes a command of another program.
+    ///
+    /// Returns true if the application needs to shut down.
+    fn handle_remote(&mut self, command: RemoteCommand) -> bool {
+        debug!("remote: {command:?}");
+        match command {
+            RemoteCommand::Jump(path) => {
+                if path.is_dir() {
+                    self.jump(path);
+                } else {
+                    warn!("remote: {} is not a directory", path.display());
+                }
+            }
+            RemoteCommand::Select(path) => {
+                if let Some(parent) = path.parent().filter(|_| path.exists()) {
+                    self.jump(parent.to_path_buf());
+                    self.select(&path);
+                } else {
+                    warn!("remote: {} does not exist", path.display());
+                }
+            }
+            RemoteCommand::Mark(path) => {
+                self.marks.toggle(&path);
+                self.redraw_panels();
+            }
+            RemoteCommand::Quit => return true,
+        }
+        false
+    }
+
+    /// Runs the shell hook and the plugin hooks of the event.
+    ///
+    /// The actions requested by the plugins are queued in `plugin_actions`.
+    fn fire_hook(&mut self, event: HookEvent, files: Vec<PathBuf>) {
+        let state = self.plugin_state();
+        let context = HookContext {
+            cwd: state.cwd,
+            selected: state.selected,
+            marked: state.marked,
+            files,
+        };
+        if let Some(command) = self.config.hooks.command(event) {
+            run_hook(command, event, &context);
+        }
+        let actions = self.plugins.fire(event, &context);
+        self.plugin_actions.extend(actions);
+    }
+
+    /// Shows the current directory in the title of the terminal (if enabled).
+    fn update_title(&mut self) -> Result<()> {
+        let cwd = self.center.panel().path();
+        if !self.config.terminal.title || self.title_path.as_deref() == Some(cwd) {
+            return Ok(());
+        }
+        if self.title_path.is_none() {
+            // Saves the previous title, so it can be restored on exit
+            self.stdout.queue(Print("\x1b[22;0t"))?;
+        }
+        let title = format!("rfm: {}", cwd.display());
+        self.title_path = Some(cwd.to_path_buf());
+        self.stdout.queue(terminal::SetTitle(title))?;
+        Ok(())
+    }
+
+    /// Fires the `cd` and `select` hooks, if the directory or the selection has changed.
+    ///
+    /// While a movement key is held down, the selection is only reported once it is released.
+    fn fire_change_hooks(&mut self) {
+        let cwd = self.center.panel().path();
+        if cwd != self.hook_cwd {
+            self.hook_cwd = cwd.to_path_buf();
+            self.fire_hook(HookEvent::Cd, Vec::new());
+        }
+        let selected = self.center.panel().selected_path();
+        if !self.preview_pending && selected != self.hook_selected.as_deref() {
+            self.hook_selected = selected.map(Path::to_path_buf);
+            self.fire_hook(HookEvent::Select, Vec::new());
+        }
+    }
+
+    /// Executes the actions of a plugin.
+    ///
+    /// Returns Ok(true) if the application needs to shut down.
+    fn run_plugin_actions(&mut self, actions: Vec<PluginAction>) -> Result<bool> {
+        if actions.is_empty() {
+            return Ok(false);
+        }
+        for action in actions {
+            match action {
+                PluginAction::Command(name) => match self.parser.command_by_name(&name) {
+                    // Plugins must not call each other (which could recurse endlessly)
+                    Some(Command::Plugin { .. }) | None => {
+                        warn!("Plugin: there is no command '{name}'")
+                    }
+                    Some(command) => {
+                        if self.execute(command)? {
+                            return Ok(true);
                         }
-                        Command::ViewTrash => {
-                            self.jump(self.trash_dir.path().to_path_buf());
+                    }
+                },
+                PluginAction::Cd(path) => self.jump(path),
+            }
+        }
+        self.redraw_panels();
+        Ok(false)
+    }
+
+    /// Opens the disk usage overview of the current directory and starts the scan in the background.
+    fn show_disk_usage(&mut self) {
+        let root = self.center.panel().path().to_path_buf();
+        let (overlay, cancel) = DiskUsageOverlay::new(root.clone());
+        let tx = self.du_tx.clone();
+        tokio::task::spawn_blocking(move || {
+            let progress = |items, size| {
+                let _ = tx.send((root.clone(), DuUpdate::Progress { items, size }));
+            };
+            if let Some(tree) = scan(&root, &cancel, progress) {
+                let _ = tx.send((root, DuUpdate::Done(tree)));
+            }
+        });
+        self.mode = Mode::DiskUsage { overlay };
+        self.redraw_console();
+    }
+
+    /// Opens the list of block devices.
+    fn show_devices(&mut self) {
+        self.mode = Mode::Devices {
+            overlay: DeviceOverlay::new(Vec::new()),
+        };
+        self.redraw_console();
+        self.refresh_devices();
+    }
+
+    /// Lists the devices in the background - D-Bus and gio may take a while.
+    fn refresh_devices(&self) {
+        let tx = self.device_tx.clone();
+        tokio::task::spawn_blocking(move || {
+            let _ = tx.send(DeviceUpdate::Listed(list_devices()));
+        });
+    }
+
+    /// Runs the action on the selected device in the background.
+    fn change_device(&mut self, action: DeviceAction) {
+        let Mode::Devices { overlay } = &self.mode else {
+            return;
+        };
+        let Some(device) = overlay.selected().cloned() else {
+            return;
+        };
+        let tx = self.device_tx.clone();
+        tokio::task::spawn_blocking(move || {
+            let _ = tx.send(action.run(&device));
+        });
+    }
+
+    /// Reports the result of a device action - mounted devices are opened.
+    fn handle_device_update(&mut self, update: DeviceUpdate) {
+        match update {
+            DeviceUpdate::Listed(Ok(devices)) => {
+                if let Mode::Devices { overlay } = &mut self.mode {
+                    overlay.update(devices);
+                    self.redraw_console();
+                }
+                return;
+            }
+            DeviceUpdate::Listed(Err(e)) => {
+                error!("Cannot list the devices (is udisks2 running?): {e}");
+                if let Mode::Devices { .. } = self.mode {
+                    self.mode = Mode::Normal;
+                    self.redraw_panels();
+                }
+                return;
+            }
+            DeviceUpdate::Mounted(path) => {
+                info!("{}", tr_fmt(Msg::Mounted, &[&path.display()]));
+                self.mode = Mode::Normal;
+                self.jump(path);
+            }
+            DeviceUpdate::Unmounted(device) => {
+                info!("{}", tr_fmt(Msg::Unmounted, &[&device.display()]))
+            }
+            DeviceUpdate::Ejected(device) => {
+                info!("{}", tr_fmt(Msg::Ejected, &[&device.display()]))
+            }
+            DeviceUpdate::Failed(e) => error!("{e}"),
+        }
+        if let Mode::Devices { .. } = self.mode {
+            self.refresh_devices();
+        }
+        self.redraw_everything();
+    }
+
+    /// Returns (and marks) `count` items from the selection on, if a count was typed
+    /// in front of the command (e.g. `10dd`) - otherwise all marked paths *or* the selected path.
+    fn counted_or_marked(&mut self) -> Vec<PathBuf> {
+        let Some(count) = self.parser.count() else {
+            return self.marked_or_selected();
+        };
+        let paths = self.center.panel().paths_from_selected(count);
+        for path in paths.iter() {
+            self.marks.insert(path.clone());
+        }
+        self.redraw_center();
+        paths
+    }
+
+    /// Returns all marked paths *or* the selected path.
+    ///
+    /// Note: This is an exclusive or - the selected path is not
+    /// returned, when there are marked paths.
+    /// If there are no marked paths, the selected path is automatically
+    /// marked - and therefore it is returned by this function.
+    fn marked_or_selected(&mut self) -> Vec<PathBuf> {
+        let files = self.marked_items();
+        // If we have nothing marked, take the current selection
+        if files.is_empty() {
+            if let Some(path) = self.center.panel().selected_path_owned() {
+                self.marks.insert(path.clone());
+                self.redraw_center();
+                vec![path]
+            } else {
+                Vec::new()
+            }
+        } else {
+            files
+        }
+    }
+
+    /// Writes the visible listing of the center panel to a file - or to the system clipboard,
+    /// if no file was given.
+    ///
+    /// Files ending with `.csv` are written as CSV, everything else as plain text.
+    fn export_listing(&self, target: &str, long: bool) {
+        if target.is_empty() {
+            let listing = self.center.panel().listing(long, false);
+            match set_system_clipboard(&listing) {
+                Ok(_) => info!("Copied listing to clipboard"),
+                Err(e) => error!("Cannot copy listing to clipboard: {e}"),
+            }
+            return;
+        }
+        let path = self.center.panel().path().join(target);
+        let csv = path
+            .extension()
+            .map(|ext| ext.eq_ignore_ascii_case("csv"))
+            .unwrap_or(false);
+        let listing = self.center.panel().listing(long, csv);
+        match std::fs::write(&path, listing) {
+            Ok(_) => info!("Exported listing to {}", path.display()),
+            Err(e) => error!("Cannot export listing to {}: {e}", path.display()),
+        }
+    }
+
+    pub async fn run(mut self) -> Result<PathBuf> {
+        // Initial draw
+        self.refresh_git_status(false);
+        self.update_title()?;
+        self.redraw_everything();
+        self.draw()?;
+
+        loop {
+            let event_reader = self.event_reader.next().fuse();
+            let which_key_at = self.pending_since.unwrap_or_else(Instant::now) + WHICH_KEY_DELAY;
+            tokio::select! {
+                // Check incoming new logs
+                () = self.logger.update() => {
+                    if let Mode::Log { overlay } = &mut self.mode {
+                        overlay.update(self.logger.get().into());
+                        self.redraw_console();
+                    }
+                }
+                // Check incoming new dir-panels
+                result = self.dir_rx.recv() => {
+                    // Shutdown if sender has been dropped
+                    if result.is_none() {
+                        break;
+                    }
+                    let (panel, state) = result.unwrap();
+
+                    // Batches of a directory that is still being read are dropped,
+                    // once the panel shows a different directory.
+                    let accepts = |managed: &ManagedPanel<DirPanel>| {
+                        managed.check_update(&state)
+                            && (!panel.is_partial() || managed.panel().path() == panel.path())
+                    };
+
+                    // Find panel and update it
+                    if accepts(&self.center) {
+                        // Notification::new().summary("update-center").body(&format!("{:?}", state)).show().unwrap();
+                        self.center.update_panel(panel);
+                        self.refresh_git_status(true);
+                        // update preview (if necessary)
+                        self.right.new_panel_delayed(self.center.panel().selected_path());
+                        self.redraw_center();
+                        self.redraw_right();
+                        self.redraw_console();
+                    } else if accepts(&self.left) {
+                        // Notification::new().summary("update-left").body(&format!("{:?}", state)).show().unwrap();
+                        self.left.update_panel(panel);
+                        // The parent panel always follows the center - also when a sibling
+                        // of the current directory was created or removed.
+                        if self.pinned.is_none() {
+                            let current = self.logical_path(self.center.panel().path());
+                            self.left.panel_mut().select_path(&current);
                         }
-                        Command::ToggleHidden => self.toggle_hidden(),
-                        Command::ToggleLog => self.toggle_log(),
-                        Command::Cd => {
-                            self.pre_console_path = self.center.panel().path().to_path_buf();
-                            self.mode = Mode::Console {
-                                console: DirConsole::from_panel(self.center.panel()),
-                            };
+                        self.redraw_left();
+                        self.redraw_console();
+                    } else if !panel.is_partial() {
+                        error!("unknown panel update: {:?}", state);
+                    }
+                    self.check_vanished();
+                }
+                // Check incoming new preview-panels
+                result = self.prev_rx.recv() => {
+                    // Shutdown if sender has been dropped
+                    if result.is_none() {
+                        break;
+                    }
+                    let (panel, state) = result.unwrap();
+
+                    if self.right.check_update(&state) {
+                        self.right.update_panel(panel);
+                        self.redraw_right();
+                        self.redraw_console();
+                    }
+                }
+                // Catch up with the preview, once a held movement key is released
+                () = tokio::time::sleep_until((self.last_move + KEY_REPEAT_PAUSE).into()), if self.preview_pending => {
+                    self.preview_pending = false;
+                    self.right.new_panel_delayed(self.center.panel().selected_path());
+                    self.redraw_right();
+                    self.redraw_footer();
+                }
+                // Show the completions of a partially typed key sequence
+                () = tokio::time::sleep_until(which_key_at.into()), if self.pending_since.is_some() && self.which_key.is_none() => {
+                    self.which_key = Some(WhichKey::new(&self.parser));
+                    self.redraw_console();
+                }
+                // Check incoming footer metadata
+                Some((path, info)) = self.footer_rx.recv() => {
+                    self.footer_pending.remove(&path);
+                    if self.footer_info.len() >= FOOTER_INFO_CAPACITY {
+                        self.footer_info.clear();
+                    }
+                    if self.center.panel().selected_path() == Some(path.as_path()) {
+                        self.redraw_footer();
+                    }
+                    self.footer_info.insert(path, info);
+                }
+                // Check incoming git status
+                Some(mut status) = self.git_rx.recv() => {
+                    if status.dir == self.center.panel().path() {
+                        status.generation = self.git_status.generation + 1;
+                        self.git_status = status;
+                        self.redraw_center();
+                    }
+                }
+                // Check incoming disk usage scans
+                Some((root, update)) = self.du_rx.recv() => {
+                    if let Mode::DiskUsage { overlay } = &mut self.mode {
+                        if overlay.root() == root {
+                            overlay.update(update);
                             self.redraw_console();
                         }
-                        Command::Search => {
-                            self.mode = Mode::Search { input: "".into() };
-                            self.redraw_footer();
-                        }
-                        Command::Rename => {
-                            let paths = self.marked_or_selected();
-                            // Single File Renaming without leaving rfm
-                            if paths.len() == 1 {
-                                if let Some(file_name) = paths[0].file_name() {
-                                    let input = file_name.to_string_lossy().to_string();
-                                    self.mode = Mode::Rename { input };
-                                    self.redraw_footer();
-                                }
-                            }
-                            // Bulkrenaming by spawning an editor to edit the file list.
-                            else {
-                                bulkrename(self, paths)?;
-                            }
-                        }
-                        Command::Next => {
-                            self.center.panel_mut().select_next_marked();
-                            self.right
-                                .new_panel_delayed(self.center.panel().selected_path());
-                            self.redraw_center();
-                            self.redraw_right();
-                        }
-                        Command::Previous => {
-                            self.center.panel_mut().select_prev_marked();
-                            self.right
-                                .new_panel_delayed(self.center.panel().selected_path());
-                            self.redraw_center();
+                    }
+                }
+                // Filesystems for the overview - unless another mode was entered meanwhile
+                Some(mounts) = self.mounts_rx.recv() => {
+                    if let Mode::Normal = self.mode {
+                        self.mode = Mode::Mounts {
+                            overlay: MountsOverlay::new(mounts),
+                        };
+                        self.redraw_console();
+                    }
+                }
+                // Results of device actions
+                Some(update) = self.device_rx.recv() => {
+                    self.handle_device_update(update);
+                }
+                // Bookmarks of the shell
+                Some(imported) = self.bookmarks_rx.recv() => {
+                    self.add_bookmarks(imported);
+                }
+                // A background job has ended - the status is published below
+                Some(()) = self.jobs_rx.recv() => {
+                    if let Some(job) = &self.shown_job {
+                        if self.jobs.info(job.id()).is_none() {
+                            self.shown_job = None;
                             self.redraw_right();
                         }
-                        Command::Mkdir => {
-                            self.mode = Mode::CreateItem {
-                                input: "".into(),
-                                is_dir: true,
-                            };
-                            self.redraw_footer();
-                        }
-                        Command::Touch => {
-                            self.mode = Mode::CreateItem {
-                                input: "".into(),
-                                is_dir: false,
-                            };
-                            self.redraw_footer();
-                        }
-                        Command::Mark => {
-                            self.center.panel_mut().mark_selected_item();
-                            self.move_cursor(Move::Down);
-                        }
-                        Command::Cut => {
-                            let files = self.marked_or_selected();
-                            info!("cut {} items", files.len());
-                            self.clipboard = Some(Clipboard { files, cut: true });
-                        }
-                        Command::Copy => {
-                            let files = self.marked_or_selected();
-                            info!("copying {} items", files.len());
-                            self.clipboard = Some(Clipboard { files, cut: false });
-                        }
-                        Command::Delete => {
-                            let files = self.marked_or_selected();
-                            info!("Deleted {} items", files.len());
-                            self.unmark_all_items();
-                            // self.stack.push(Operation::MoveItems { from: files.clone(), to: trash_dir.path().to_path_buf() });
-                            for file in files {
-                                let destination =
-                                    get_destination(&file, self.trash_dir.path()).unwrap();
-                                let result = std::fs::rename(&file, &destination);
-                                if let Err(e) = result {
-                                    error!("{e}");
-                                }
+                    }
+                    if let Mode::Jobs { .. } = self.mode {
+                        self.redraw_console();
+                    }
+                }
+                // Progress of the shown job and of the job list
+                () = tokio::time::sleep(JOB_REFRESH), if self.shown_job.is_some() || matches!(self.mode, Mode::Jobs { .. }) => {
+                    if self.shown_job.is_some() {
+                        self.redraw_right();
+                    }
+                    if let Mode::Jobs { .. } = self.mode {
+                        self.redraw_console();
+                    }
+                }
+                // Files of the system clipboard that were requested by a paste
+                Some((target, overwrite, files)) = self.import_rx.recv() => {
+                    self.paste_into(Clipboard { files, cut: false }, target, overwrite);
+                }
+                // `.rfm.toml` of the current directory tree
+                Some((dir, project)) = self.project_rx.recv() => {
+                    if let Some(project) = self.project.update(&dir, project) {
+                        self.parser.set_project(project);
+                    }
+                }
+                // Commands of other programs
+                Some(command) = self.remote_rx.recv() => {
+                    if self.handle_remote(command) {
+                        break;
+                    }
+                }
+                // Hooks of finished background operations
+                Some((event, files)) = self.hook_rx.recv() => {
+                    self.fire_hook(event, files);
+                }
+                // Keep the trash view up to date
+                Some(()) = self.trash_rx.recv() => {
+                    // A single deletion triggers multiple events - handle them at once
+                    while self.trash_rx.try_recv().is_ok() {}
+                    self.list_trash();
+                }
+                Some(listing) = self.trash_listing_rx.recv() => {
+                    self.trash_listing = TrashListing {
+                        generation: self.trash_listing.generation + 1,
+                        ..listing
+                    };
+                    let (_, changed) = std::mem::take(&mut self.trash_listing_state);
+                    if changed {
+                        self.list_trash();
+                    }
+                    let Some(trash) = self.trash.as_ref() else {
+                        continue;
+                    };
+                    let trash_dir = trash.files_dir();
+                    if self.center.panel().path() == trash_dir {
+                        self.center.reload();
+                    }
+                    if self.left.panel().path() == trash_dir {
+                        self.left.reload();
+                    }
+                    self.redraw_header();
+                }
+                // Check incoming new events
+                result = event_reader => {
+                    // Shutdown if reader has been dropped
+                    match result {
+                        Some(event) => {
+                            if self.handle_event(event?)? {
+                                break;
                             }
-                            self.left.reload();
-                            self.center.reload();
-                            self.right.reload();
+                            self.update_which_key();
+                            self.check_vanished();
                         }
-                        Command::Paste { overwrite } => {
-                            self.unmark_all_items();
-                            let current_path = self.center.panel().path().to_path_buf();
-                            let clipboard = std::mem::replace(&mut self.clipboard, None);
-                            tokio::task::spawn_blocking(move || {
-                                if let Some(clipboard) = clipboard {
-                                    info!(
-                                        "paste {} items, overwrite = {}",
-                                        clipboard.files.len(),
-                                        overwrite
-                                    );
-                                    for file in clipboard.files.iter() {
-                                        let result = if clipboard.cut {
-                                            move_item(file, &current_path)
-                                        } else {
-                                            copy_item(file, &current_path)
-                                        };
-                                        if let Err(e) = result {
-                                            error!("{e}");
-                                        }
-                                    }
-                                }
-                            });
-                            self.left.reload();
-                            self.center.reload();
-                            self.right.reload();
-                            self.redraw_panels();
+                        None => break,
+                    }
+                }
+            }
+            self.fire_change_hooks();
+            let actions = std::mem::take(&mut self.plugin_actions);
+            if self.run_plugin_actions(actions)? {
+                break;
+            }
+            self.refresh_plugin_status();
+            self.refresh_git_status(false);
+            self.update_title()?;
+            if let Some(notifier) = self.dir_notifier.as_mut() {
+                notifier.update(self.center.panel().path());
+            }
+            self.frecency.visit(self.center.panel().path());
+            if self.project.changed(self.center.panel().path()) {
+                let dir = self.center.panel().path().to_path_buf();
+                let tx = self.project_tx.clone();
+                tokio::task::spawn_blocking(move || {
+                    let project = Project::find(&dir);
+                    let _ = tx.send((dir, project));
+                });
+            }
+            if let Some(status_file) = self.status_file.as_mut() {
+                status_file.update(Status {
+                    cwd: self.center.panel().path().to_path_buf(),
+                    selected: self.center.panel().selected_path_owned(),
+                    marked: self.marks.len(),
+                    jobs: self.jobs.list(),
+                });
+            }
+            // Always redraw what needs to be redrawn
+            self.draw()?;
+        }
+        // Requests of the plugins are ignored, as rfm is about to exit anyway
+        self.fire_hook(HookEvent::Quit, Vec::new());
+
+        // Cleanup after leaving this function
+        if self.title_path.is_some() {
+            self.stdout.queue(Print("\x1b[23;0t"))?;
+        }
+        self.stdout
+            .queue(Clear(ClearType::All))?
+            .queue(cursor::MoveTo(0, 0))?
+            .queue(cursor::Show)?
+            .flush()?;
+        Ok(self.center.panel().path().to_path_buf())
+    }
+
+    /// Executes a command of the normal mode.
+    ///
+    /// Returns Ok(true) if the application needs to shut down.
+    fn execute(&mut self, command: Command) -> Result<bool> {
+        match command {
+            Command::Move(direction) => {
+                let count = self.parser.count().unwrap_or(1);
+                self.move_cursor_counted(direction, count);
+            }
+            Command::ViewTrash => match self.trash.as_ref().map(Trash::files_dir) {
+                Some(trash_dir) => self.jump(trash_dir),
+                None => warn!("{}", tr(Msg::NoTrashView)),
+            },
+            Command::EmptyTrash => match self.trash.as_ref() {
+                None => warn!("{}", tr(Msg::NoTrashView)),
+                Some(_) if self.trash_listing.summary.0 == 0 => info!("{}", tr(Msg::TrashIsEmpty)),
+                Some(_) => {
+                    self.mode = Mode::Confirm {
+                        operation: Dangerous::EmptyTrash(self.trash_listing.summary.0),
+                    };
+                    self.redraw_footer();
+                }
+            },
+            Command::ToggleHidden => self.toggle_hidden(),
+            Command::ToggleLog => self.toggle_log(),
+            Command::Help => {
+                self.mode = Mode::Help {
+                    overlay: HelpOverlay::new(&self.parser),
+                };
+                self.redraw_console();
+            }
+            Command::DiskUsage => self.show_disk_usage(),
+            Command::Devices => self.show_devices(),
+            Command::Mounts => {
+                let tx = self.mounts_tx.clone();
+                tokio::task::spawn_blocking(move || {
+                    let _ = tx.send(list_mounts());
+                });
+            }
+            Command::Jobs => {
+                self.mode = Mode::Jobs {
+                    overlay: JobsOverlay::new(self.jobs.clone()),
+                };
+                self.redraw_console();
+            }
+            // Commands of a `.rfm.toml` must be allowed before they run for the first time
+            Command::Shell {
+                command,
+                dir: Some(root),
+            } if self.project.untrusted().is_some() => {
+                self.mode = Mode::Confirm {
+                    operation: Dangerous::ProjectCommand { command, root },
+                };
+                self.redraw_footer();
+            }
+            Command::Shell { command, dir } => {
+                let dir = dir.unwrap_or_else(|| self.center.panel().path().to_path_buf());
+                self.center.freeze();
+                if let Err(e) = run_shell(&command, &dir) {
+                    error!("'{command}' failed: {e}");
+                }
+                self.center.unfreeze();
+                self.redraw_everything();
+            }
+            Command::Subshell => {
+                let dir = self.center.panel().path().to_path_buf();
+                self.center.freeze();
+                if let Err(e) = run_subshell(&dir) {
+                    error!("Cannot start the shell: {e}");
+                }
+                self.center.unfreeze();
+                self.left.reload();
+                self.right.reload();
+                self.redraw_everything();
+            }
+            Command::Run => {
+                self.command_history.reset();
+                self.mode = Mode::RunCommand {
+                    input: LineEditor::default(),
+                };
+                self.redraw_footer();
+            }
+            Command::CopyToClipboard(part) => self.copy_to_clipboard(part),
+            Command::FirstLetter => {
+                self.mode = Mode::FirstLetter;
+                self.redraw_footer();
+            }
+            Command::SetMark => {
+                self.mode = Mode::SetMark;
+                self.redraw_footer();
+            }
+            Command::JumpMark => {
+                self.mode = Mode::JumpMark;
+                self.redraw_footer();
+                self.redraw_console();
+            }
+            Command::FrecentJump => {
+                self.mode = Mode::FrecentJump {
+                    input: LineEditor::default(),
+                    best: None,
+                };
+                self.redraw_footer();
+            }
+            Command::ClearCache => {
+                // The left panel shares its cache with the center
+                let cleared = self.center.clear_cache() + self.right.clear_cache();
+                info!("Cleared {cleared} cached panels");
+            }
+            Command::TogglePin => self.toggle_pin(),
+            Command::NewTab => self.open_tab(self.center.panel().path().to_path_buf()),
+            Command::CloseTab => self.close_tab(),
+            Command::NextTab => self.switch_tab(self.active_tab + 1),
+            Command::PreviousTab => self.switch_tab(self.active_tab + self.tabs.len() - 1),
+            Command::OpenMarkedInTabs => self.open_marked_in_tabs(),
+            Command::Layout(change) => self.change_layout(change),
+            Command::Cd => {
+                self.pre_console_path = self.center.panel().path().to_path_buf();
+                self.console_history.reset();
+                self.mode = Mode::Console {
+                    console: DirConsole::from_panel(self.center.panel()),
+                };
+                self.redraw_console();
+            }
+            Command::Search => {
+                self.search_history.reset();
+                self.mode = Mode::Search {
+                    input: LineEditor::default(),
+                };
+                self.redraw_footer();
+            }
+            Command::Rename(cursor) => {
+                let paths = self.marked_or_selected();
+                // Single File Renaming without leaving rfm
+                if paths.len() == 1 {
+                    if let Some(file_name) = paths[0].file_name() {
+                        let input = LineEditor::rename(&file_name.to_string_lossy(), cursor);
+                        self.mode = Mode::Rename { input };
+                        self.redraw_footer();
+                    }
+                }
+                // Bulkrenaming by spawning an editor to edit the file list.
+                else {
+                    bulkrename(self, paths)?;
+                }
+            }
+            Command::Next => {
+                self.center.panel_mut().select_next_marked();
+                self.right
+                    .new_panel_delayed(self.center.panel().selected_path());
+                self.redraw_center();
+                self.redraw_right();
+            }
+            Command::Previous => {
+                self.center.panel_mut().select_prev_marked();
+                self.right
+                    .new_panel_delayed(self.center.panel().selected_path());
+                self.redraw_center();
+                self.redraw_right();
+            }
+            Command::Bookmarks => {
+                self.mode = Mode::Bookmarks {
+                    overlay: BookmarkOverlay::new(self.bookmarks.clone()),
+                };
+                self.redraw_console();
+            }
+            Command::Export { long } => {
+                self.mode = Mode::Export {
+                    input: LineEditor::default(),
+                    long,
+                };
+                self.redraw_footer();
+            }
+            Command::Mkdir => {
+                self.mode = Mode::CreateItem {
+                    input: LineEditor::default(),
+                    is_dir: true,
+                };
+                self.redraw_footer();
+            }
+            Command::Touch => {
+                self.mode = Mode::CreateItem {
+                    input: LineEditor::default(),
+                    is_dir: false,
+                };
+                self.redraw_footer();
+            }
+            Command::Mark => {
+                let count = self.parser.count().unwrap_or(1);
+                for path in self.center.panel().paths_from_selected(count) {
+                    self.marks.toggle(&path);
+                }
+                self.redraw_center();
+                self.move_cursor_counted(Move::Down, count);
+            }
+            Command::Cut => {
+                let files = self.counted_or_marked();
+                info!("cut {} items", files.len());
+                publish_clipboard(&files);
+                self.published = files.clone();
+                self.clipboard = Some(Clipboard { files, cut: true });
+            }
+            Command::Copy => {
+                let files = self.counted_or_marked();
+                info!("copying {} items", files.len());
+                publish_clipboard(&files);
+                self.published = files.clone();
+                self.clipboard = Some(Clipboard { files, cut: false });
+            }
+            Command::Delete => {
+                let files = self.counted_or_marked();
+                if self.trash.is_none() && !files.is_empty() {
+                    self.mode = Mode::Confirm {
+                        operation: Dangerous::DeletePermanently(files),
+                    };
+                    self.redraw_footer();
+                } else if self.is_root && files.iter().any(|f| f.is_dir() && !f.is_symlink()) {
+                    self.mode = Mode::Confirm {
+                        operation: Dangerous::Delete(files),
+                    };
+                    self.redraw_footer();
+                } else {
+                    self.delete_items(files, false);
+                }
+            }
+            Command::Shred => {
+                let files = self.counted_or_marked();
+                if !files.is_empty() {
+                    // The marked items may live on other filesystems than the current directory -
+                    // symlinks are only removed, so their own directory counts
+                    let copy_on_write = files.iter().any(|file| match file.is_symlink() {
+                        true => file.parent().is_some_and(is_copy_on_write),
+                        false => is_copy_on_write(file),
+                    });
+                    self.mode = Mode::Confirm {
+                        operation: Dangerous::Shred {
+                            files,
+                            copy_on_write,
+                        },
+                    };
+                    self.redraw_footer();
+                }
+            }
+            Command::DeletePermanently => {
+                let files = self.counted_or_marked();
+                if !files.is_empty() {
+                    self.mode = Mode::Confirm {
+                        operation: Dangerous::DeletePermanently(files),
+                    };
+                    self.redraw_footer();
+                }
+            }
+            Command::Chown { group_only } => {
+                let paths = self.marked_or_selected();
+                if !paths.is_empty() {
+                    let input = if group_only { ":" } else { "" };
+                    self.mode = Mode::Chown {
+                        input: LineEditor::new(input),
+                        paths,
+                        users: user_names(),
+                        groups: group_names(),
+                    };
+                    self.redraw_footer();
+                }
+            }
+            Command::Paste { overwrite } => {
+                if overwrite && self.is_root {
+                    self.mode = Mode::Confirm {
+                        operation: Dangerous::PasteOverwrite,
+                    };
+                    self.redraw_footer();
+                } else {
+                    self.paste(overwrite);
+                }
+            }
+            Command::PasteSymlink { relative } => {
+                self.unmark_all_items();
+                let current_path = self.center.panel().path().to_path_buf();
+                if let Some(clipboard) = self.clipboard.take() {
+                    info!(
+                        "linking {} items, relative = {relative}",
+                        clipboard.files.len()
+                    );
+                    for file in clipboard.files.iter() {
+                        if let Err(e) = link_item(file, &current_path, relative) {
+                            error!("Cannot link {}: {e}", file.display());
                         }
-                        Command::Quit => return Ok(true),
-                        Command::None => self.redraw_footer(),
+                    }
+                }
+                self.left.reload();
+                self.center.reload();
+                self.right.reload();
+                self.redraw_panels();
+            }
+            Command::PasteHardlink => {
+                self.unmark_all_items();
+                let current_path = self.center.panel().path().to_path_buf();
+                if let Some(clipboard) = self.clipboard.take() {
+                    info!("hardlinking {} items", clipboard.files.len());
+                    for file in clipboard.files.iter() {
+                        if let Err(e) = hardlink_item(file, &current_path) {
+                            error!("Cannot link {}: {e}", file.display());
+                        }
+                    }
+                }
+                self.left.reload();
+                self.center.reload();
+                self.right.reload();
+                self.redraw_panels();
+            }
+            Command::Plugin { index, .. } => {
+                let actions = self.plugins.call_binding(index, self.plugin_state());
+                return self.run_plugin_actions(actions);
+            }
+            Command::Prefilled { command, input } => {
+                let quit = self.execute(*command)?;
+                if let Some(editor) = self.mode.input_mut() {
+                    editor.set(input);
+                    self.redraw_footer();
+                }
+                return Ok(quit);
+            }
+            Command::Template(template) => {
+                self.run_command(&template);
+                self.redraw_everything();
+            }
+            Command::Quit => return Ok(true),
+            Command::None => self.redraw_footer(),
+        }
+        Ok(false)
+    }
+
+    /// Handles mouse events inside the center panel (only in mouse mode, see `--mouse`).
+    ///
+    /// A click selects an item, dragging marks a range of items and
+    /// ctrl-click toggles the mark of a single item.
+    fn handle_mouse(&mut self, event: MouseEvent) {
+        let y_range = &self.layout.y_range;
+        let in_center =
+            self.layout.center_x_range.contains(&event.column) && y_range.contains(&event.row);
+        let row = event.row.saturating_sub(y_range.start) as usize;
+        match event.kind {
+            MouseEventKind::ScrollDown => self.move_cursor(Move::Down),
+            MouseEventKind::ScrollUp => self.move_cursor(Move::Up),
+            MouseEventKind::Down(MouseButton::Left) if in_center => {
+                let Some(path) = self.center.panel().path_at_row(row) else {
+                    return;
+                };
+                if event.modifiers.contains(KeyModifiers::CONTROL) {
+                    self.marks.toggle(&path);
+                } else {
+                    self.drag = Some((path.clone(), Vec::new()));
+                }
+                self.center.panel_mut().select_path(&path);
+                self.update_preview();
+                self.redraw_center();
+            }
+            MouseEventKind::Drag(MouseButton::Left) if in_center => {
+                let Some(current) = self.center.panel().path_at_row(row) else {
+                    return;
+                };
+                let Some((anchor, marked)) = self.drag.as_mut() else {
+                    return;
+                };
+                let range = self.center.panel().paths_between(anchor, &current);
+                for path in marked.iter().filter(|p| !range.contains(p)) {
+                    self.marks.remove(path);
+                }
+                for path in range.iter() {
+                    self.marks.insert(path.clone());
+                }
+                *marked = range;
+                self.center.panel_mut().select_path(&current);
+                self.update_preview();
+                self.redraw_center();
+            }
+            MouseEventKind::Up(MouseButton::Left) => self.drag = None,
+            _ => (),
+        }
+    }
+
+    /// Handles the terminal events.
+    ///
+    /// Returns Ok(true) if the application needs to shut down.
+    fn handle_event(&mut self, event: Event) -> Result<bool> {
+        if let Event::Mouse(mouse_event) = event {
+            if let Mode::Normal = self.mode {
+                self.handle_mouse(mouse_event);
+            }
+        }
+        if let Event::Key(key_event) = event {
+            // If we hit escape - go back to normal mode.
+            if let KeyCode::Esc = key_event.code {
+                if let Mode::Console { .. } = self.mode {
+                    self.jump(self.pre_console_path.clone());
+                }
+                self.mode = Mode::Normal;
+                self.parser.clear();
+                self.center.panel_mut().clear_search();
+                self.right
+                    .new_panel_delayed(self.center.panel().selected_path());
+                self.redraw_panels();
+                self.redraw_footer();
+                self.unmark_all_items();
+            }
+            match &mut self.mode {
+                Mode::Normal => {
+                    let command = self.parser.add_event(key_event);
+                    if self.execute(command)? {
+                        return Ok(true);
                     }
                 }
                 Mode::Console { console } => match key_event.code {
@@ -956,9 +3038,21 @@ impl PanelManager {
                         self.redraw_console();
                     }
                     KeyCode::Enter => {
+                        if let Some(url) = console.url() {
+                            self.open_remote(&url);
+                        }
+                        let path = self.center.panel().path().to_string_lossy().to_string();
+                        self.console_history.push(&path);
                         self.mode = Mode::Normal;
                         self.redraw_panels();
                     }
+                    KeyCode::Up | KeyCode::Down => {
+                        let older = key_event.code == KeyCode::Up;
+                        if let Some(path) = console.recall(&mut self.console_history, older) {
+                            self.jump(path);
+                        }
+                        self.redraw_console();
+                    }
                     KeyCode::Tab => {
                         if let Some(path) = console.tab() {
                             self.jump(path);
@@ -981,67 +3075,490 @@ impl PanelManager {
                 },
                 Mode::CreateItem { input, is_dir } => {
                     match key_event.code {
-                        KeyCode::Backspace => {
-                            input.pop();
-                            self.redraw_footer();
-                        }
                         KeyCode::Enter => {
-                            let current_path = self.center.panel().path();
-                            let create_fn = if *is_dir {
-                                |item| fs_extra::dir::create(item, false)
-                            } else {
-                                |item| {
-                                    let _ = OpenOptions::new()
-                                        .read(true)
-                                        .append(true)
-                                        .create(true)
-                                        .open(item)?;
-                                    Ok(())
+                            // Braces create many items at once (like `img_{01..10}.txt`)
+                            for name in expand_braces(input.text().trim()) {
+                                let item = self.center.panel().path().join(name);
+                                if let Err(e) = create_item(&item, *is_dir) {
+                                    error!("{e}");
                                 }
-                            };
-                            if let Err(e) = create_fn(current_path.join(input.trim())) {
-                                error!("{e}");
                             }
                             // self.stack.push(Operation::Mkdir { path: new_dir.clone() });
                             self.mode = Mode::Normal;
                             self.redraw_panels();
                         }
                         KeyCode::Tab => {
-                            /* autocomplete here ? */
+                            let current_path = self.center.panel().path();
+                            if let Some(completion) = complete_path(current_path, input.text()) {
+                                input.set(completion);
+                            }
+                            self.redraw_footer();
+                        }
+                        _ => {
+                            if input.handle(key_event) {
+                                self.redraw_footer();
+                            }
+                        }
+                    }
+                }
+                Mode::RunCommand { input } => match key_event.code {
+                    KeyCode::Enter => {
+                        let command = input.text().trim().to_string();
+                        self.mode = Mode::Normal;
+                        self.command_history.push(&command);
+                        self.run_command(&command);
+                        self.redraw_everything();
+                    }
+                    KeyCode::Up | KeyCode::Down => {
+                        let entry = if key_event.code == KeyCode::Up {
+                            self.command_history.prev(input.text())
+                        } else {
+                            self.command_history.next()
+                        };
+                        if let Some(entry) = entry {
+                            input.set(entry);
+                            self.redraw_footer();
+                        }
+                    }
+                    _ => {
+                        if input.handle(key_event) {
                             self.redraw_footer();
                         }
-                        KeyCode::Char(c) => {
-                            input.push(c);
+                    }
+                },
+                Mode::Export { input, long } => match key_event.code {
+                    KeyCode::Enter => {
+                        let target = input.text().trim().to_string();
+                        let long = *long;
+                        self.export_listing(&target, long);
+                        self.mode = Mode::Normal;
+                        self.redraw_footer();
+                    }
+                    _ => {
+                        if input.handle(key_event) {
                             self.redraw_footer();
                         }
+                    }
+                },
+                Mode::Bookmarks { overlay } => match key_event.code {
+                    KeyCode::Backspace => {
+                        overlay.del();
+                        self.redraw_console();
+                    }
+                    KeyCode::Up | KeyCode::BackTab => {
+                        overlay.up();
+                        self.redraw_console();
+                    }
+                    KeyCode::Down | KeyCode::Tab => {
+                        overlay.down();
+                        self.redraw_console();
+                    }
+                    KeyCode::Enter => {
+                        let selected = overlay.selected_path();
+                        self.mode = Mode::Normal;
+                        if let Some(path) = selected {
+                            self.jump(path);
+                        }
+                        self.redraw_panels();
+                    }
+                    KeyCode::Char(c) => {
+                        overlay.insert(c);
+                        self.redraw_console();
+                    }
+                    _ => (),
+                },
+                Mode::Help { overlay } => {
+                    match key_event.code {
+                        KeyCode::Backspace => overlay.del(),
+                        KeyCode::Up => overlay.up(1),
+                        KeyCode::Down => overlay.down(1),
+                        KeyCode::PageUp => overlay.up(overlay.page()),
+                        KeyCode::PageDown => overlay.down(overlay.page()),
+                        KeyCode::Char(c) => overlay.insert(c),
+                        _ => (),
+                    }
+                    self.redraw_console();
+                }
+                Mode::Log { overlay } if overlay.is_searching() => {
+                    match key_event.code {
+                        KeyCode::Enter => overlay.end_search(),
+                        KeyCode::Backspace => overlay.del(),
+                        KeyCode::Char(c) => overlay.insert(c),
+                        _ => (),
+                    }
+                    self.redraw_console();
+                }
+                Mode::Log { overlay } => {
+                    match key_event.code {
+                        KeyCode::Up | KeyCode::Char('k') => overlay.up(1),
+                        KeyCode::Down | KeyCode::Char('j') => overlay.down(1),
+                        KeyCode::PageUp => overlay.up(overlay.page()),
+                        KeyCode::PageDown => overlay.down(overlay.page()),
+                        KeyCode::Home | KeyCode::Char('g') => overlay.top(),
+                        KeyCode::End | KeyCode::Char('G') => overlay.bottom(),
+                        KeyCode::Char('e') => overlay.set_level(log::Level::Error),
+                        KeyCode::Char('w') => overlay.set_level(log::Level::Warn),
+                        KeyCode::Char('i') => overlay.set_level(log::Level::Info),
+                        KeyCode::Char('d') => overlay.set_level(log::Level::Debug),
+                        KeyCode::Char('/') => overlay.start_search(),
+                        KeyCode::Char('y') => {
+                            if let Some(line) = overlay.selected_line() {
+                                match set_system_clipboard(&line) {
+                                    Ok(_) => info!("Copied log line to clipboard"),
+                                    Err(e) => error!("Cannot copy log line to clipboard: {e}"),
+                                }
+                            }
+                        }
+                        KeyCode::Char('q') => {
+                            self.mode = Mode::Normal;
+                            self.redraw_panels();
+                        }
                         _ => (),
                     }
+                    self.redraw_console();
+                }
+                Mode::DiskUsage { overlay } if overlay.is_confirming() => {
+                    match (key_event.code, overlay.selected_path()) {
+                        (KeyCode::Char('y'), Some(path)) => {
+                            overlay.remove_selected();
+                            self.delete_items(vec![path], false);
+                        }
+                        _ => {
+                            overlay.cancel_delete();
+                            info!("{}", tr(Msg::Cancelled));
+                        }
+                    }
+                    self.redraw_console();
+                }
+                Mode::DiskUsage { overlay } => match key_event.code {
+                    KeyCode::Up | KeyCode::Char('k') => {
+                        overlay.up();
+                        self.redraw_console();
+                    }
+                    KeyCode::Down | KeyCode::Char('j') => {
+                        overlay.down();
+                        self.redraw_console();
+                    }
+                    KeyCode::Right | KeyCode::Enter | KeyCode::Char('l') => {
+                        overlay.enter();
+                        self.redraw_console();
+                    }
+                    KeyCode::Left | KeyCode::Backspace | KeyCode::Char('h') => {
+                        overlay.leave();
+                        self.redraw_console();
+                    }
+                    KeyCode::Char('d') | KeyCode::Delete => {
+                        overlay.request_delete();
+                        self.redraw_console();
+                    }
+                    KeyCode::Char('q') => {
+                        self.mode = Mode::Normal;
+                        self.redraw_panels();
+                    }
+                    _ => (),
+                },
+                Mode::Devices { overlay } => match key_event.code {
+                    KeyCode::Up | KeyCode::Char('k') => {
+                        overlay.up();
+                        self.redraw_console();
+                    }
+                    KeyCode::Down | KeyCode::Char('j') => {
+                        overlay.down();
+                        self.redraw_console();
+                    }
+                    // Opens mounted devices and mounts the others
+                    KeyCode::Right | KeyCode::Enter | KeyCode::Char('l') => {
+                        match overlay.selected().and_then(|d| d.mount_points.first()) {
+                            Some(path) => {
+                                let path = path.clone();
+                                self.mode = Mode::Normal;
+                                self.jump(path);
+                                self.redraw_everything();
+                            }
+                            None => self.change_device(DeviceAction::Mount),
+                        }
+                    }
+                    KeyCode::Char('m') => self.change_device(DeviceAction::Mount),
+                    KeyCode::Char('u') => self.change_device(DeviceAction::Unmount),
+                    KeyCode::Char('e') => self.change_device(DeviceAction::Eject),
+                    KeyCode::Char('r') => self.refresh_devices(),
+                    KeyCode::Char('q') => {
+                        self.mode = Mode::Normal;
+                        self.redraw_panels();
+                    }
+                    _ => (),
+                },
+                Mode::Mounts { overlay } => match key_event.code {
+                    KeyCode::Up | KeyCode::Char('k') => {
+                        overlay.up();
+                        self.redraw_console();
+                    }
+                    KeyCode::Down | KeyCode::Char('j') => {
+                        overlay.down();
+                        self.redraw_console();
+                    }
+                    KeyCode::Right | KeyCode::Enter | KeyCode::Char('l') => {
+                        if let Some(path) = overlay.selected_path().map(Path::to_path_buf) {
+                            self.mode = Mode::Normal;
+                            self.jump(path);
+                            self.redraw_everything();
+                        }
+                    }
+                    KeyCode::Char('q') => {
+                        self.mode = Mode::Normal;
+                        self.redraw_panels();
+                    }
+                    _ => (),
+                },
+                Mode::Jobs { overlay } => match key_event.code {
+                    KeyCode::Up | KeyCode::Char('k') => {
+                        overlay.up();
+                        self.redraw_console();
+                    }
+                    KeyCode::Down | KeyCode::Char('j') => {
+                        overlay.down();
+                        self.redraw_console();
+                    }
+                    // Shows the progress of the job in the right column - or the preview again
+                    KeyCode::Right | KeyCode::Enter | KeyCode::Char('l') => {
+                        let id = overlay.selected_id();
+                        self.shown_job = match (&self.shown_job, id) {
+                            (Some(shown), Some(id)) if shown.id() == id => None,
+                            (_, Some(id)) => Some(JobPreview::new(self.jobs.clone(), id)),
+                            (_, None) => None,
+                        };
+                        self.mode = Mode::Normal;
+                        self.redraw_panels();
+                    }
+                    KeyCode::Char('q') => {
+                        self.mode = Mode::Normal;
+                        self.redraw_panels();
+                    }
+                    _ => (),
+                },
+                Mode::Confirm { .. } => {
+                    let Mode::Confirm { operation } =
+                        std::mem::replace(&mut self.mode, Mode::Normal)
+                    else {
+                        unreachable!()
+                    };
+                    match (key_event.code, operation) {
+                        (KeyCode::Char('y'), Dangerous::Delete(files)) => {
+                            self.delete_items(files, false)
+                        }
+                        (KeyCode::Char('y'), Dangerous::DeletePermanently(files)) => {
+                            self.delete_items(files, true)
+                        }
+                        (KeyCode::Char('y'), Dangerous::Shred { files, .. }) => {
+                            self.shred_items(files)
+                        }
+                        (KeyCode::Char('y'), Dangerous::EmptyTrash(_)) => self.empty_trash(),
+                        (KeyCode::Char('y'), Dangerous::ProjectCommand { command, root }) => {
+                            self.project.allow();
+                            self.execute(Command::Shell {
+                                command,
+                                dir: Some(root),
+                            })?;
+                        }
+                        (KeyCode::Char('y'), Dangerous::PasteOverwrite) => self.paste(true),
+                        _ => info!("{}", tr(Msg::Cancelled)),
+                    }
+                    self.redraw_footer();
+                }
+                Mode::SetMark => {
+                    if let KeyCode::Char(c) = key_event.code {
+                        self.session_marks.set(
+                            c,
+                            self.center.panel().path().to_path_buf(),
+                            self.center.panel().selected_path_owned(),
+                        );
+                    }
+                    self.mode = Mode::Normal;
+                    self.redraw_footer();
                 }
+                Mode::JumpMark => {
+                    if let KeyCode::Char(c) = key_event.code {
+                        match self.session_marks.get(c) {
+                            Some((dir, selected)) => {
+                                let selected = selected.map(Path::to_path_buf);
+                                self.jump(dir.to_path_buf());
+                                if let Some(selected) = selected {
+                                    self.center.panel_mut().select_path(&selected);
+                                    self.right
+                                        .new_panel_delayed(self.center.panel().selected_path());
+                                }
+                            }
+                            None => warn!("{}", tr_fmt(Msg::NoSuchMark, &[&c])),
+                        }
+                    }
+                    self.mode = Mode::Normal;
+                    self.redraw_panels();
+                    self.redraw_footer();
+                }
+                Mode::FrecentJump { input, best } => match key_event.code {
+                    KeyCode::Enter => {
+                        let best = best.take();
+                        self.mode = Mode::Normal;
+                        if let Some(path) = best {
+                            self.jump(path);
+                        }
+                        self.redraw_footer();
+                    }
+                    _ => {
+                        if input.handle(key_event) {
+                            *best = self
+                                .frecency
+                                .query(input.text(), self.center.panel().path());
+                            self.redraw_footer();
+                        }
+                    }
+                },
+                Mode::FirstLetter => {
+                    if let KeyCode::Char(c) = key_event.code {
+                        if let Some(position) = self.center.panel().next_starting_with(c) {
+                            self.select_position(position);
+                        }
+                    }
+                    self.mode = Mode::Normal;
+                    self.redraw_footer();
+                }
+                Mode::ConfigErrors { overlay } => match key_event.code {
+                    KeyCode::Up | KeyCode::Char('k') => {
+                        overlay.up();
+                        self.redraw_console();
+                    }
+                    KeyCode::Down | KeyCode::Char('j') => {
+                        overlay.down();
+                        self.redraw_console();
+                    }
+                    KeyCode::Enter | KeyCode::Char('e') => {
+                        if let Some(path) = overlay.selected_path() {
+                            self.center.freeze();
+                            if let Err(e) = self.opener.edit(path) {
+                                error!("Opening config failed: {e}");
+                            }
+                            self.center.unfreeze();
+                            let done = match &mut self.mode {
+                                Mode::ConfigErrors { overlay } => overlay.recheck_selected(),
+                                _ => true,
+                            };
+                            if done {
+                                self.mode = Mode::Normal;
+                            }
+                        }
+                        self.redraw_everything();
+                    }
+                    KeyCode::Char('q') => {
+                        self.mode = Mode::Normal;
+                        self.redraw_panels();
+                    }
+                    _ => (),
+                },
+                Mode::Chown {
+                    input,
+                    paths,
+                    users,
+                    groups,
+                } => match key_event.code {
+                    KeyCode::Tab => {
+                        if let Some(completion) = complete(input.text(), users, groups) {
+                            input.set(completion);
+                        }
+                        self.redraw_footer();
+                    }
+                    KeyCode::Enter => {
+                        match parse_owner(input.text().trim()) {
+                            Ok((uid, gid)) => {
+                                let mut changed = 0;
+                                for path in paths.iter() {
+                                    match change_owner(path, uid, gid) {
+                                        Ok(()) => changed += 1,
+                                        Err(e) => error!("{e}"),
+                                    }
+                                }
+                                info!("{}", tr_fmt(Msg::ChownDone, &[&changed]));
+                            }
+                            Err(e) => error!("chown: {e}"),
+                        }
+                        self.mode = Mode::Normal;
+                        self.unmark_all_items();
+                        self.redraw_footer();
+                    }
+                    _ => {
+                        if input.handle(key_event) {
+                            self.redraw_footer();
+                        }
+                    }
+                },
                 Mode::Search { input } => {
                     if let KeyCode::Enter = key_event.code {
-                        self.center.panel_mut().finish_search(&input);
-                        self.center.panel_mut().select_next_marked();
+                        self.search_history.push(input.text());
+                        let matches = self.center.panel_mut().finish_search(input.text());
+                        self.marks.clear_dir(self.center.panel().path());
+                        for path in matches {
+                            self.marks.insert(path);
+                        }
+                        self.center.panel_mut().sync_marks(&self.marks);
                         self.right
                             .new_panel_delayed(self.center.panel().selected_path());
                         self.mode = Mode::Normal;
                         self.redraw_center();
                         self.redraw_right();
+                    } else if let KeyCode::Tab | KeyCode::BackTab = key_event.code {
+                        // Jump between the matches without leaving the search
+                        self.center
+                            .panel_mut()
+                            .cycle_search(key_event.code == KeyCode::Tab);
+                        self.right
+                            .new_panel_delayed(self.center.panel().selected_path());
+                        self.redraw_center();
+                        self.redraw_right();
                     } else {
-                        if let KeyCode::Char(c) = key_event.code {
-                            input.push(c.to_ascii_lowercase());
-                        }
-                        if let KeyCode::Backspace = key_event.code {
-                            input.pop();
+                        let changed = match key_event.code {
+                            KeyCode::Char(c)
+                                if key_event
+                                    .modifiers
+                                    .difference(KeyModifiers::SHIFT)
+                                    .is_empty() =>
+                            {
+                                input.insert(c.to_ascii_lowercase());
+                                true
+                            }
+                            KeyCode::Up => match self.search_history.prev(input.text()) {
+                                Some(entry) => {
+                                    input.set(entry);
+                                    true
+                                }
+                                None => false,
+                            },
+                            KeyCode::Down => match self.search_history.next() {
+                                Some(entry) => {
+                                    input.set(entry);
+                                    true
+                                }
+                                None => false,
+                            },
+                            _ => input.handle(key_event),
+                        };
+                        if changed {
+                            self.center
+                                .panel_mut()
+                                .update_search(input.text().to_string());
+                            self.right
+                                .new_panel_delayed(self.center.panel().selected_path());
+                            self.redraw_center();
+                            self.redraw_right();
                         }
-                        self.center.panel_mut().update_search(input.clone());
-                        self.redraw_center();
                     }
                 }
                 Mode::Rename { input } => {
                     if let KeyCode::Enter = key_event.code {
                         // TODO: Actually rename the selection
                         if let Some(from) = self.center.panel().selected_path() {
-                            let to = from.parent().map(|p| p.join(input)).unwrap_or_default();
+                            let to = from
+                                .parent()
+                                .map(|p| p.join(input.text()))
+                                .unwrap_or_default();
                             if let Err(e) = std::fs::rename(from, to) {
                                 error!("{e}");
                             }
@@ -1050,81 +3567,195 @@ impl PanelManager {
                         self.center.reload();
                         self.right.reload();
                         self.redraw_panels();
-                    } else {
-                        if let KeyCode::Char(c) = key_event.code {
-                            input.push(c);
-                        }
-                        if let KeyCode::Backspace = key_event.code {
-                            input.pop();
-                        }
-                        self.redraw_center();
+                    } else if input.handle(key_event) {
+                        self.redraw_footer();
                     }
                 }
             }
         }
         if let Event::Resize(sx, sy) = event {
-            self.layout = MillerColumns::from_size((sx, sy));
+            self.relayout((sx, sy));
             self.redraw_everything();
         }
         Ok(false)
     }
 }
 
+/// Creates the content of the bulkrename file.
+///
+/// Every name is preceded by a comment with the original name and the size of the item.
+fn bulkrename_file(old_paths: &[PathBuf]) -> String {
+    let mut out = tr(Msg::BulkrenameHeader).to_string();
+    for path in old_paths {
+        let name = path
+            .file_name()
+            .map(|n| n.to_string_lossy().to_string())
+            .unwrap_or_default();
+        let size = if path.is_dir() {
+            "dir".to_string()
+        } else {
+            path.metadata()
+                .map(|m| file_size_str(m.size()))
+                .unwrap_or_default()
+        };
+        out.push_str(&format!("\n# {name} ({size})\n"));
+        if name.starts_with('#') || name.starts_with('\\') {
+            out.push('\\');
+        }
+        out.push_str(&name);
+        out.push('\n');
+    }
+    out
+}
+
+/// Extracts the new names from the bulkrename file - comments and empty lines are skipped.
+fn parse_bulkrename_file(contents: &str) -> Vec<String> {
+    contents
+        .lines()
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(|line| line.strip_prefix('\\').unwrap_or(line).to_string())
+        .collect()
+}
+
+/// Checks if the new names can be applied, and returns a description of every problem.
+fn check_bulkrename(old_paths: &[PathBuf], new_names: &[String]) -> Vec<String> {
+    if new_names.len() != old_paths.len() {
+        return vec![tr_fmt(
+            Msg::BulkrenameCount,
+            &[&new_names.len(), &old_paths.len()],
+        )];
+    }
+    let mut problems = Vec::new();
+    for (old_path, new_name) in old_paths.iter().zip(new_names) {
+        let new_path = old_path.with_file_name(new_name);
+        if new_name.contains('/') {
+            problems.push(tr_fmt(Msg::BulkrenameSlash, &[new_name]));
+        } else if new_path != *old_path && new_path.exists() && !old_paths.contains(&new_path) {
+            // Names of files that are renamed as well are free - which allows swaps and chains
+            let old_name = old_path.file_name().unwrap_or_default().to_string_lossy();
+            problems.push(tr_fmt(
+                Msg::BulkrenameExists,
+                &[&old_name, new_name, new_name],
+            ));
+        }
+    }
+    for (idx, name) in new_names.iter().enumerate() {
+        if new_names[..idx].contains(name) {
+            problems.push(tr_fmt(Msg::BulkrenameDuplicate, &[name]));
+        }
+    }
+    problems
+}
+
+/// Puts the problems as comments at the top of the bulkrename file - replacing the ones
+/// of the previous attempt, so the edits (and the original names) are kept.
+fn annotate_bulkrename_file(contents: &str, problems: &[String]) -> String {
+    let marker = format!("# {}", tr(Msg::BulkrenameFailed));
+    let previous = match contents.strip_prefix(&marker) {
+        // Skip the problems of the previous attempt, up to the first empty line
+        Some(rest) => rest
+            .split_once("\n\n")
+            .map(|(_, edits)| edits)
+            .unwrap_or_default(),
+        None => contents,
+    };
+    let mut annotated = format!("{marker}\n");
+    for problem in problems {
+        annotated.push_str(&format!("# {problem}\n"));
+    }
+    annotated.push('\n');
+    annotated.push_str(previous);
+    annotated
+}
+
 fn bulkrename(mgr: &mut PanelManager, old_paths: Vec<PathBuf>) -> Result<()> {
     // Write selected filenames to a temporary file.
     let temp_path = std::env::temp_dir().join("rfm_bulkrename");
-    let old_names = old_paths
-        .iter()
-        .map(|p| p.file_name().unwrap().to_string_lossy())
-        .collect::<Vec<_>>()
-        .join("\n");
-    std::fs::write(&temp_path, old_names)?;
-
-    // Let the user edit the temporary file.
+    std::fs::write(&temp_path, bulkrename_file(&old_paths))?;
+
+    // Let the user edit the temporary file - again, until all problems are fixed.
     info!("Opening bulkrename file '{}'", temp_path.to_string_lossy());
     mgr.center.freeze();
-    if let Err(e) = mgr.opener.open(temp_path.clone()) {
-        error!("Opening bulkrename file failed: {e}");
-        std::fs::remove_file(&temp_path)?; // TODO: handle error
-    } else {
-        // Check if there's a 1-to-1 correspondence between old and new file names.
+    loop {
+        let before = std::fs::read_to_string(&temp_path)?;
+        if let Err(e) = mgr.opener.open_and_wait(temp_path.clone()) {
+            error!("Opening bulkrename file failed: {e}");
+            std::fs::remove_file(&temp_path)?; // TODO: handle error
+            break;
+        }
         let contents = std::fs::read_to_string(&temp_path)?;
-        let new_file_names: Vec<&str> = contents.trim_matches('\n').lines().collect();
-        if new_file_names.len() != old_paths.len() {
-            error!(
-                "Bulkrename file has {} lines, but {} files should be renamed.",
-                new_file_names.len(),
-                old_paths.len()
-            );
-            // TODO: reeddit with original file names added as comment.
+        let new_names = parse_bulkrename_file(&contents);
+        let problems = check_bulkrename(&old_paths, &new_names);
+        if !problems.is_empty() {
+            for problem in problems.iter() {
+                error!("Bulkrename: {problem}");
+            }
+            if contents == before {
+                // The file was closed without changes - give up, but keep the edits.
+                error!("{}", tr_fmt(Msg::BulkrenameKept, &[&temp_path.display()]));
+                break;
+            }
+            std::fs::write(&temp_path, annotate_bulkrename_file(&contents, &problems))?;
+            continue;
         } else {
-            // Check if any new file path exists already.
-            // TODO: allow swapping names of files by renaming to a intermediary file names first.
-            let new_paths: Vec<PathBuf> = old_paths
+            // Rename old path to new paths - either all of them, or none.
+            let renames: Vec<(PathBuf, PathBuf)> = old_paths
                 .iter()
-                .zip(&new_file_names)
-                .map(|(p, n)| p.with_file_name(n))
+                .zip(&new_names)
+                .map(|(old_path, new_name)| (old_path.clone(), old_path.with_file_name(new_name)))
+                .filter(|(old_path, new_path)| old_path != new_path)
                 .collect();
-            let collisions: Vec<_> = new_paths.iter().filter(|p| p.exists()).collect();
-            if collisions.len() > 0 {
-                error!("Bulkrename wants to rename a path to an already existing path.");
-                // TODO: reeddit with additional information about colliding paths.
-            } else {
-                // Rename old path to new paths.
-                for (old_path, new_path) in old_paths.iter().zip(&new_paths) {
-                    info!(
-                        "Bulkrename path '{}' to '{}'",
-                        old_path.to_string_lossy(),
-                        new_path.to_string_lossy()
-                    );
-                    std::fs::rename(old_path, new_path)?;
+            for (old_path, new_path) in renames.iter() {
+                info!(
+                    "Bulkrename path '{}' to '{}'",
+                    old_path.to_string_lossy(),
+                    new_path.to_string_lossy()
+                );
+            }
+            match rename_all(&renames) {
+                Ok(()) => std::fs::remove_file(&temp_path)?,
+                Err(e) => {
+                    error!("{}", tr_fmt(Msg::BulkrenameRolledBack, &[&e]));
+                    error!("{}", tr_fmt(Msg::BulkrenameKept, &[&temp_path.display()]));
                 }
             }
+            break;
         }
     }
-    std::fs::remove_file(temp_path)?;
     mgr.center.unfreeze();
     mgr.redraw_everything();
 
     Ok(())
 }
+
+#[test]
+fn test_bulkrename_file() {
+    let old_paths = vec![
+        PathBuf::from("/nonexistent/a.txt"),
+        PathBuf::from("/nonexistent/#autosave#"),
+    ];
+    let contents = bulkrename_file(&old_paths);
+    assert!(contents.contains("\n\\#autosave#\n"));
+    assert_eq!(
+        parse_bulkrename_file(&contents),
+        vec!["a.txt".to_string(), "#autosave#".to_string()]
+    );
+    let problems = check_bulkrename(&old_paths, &["b.txt".into(), "b.txt".into()]);
+    assert_eq!(problems, vec!["'b.txt' is used for more than one file."]);
+    let dir = tempfile::tempdir().unwrap();
+    let swapped = vec![dir.path().join("a"), dir.path().join("b")];
+    for path in swapped.iter() {
+        std::fs::write(path, "").unwrap();
+    }
+    assert!(check_bulkrename(&swapped, &["b".into(), "a".into()]).is_empty());
+
+    // The problems of the previous attempt are replaced
+    let annotated = annotate_bulkrename_file(&contents, &problems);
+    let annotated = annotate_bulkrename_file(&annotated, &["second".to_string()]);
+    assert!(!annotated.contains("more than one file"));
+    assert!(annotated.ends_with(&contents));
+    assert_eq!(
+        parse_bulkrename_file(&annotated),
+        parse_bulkrename_file(&contents)
+    );
+}
@@ -0,0 +1,91 @@
+use std::collections::HashSet;
+
+use super::*;
+
+/// Registry of all marked paths.
+///
+/// Marks are keyed by their path, so they survive moving around
+/// between directories (and the panels being re-created from the cache).
+/// Every change to the registry increases its generation,
+/// which lets the panels know when they have to re-apply the marks to their elements
+/// (see [`DirPanel::sync_marks`]).
+#[derive(Debug, Clone)]
+pub struct Marks {
+    paths: HashSet<PathBuf>,
+    generation: u64,
+}
+
+impl Default for Marks {
+    fn default() -> Self {
+        // NOTE: Panels start with generation 0,
+        // so the first sync always applies the registry.
+        Marks {
+            paths: HashSet::new(),
+            generation: 1,
+        }
+    }
+}
+
+impl Marks {
+    pub fn generation(&self) -> u64 {
+        self.generation
+    }
+
+    pub fn contains(&self, path: &Path) -> bool {
+        self.paths.contains(path)
+    }
+
+    pub fn len(&self) -> usize {
+        self.paths.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.paths.is_empty()
+    }
+
+    /// Marks the given path.
+    pub fn insert(&mut self, path: PathBuf) {
+        if self.paths.insert(path) {
+            self.generation += 1;
+        }
+    }
+
+    /// Unmarks the given path.
+    pub fn remove(&mut self, path: &Path) {
+        if self.paths.remove(path) {
+            self.generation += 1;
+        }
+    }
+
+    /// Marks the path if it is unmarked, and unmarks it otherwise.
+    pub fn toggle(&mut self, path: &Path) {
+        if !self.paths.remove(path) {
+            self.paths.insert(path.to_path_buf());
+        }
+        self.generation += 1;
+    }
+
+    /// Removes all marks of items that live directly inside the given directory.
+    pub fn clear_dir(&mut self, directory: &Path) {
+        let len = self.paths.len();
+        self.paths.retain(|p| p.parent() != Some(directory));
+        if len != self.paths.len() {
+            self.generation += 1;
+        }
+    }
+
+    /// Removes all marks.
+    pub fn clear(&mut self) {
+        if !self.paths.is_empty() {
+            self.paths.clear();
+            self.generation += 1;
+        }
+    }
+
+    /// Returns all marked paths in a sorted order.
+    pub fn paths(&self) -> Vec<PathBuf> {
+        let mut paths: Vec<PathBuf> = self.paths.iter().cloned().collect();
+        paths.sort();
+        paths
+    }
+}
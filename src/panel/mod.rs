@@ -19,15 +19,29 @@ use std::{
 };
 use tokio::sync::mpsc;
 
-use crate::{commands::Move, content::PanelCache};
+use crate::{commands::Move, config::default_ratios, content::PanelCache};
 
+mod bookmarks;
+mod config_errors;
 mod console;
+mod devices;
 mod directory;
+mod disk_usage;
+mod footer;
+mod help;
+mod jobs;
+mod line_editor;
+mod log_view;
 pub mod manager;
+mod marks;
+mod mounts;
 mod preview;
+mod session_marks;
+mod which_key;
 
-pub use directory::{DirElem, DirPanel};
-pub use preview::{FilePreview, Preview, PreviewPanel};
+pub use directory::{init_hidden_patterns, init_sorting, DirElem, DirPanel, Visibility};
+pub use marks::Marks;
+pub use preview::{escape_control, FilePreview, PreviewPanel};
 
 /// Basic trait that lets us draw something on the terminal in a specified range.
 pub trait Draw {
@@ -36,7 +50,7 @@ pub trait Draw {
 }
 
 /// Basic trait for managing the content of a panel
-pub trait PanelContent: Draw + Clone + Send {
+pub trait PanelContent: Draw + Clone + Send + 'static {
     /// Path of the panel
     fn path(&self) -> &Path;
 
@@ -188,6 +202,9 @@ pub struct ManagedPanel<PanelType: BasePanel> {
 
     /// Sends request for new panel content.
     content_tx: mpsc::UnboundedSender<PanelUpdate>,
+
+    /// Disabled panels are empty and never request any content (e.g. a hidden preview)
+    disabled: bool,
 }
 
 impl<PanelType: BasePanel> ManagedPanel<PanelType> {
@@ -199,24 +216,33 @@ impl<PanelType: BasePanel> ManagedPanel<PanelType> {
         let state = Arc::new(Mutex::new(PanelState::default()));
         let watcher_state = state.clone();
         let watcher_tx = content_tx.clone();
+        let watcher_cache = cache.clone();
         let watcher = notify::recommended_watcher(
             move |res: std::result::Result<notify::Event, notify::Error>| {
                 if let Ok(event) = res {
+                    // Changed or removed items must not be shown from the cache anymore
+                    if !event.kind.is_access() && !event.kind.is_create() {
+                        for path in event.paths.iter() {
+                            watcher_cache.remove(path);
+                        }
+                    }
                     match event.kind {
-                        notify::EventKind::Create(_) | notify::EventKind::Remove(_) => {
+                        // NOTE: Renames (e.g. moving a sibling of the current directory)
+                        // change the listing just like creating or removing an entry.
+                        notify::EventKind::Create(_)
+                        | notify::EventKind::Remove(_)
+                        | notify::EventKind::Modify(notify::event::ModifyKind::Name(_)) => {
                             let state = watcher_state.lock().clone();
                             info!("Updating: {}", state.path().display());
                             if let Err(e) = watcher_tx.send(PanelUpdate { state }) {
                                 error!("{e}");
                             }
                         }
-                        notify::EventKind::Modify(_) => {
-                            if reload_on_modify {
-                                let state = watcher_state.lock().clone();
-                                info!("Updating: {}", state.path().display());
-                                if let Err(e) = watcher_tx.send(PanelUpdate { state }) {
-                                    error!("{e}");
-                                }
+                        notify::EventKind::Modify(_) if reload_on_modify => {
+                            let state = watcher_state.lock().clone();
+                            info!("Updating: {}", state.path().display());
+                            if let Err(e) = watcher_tx.send(PanelUpdate { state }) {
+                                error!("{e}");
                             }
                         }
                         _ => (),
@@ -231,6 +257,24 @@ impl<PanelType: BasePanel> ManagedPanel<PanelType> {
             watcher,
             cache,
             content_tx,
+            disabled: false,
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        !self.disabled
+    }
+
+    /// Enables or disables the panel.
+    ///
+    /// A disabled panel is emptied, and requests for new content are ignored
+    /// until it is enabled again.
+    pub fn set_enabled(&mut self, enabled: bool) {
+        self.disabled = !enabled;
+        if self.disabled {
+            unwatch_path(&mut self.watcher, self.panel.path());
+            // Also invalidates the content that is still requested
+            self.update(PanelType::empty());
         }
     }
 
@@ -243,6 +287,9 @@ impl<PanelType: BasePanel> ManagedPanel<PanelType> {
     /// The panel is created instantly, so there is no "loading..." or
     /// waiting for the content manager to fetch some data in the background.
     pub fn new_panel_instant<P: AsRef<Path>>(&mut self, path: Option<P>) {
+        if self.disabled {
+            return;
+        }
         if let Some(path) = path.and_then(|p| canonicalize(p.as_ref()).ok()) {
             // Only create a new panel when the path has changed
             if path == self.panel.path() {
@@ -282,7 +329,8 @@ impl<PanelType: BasePanel> ManagedPanel<PanelType> {
                 .expect("Receiver dropped or closed");
         } else {
             debug!("new-panel-instant: empty panel");
-            self.update(PanelType::empty());
+            // Also stops watching the previous directory (e.g. the parent of '/')
+            self.update_panel(PanelType::empty());
         }
     }
 
@@ -293,6 +341,9 @@ impl<PanelType: BasePanel> ManagedPanel<PanelType> {
     /// If the cache is empty, a generic "loading..." panel is created.
     /// An empty panel is created if the given path is `None`.
     pub fn new_panel_delayed<P: AsRef<Path>>(&mut self, path: Option<P>) {
+        if self.disabled {
+            return;
+        }
         if let Some(path) = path.and_then(|p| canonicalize(p.as_ref()).ok()) {
             // Only create a new panel when the path has changed
             if path == self.panel.path() {
@@ -324,11 +375,14 @@ impl<PanelType: BasePanel> ManagedPanel<PanelType> {
                 })
                 .expect("Receiver dropped or closed");
         } else {
-            self.update(PanelType::empty());
+            self.update_panel(PanelType::empty());
         }
     }
 
     pub fn reload(&self) {
+        if self.disabled {
+            return;
+        }
         self.content_tx
             .send(PanelUpdate {
                 state: self.state.lock().clone(),
@@ -372,6 +426,15 @@ impl<PanelType: BasePanel> ManagedPanel<PanelType> {
         self.update(panel);
     }
 
+    /// Drops all cached panels and returns how many there were.
+    ///
+    /// NOTE: The cache may be shared with other panels.
+    pub fn clear_cache(&self) -> usize {
+        let len = self.cache.len();
+        self.cache.clear();
+        len
+    }
+
     /// Returns a mutable reference to the managed panel
     pub fn panel_mut(&mut self) -> &mut PanelType {
         &mut self.panel
@@ -393,17 +456,34 @@ struct MillerColumns {
 }
 
 impl MillerColumns {
-    pub fn from_size(terminal_size: (u16, u16)) -> Self {
+    /// Splits the width according to the ratios of the left, center and right column.
+    ///
+    /// The center column cannot be collapsed - without it, the default ratios are used.
+    pub fn from_size(terminal_size: (u16, u16), ratios: [u16; 3]) -> Self {
         let (sx, sy) = terminal_size;
+        let [left, center, right] = if ratios[1] == 0 {
+            default_ratios()
+        } else {
+            ratios
+        };
+        let [left, center, right] = [left as u32, center as u32, right as u32];
+        let split = |ratio: u32| (sx as u32 * ratio / (left + center + right)) as u16;
+        let left_end = split(left);
+        let center_end = split(left + center);
         Self {
-            left_x_range: 0..(sx / 8),
-            center_x_range: (sx / 8)..(sx / 2),
-            right_x_range: (sx / 2)..sx,
+            left_x_range: 0..left_end,
+            center_x_range: left_end..center_end,
+            right_x_range: center_end..sx,
             y_range: 1..sy.saturating_sub(1), // 1st line is reserved for the header, last for the footer
             width: sx,
         }
     }
 
+    /// Size of the terminal
+    pub fn size(&self) -> (u16, u16) {
+        (self.width, self.y_range.end.saturating_add(1))
+    }
+
     pub fn footer(&self) -> u16 {
         self.y_range.end.saturating_add(1)
     }
@@ -416,3 +496,25 @@ impl MillerColumns {
         self.width
     }
 }
+
+#[test]
+fn test_miller_columns() {
+    let layout = MillerColumns::from_size((80, 24), default_ratios());
+    assert_eq!(layout.left_x_range, 0..10);
+    assert_eq!(layout.center_x_range, 10..40);
+    assert_eq!(layout.right_x_range, 40..80);
+    assert_eq!(layout.size(), (80, 24));
+
+    let layout = MillerColumns::from_size((80, 24), [0, 1, 1]);
+    assert!(layout.left_x_range.is_empty());
+    assert_eq!(layout.center_x_range, 0..40);
+
+    // Single column
+    let layout = MillerColumns::from_size((40, 24), [0, 1, 0]);
+    assert_eq!(layout.center_x_range, 0..40);
+    assert!(layout.right_x_range.is_empty());
+
+    // Without the center column, the default is used
+    let layout = MillerColumns::from_size((80, 24), [1, 0, 1]);
+    assert_eq!(layout.center_x_range, 10..40);
+}
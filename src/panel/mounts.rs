@@ -0,0 +1,108 @@
+use crate::{
+    devices::Mount,
+    locale::{tr, Msg},
+    util::{file_size_str, ExactWidth},
+};
+
+use super::*;
+
+/// Width of the usage bar
+const BAR_WIDTH: usize = 10;
+
+/// Full-screen list of the mounted filesystems with their size and free space (like `df -h`).
+pub struct MountsOverlay {
+    mounts: Vec<Mount>,
+    selected: usize,
+}
+
+impl Draw for MountsOverlay {
+    fn draw(
+        &mut self,
+        stdout: &mut Stdout,
+        x_range: Range<u16>,
+        y_range: Range<u16>,
+    ) -> Result<()> {
+        let width = x_range.end.saturating_sub(x_range.start) as usize;
+        let height = y_range.end.saturating_sub(y_range.start) as usize;
+        // One line for the header
+        let n_rows = height.saturating_sub(1);
+
+        queue!(
+            stdout,
+            cursor::MoveTo(x_range.start, y_range.start),
+            PrintStyledContent(
+                format!(" {}", tr(Msg::Mounts))
+                    .exact_width(width)
+                    .bold()
+                    .dark_green()
+                    .reverse()
+            ),
+        )?;
+
+        // Keep the selection visible
+        let scroll = self.selected.saturating_sub(n_rows.saturating_sub(1));
+        for row in 0..n_rows {
+            let y = y_range.start + 1 + row as u16;
+            queue!(stdout, cursor::MoveTo(x_range.start, y))?;
+            let Some(mount) = self.mounts.get(scroll + row) else {
+                queue!(stdout, Print(" ".repeat(width)))?;
+                continue;
+            };
+            // Filesystems that did not respond show question marks
+            let (size, used, free, bar) = match mount.space {
+                Some((size, free)) => {
+                    let used = size.saturating_sub(free);
+                    let ratio = used as f64 / size.max(1) as f64;
+                    let filled = ((ratio * BAR_WIDTH as f64).round() as usize).min(BAR_WIDTH);
+                    (
+                        file_size_str(size),
+                        file_size_str(used),
+                        file_size_str(free),
+                        format!("{}{}", "#".repeat(filled), " ".repeat(BAR_WIDTH - filled)),
+                    )
+                }
+                None => {
+                    let unknown = "?".to_string();
+                    let bar = format!("{:^BAR_WIDTH$}", "?");
+                    (unknown.clone(), unknown.clone(), unknown, bar)
+                }
+            };
+            let line = format!(
+                " {:<20} {:<8} {size:>9} {used:>9} {free:>9} [{bar}] {}",
+                mount.device,
+                mount.fs_type,
+                mount.mount_point.display(),
+            )
+            .exact_width(width);
+            if scroll + row == self.selected {
+                queue!(stdout, PrintStyledContent(line.bold().negative()))?;
+            } else {
+                queue!(stdout, Print(line))?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl MountsOverlay {
+    pub fn new(mounts: Vec<Mount>) -> Self {
+        MountsOverlay {
+            mounts,
+            selected: 0,
+        }
+    }
+
+    pub fn selected_path(&self) -> Option<&Path> {
+        self.mounts
+            .get(self.selected)
+            .map(|mount| mount.mount_point.as_path())
+    }
+
+    pub fn up(&mut self) {
+        self.selected = self.selected.saturating_sub(1);
+    }
+
+    pub fn down(&mut self) {
+        self.selected = (self.selected + 1).min(self.mounts.len().saturating_sub(1));
+    }
+}
@@ -3,10 +3,17 @@ use std::{
     io::{self, BufRead, Stdout},
     ops::Range,
     path::{Path, PathBuf},
+    sync::Arc,
     time::{SystemTime, UNIX_EPOCH},
 };
 
-use crate::util::ExactWidth;
+use crate::{
+    exif::ImageInfo,
+    locale::{tr_fmt, Msg},
+    structured::{json_lines, yaml_lines, Line, Syntax},
+    util::{exec_allowed, file_size_str, ExactWidth},
+    vfs::is_remote,
+};
 
 use super::{BasePanel, DirPanel, Draw, PanelContent};
 use crossterm::{
@@ -14,19 +21,47 @@ use crossterm::{
     style::{self, Colors, Print, PrintStyledContent, ResetColor, SetColors, Stylize},
     Result,
 };
-use image::DynamicImage;
+use image::{DynamicImage, RgbImage};
+use parking_lot::Mutex;
+
+/// Images are scaled down to this size when they are loaded, so the cached previews stay small.
+const MAX_IMAGE_SIZE: u32 = 512;
+
+/// Image that was scaled for a panel size.
+type Thumbnail = Arc<Mutex<Option<((u32, u32), RgbImage)>>>;
 
 #[derive(Debug, Clone)]
 pub enum Preview {
-    Image { img: Option<DynamicImage> },
-    Text { lines: Vec<String> },
+    Image {
+        img: Option<DynamicImage>,
+        /// Shown above the image
+        info: Option<ImageInfo>,
+        /// Result of the last draw call - shared with the copies in the preview cache,
+        /// so the image is only scaled again when the panel size changes
+        thumbnail: Thumbnail,
+    },
+    Text {
+        lines: Vec<String>,
+    },
+    /// Pretty-printed and highlighted JSON or YAML
+    Structured {
+        lines: Vec<Line>,
+    },
 }
 
+/// Remote files up to this size are previewed - larger ones would take too long to transfer.
+const REMOTE_MAX_SIZE: u64 = 4 * 1024 * 1024;
+
+/// JSON and YAML files up to this size are pretty-printed.
+const STRUCTURED_MAX_SIZE: u64 = 8 * 1024 * 1024;
+
 #[derive(Debug, Clone)]
 pub struct FilePreview {
     path: PathBuf,
     modified: SystemTime,
     preview: Preview,
+    /// Wrap long lines of text previews, instead of truncating them
+    wrap: bool,
 }
 
 impl Draw for FilePreview {
@@ -49,14 +84,32 @@ impl Draw for FilePreview {
         }
 
         match &self.preview {
-            Preview::Image { img } => {
+            Preview::Image {
+                img,
+                info,
+                thumbnail,
+            } => {
+                // Dimensions, camera and date in the first line
+                let (y_range, height) = match info {
+                    Some(info) => {
+                        queue!(
+                            stdout,
+                            cursor::MoveTo(x_range.start + 1, y_range.start),
+                            PrintStyledContent(
+                                format!(" {}", info.summary())
+                                    .exact_width(width as usize)
+                                    .dark_grey()
+                            ),
+                        )?;
+                        (y_range.start + 1..y_range.end, height.saturating_sub(1))
+                    }
+                    None => (y_range, height),
+                };
                 // load image
                 if let Some(img) = img {
                     // crop height
                     let img_height = ((height as f32) - (height as f32) / 3.6).round();
-                    let img = img
-                        .thumbnail_exact(width as u32, img_height as u32)
-                        .into_rgb8();
+                    let img = scaled(img, thumbnail, width as u32, img_height as u32);
                     for y in 0..height {
                         // cursor y
                         let cy = y_range.start.saturating_add(y);
@@ -88,7 +141,7 @@ impl Draw for FilePreview {
                     queue!(
                         stdout,
                         cursor::MoveTo(x_range.start + 1, y_range.start + 1),
-                        Print(format!("Failed to load image '{}'", self.path().display())),
+                        Print(tr_fmt(Msg::ImageFailed, &[&self.path().display()])),
                     )?;
                     for y in y_range.start + 1..y_range.end {
                         for x in x_range.start + 1..x_range.end {
@@ -100,29 +153,57 @@ impl Draw for FilePreview {
                 // let density =
                 //     "$@B%8&WM#*oahkbdpqwmZO0QLCJUYXzcvunxrjft/\\|()1{}[]?-_+~<>i!lI;:,\"^`\'. ";
             }
-            Preview::Text { lines } => {
-                // Print preview
+            Preview::Structured { lines } => {
+                let width = width.saturating_sub(1) as usize;
                 let mut idx = 0;
                 for line in lines.iter().take(height as usize) {
                     let cy = idx + y_range.start;
-                    let line = line
-                        // .replace('\r', "")
-                        .exact_width(width.saturating_sub(1) as usize);
-                    queue!(stdout, cursor::MoveTo(x_range.start + 1, cy), Print(" "),)?;
-                    for (i, c) in line.escape_default().enumerate() {
-                        queue!(
-                            stdout,
-                            cursor::MoveTo(x_range.start + 2 + i as u16, cy),
-                            // Print(" "),
-                            Print(c),
-                        )?;
+                    queue!(stdout, cursor::MoveTo(x_range.start + 1, cy), Print(" "))?;
+                    let mut remaining = width;
+                    for (syntax, text) in line {
+                        let text: String = escape_control(text).chars().take(remaining).collect();
+                        remaining -= text.chars().count();
+                        let text = match syntax {
+                            Syntax::Key => text.dark_cyan(),
+                            Syntax::String => text.dark_green(),
+                            Syntax::Number => text.dark_magenta(),
+                            Syntax::Literal => text.dark_yellow(),
+                            Syntax::Punctuation => text.grey(),
+                            Syntax::Comment | Syntax::Elided => text.dark_grey().italic(),
+                            Syntax::Plain => text.reset(),
+                        };
+                        queue!(stdout, PrintStyledContent(text))?;
                     }
+                    queue!(stdout, Print(" ".repeat(remaining)))?;
                     idx += 1;
                 }
-                for cy in idx + 1..y_range.end {
-                    for cx in x_range.start + 1..x_range.end {
-                        queue!(stdout, cursor::MoveTo(cx, cy), Print(" "),)?;
-                    }
+                for cy in idx + y_range.start..y_range.end {
+                    queue!(
+                        stdout,
+                        cursor::MoveTo(x_range.start + 1, cy),
+                        Print(" ".repeat(width + 1))
+                    )?;
+                }
+            }
+            Preview::Text { lines } => {
+                let width = width.saturating_sub(1) as usize;
+                let rows = text_rows(lines, width, self.wrap);
+                let mut idx = 0;
+                for row in rows.iter().take(height as usize) {
+                    queue!(
+                        stdout,
+                        cursor::MoveTo(x_range.start + 1, y_range.start + idx),
+                        Print(" "),
+                        Print(row),
+                    )?;
+                    idx += 1;
+                }
+                for cy in y_range.start + idx..y_range.end {
+                    queue!(
+                        stdout,
+                        cursor::MoveTo(x_range.start + 1, cy),
+                        Print(" ".repeat(width + 1))
+                    )?;
                 }
             }
         }
@@ -131,6 +212,20 @@ impl Draw for FilePreview {
 }
 
 impl FilePreview {
+    /// Text preview with the given lines (e.g. generated by a plugin).
+    pub fn text(path: PathBuf, lines: Vec<String>) -> Self {
+        FilePreview {
+            path,
+            modified: SystemTime::now(),
+            preview: Preview::Text { lines },
+            wrap: false,
+        }
+    }
+
+    pub fn set_wrap(&mut self, wrap: bool) {
+        self.wrap = wrap;
+    }
+
     pub fn new(path: PathBuf) -> Self {
         let extension = path
             .extension()
@@ -144,28 +239,68 @@ impl FilePreview {
             .and_then(|m| m.modified().ok())
             .unwrap_or_else(SystemTime::now);
 
+        let too_large =
+            is_remote(&path) && path.metadata().is_ok_and(|m| m.len() > REMOTE_MAX_SIZE);
+
         let preview = match extension.as_str() {
+            _ if too_large => Preview::Text {
+                lines: vec![format!(
+                    "(no preview - remote files larger than {})",
+                    file_size_str(REMOTE_MAX_SIZE)
+                )],
+            },
             "png" | "bmp" | "jpg" | "jpeg" => {
-                if let Ok(img_bytes) = image::io::Reader::open(&path) {
-                    let img = img_bytes.decode().ok();
-                    Preview::Image { img }
-                } else {
-                    Preview::Image { img: None }
+                let info = ImageInfo::read(&path);
+                let img = image::io::Reader::open(&path)
+                    .ok()
+                    .and_then(|reader| reader.decode().ok())
+                    .map(|img| {
+                        if img.width() > MAX_IMAGE_SIZE || img.height() > MAX_IMAGE_SIZE {
+                            img.thumbnail(MAX_IMAGE_SIZE, MAX_IMAGE_SIZE)
+                        } else {
+                            img
+                        }
+                    });
+                Preview::Image {
+                    img,
+                    info,
+                    thumbnail: Thumbnail::default(),
+                }
+            }
+            "json" | "geojson" | "yaml" | "yml" => {
+                let text = path
+                    .metadata()
+                    .ok()
+                    .filter(|m| m.len() <= STRUCTURED_MAX_SIZE)
+                    .and_then(|_| std::fs::read_to_string(&path).ok());
+                let lines = match (extension.as_str(), text) {
+                    ("yaml" | "yml", Some(text)) => Some(yaml_lines(&text)),
+                    (_, Some(text)) => json_lines(&text),
+                    (_, None) => None,
+                };
+                match lines {
+                    Some(lines) => Preview::Structured { lines },
+                    None => Preview::Text {
+                        lines: read_lines(&path),
+                    },
                 }
             }
             "wav" | "aiff" | "au" | "flac" | "m4a" | "mp3" | "opus" | "mov" | "pdf" | "doc"
             | "docx" | "ppt" | "pptx" | "xls" | "xlsx" | "zip" => {
-                let lines = match std::process::Command::new("mediainfo").arg(&path).output() {
-                    Ok(output) => output.stdout.lines().take(128).flatten().collect(),
-                    Err(e) => {
-                        let mut lines = Vec::new();
-                        lines.push("Error: Could not run mediainfo".to_string());
-                        lines.push(e.to_string());
-                        lines.push("".to_string());
-                        lines.push(
-                            "You must have mediainfo installed to get a preview for this file-type.".to_string(),
-                        );
-                        lines
+                let lines = if !exec_allowed() {
+                    vec!["(no preview - external programs are disabled)".to_string()]
+                } else {
+                    match std::process::Command::new("mediainfo").arg(&path).output() {
+                        Ok(output) => output.stdout.lines().take(128).flatten().collect(),
+                        Err(e) => {
+                            vec![
+                                "Error: Could not run mediainfo".to_string(),
+                                e.to_string(),
+                                "".to_string(),
+                                "You must have mediainfo installed to get a preview for this file-type."
+                                    .to_string(),
+                            ]
+                        }
                     }
                 };
                 Preview::Text { lines }
@@ -180,27 +315,75 @@ impl FilePreview {
             //     let lines: Vec<String> = output.stdout.lines().take(128).flatten().collect();
             //     Preview::Text { lines }
             // }
-            _ => {
-                // Simple method
-                if let Ok(file) = File::open(&path) {
-                    let lines = io::BufReader::new(file)
-                        .lines()
-                        .take(128)
-                        .flatten()
-                        .collect();
-                    Preview::Text { lines }
-                } else {
-                    Preview::Text { lines: Vec::new() }
-                }
-            }
+            _ => Preview::Text {
+                lines: read_lines(&path),
+            },
         };
 
         FilePreview {
             path,
             modified,
             preview,
+            wrap: false,
+        }
+    }
+}
+
+/// Scales the image to the size - the last result is reused, if the size did not change.
+fn scaled(img: &DynamicImage, thumbnail: &Thumbnail, width: u32, height: u32) -> RgbImage {
+    let mut thumbnail = thumbnail.lock();
+    match thumbnail.as_ref() {
+        Some((size, scaled)) if *size == (width, height) => scaled.clone(),
+        _ => {
+            let scaled = img.thumbnail_exact(width, height).into_rgb8();
+            *thumbnail = Some(((width, height), scaled.clone()));
+            scaled
+        }
+    }
+}
+
+/// Expands tabs and escapes other control characters, so the text cannot break the layout
+/// (or send escape sequences to the terminal).
+pub fn escape_control(text: &str) -> String {
+    text.chars()
+        .flat_map(|c| match c {
+            '\t' => "    ".chars().collect(),
+            c if c.is_control() => c.escape_default().collect(),
+            c => vec![c],
+        })
+        .collect()
+}
+
+/// Rows of a text preview with the given width - long lines are either wrapped or truncated.
+fn text_rows(lines: &[String], width: usize, wrap: bool) -> Vec<String> {
+    let mut rows = Vec::new();
+    for line in lines {
+        let line = escape_control(line);
+        if !wrap || width == 0 {
+            rows.push(line.exact_width(width));
+            continue;
+        }
+        let chars: Vec<char> = line.chars().collect();
+        if chars.is_empty() {
+            rows.push(" ".repeat(width));
+        }
+        for chunk in chars.chunks(width) {
+            rows.push(chunk.iter().collect::<String>().exact_width(width));
         }
     }
+    rows
+}
+
+/// The first lines of a text file.
+fn read_lines(path: &Path) -> Vec<String> {
+    match File::open(path) {
+        Ok(file) => io::BufReader::new(file)
+            .lines()
+            .take(128)
+            .flatten()
+            .collect(),
+        Err(_) => Vec::new(),
+    }
 }
 
 impl PanelContent for FilePreview {
@@ -305,3 +488,42 @@ impl PreviewPanel {
         }
     }
 }
+
+#[test]
+fn test_text_rows() {
+    let lines = vec!["abcdefgh".to_string(), String::new(), "\tx\r".to_string()];
+    assert_eq!(text_rows(&lines, 5, false), vec!["abcd~", "     ", "    ~"]);
+    assert_eq!(
+        text_rows(&lines, 5, true),
+        vec!["abcde", "fgh  ", "     ", "    x", "\\r   "]
+    );
+
+    // Structured previews keep the raw text of the file, which is escaped when it is drawn
+    let yaml = yaml_lines("key: \x1b[31mred\tend");
+    let text: String = yaml[0].iter().map(|(_, s)| escape_control(s)).collect();
+    assert_eq!(text, "key: \\u{1b}[31mred    end");
+}
+
+#[test]
+fn test_scaled_thumbnail() {
+    let img = DynamicImage::new_rgb8(64, 32);
+    let preview = Preview::Image {
+        img: Some(img.clone()),
+        info: None,
+        thumbnail: Thumbnail::default(),
+    };
+    // The copy in the cache shares the scaled image
+    let cached = preview.clone();
+    let Preview::Image { thumbnail, .. } = &preview else {
+        unreachable!()
+    };
+    assert_eq!(scaled(&img, thumbnail, 16, 8).dimensions(), (16, 8));
+    let Preview::Image { thumbnail, .. } = &cached else {
+        unreachable!()
+    };
+    assert_eq!(
+        thumbnail.lock().as_ref().map(|(size, _)| *size),
+        Some((16, 8))
+    );
+    assert_eq!(scaled(&img, thumbnail, 8, 4).dimensions(), (8, 4));
+}
@@ -0,0 +1,73 @@
+use std::collections::BTreeMap;
+
+use crate::util::ExactWidth;
+
+use super::*;
+
+/// Vim-style marks (`M<key>` and `` `<key> ``) - unlike the jump-marks of the config,
+/// they only live as long as rfm runs.
+///
+/// While a mark is chosen, they are listed in a popup at the bottom of the screen.
+#[derive(Debug, Default)]
+pub struct SessionMarks {
+    /// Directory and selected item of every mark
+    marks: BTreeMap<char, (PathBuf, Option<PathBuf>)>,
+}
+
+impl Draw for SessionMarks {
+    fn draw(
+        &mut self,
+        stdout: &mut Stdout,
+        x_range: Range<u16>,
+        y_range: Range<u16>,
+    ) -> Result<()> {
+        let width = x_range.end.saturating_sub(x_range.start) as usize;
+        let height = y_range.end.saturating_sub(y_range.start) as usize;
+        // Keep one line for the border
+        let n_rows = self.marks.len().min(height.saturating_sub(1));
+        let y_start = y_range.end.saturating_sub(n_rows as u16 + 1);
+        queue!(
+            stdout,
+            cursor::MoveTo(x_range.start, y_start),
+            PrintStyledContent("―".repeat(width).dark_green().bold()),
+        )?;
+        for (row, (key, (dir, _))) in self.marks.iter().take(n_rows).enumerate() {
+            queue!(
+                stdout,
+                cursor::MoveTo(x_range.start, y_start + 1 + row as u16),
+                PrintStyledContent(format!(" {key}   ").bold()),
+                Print(
+                    dir.display()
+                        .to_string()
+                        .exact_width(width.saturating_sub(5))
+                ),
+            )?;
+        }
+        Ok(())
+    }
+}
+
+impl SessionMarks {
+    /// Sets (or moves) the mark to the directory and its selected item.
+    pub fn set(&mut self, key: char, dir: PathBuf, selected: Option<PathBuf>) {
+        self.marks.insert(key, (dir, selected));
+    }
+
+    pub fn get(&self, key: char) -> Option<(&Path, Option<&Path>)> {
+        self.marks
+            .get(&key)
+            .map(|(dir, selected)| (dir.as_path(), selected.as_deref()))
+    }
+}
+
+#[test]
+fn test_session_marks() {
+    let mut marks = SessionMarks::default();
+    marks.set('a', PathBuf::from("/tmp"), None);
+    marks.set('a', PathBuf::from("/usr"), Some(PathBuf::from("/usr/bin")));
+    assert_eq!(
+        marks.get('a'),
+        Some((Path::new("/usr"), Some(Path::new("/usr/bin"))))
+    );
+    assert_eq!(marks.get('b'), None);
+}
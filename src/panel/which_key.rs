@@ -0,0 +1,63 @@
+use crate::{commands::CommandParser, util::ExactWidth};
+
+use super::*;
+
+/// Popup that lists the possible completions of a partially typed key sequence.
+pub struct WhichKey {
+    /// Keys, command name and description of every completion
+    entries: Vec<(String, &'static str, String)>,
+}
+
+impl Draw for WhichKey {
+    fn draw(
+        &mut self,
+        stdout: &mut Stdout,
+        x_range: Range<u16>,
+        y_range: Range<u16>,
+    ) -> Result<()> {
+        let width = x_range.end.saturating_sub(x_range.start) as usize;
+        let height = y_range.end.saturating_sub(y_range.start) as usize;
+        // Keep one line for the border
+        let n_rows = self.entries.len().min(height.saturating_sub(1));
+        let y_start = y_range.end.saturating_sub(n_rows as u16 + 1);
+        let keys_width = self
+            .entries
+            .iter()
+            .map(|(keys, _, _)| keys.chars().count())
+            .max()
+            .unwrap_or(0)
+            + 3;
+        let name_width = 24.min(width / 4);
+
+        queue!(
+            stdout,
+            cursor::MoveTo(x_range.start, y_start),
+            PrintStyledContent("―".repeat(width).dark_green().bold()),
+        )?;
+        for (row, (keys, name, description)) in self.entries.iter().take(n_rows).enumerate() {
+            let rest = width.saturating_sub(keys_width + name_width);
+            queue!(
+                stdout,
+                cursor::MoveTo(x_range.start, y_start + 1 + row as u16),
+                PrintStyledContent(format!(" {keys}").exact_width(keys_width).bold()),
+                PrintStyledContent(name.exact_width(name_width).dark_green()),
+                Print(description.exact_width(rest)),
+            )?;
+        }
+        Ok(())
+    }
+}
+
+impl WhichKey {
+    pub fn new(parser: &CommandParser) -> Self {
+        let entries = parser
+            .completions()
+            .into_iter()
+            .map(|(keys, cmd)| {
+                let (name, description) = cmd.describe();
+                (keys, name, description)
+            })
+            .collect();
+        WhichKey { entries }
+    }
+}
@@ -0,0 +1,432 @@
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::SystemTime,
+};
+
+use log::{error, info};
+use parking_lot::Mutex;
+use rlua::{Context, Function, Lua, RegistryKey};
+
+use crate::hooks::{HookContext, HookEvent};
+
+/// Maximum number of plugin previews that are remembered
+const PREVIEW_CACHE_SIZE: usize = 256;
+
+/// State of rfm that plugins can query, while one of their functions runs.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PluginState {
+    pub cwd: PathBuf,
+    pub selected: Option<PathBuf>,
+    pub marked: Vec<PathBuf>,
+}
+
+/// Requests of a plugin - executed by the panel-manager once the plugin function returned.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PluginAction {
+    /// Runs the command with the given name (the names of `keys.toml`, e.g. "mkdir" or "tabs.new")
+    Command(String),
+    /// Jumps to the directory
+    Cd(PathBuf),
+}
+
+/// Keybinding that calls a plugin function.
+pub struct PluginBinding {
+    pub keys: String,
+    pub description: String,
+    function: RegistryKey,
+}
+
+/// Preview of a plugin, together with the modification time of the file.
+type CachedPreview = (SystemTime, Option<Vec<String>>);
+
+/// Everything the `rfm` table of the plugins writes to.
+#[derive(Default)]
+struct Shared {
+    state: PluginState,
+    actions: Vec<PluginAction>,
+    bindings: Vec<PluginBinding>,
+    previewers: Vec<RegistryKey>,
+    statuslines: Vec<RegistryKey>,
+    /// Event name and function of `rfm.on`
+    hooks: Vec<(String, RegistryKey)>,
+}
+
+/// Runs the Lua plugins of `~/.config/rfm/plugins/`.
+///
+/// Plugins use the global `rfm` table to query the state (`cwd`, `selected`, `marked`),
+/// to run commands (`command`, `cd`, `notify`) and to register their functions:
+/// `bind(keys, function, description)` adds a keybinding, `preview(function(path))`
+/// generates text previews, `status(function())` adds text to the header and
+/// `on(event, function(files))` is called on the events of [`HookEvent`].
+///
+/// The host is shared with the preview worker, so only one thread runs Lua code at a time.
+/// Events without registered functions never wait for it.
+pub struct PluginHost {
+    lua: Mutex<Lua>,
+    shared: Arc<Mutex<Shared>>,
+    previews: Mutex<HashMap<PathBuf, CachedPreview>>,
+}
+
+impl Default for PluginHost {
+    fn default() -> Self {
+        PluginHost::new()
+    }
+}
+
+impl PluginHost {
+    pub fn new() -> Self {
+        let host = PluginHost {
+            lua: Mutex::new(Lua::new()),
+            shared: Arc::default(),
+            previews: Mutex::default(),
+        };
+        let result = host
+            .lua
+            .lock()
+            .context(|ctx| register_api(ctx, host.shared.clone()));
+        if let Err(e) = result {
+            error!("Cannot initialize the plugin API: {e}");
+        }
+        host
+    }
+
+    /// Loads all `*.lua` files of the directory (in alphabetical order).
+    ///
+    /// Broken plugins are reported and skipped.
+    pub fn load_dir(&self, dir: &Path) {
+        let mut files: Vec<PathBuf> = std::fs::read_dir(dir)
+            .into_iter()
+            .flatten()
+            .flatten()
+            .map(|entry| entry.path())
+            .filter(|path| path.extension().is_some_and(|ext| ext == "lua"))
+            .collect();
+        files.sort();
+        for file in files {
+            let result = std::fs::read_to_string(&file)
+                .map_err(|e| e.to_string())
+                .and_then(|source| self.load(&file.to_string_lossy(), &source));
+            match result {
+                Ok(()) => info!("Loaded plugin {}", file.display()),
+                Err(e) => error!("Plugin {}: {e}", file.display()),
+            }
+        }
+    }
+
+    /// Runs the source of a plugin.
+    pub fn load(&self, name: &str, source: &str) -> Result<(), String> {
+        self.lua
+            .lock()
+            .context(|ctx| ctx.load(source).set_name(name)?.exec())
+            .map_err(|e| e.to_string())
+    }
+
+    /// Keybindings of all plugins - the index is used by [`PluginHost::call_binding`].
+    pub fn bindings(&self) -> Vec<(String, String)> {
+        self.shared
+            .lock()
+            .bindings
+            .iter()
+            .map(|b| (b.keys.clone(), b.description.clone()))
+            .collect()
+    }
+
+    /// Calls the function of a keybinding and returns the actions it requested.
+    pub fn call_binding(&self, index: usize, state: PluginState) -> Vec<PluginAction> {
+        self.shared.lock().state = state;
+        let result = self.lua.lock().context(|ctx| {
+            let function: Function = {
+                let shared = self.shared.lock();
+                let Some(binding) = shared.bindings.get(index) else {
+                    return Ok(());
+                };
+                ctx.registry_value(&binding.function)?
+            };
+            function.call::<_, ()>(())
+        });
+        if let Err(e) = result {
+            error!("Plugin: {e}");
+        }
+        std::mem::take(&mut self.shared.lock().actions)
+    }
+
+    /// Calls the functions registered for the event and returns the actions they requested.
+    pub fn fire(&self, event: HookEvent, context: &HookContext) -> Vec<PluginAction> {
+        {
+            let mut shared = self.shared.lock();
+            if !shared.hooks.iter().any(|(name, _)| name == event.name()) {
+                return Vec::new();
+            }
+            shared.state = PluginState {
+                cwd: context.cwd.clone(),
+                selected: context.selected.clone(),
+                marked: context.marked.clone(),
+            };
+        }
+        let files: Vec<String> = context
+            .files
+            .iter()
+            .map(|p| p.to_string_lossy().to_string())
+            .collect();
+        let result = self.lua.lock().context(|ctx| {
+            let functions: Vec<Function> = {
+                let shared = self.shared.lock();
+                shared
+                    .hooks
+                    .iter()
+                    .filter(|(name, _)| name == event.name())
+                    .map(|(_, key)| ctx.registry_value(key))
+                    .collect::<rlua::Result<_>>()?
+            };
+            for function in functions {
+                function.call::<_, ()>(files.clone())?;
+            }
+            Ok::<_, rlua::Error>(())
+        });
+        if let Err(e) = result {
+            error!("Plugin hook {}: {e}", event.name());
+        }
+        std::mem::take(&mut self.shared.lock().actions)
+    }
+
+    /// Returns the registered functions.
+    ///
+    /// NOTE: The lock is released before the functions are called, because they may call `rfm.*`.
+    fn functions<'lua>(
+        &self,
+        ctx: Context<'lua>,
+        select: impl Fn(&Shared) -> &Vec<RegistryKey>,
+    ) -> rlua::Result<Vec<Function<'lua>>> {
+        let shared = self.shared.lock();
+        select(&shared)
+            .iter()
+            .map(|key| ctx.registry_value(key))
+            .collect()
+    }
+
+    /// Returns true if a plugin generates previews.
+    pub fn has_previewers(&self) -> bool {
+        !self.shared.lock().previewers.is_empty()
+    }
+
+    /// Text preview of the first plugin that handles the file.
+    ///
+    /// NOTE: Reads the metadata of the file, so call it in the background.
+    pub fn preview(&self, path: &Path) -> Option<Vec<String>> {
+        if !self.has_previewers() {
+            return None;
+        }
+        let modified = path
+            .metadata()
+            .and_then(|m| m.modified())
+            .unwrap_or(SystemTime::UNIX_EPOCH);
+        if let Some((cached, lines)) = self.previews.lock().get(path) {
+            if *cached == modified {
+                return lines.clone();
+            }
+        }
+        let path_str = path.to_string_lossy().to_string();
+        let result = self.lua.lock().context(|ctx| {
+            for function in self.functions(ctx, |shared| &shared.previewers)? {
+                if let Some(text) = function.call::<_, Option<String>>(path_str.clone())? {
+                    return Ok(Some(text));
+                }
+            }
+            Ok::<_, rlua::Error>(None)
+        });
+        let lines = result
+            .unwrap_or_else(|e| {
+                error!("Plugin preview: {e}");
+                None
+            })
+            .map(|text| text.lines().map(String::from).collect());
+        let mut previews = self.previews.lock();
+        if previews.len() >= PREVIEW_CACHE_SIZE {
+            previews.clear();
+        }
+        previews.insert(path.to_path_buf(), (modified, lines.clone()));
+        lines
+    }
+
+    /// Text of all status functions, which is shown in the header.
+    pub fn status(&self, state: PluginState) -> Option<String> {
+        if self.shared.lock().statuslines.is_empty() {
+            return None;
+        }
+        self.shared.lock().state = state;
+        let result = self.lua.lock().context(|ctx| {
+            let mut parts = Vec::new();
+            for function in self.functions(ctx, |shared| &shared.statuslines)? {
+                if let Some(text) = function.call::<_, Option<String>>(())? {
+                    parts.push(text);
+                }
+            }
+            Ok::<_, rlua::Error>(parts)
+        });
+        match result {
+            Ok(parts) if !parts.is_empty() => Some(parts.join(" ")),
+            Ok(_) => None,
+            Err(e) => {
+                error!("Plugin status: {e}");
+                None
+            }
+        }
+    }
+}
+
+/// Creates the global `rfm` table.
+fn register_api(ctx: Context, shared: Arc<Mutex<Shared>>) -> rlua::Result<()> {
+    let api = ctx.create_table()?;
+
+    let s = shared.clone();
+    api.set(
+        "cwd",
+        ctx.create_function(move |_, ()| Ok(s.lock().state.cwd.to_string_lossy().to_string()))?,
+    )?;
+    let s = shared.clone();
+    api.set(
+        "selected",
+        ctx.create_function(move |_, ()| {
+            let state = &s.lock().state;
+            Ok(state
+                .selected
+                .as_ref()
+                .map(|p| p.to_string_lossy().to_string()))
+        })?,
+    )?;
+    let s = shared.clone();
+    api.set(
+        "marked",
+        ctx.create_function(move |_, ()| {
+            let state = &s.lock().state;
+            Ok(state
+                .marked
+                .iter()
+                .map(|p| p.to_string_lossy().to_string())
+                .collect::<Vec<_>>())
+        })?,
+    )?;
+    let s = shared.clone();
+    api.set(
+        "command",
+        ctx.create_function(move |_, name: String| {
+            s.lock().actions.push(PluginAction::Command(name));
+            Ok(())
+        })?,
+    )?;
+    let s = shared.clone();
+    api.set(
+        "cd",
+        ctx.create_function(move |_, path: String| {
+            s.lock().actions.push(PluginAction::Cd(path.into()));
+            Ok(())
+        })?,
+    )?;
+    api.set(
+        "notify",
+        ctx.create_function(|_, message: String| {
+            info!("{message}");
+            Ok(())
+        })?,
+    )?;
+    let s = shared.clone();
+    api.set(
+        "bind",
+        ctx.create_function(
+            move |ctx, (keys, function, description): (String, Function, Option<String>)| {
+                let function = ctx.create_registry_value(function)?;
+                s.lock().bindings.push(PluginBinding {
+                    description: description.unwrap_or_else(|| format!("Plugin function ({keys})")),
+                    keys,
+                    function,
+                });
+                Ok(())
+            },
+        )?,
+    )?;
+    let s = shared.clone();
+    api.set(
+        "preview",
+        ctx.create_function(move |ctx, function: Function| {
+            let function = ctx.create_registry_value(function)?;
+            s.lock().previewers.push(function);
+            Ok(())
+        })?,
+    )?;
+    let s = shared.clone();
+    api.set(
+        "status",
+        ctx.create_function(move |ctx, function: Function| {
+            let function = ctx.create_registry_value(function)?;
+            s.lock().statuslines.push(function);
+            Ok(())
+        })?,
+    )?;
+    let s = shared;
+    api.set(
+        "on",
+        ctx.create_function(move |ctx, (event, function): (String, Function)| {
+            let function = ctx.create_registry_value(function)?;
+            s.lock().hooks.push((event, function));
+            Ok(())
+        })?,
+    )?;
+
+    ctx.globals().set("rfm", api)
+}
+
+#[test]
+fn test_plugins() {
+    let host = PluginHost::new();
+    host.load(
+        "test",
+        r#"
+        rfm.bind("zz", function()
+            rfm.command("mkdir")
+            rfm.cd(rfm.cwd() .. "/" .. #rfm.marked())
+        end, "Test binding")
+        rfm.preview(function(path)
+            if path:match("%.test$") then return "first\nsecond" end
+        end)
+        rfm.status(function() return rfm.selected() end)
+        rfm.on("after_paste", function(files) rfm.cd(files[1]) end)
+        "#,
+    )
+    .unwrap();
+    assert!(host.load("broken", "rfm.bind(").is_err());
+
+    assert_eq!(
+        host.bindings(),
+        vec![("zz".to_string(), "Test binding".to_string())]
+    );
+    let state = PluginState {
+        cwd: "/home".into(),
+        selected: Some("/home/a.test".into()),
+        marked: vec!["/home/a.test".into(), "/home/b".into()],
+    };
+    assert_eq!(
+        host.call_binding(0, state.clone()),
+        vec![
+            PluginAction::Command("mkdir".into()),
+            PluginAction::Cd("/home/2".into())
+        ]
+    );
+    assert_eq!(host.status(state).as_deref(), Some("/home/a.test"));
+    assert_eq!(
+        host.preview(Path::new("/home/a.test")),
+        Some(vec!["first".to_string(), "second".to_string()])
+    );
+    assert_eq!(host.preview(Path::new("/home/a.txt")), None);
+
+    let context = HookContext {
+        files: vec!["/tmp/pasted".into()],
+        ..Default::default()
+    };
+    assert_eq!(
+        host.fire(HookEvent::AfterPaste, &context),
+        vec![PluginAction::Cd("/tmp/pasted".into())]
+    );
+    assert_eq!(host.fire(HookEvent::Quit, &context), vec![]);
+}
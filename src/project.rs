@@ -0,0 +1,241 @@
+use std::{
+    collections::{BTreeMap, HashSet},
+    os::unix::ffi::OsStrExt,
+    path::{Path, PathBuf},
+};
+
+use log::{info, warn};
+use serde::Deserialize;
+use sha1::{Digest, Sha1};
+
+use crate::util::xdg_state_home;
+
+/// Name of the file that defines the commands of a directory tree.
+pub const PROJECT_FILE: &str = ".rfm.toml";
+
+/// Content of a `.rfm.toml`.
+#[derive(Deserialize, Debug, Default, Clone, PartialEq, Eq)]
+pub struct ProjectConfig {
+    /// Key-sequences mapped to shell commands (e.g. `b = "cargo build"`).
+    ///
+    /// The commands run inside the directory of the `.rfm.toml`.
+    #[serde(default)]
+    pub commands: BTreeMap<String, String>,
+}
+
+/// Commands that are only active inside the directory tree of a `.rfm.toml`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Project {
+    /// Directory that contains the `.rfm.toml`
+    pub root: PathBuf,
+
+    pub config: ProjectConfig,
+
+    /// SHA-1 of the location and the content of the `.rfm.toml` (see [`AllowList`])
+    pub hash: String,
+}
+
+impl Project {
+    /// Returns the project of the nearest `.rfm.toml` in the directory or one of its ancestors.
+    ///
+    /// A file that cannot be parsed is skipped with a warning.
+    /// This reads the filesystem - call it in the background.
+    pub fn find(dir: &Path) -> Option<Project> {
+        let root = dir
+            .ancestors()
+            .find(|ancestor| ancestor.join(PROJECT_FILE).is_file())?;
+        let path = root.join(PROJECT_FILE);
+        let content = std::fs::read_to_string(&path)
+            .map_err(|e| warn!("Cannot read {}: {e}", path.display()))
+            .ok()?;
+        match toml::from_str(&content) {
+            Ok(config) => Some(Project {
+                root: root.to_path_buf(),
+                config,
+                hash: project_hash(root, &content),
+            }),
+            Err(e) => {
+                warn!("Configuration error in {}: {e}", path.display());
+                None
+            }
+        }
+    }
+}
+
+fn project_hash(root: &Path, content: &str) -> String {
+    let mut hasher = Sha1::new();
+    hasher.update(root.as_os_str().as_bytes());
+    hasher.update([0]);
+    hasher.update(content.as_bytes());
+    hasher
+        .finalize()
+        .iter()
+        .map(|byte| format!("{byte:02x}"))
+        .collect()
+}
+
+/// `.rfm.toml` files whose commands the user allowed to run (like `direnv allow`).
+///
+/// The hashes are stored in `~/.local/state/rfm/allowed_projects`, one per line.
+/// Editing or moving a file revokes the permission.
+#[derive(Debug, Default)]
+pub struct AllowList {
+    hashes: HashSet<String>,
+    file: Option<PathBuf>,
+}
+
+impl AllowList {
+    pub fn open() -> Self {
+        let file = xdg_state_home().map(|dir| dir.join("rfm").join("allowed_projects"));
+        AllowList::from_file(file)
+    }
+
+    fn from_file(file: Option<PathBuf>) -> Self {
+        let hashes = file
+            .as_ref()
+            .and_then(|file| std::fs::read_to_string(file).ok())
+            .map(|content| content.lines().map(String::from).collect())
+            .unwrap_or_default();
+        AllowList { hashes, file }
+    }
+
+    pub fn contains(&self, project: &Project) -> bool {
+        self.hashes.contains(&project.hash)
+    }
+
+    /// Allows the commands of the project and saves the list.
+    pub fn allow(&mut self, project: &Project) {
+        if !self.hashes.insert(project.hash.clone()) {
+            return;
+        }
+        if let Err(e) = self.save() {
+            warn!("Cannot save the allowed projects: {e}");
+        }
+    }
+
+    fn save(&self) -> std::io::Result<()> {
+        let Some(file) = &self.file else {
+            return Ok(());
+        };
+        if let Some(dir) = file.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        let content: String = self.hashes.iter().map(|hash| format!("{hash}\n")).collect();
+        std::fs::write(file, content)
+    }
+}
+
+/// Keeps track of the project of the current directory.
+#[derive(Debug, Default)]
+pub struct ProjectTracker {
+    /// Last directory that was checked
+    last: PathBuf,
+
+    /// Project of the last directory
+    current: Option<Project>,
+
+    allowed: AllowList,
+}
+
+impl ProjectTracker {
+    pub fn open() -> Self {
+        ProjectTracker {
+            allowed: AllowList::open(),
+            ..Default::default()
+        }
+    }
+
+    /// Returns `true` if the directory differs from the last one,
+    /// so its project must be looked up (with [`Project::find`]).
+    pub fn changed(&mut self, dir: &Path) -> bool {
+        if dir == self.last {
+            return false;
+        }
+        self.last = dir.to_path_buf();
+        true
+    }
+
+    /// Takes the project that was found for the directory,
+    /// and returns it if it differs from the previous one.
+    ///
+    /// The outer `None` means "unchanged" (or the directory was already left again),
+    /// `Some(None)` means we left the project.
+    pub fn update(&mut self, dir: &Path, project: Option<Project>) -> Option<Option<&Project>> {
+        if dir != self.last || project == self.current {
+            return None;
+        }
+        match &project {
+            Some(p) if self.allowed.contains(p) => {
+                info!("Using project commands of {}", p.root.display())
+            }
+            Some(p) => info!(
+                "Project commands of {} are not allowed yet - they ask before they run",
+                p.root.display()
+            ),
+            None => info!("Left the project - project commands are removed"),
+        }
+        self.current = project;
+        Some(self.current.as_ref())
+    }
+
+    /// Returns the current project if its commands have not been allowed yet.
+    pub fn untrusted(&self) -> Option<&Project> {
+        self.current.as_ref().filter(|p| !self.allowed.contains(p))
+    }
+
+    /// Allows the commands of the current project.
+    pub fn allow(&mut self) {
+        if let Some(project) = &self.current {
+            info!("Allowed the project commands of {}", project.root.display());
+            self.allowed.allow(project);
+        }
+    }
+}
+
+#[test]
+fn test_project() {
+    let dir = tempfile::tempdir().unwrap();
+    let sub = dir.path().join("src/bin");
+    std::fs::create_dir_all(&sub).unwrap();
+    std::fs::write(
+        dir.path().join(PROJECT_FILE),
+        "[commands]\nb = \"cargo build\"\n",
+    )
+    .unwrap();
+    let mut tracker = ProjectTracker {
+        allowed: AllowList::from_file(Some(dir.path().join("state/allowed_projects"))),
+        ..Default::default()
+    };
+    assert!(tracker.changed(&sub));
+    assert!(!tracker.changed(&sub));
+    let project = tracker.update(&sub, Project::find(&sub)).unwrap().unwrap();
+    assert_eq!(project.root, dir.path());
+    assert_eq!(project.config.commands["b"], "cargo build");
+    assert!(tracker.untrusted().is_some());
+    tracker.allow();
+    assert!(tracker.untrusted().is_none());
+    // Same project
+    assert!(tracker.changed(dir.path()));
+    assert!(tracker
+        .update(dir.path(), Project::find(dir.path()))
+        .is_none());
+    // Stale result of a directory that was already left
+    assert!(tracker.update(&sub, None).is_none());
+    // Left the project
+    assert!(tracker.changed(Path::new("/")));
+    assert!(matches!(tracker.update(Path::new("/"), None), Some(None)));
+
+    // The permission survives restarts, but not changes of the file
+    let mut allowed = AllowList::from_file(Some(dir.path().join("state/allowed_projects")));
+    let project = Project::find(&sub).unwrap();
+    assert!(allowed.contains(&project));
+    std::fs::write(
+        dir.path().join(PROJECT_FILE),
+        "[commands]\nb = \"rm -rf ~\"\n",
+    )
+    .unwrap();
+    let changed = Project::find(&sub).unwrap();
+    assert!(!allowed.contains(&changed));
+    allowed.allow(&changed);
+    assert!(allowed.contains(&changed));
+}
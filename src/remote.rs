@@ -0,0 +1,202 @@
+use std::{
+    io::{self, BufRead, BufReader, Write},
+    os::unix::net::UnixStream,
+    path::{Path, PathBuf},
+};
+
+use log::{debug, warn};
+use once_cell::sync::OnceCell;
+use tokio::{
+    io::{AsyncBufReadExt, AsyncWriteExt},
+    net::{UnixListener, UnixStream as AsyncUnixStream},
+    sync::mpsc,
+};
+
+/// Environment variable with the socket of the rfm instance that started a program.
+pub const SOCKET_ENV: &str = "RFM_SOCKET";
+
+/// Socket of this instance, once the server listens on it.
+static SOCKET: OnceCell<PathBuf> = OnceCell::new();
+
+/// Environment of the programs started by rfm, so they talk to this instance by default
+/// (use it with [`std::process::Command::envs`]).
+///
+/// NOTE: Our own environment is not changed, because the threads of the runtime read it concurrently.
+pub fn socket_env() -> Option<(&'static str, &'static PathBuf)> {
+    SOCKET.get().map(|path| (SOCKET_ENV, path))
+}
+
+/// Commands that other programs can send to a running rfm.
+///
+/// Every command is a single line like `jump /home/user` - paths must be absolute.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RemoteCommand {
+    /// Changes into the directory
+    Jump(PathBuf),
+    /// Changes into the parent of the path and selects it
+    Select(PathBuf),
+    /// Toggles the mark of the path
+    Mark(PathBuf),
+    Quit,
+}
+
+impl RemoteCommand {
+    pub fn parse(line: &str) -> Result<Self, String> {
+        let line = line.trim();
+        let (name, argument) = line.split_once(' ').unwrap_or((line, ""));
+        let path = || {
+            let path = PathBuf::from(argument.trim());
+            if path.is_absolute() {
+                Ok(path)
+            } else {
+                Err(format!("'{name}' needs an absolute path"))
+            }
+        };
+        match name {
+            "jump" => Ok(RemoteCommand::Jump(path()?)),
+            "select" => Ok(RemoteCommand::Select(path()?)),
+            "mark" => Ok(RemoteCommand::Mark(path()?)),
+            "quit" => Ok(RemoteCommand::Quit),
+            _ => Err(format!("unknown command '{name}'")),
+        }
+    }
+}
+
+/// Listens on `$XDG_RUNTIME_DIR/rfm/rfm-<pid>.sock` for [`RemoteCommand`]s.
+///
+/// Every line is answered with `ok` or `error: <reason>`.
+/// The socket is removed again when rfm exits.
+#[derive(Debug)]
+pub struct RemoteServer {
+    path: PathBuf,
+}
+
+impl RemoteServer {
+    /// Returns `None` if `XDG_RUNTIME_DIR` is not set or the socket cannot be created.
+    ///
+    /// Must be called inside the tokio runtime.
+    pub fn new(tx: mpsc::UnboundedSender<RemoteCommand>) -> Option<Self> {
+        let path = socket_dir()?.join(format!("rfm-{}.sock", std::process::id()));
+        match RemoteServer::bind(path, tx) {
+            Ok(server) => Some(server),
+            Err(e) => {
+                warn!("remote: cannot create the socket: {e}");
+                None
+            }
+        }
+    }
+
+    fn bind(path: PathBuf, tx: mpsc::UnboundedSender<RemoteCommand>) -> io::Result<Self> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        // A leftover of a crashed instance with the same pid
+        let _ = std::fs::remove_file(&path);
+        let listener = UnixListener::bind(&path)?;
+        debug!("Listening for remote commands on {}", path.display());
+        let _ = SOCKET.set(path.clone());
+        tokio::spawn(async move {
+            while let Ok((stream, _)) = listener.accept().await {
+                tokio::spawn(serve(stream, tx.clone()));
+            }
+        });
+        Ok(RemoteServer { path })
+    }
+}
+
+impl Drop for RemoteServer {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.path);
+    }
+}
+
+async fn serve(stream: AsyncUnixStream, tx: mpsc::UnboundedSender<RemoteCommand>) {
+    let (reader, mut writer) = stream.into_split();
+    let mut lines = tokio::io::BufReader::new(reader).lines();
+    while let Ok(Some(line)) = lines.next_line().await {
+        let answer = match RemoteCommand::parse(&line) {
+            Ok(command) => match tx.send(command) {
+                Ok(()) => "ok\n".to_string(),
+                Err(_) => "error: rfm is shutting down\n".to_string(),
+            },
+            Err(e) => format!("error: {e}\n"),
+        };
+        if writer.write_all(answer.as_bytes()).await.is_err() {
+            break;
+        }
+    }
+}
+
+fn socket_dir() -> Option<PathBuf> {
+    let runtime_dir = std::env::var_os("XDG_RUNTIME_DIR").filter(|d| !d.is_empty())?;
+    Some(Path::new(&runtime_dir).join("rfm"))
+}
+
+/// Socket of the instance that started us, or else the most recently started instance.
+pub fn find_socket() -> Option<PathBuf> {
+    if let Some(socket) = std::env::var_os(SOCKET_ENV).filter(|s| !s.is_empty()) {
+        return Some(socket.into());
+    }
+    std::fs::read_dir(socket_dir()?)
+        .ok()?
+        .flatten()
+        .filter(|entry| entry.file_name().to_string_lossy().ends_with(".sock"))
+        .filter_map(|entry| Some((entry.metadata().ok()?.modified().ok()?, entry.path())))
+        .max()
+        .map(|(_, path)| path)
+}
+
+/// Sends a command to a running rfm and returns its answer.
+///
+/// Relative paths are resolved against the current directory of the caller.
+pub fn send(socket: &Path, command: &str) -> io::Result<String> {
+    let command = match command.split_once(' ') {
+        Some((name, path)) if !Path::new(path).is_absolute() => {
+            let path = std::env::current_dir()?.join(path);
+            let path = path.canonicalize().unwrap_or(path);
+            format!("{name} {}", path.display())
+        }
+        _ => command.to_string(),
+    };
+    let mut stream = UnixStream::connect(socket)?;
+    stream.write_all(format!("{command}\n").as_bytes())?;
+    let mut answer = String::new();
+    BufReader::new(stream).read_line(&mut answer)?;
+    Ok(answer.trim_end().to_string())
+}
+
+#[test]
+fn test_remote() {
+    assert_eq!(
+        RemoteCommand::parse("jump /home/user"),
+        Ok(RemoteCommand::Jump("/home/user".into()))
+    );
+    assert_eq!(
+        RemoteCommand::parse("select /tmp/a b\n"),
+        Ok(RemoteCommand::Select("/tmp/a b".into()))
+    );
+    assert_eq!(RemoteCommand::parse("quit"), Ok(RemoteCommand::Quit));
+    assert!(RemoteCommand::parse("mark relative").is_err());
+    assert!(RemoteCommand::parse("delete /").is_err());
+
+    let runtime = tokio::runtime::Runtime::new().unwrap();
+    let dir = tempfile::tempdir().unwrap();
+    let socket = dir.path().join("test.sock");
+    let (tx, mut rx) = mpsc::unbounded_channel();
+    let server = runtime.block_on(async { RemoteServer::bind(socket.clone(), tx).unwrap() });
+    let answers = std::thread::spawn(move || {
+        (
+            send(&socket, "mark /tmp/x").unwrap(),
+            send(&socket, "fly away").unwrap(),
+        )
+    });
+    assert_eq!(
+        runtime.block_on(rx.recv()),
+        Some(RemoteCommand::Mark("/tmp/x".into()))
+    );
+    let (ok, error) = answers.join().unwrap();
+    assert_eq!(ok, "ok");
+    assert_eq!(error, "error: unknown command 'fly'");
+    drop(server);
+    assert!(!dir.path().join("test.sock").exists());
+}
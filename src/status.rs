@@ -0,0 +1,98 @@
+use std::{
+    io,
+    path::{Path, PathBuf},
+};
+
+use log::{debug, warn};
+use serde::Serialize;
+
+/// What rfm is currently doing - written as JSON for status bars (e.g. waybar or tmux).
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize)]
+pub struct Status {
+    /// Directory of the center panel
+    pub cwd: PathBuf,
+
+    /// Selected item of the center panel
+    pub selected: Option<PathBuf>,
+
+    /// Number of marked items (in all directories)
+    pub marked: usize,
+
+    /// Descriptions of the file operations that are still running
+    pub jobs: Vec<String>,
+}
+
+/// Keeps `$XDG_RUNTIME_DIR/rfm/status-<pid>.json` up to date.
+///
+/// The file is replaced atomically, so readers never see a half-written status,
+/// and removed again when rfm exits.
+#[derive(Debug)]
+pub struct StatusFile {
+    path: PathBuf,
+
+    /// Last status that was written
+    last: Option<Status>,
+}
+
+impl StatusFile {
+    /// Returns `None` if `XDG_RUNTIME_DIR` is not set.
+    pub fn new() -> Option<Self> {
+        let runtime_dir = std::env::var_os("XDG_RUNTIME_DIR").filter(|d| !d.is_empty())?;
+        let path = Path::new(&runtime_dir)
+            .join("rfm")
+            .join(format!("status-{}.json", std::process::id()));
+        debug!("Writing status to {}", path.display());
+        Some(StatusFile::at(path))
+    }
+
+    fn at(path: PathBuf) -> Self {
+        StatusFile { path, last: None }
+    }
+
+    /// Writes the status, if it has changed since the last call.
+    pub fn update(&mut self, status: Status) {
+        if self.last.as_ref() == Some(&status) {
+            return;
+        }
+        if let Err(e) = self.write(&status) {
+            warn!("status: cannot write {}: {e}", self.path.display());
+        }
+        self.last = Some(status);
+    }
+
+    fn write(&self, status: &Status) -> io::Result<()> {
+        if let Some(parent) = self.path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let temp = self.path.with_extension("json.tmp");
+        std::fs::write(&temp, serde_json::to_vec(status)?)?;
+        std::fs::rename(&temp, &self.path)
+    }
+}
+
+impl Drop for StatusFile {
+    fn drop(&mut self) {
+        if self.last.is_some() {
+            let _ = std::fs::remove_file(&self.path);
+        }
+    }
+}
+
+#[test]
+fn test_status_file() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("rfm").join("status.json");
+    let mut file = StatusFile::at(path.clone());
+    file.update(Status {
+        cwd: PathBuf::from("/tmp"),
+        selected: Some(PathBuf::from("/tmp/a.txt")),
+        marked: 2,
+        jobs: Vec::new(),
+    });
+    assert_eq!(
+        std::fs::read_to_string(&path).unwrap(),
+        r#"{"cwd":"/tmp","selected":"/tmp/a.txt","marked":2,"jobs":[]}"#
+    );
+    drop(file);
+    assert!(!path.exists());
+}
@@ -0,0 +1,378 @@
+use std::fmt;
+
+use serde::{
+    de::{MapAccess, SeqAccess, Visitor},
+    Deserialize, Deserializer,
+};
+
+/// Arrays and objects with more entries only show the first ones.
+const MAX_ENTRIES: usize = 20;
+
+/// Maximum number of lines of a structured preview.
+const MAX_LINES: usize = 512;
+
+/// Kind of a highlighted piece of text.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Syntax {
+    Key,
+    String,
+    Number,
+    /// `true`, `false` and `null`
+    Literal,
+    /// Brackets, commas, colons and list markers
+    Punctuation,
+    Comment,
+    /// Marker for entries that are not shown
+    Elided,
+    Plain,
+}
+
+/// Line of a structured preview, split into highlighted pieces.
+pub type Line = Vec<(Syntax, String)>;
+
+/// JSON (or YAML) value that keeps the order of the object keys.
+#[derive(Debug, Clone, PartialEq)]
+enum Node {
+    Null,
+    Bool(bool),
+    Number(String),
+    String(String),
+    Array(Vec<Node>),
+    Object(Vec<(String, Node)>),
+}
+
+impl Node {
+    /// True if an array or object somewhere in the value has more than [`MAX_ENTRIES`] entries.
+    fn is_long(&self) -> bool {
+        match self {
+            Node::Array(items) => items.len() > MAX_ENTRIES || items.iter().any(Node::is_long),
+            Node::Object(entries) => {
+                entries.len() > MAX_ENTRIES || entries.iter().any(|(_, value)| value.is_long())
+            }
+            _ => false,
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for Node {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_any(NodeVisitor)
+    }
+}
+
+struct NodeVisitor;
+
+impl<'de> Visitor<'de> for NodeVisitor {
+    type Value = Node;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("a JSON value")
+    }
+
+    fn visit_unit<E>(self) -> Result<Node, E> {
+        Ok(Node::Null)
+    }
+
+    fn visit_bool<E>(self, v: bool) -> Result<Node, E> {
+        Ok(Node::Bool(v))
+    }
+
+    fn visit_i64<E>(self, v: i64) -> Result<Node, E> {
+        Ok(Node::Number(v.to_string()))
+    }
+
+    fn visit_u64<E>(self, v: u64) -> Result<Node, E> {
+        Ok(Node::Number(v.to_string()))
+    }
+
+    fn visit_f64<E>(self, v: f64) -> Result<Node, E> {
+        Ok(Node::Number(v.to_string()))
+    }
+
+    fn visit_str<E>(self, v: &str) -> Result<Node, E> {
+        Ok(Node::String(v.to_string()))
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Node, A::Error> {
+        let mut items = Vec::new();
+        while let Some(item) = seq.next_element()? {
+            items.push(item);
+        }
+        Ok(Node::Array(items))
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Node, A::Error> {
+        let mut entries = Vec::new();
+        while let Some(entry) = map.next_entry()? {
+            entries.push(entry);
+        }
+        Ok(Node::Object(entries))
+    }
+}
+
+/// Pretty-prints and highlights a JSON document - `None` if it is no valid JSON.
+///
+/// Only the first [`MAX_ENTRIES`] entries of arrays and objects are shown.
+pub fn json_lines(text: &str) -> Option<Vec<Line>> {
+    let node: Node = serde_json::from_str(text).ok()?;
+    let mut printer = Printer::default();
+    printer.node(&node, 0, Vec::new(), false);
+    Some(printer.lines)
+}
+
+#[derive(Default)]
+struct Printer {
+    lines: Vec<Line>,
+}
+
+impl Printer {
+    /// Prints the node, with `prefix` (e.g. the key) in front of its first line.
+    fn node(&mut self, node: &Node, indent: usize, mut prefix: Line, comma: bool) {
+        if self.lines.len() >= MAX_LINES {
+            return;
+        }
+        let pad = (Syntax::Plain, "  ".repeat(indent));
+        prefix.insert(0, pad.clone());
+        let end = |close: &str| {
+            let mut line = vec![pad.clone(), (Syntax::Punctuation, close.to_string())];
+            if comma {
+                line.push((Syntax::Punctuation, ",".to_string()));
+            }
+            line
+        };
+        let scalar = match node {
+            Node::Null => (Syntax::Literal, "null".to_string()),
+            Node::Bool(b) => (Syntax::Literal, b.to_string()),
+            Node::Number(n) => (Syntax::Number, n.clone()),
+            Node::String(s) => (Syntax::String, quote(s)),
+            Node::Array(items) if items.is_empty() => (Syntax::Punctuation, "[]".to_string()),
+            Node::Object(entries) if entries.is_empty() => (Syntax::Punctuation, "{}".to_string()),
+            Node::Array(items) => {
+                prefix.push((Syntax::Punctuation, "[".to_string()));
+                self.lines.push(prefix);
+                for (idx, item) in items.iter().take(MAX_ENTRIES).enumerate() {
+                    self.node(item, indent + 1, Vec::new(), idx + 1 < items.len());
+                }
+                self.elided(items.len(), indent + 1);
+                self.lines.push(end("]"));
+                return;
+            }
+            Node::Object(entries) => {
+                prefix.push((Syntax::Punctuation, "{".to_string()));
+                self.lines.push(prefix);
+                for (idx, (key, value)) in entries.iter().take(MAX_ENTRIES).enumerate() {
+                    let key = vec![
+                        (Syntax::Key, quote(key)),
+                        (Syntax::Punctuation, ": ".to_string()),
+                    ];
+                    self.node(value, indent + 1, key, idx + 1 < entries.len());
+                }
+                self.elided(entries.len(), indent + 1);
+                self.lines.push(end("}"));
+                return;
+            }
+        };
+        prefix.push(scalar);
+        if comma {
+            prefix.push((Syntax::Punctuation, ",".to_string()));
+        }
+        self.lines.push(prefix);
+    }
+
+    /// Prints the node in the block style of YAML, with `prefix` in front of its first line.
+    fn yaml_node(&mut self, node: &Node, indent: usize, mut prefix: Line) {
+        if self.lines.len() >= MAX_LINES {
+            return;
+        }
+        let pad = (Syntax::Plain, "  ".repeat(indent));
+        let scalar = match node {
+            Node::Null => (Syntax::Literal, "null".to_string()),
+            Node::Bool(b) => (Syntax::Literal, b.to_string()),
+            Node::Number(n) => (Syntax::Number, n.clone()),
+            Node::String(s) => (Syntax::String, yaml_quote(s)),
+            Node::Array(items) if items.is_empty() => (Syntax::Punctuation, "[]".to_string()),
+            Node::Object(entries) if entries.is_empty() => (Syntax::Punctuation, "{}".to_string()),
+            // The first entry continues the line of the prefix (e.g. the list marker)
+            Node::Array(items) => {
+                for (idx, item) in items.iter().take(MAX_ENTRIES).enumerate() {
+                    let mut line = match idx {
+                        0 => std::mem::take(&mut prefix),
+                        _ => vec![pad.clone()],
+                    };
+                    line.push((Syntax::Punctuation, "- ".to_string()));
+                    self.yaml_node(item, indent + 1, line);
+                }
+                self.elided(items.len(), indent);
+                return;
+            }
+            Node::Object(entries) => {
+                for (idx, (key, value)) in entries.iter().take(MAX_ENTRIES).enumerate() {
+                    let mut line = match idx {
+                        0 => std::mem::take(&mut prefix),
+                        _ => vec![pad.clone()],
+                    };
+                    line.push((Syntax::Key, yaml_quote(key)));
+                    line.push((Syntax::Punctuation, ":".to_string()));
+                    // Non-empty collections start on the next line
+                    let nested = match value {
+                        Node::Array(items) => !items.is_empty(),
+                        Node::Object(entries) => !entries.is_empty(),
+                        _ => false,
+                    };
+                    if nested {
+                        self.lines.push(line);
+                        let pad = (Syntax::Plain, "  ".repeat(indent + 1));
+                        self.yaml_node(value, indent + 1, vec![pad]);
+                    } else {
+                        line.push((Syntax::Plain, " ".to_string()));
+                        self.yaml_node(value, indent + 1, line);
+                    }
+                }
+                self.elided(entries.len(), indent);
+                return;
+            }
+        };
+        prefix.push(scalar);
+        self.lines.push(prefix);
+    }
+
+    fn elided(&mut self, len: usize, indent: usize) {
+        if len > MAX_ENTRIES {
+            self.lines.push(vec![
+                (Syntax::Plain, "  ".repeat(indent)),
+                (Syntax::Elided, format!("… {} more", len - MAX_ENTRIES)),
+            ]);
+        }
+    }
+}
+
+fn quote(s: &str) -> String {
+    serde_json::to_string(s).unwrap_or_default()
+}
+
+/// Keeps plain strings unquoted, unless YAML would read them as something else.
+fn yaml_quote(s: &str) -> String {
+    let plain = s
+        .chars()
+        .all(|c| c.is_alphanumeric() || matches!(c, ' ' | '_' | '-' | '.' | '/'))
+        && !s.starts_with(['-', ' ', '.'])
+        && !s.ends_with(' ')
+        && s.parse::<f64>().is_err()
+        && !matches!(s, "" | "true" | "false" | "null" | "yes" | "no");
+    match plain {
+        true => s.to_string(),
+        false => quote(s),
+    }
+}
+
+/// Highlights a YAML document.
+///
+/// Documents with long sequences or mappings are parsed and printed again, so that only the
+/// first [`MAX_ENTRIES`] entries are shown (which drops the comments). All other documents are
+/// highlighted line by line (keys, scalars, list markers and comments).
+pub fn yaml_lines(text: &str) -> Vec<Line> {
+    match serde_yaml::from_str::<Node>(text) {
+        Ok(node) if node.is_long() => {
+            let mut printer = Printer::default();
+            printer.yaml_node(&node, 0, vec![(Syntax::Plain, String::new())]);
+            printer.lines
+        }
+        _ => text.lines().take(MAX_LINES).map(yaml_line).collect(),
+    }
+}
+
+fn yaml_line(line: &str) -> Line {
+    let content = line.trim_start();
+    let mut out = vec![(
+        Syntax::Plain,
+        line[..line.len() - content.len()].to_string(),
+    )];
+    if content.starts_with('#') {
+        out.push((Syntax::Comment, content.to_string()));
+        return out;
+    }
+    let mut rest = content;
+    while let Some(item) = rest.strip_prefix("- ") {
+        out.push((Syntax::Punctuation, "- ".to_string()));
+        rest = item;
+    }
+    // Keys end with ": " or a colon at the end of the line (but not inside quotes)
+    let key_end = if rest.starts_with('"') || rest.starts_with('\'') {
+        None
+    } else {
+        rest.find(": ")
+            .or_else(|| rest.strip_suffix(':').map(str::len))
+    };
+    if let Some(end) = key_end {
+        out.push((Syntax::Key, rest[..end].to_string()));
+        out.push((Syntax::Punctuation, ":".to_string()));
+        rest = &rest[end + 1..];
+    }
+    // Trailing comments
+    let (value, comment) = match rest.find(" #") {
+        Some(idx) if !rest.trim_start().starts_with(['"', '\'']) => rest.split_at(idx),
+        _ => (rest, ""),
+    };
+    let trimmed = value.trim();
+    let syntax = match trimmed {
+        "" => Syntax::Plain,
+        "true" | "false" | "null" | "~" | "yes" | "no" => Syntax::Literal,
+        _ if trimmed.parse::<f64>().is_ok() => Syntax::Number,
+        _ if trimmed.starts_with(['"', '\'']) => Syntax::String,
+        _ if matches!(trimmed, "|" | ">" | "|-" | ">-" | "---" | "...") => Syntax::Punctuation,
+        _ => Syntax::String,
+    };
+    out.push((syntax, value.to_string()));
+    if !comment.is_empty() {
+        out.push((Syntax::Comment, comment.to_string()));
+    }
+    out
+}
+
+#[test]
+fn test_structured() {
+    let text = |lines: &[Line]| -> Vec<String> {
+        lines
+            .iter()
+            .map(|line| line.iter().map(|(_, s)| s.as_str()).collect())
+            .collect()
+    };
+    let lines = json_lines(r#"{"b":[1,2.5,null],"a":{"x":"y"},"c":[]}"#).unwrap();
+    assert_eq!(
+        text(&lines),
+        vec![
+            "{",
+            "  \"b\": [",
+            "    1,",
+            "    2.5,",
+            "    null",
+            "  ],",
+            "  \"a\": {",
+            "    \"x\": \"y\"",
+            "  },",
+            "  \"c\": []",
+            "}",
+        ]
+    );
+    assert_eq!(lines[1][1], (Syntax::Key, "\"b\"".to_string()));
+    assert!(json_lines("{nope").is_none());
+
+    let long = format!("[{}]", vec!["0"; 25].join(","));
+    let lines = text(&json_lines(&long).unwrap());
+    assert_eq!(lines.len(), 1 + MAX_ENTRIES + 2);
+    assert_eq!(lines[MAX_ENTRIES + 1], "  … 5 more");
+
+    let yaml = yaml_lines("# config\nname: rfm # comment\nitems:\n  - 42\n  - key: true");
+    assert_eq!(yaml[0][1], (Syntax::Comment, "# config".to_string()));
+    assert_eq!(yaml[1][1], (Syntax::Key, "name".to_string()));
+    assert_eq!(yaml[1][3], (Syntax::String, " rfm".to_string()));
+    assert_eq!(yaml[1][4], (Syntax::Comment, " # comment".to_string()));
+    assert_eq!(yaml[3][2], (Syntax::Number, "42".to_string()));
+    assert_eq!(yaml[4][4], (Syntax::Literal, " true".to_string()));
+
+    let long = format!("# long\nitems:\n{}", "  - a: 1\n    b: x y\n".repeat(25));
+    let lines = text(&yaml_lines(&long));
+    assert_eq!(lines.len(), 1 + 2 * MAX_ENTRIES + 1);
+    assert_eq!(lines[..3], ["items:", "  - a: 1", "    b: x y"]);
+    assert_eq!(lines[2 * MAX_ENTRIES + 1], "  … 5 more");
+}
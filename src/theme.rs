@@ -0,0 +1,232 @@
+use crossterm::style::{Attribute, Attributes, Color, ContentStyle};
+use globset::{GlobBuilder, GlobSet, GlobSetBuilder};
+use log::{error, warn};
+use once_cell::sync::OnceCell;
+use serde::Deserialize;
+
+pub static THEME: OnceCell<ThemeEngine> = OnceCell::new();
+
+/// Content of the `theme.toml`.
+#[derive(Deserialize, Debug, Default)]
+pub struct ThemeConfig {
+    /// Custom styles for files, that are matched by their name.
+    ///
+    /// The rules are checked from top to bottom, the first matching rule wins.
+    #[serde(default)]
+    files: Vec<FileRule>,
+}
+
+#[derive(Deserialize, Debug)]
+struct FileRule {
+    /// Glob that is matched against the file name (e.g. `*.rs` or `Makefile`)
+    pattern: String,
+
+    /// Foreground color - either a name (e.g. `dark_yellow`),
+    /// an ansi value (e.g. `208`) or a hex value (e.g. `#ff8700`)
+    #[serde(default)]
+    color: Option<String>,
+
+    /// Additional attributes (e.g. `bold`, `dim`, `italic`)
+    #[serde(default)]
+    attributes: Vec<String>,
+}
+
+/// Color and attributes that are applied on top of the default style of an element.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct FileStyle {
+    color: Option<Color>,
+    attributes: Attributes,
+}
+
+impl FileStyle {
+    pub fn apply(&self, mut style: ContentStyle) -> ContentStyle {
+        if let Some(color) = self.color {
+            style.foreground_color = Some(color);
+        }
+        style.attributes.extend(self.attributes);
+        style
+    }
+}
+
+/// Decides which style a file gets, based on its name.
+///
+/// The rules of the theme config take precedence over the ones from `LS_COLORS`.
+pub struct ThemeEngine {
+    globs: GlobSet,
+    styles: Vec<FileStyle>,
+}
+
+impl ThemeEngine {
+    pub fn new(config: ThemeConfig) -> Self {
+        let mut builder = GlobSetBuilder::new();
+        let mut styles = Vec::new();
+
+        let config_rules = config.files.into_iter().map(|rule| {
+            let mut style = FileStyle::default();
+            if let Some(color) = &rule.color {
+                match parse_color(color) {
+                    Some(color) => style.color = Some(color),
+                    None => warn!("theme: unknown color '{color}'"),
+                }
+            }
+            for attribute in rule.attributes.iter() {
+                match parse_attribute(attribute) {
+                    Some(attribute) => style.attributes.set(attribute),
+                    None => warn!("theme: unknown attribute '{attribute}'"),
+                }
+            }
+            (rule.pattern, style)
+        });
+        let ls_colors = std::env::var("LS_COLORS").unwrap_or_default();
+        let ls_rules = parse_ls_colors(&ls_colors);
+
+        for (pattern, style) in config_rules.chain(ls_rules) {
+            // NOTE: Match case-insensitive, so "*.jpg" also matches "IMG.JPG"
+            match GlobBuilder::new(&pattern)
+                .case_insensitive(true)
+                .literal_separator(true)
+                .build()
+            {
+                Ok(glob) => {
+                    builder.add(glob);
+                    styles.push(style);
+                }
+                Err(e) => warn!("theme: invalid pattern '{pattern}': {e}"),
+            }
+        }
+        let globs = builder.build().unwrap_or_else(|e| {
+            error!("theme: {e}");
+            GlobSet::empty()
+        });
+        ThemeEngine { globs, styles }
+    }
+
+    pub fn init(config: ThemeConfig) {
+        if THEME.set(ThemeEngine::new(config)).is_err() {
+            error!("Theme engine was already initialized.");
+        }
+    }
+
+    /// Returns the style of the first rule that matches the given file name.
+    pub fn get_style(name: &str) -> Option<FileStyle> {
+        let engine = THEME.get()?;
+        let idx = engine.globs.matches(name).into_iter().min()?;
+        engine.styles.get(idx).copied()
+    }
+}
+
+/// Parses a color from the theme config.
+fn parse_color(color: &str) -> Option<Color> {
+    if let Some(hex) = color.strip_prefix('#') {
+        if hex.len() != 6 {
+            return None;
+        }
+        let value = u32::from_str_radix(hex, 16).ok()?;
+        return Some(Color::Rgb {
+            r: (value >> 16) as u8,
+            g: (value >> 8) as u8,
+            b: value as u8,
+        });
+    }
+    if let Ok(value) = color.parse::<u8>() {
+        return Some(Color::AnsiValue(value));
+    }
+    Color::try_from(color).ok()
+}
+
+fn parse_attribute(attribute: &str) -> Option<Attribute> {
+    match attribute.to_lowercase().as_str() {
+        "bold" => Some(Attribute::Bold),
+        "dim" => Some(Attribute::Dim),
+        "italic" => Some(Attribute::Italic),
+        "underlined" | "underline" => Some(Attribute::Underlined),
+        "blink" => Some(Attribute::SlowBlink),
+        "reverse" => Some(Attribute::Reverse),
+        "crossed_out" | "strikethrough" => Some(Attribute::CrossedOut),
+        _ => None,
+    }
+}
+
+/// Extracts the file-name rules (e.g. `*.tar=01;31`) from `LS_COLORS`.
+///
+/// Rules for file types (e.g. `di=01;34`) are ignored,
+/// because rfm has its own styles for directories and executables.
+fn parse_ls_colors(ls_colors: &str) -> Vec<(String, FileStyle)> {
+    ls_colors
+        .split(':')
+        .filter_map(|entry| entry.split_once('='))
+        .filter(|(pattern, _)| pattern.starts_with('*'))
+        .filter_map(|(pattern, sgr)| Some((pattern.to_string(), parse_sgr(sgr)?)))
+        .collect()
+}
+
+/// Parses a "select graphic rendition" sequence like `01;38;5;208`.
+fn parse_sgr(sgr: &str) -> Option<FileStyle> {
+    const COLORS: [Color; 8] = [
+        Color::Black,
+        Color::DarkRed,
+        Color::DarkGreen,
+        Color::DarkYellow,
+        Color::DarkBlue,
+        Color::DarkMagenta,
+        Color::DarkCyan,
+        Color::Grey,
+    ];
+    const BRIGHT_COLORS: [Color; 8] = [
+        Color::DarkGrey,
+        Color::Red,
+        Color::Green,
+        Color::Yellow,
+        Color::Blue,
+        Color::Magenta,
+        Color::Cyan,
+        Color::White,
+    ];
+    let mut style = FileStyle::default();
+    let mut codes = sgr.split(';').map(|c| c.parse::<u8>().ok());
+    while let Some(code) = codes.next() {
+        match code? {
+            0 => (),
+            1 => style.attributes.set(Attribute::Bold),
+            2 => style.attributes.set(Attribute::Dim),
+            3 => style.attributes.set(Attribute::Italic),
+            4 => style.attributes.set(Attribute::Underlined),
+            5 => style.attributes.set(Attribute::SlowBlink),
+            7 => style.attributes.set(Attribute::Reverse),
+            9 => style.attributes.set(Attribute::CrossedOut),
+            c @ 30..=37 => style.color = Some(COLORS[(c - 30) as usize]),
+            c @ 90..=97 => style.color = Some(BRIGHT_COLORS[(c - 90) as usize]),
+            c @ (38 | 48) => {
+                let color = match codes.next()?? {
+                    5 => Color::AnsiValue(codes.next()??),
+                    2 => Color::Rgb {
+                        r: codes.next()??,
+                        g: codes.next()??,
+                        b: codes.next()??,
+                    },
+                    _ => return None,
+                };
+                // NOTE: We don't support background colors
+                if c == 38 {
+                    style.color = Some(color);
+                }
+            }
+            // Ignore everything else (e.g. background colors)
+            _ => (),
+        }
+    }
+    Some(style)
+}
+
+#[test]
+fn test_parse_sgr() {
+    let style = parse_sgr("01;38;5;208").unwrap();
+    assert_eq!(style.color, Some(Color::AnsiValue(208)));
+    assert!(style.attributes.has(Attribute::Bold));
+
+    let style = parse_sgr("00;91").unwrap();
+    assert_eq!(style.color, Some(Color::Red));
+    assert!(style.attributes.is_empty());
+
+    assert_eq!(parse_sgr("38;5"), None);
+}
@@ -0,0 +1,415 @@
+use std::{
+    collections::HashMap,
+    ffi::OsString,
+    fs::OpenOptions,
+    io::{self, Write},
+    os::unix::ffi::{OsStrExt, OsStringExt},
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
+
+use log::{error, warn};
+use notify::{RecommendedWatcher, RecursiveMode, Watcher};
+use tempfile::TempDir;
+use tokio::sync::mpsc;
+
+use crate::util::{format_time, xdg_config_home};
+
+/// Trash that remembers where the deleted items came from.
+///
+/// The layout follows the freedesktop.org trash specification:
+/// The trashed items live in `files/`, and for every item there is a
+/// `info/<name>.trashinfo` file, that contains the original path and the deletion date.
+///
+/// If multiple items with the same name are deleted, they get a unique suffix inside the trash,
+/// while their original name is preserved in the info file.
+pub struct Trash {
+    /// Root directory of the trash
+    root: PathBuf,
+
+    /// Keeps the temporary directory alive - it is deleted when the trash is dropped.
+    _tempdir: Option<TempDir>,
+}
+
+/// Metadata of a trashed item.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TrashEntry {
+    /// Path where the item was deleted from
+    pub original: PathBuf,
+
+    /// Deletion date as written to the info file (`YYYY-MM-DDThh:mm:ss`)
+    pub deleted: String,
+}
+
+impl Trash {
+    /// Creates a new trash inside a temporary directory,
+    /// that is deleted when rfm is closed.
+    pub fn temporary() -> io::Result<Self> {
+        Trash::from_tempdir(tempfile::tempdir()?)
+    }
+
+    /// Like [`Trash::temporary`], but falls back to a temporary directory in
+    /// `$XDG_CACHE_HOME/rfm` or the config directory, if the system's temp directory
+    /// cannot be used (e.g. a read-only `/tmp` or an exceeded quota).
+    pub fn temporary_with_fallback() -> io::Result<Self> {
+        let mut result = Trash::temporary();
+        for dir in fallback_dirs() {
+            match result {
+                Ok(trash) => return Ok(trash),
+                Err(e) => warn!("Cannot create the trash: {e} - trying {}", dir.display()),
+            }
+            result = std::fs::create_dir_all(&dir).and_then(|_| {
+                let tempdir = tempfile::Builder::new().prefix("trash-").tempdir_in(&dir)?;
+                Trash::from_tempdir(tempdir)
+            });
+        }
+        result
+    }
+
+    fn from_tempdir(tempdir: TempDir) -> io::Result<Self> {
+        // The panels work with canonicalized paths, so the trash has to do the same
+        let trash = Trash {
+            root: tempdir.path().canonicalize()?,
+            _tempdir: Some(tempdir),
+        };
+        trash.create_dirs()?;
+        Ok(trash)
+    }
+
+    /// Handle to the same trash, which can be moved into a background task
+    /// (it does not keep a temporary trash alive).
+    pub fn handle(&self) -> Trash {
+        Trash {
+            root: self.root.clone(),
+            _tempdir: None,
+        }
+    }
+
+    fn create_dirs(&self) -> io::Result<()> {
+        std::fs::create_dir_all(self.files_dir())?;
+        std::fs::create_dir_all(self.info_dir())
+    }
+
+    /// Directory that contains the trashed items.
+    pub fn files_dir(&self) -> PathBuf {
+        self.root.join("files")
+    }
+
+    /// Directory that contains the info files.
+    pub fn info_dir(&self) -> PathBuf {
+        self.root.join("info")
+    }
+
+    fn info_path(&self, name: &OsString) -> PathBuf {
+        let mut info_name = name.clone();
+        info_name.push(".trashinfo");
+        self.info_dir().join(info_name)
+    }
+
+    /// Moves the item into the trash and returns its new path.
+    pub fn put(&self, path: &Path) -> io::Result<PathBuf> {
+        // NOTE: Only canonicalize the parent, otherwise we would trash the target of a symlink
+        let (parent, name) = match (path.parent(), path.file_name()) {
+            (Some(parent), Some(name)) => (parent, name.to_os_string()),
+            _ => return Err(io::Error::other(format!("cannot trash {}", path.display()))),
+        };
+        let parent = if parent.as_os_str().is_empty() {
+            Path::new(".").canonicalize()?
+        } else {
+            parent.canonicalize()?
+        };
+        let original = parent.join(&name);
+
+        // Reserve a unique name by creating the info file first
+        let mut counter = 0;
+        let (trash_name, mut info_file) = loop {
+            let candidate = numbered_name(&name, counter);
+            let result = OpenOptions::new()
+                .write(true)
+                .create_new(true)
+                .open(self.info_path(&candidate));
+            match result {
+                Ok(file) if !self.files_dir().join(&candidate).exists() => break (candidate, file),
+                Ok(_) => {
+                    // The info file was missing, but the item exists - leave it alone.
+                    std::fs::remove_file(self.info_path(&candidate))?;
+                }
+                Err(e) if e.kind() == io::ErrorKind::AlreadyExists => (),
+                Err(e) => return Err(e),
+            }
+            counter += 1;
+        };
+
+        let deleted = format_time(SystemTime::now()).replace(' ', "T");
+        let info = format!(
+            "[Trash Info]\nPath={}\nDeletionDate={deleted}\n",
+            encode_path(&original)
+        );
+        let destination = self.files_dir().join(&trash_name);
+        let result = info_file
+            .write_all(info.as_bytes())
+            .and_then(|_| std::fs::rename(&original, &destination));
+        if let Err(e) = result {
+            let _ = std::fs::remove_file(self.info_path(&trash_name));
+            return Err(e);
+        }
+        Ok(destination)
+    }
+
+    /// Returns the metadata for an item inside the trash.
+    pub fn entry(&self, trashed: &Path) -> Option<TrashEntry> {
+        let name = trashed.file_name()?.to_os_string();
+        let content = std::fs::read_to_string(self.info_path(&name)).ok()?;
+        let mut original = None;
+        let mut deleted = String::new();
+        for line in content.lines() {
+            if let Some(path) = line.strip_prefix("Path=") {
+                original = Some(decode_path(path));
+            } else if let Some(date) = line.strip_prefix("DeletionDate=") {
+                deleted = date.to_string();
+            }
+        }
+        Some(TrashEntry {
+            original: original?,
+            deleted,
+        })
+    }
+
+    /// Returns the metadata of all items inside the trash, indexed by their path in the trash.
+    pub fn entries(&self) -> HashMap<PathBuf, TrashEntry> {
+        let Ok(dir) = std::fs::read_dir(self.files_dir()) else {
+            return HashMap::new();
+        };
+        dir.flatten()
+            .filter_map(|item| {
+                let path = item.path();
+                self.entry(&path).map(|entry| (path, entry))
+            })
+            .collect()
+    }
+
+    /// Number of trashed items and their total size (in bytes, including the content of directories).
+    ///
+    /// This walks the whole trash - call it in the background.
+    pub fn summary(&self) -> (usize, u64) {
+        let Ok(dir) = std::fs::read_dir(self.files_dir()) else {
+            return (0, 0);
+        };
+        let items = dir.count();
+        let size = walkdir::WalkDir::new(self.files_dir())
+            .min_depth(1)
+            .into_iter()
+            .flatten()
+            .filter(|entry| !entry.file_type().is_dir())
+            .filter_map(|entry| entry.metadata().ok())
+            .map(|metadata| metadata.len())
+            .sum();
+        (items, size)
+    }
+
+    /// Watches the trash and sends a notification whenever items are added or removed.
+    ///
+    /// In contrast to the watchers of the panels, this one stays active all the time,
+    /// so changes made while a panel is frozen (or by other processes) are never missed.
+    pub fn watch(&self, tx: mpsc::UnboundedSender<()>) -> notify::Result<RecommendedWatcher> {
+        let mut watcher =
+            notify::recommended_watcher(move |res: notify::Result<notify::Event>| match res {
+                Ok(event) if !event.kind.is_access() => {
+                    let _ = tx.send(());
+                }
+                Ok(_) => (),
+                Err(e) => error!("trash-watcher: {e}"),
+            })?;
+        watcher.watch(&self.files_dir(), RecursiveMode::NonRecursive)?;
+        watcher.watch(&self.info_dir(), RecursiveMode::NonRecursive)?;
+        Ok(watcher)
+    }
+}
+
+/// Directories for the trash, if the system's temp directory cannot be used.
+fn fallback_dirs() -> Vec<PathBuf> {
+    let cache = std::env::var_os("XDG_CACHE_HOME")
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|home| Path::new(&home).join(".cache")));
+    let config = xdg_config_home().ok();
+    cache
+        .into_iter()
+        .chain(config)
+        .map(|dir| dir.join("rfm"))
+        .collect()
+}
+
+/// Permanently removes all items of the trash (given by its `files/` and `info/` directories).
+///
+/// `progress` is called after every item with the number of removed items and the total.
+/// Returns the number of items that could not be removed.
+pub fn empty(files_dir: &Path, info_dir: &Path, mut progress: impl FnMut(usize, usize)) -> usize {
+    let items: Vec<PathBuf> = match std::fs::read_dir(files_dir) {
+        Ok(dir) => dir.flatten().map(|item| item.path()).collect(),
+        Err(e) => {
+            error!("Cannot read the trash: {e}");
+            return 0;
+        }
+    };
+    let mut failed = 0;
+    for (idx, item) in items.iter().enumerate() {
+        let result = if item.is_dir() && !item.is_symlink() {
+            std::fs::remove_dir_all(item)
+        } else {
+            std::fs::remove_file(item)
+        };
+        match result {
+            Ok(()) => {
+                if let Some(name) = item.file_name() {
+                    let mut info_name = name.to_os_string();
+                    info_name.push(".trashinfo");
+                    let _ = std::fs::remove_file(info_dir.join(info_name));
+                }
+            }
+            Err(e) => {
+                error!("Cannot remove {}: {e}", item.display());
+                failed += 1;
+            }
+        }
+        progress(idx + 1, items.len());
+    }
+    failed
+}
+
+/// Metadata of all trashed items, as shown in the trash view.
+#[derive(Debug, Default)]
+pub struct TrashListing {
+    pub entries: HashMap<PathBuf, TrashEntry>,
+
+    /// Number of items and total size of the trash
+    pub summary: (usize, u64),
+
+    /// Increased whenever the entries are updated, so the panels know when to re-apply them
+    pub generation: u64,
+}
+
+/// Appends a counter to the name (before the extension), unless the counter is zero.
+///
+/// E.g. `notes.txt` becomes `notes.2.txt` for `counter = 2`.
+fn numbered_name(name: &OsString, counter: usize) -> OsString {
+    if counter == 0 {
+        return name.clone();
+    }
+    let path = Path::new(name);
+    match (path.file_stem(), path.extension()) {
+        (Some(stem), Some(ext)) if !stem.is_empty() => {
+            let mut out = stem.to_os_string();
+            out.push(format!(".{counter}."));
+            out.push(ext);
+            out
+        }
+        _ => {
+            let mut out = name.clone();
+            out.push(format!(".{counter}"));
+            out
+        }
+    }
+}
+
+/// Percent-encodes a path, as required for the `Path=` key of the info file (and file URIs).
+pub fn encode_path(path: &Path) -> String {
+    let mut out = String::new();
+    for &byte in path.as_os_str().as_bytes() {
+        if byte.is_ascii_alphanumeric() || b"/-_.~".contains(&byte) {
+            out.push(byte as char);
+        } else {
+            out.push_str(&format!("%{byte:02X}"));
+        }
+    }
+    out
+}
+
+/// Reverses [`encode_path`].
+pub fn decode_path(encoded: &str) -> PathBuf {
+    let bytes = encoded.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut idx = 0;
+    while idx < bytes.len() {
+        let hex = bytes
+            .get(idx + 1..idx + 3)
+            .and_then(|h| std::str::from_utf8(h).ok())
+            .and_then(|h| u8::from_str_radix(h, 16).ok());
+        match (bytes[idx], hex) {
+            (b'%', Some(byte)) => {
+                out.push(byte);
+                idx += 3;
+            }
+            (byte, _) => {
+                out.push(byte);
+                idx += 1;
+            }
+        }
+    }
+    OsString::from_vec(out).into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_path_encoding() {
+        let path = Path::new("/home/user/my notes/100%.txt");
+        let encoded = encode_path(path);
+        assert_eq!(encoded, "/home/user/my%20notes/100%25.txt");
+        assert_eq!(decode_path(&encoded), path);
+    }
+
+    #[test]
+    fn test_name_collisions() {
+        let trash = Trash::temporary().unwrap();
+        let dir = tempfile::tempdir().unwrap();
+        for sub in ["a", "b", "c"] {
+            let file = dir.path().join(sub).join("notes.txt");
+            std::fs::create_dir(file.parent().unwrap()).unwrap();
+            std::fs::write(&file, sub).unwrap();
+            trash.put(&file).unwrap();
+        }
+        for (name, content) in [
+            ("notes.txt", "a"),
+            ("notes.1.txt", "b"),
+            ("notes.2.txt", "c"),
+        ] {
+            let trashed = trash.files_dir().join(name);
+            assert_eq!(std::fs::read_to_string(&trashed).unwrap(), content);
+            let entry = trash.entry(&trashed).unwrap();
+            assert_eq!(
+                entry.original,
+                dir.path()
+                    .canonicalize()
+                    .unwrap()
+                    .join(content)
+                    .join("notes.txt")
+            );
+        }
+        assert_eq!(trash.entries().len(), 3);
+    }
+
+    #[test]
+    fn test_empty() {
+        let trash = Trash::temporary().unwrap();
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::create_dir(dir.path().join("sub")).unwrap();
+        std::fs::write(dir.path().join("sub/a"), "1234").unwrap();
+        std::fs::write(dir.path().join("b"), "56").unwrap();
+        trash.put(&dir.path().join("sub")).unwrap();
+        trash.put(&dir.path().join("b")).unwrap();
+        assert_eq!(trash.summary(), (2, 6));
+
+        let mut reported = Vec::new();
+        let failed = empty(&trash.files_dir(), &trash.info_dir(), |done, total| {
+            reported.push((done, total))
+        });
+        assert_eq!(failed, 0);
+        assert_eq!(reported, vec![(1, 2), (2, 2)]);
+        assert_eq!(trash.summary(), (0, 0));
+        assert!(trash.entries().is_empty());
+        assert_eq!(std::fs::read_dir(trash.info_dir()).unwrap().count(), 0);
+    }
+}
@@ -1,10 +1,36 @@
 use std::{
     error::Error,
+    io::{BufRead, Read, Seek, Write},
+    os::unix::{fs::MetadataExt, io::AsRawFd},
     path::{Path, PathBuf},
+    process::{Command, Stdio},
+    sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering},
+    time::{Duration, Instant, SystemTime},
 };
 
-use fs_extra::dir::CopyOptions;
+use filetime::FileTime;
+use log::{debug, error, info, warn};
 use notify_rust::Notification;
+use parking_lot::Mutex;
+
+use crate::{
+    config::{CopyBackend, CopyConfig, IoPriority},
+    trash::{decode_path, encode_path},
+};
+use time::OffsetDateTime;
+
+/// Set by `--no-exec` - if set, rfm must not run any external programs.
+static NO_EXEC: AtomicBool = AtomicBool::new(false);
+
+/// Disables all external programs (opener, editor, git, mediainfo) for the rest of the session.
+pub fn disable_exec() {
+    NO_EXEC.store(true, Ordering::Relaxed);
+}
+
+/// Returns `false` if rfm was started with `--no-exec`.
+pub fn exec_allowed() -> bool {
+    !NO_EXEC.load(Ordering::Relaxed)
+}
 
 pub fn file_size_str(file_size: u64) -> String {
     match file_size {
@@ -22,6 +48,164 @@ pub fn file_size_str(file_size: u64) -> String {
     }
 }
 
+/// Formats a timestamp as `YYYY-MM-DD hh:mm:ss`.
+pub fn format_time(time: SystemTime) -> String {
+    let t = OffsetDateTime::from(time);
+    format!(
+        "{}-{:02}-{:02} {:02}:{:02}:{:02}",
+        t.year(),
+        u8::from(t.month()),
+        t.day(),
+        t.hour(),
+        t.minute(),
+        t.second()
+    )
+}
+
+/// Puts some text into the system clipboard.
+///
+/// There is no portable way to do this, so we just try the usual clipboard tools
+/// one after another, until one of them succeeds.
+/// Without any tool (e.g. over ssh or with `--no-exec`), the text is sent to the terminal
+/// as an OSC 52 sequence.
+pub fn set_system_clipboard(text: &str) -> Result<(), Box<dyn Error>> {
+    let tools: [(&str, &[&str]); 4] = [
+        ("wl-copy", &[]),
+        ("xclip", &["-selection", "clipboard"]),
+        ("xsel", &["--clipboard", "--input"]),
+        ("pbcopy", &[]),
+    ];
+    let tools = if exec_allowed() { &tools[..] } else { &[] };
+    for &(tool, args) in tools {
+        let child = Command::new(tool)
+            .args(args)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .spawn();
+        if let Ok(mut child) = child {
+            if let Some(mut stdin) = child.stdin.take() {
+                stdin.write_all(text.as_bytes())?;
+            }
+            if child.wait()?.success() {
+                return Ok(());
+            }
+        }
+    }
+    // Most terminals support OSC 52, but we cannot know whether it worked
+    let mut stdout = std::io::stdout();
+    write!(stdout, "\x1b]52;c;{}\x07", base64(text.as_bytes()))?;
+    stdout.flush()?;
+    Ok(())
+}
+
+/// Publishes the files as `text/uri-list` on the system clipboard,
+/// so they can be pasted in graphical file managers (e.g. Nautilus or Dolphin).
+///
+/// Only `wl-copy` and `xclip` support other types than plain text.
+/// This runs an external program - call it in the background.
+pub fn set_clipboard_files(files: &[PathBuf]) -> Result<(), Box<dyn Error>> {
+    if !exec_allowed() {
+        Err("external programs are disabled (--no-exec)")?
+    }
+    if !graphical_session() {
+        Err("no graphical session")?
+    }
+    let uri_list: String = files
+        .iter()
+        .map(|f| format!("file://{}\r\n", encode_path(f)))
+        .collect();
+    let tools: [(&str, &[&str]); 2] = [
+        ("wl-copy", &["--type", "text/uri-list"]),
+        ("xclip", &["-selection", "clipboard", "-t", "text/uri-list"]),
+    ];
+    for (tool, args) in tools {
+        let child = Command::new(tool)
+            .args(args)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .spawn();
+        if let Ok(mut child) = child {
+            if let Some(mut stdin) = child.stdin.take() {
+                stdin.write_all(uri_list.as_bytes())?;
+            }
+            if child.wait()?.success() {
+                return Ok(());
+            }
+        }
+    }
+    Err("no clipboard tool with support for uri-lists found (tried wl-copy and xclip)")?
+}
+
+/// Returns the files of a `text/uri-list` on the system clipboard
+/// (e.g. copied in a graphical file manager).
+///
+/// This runs an external program - call it in the background.
+pub fn get_clipboard_files() -> Vec<PathBuf> {
+    if !exec_allowed() || !graphical_session() {
+        return Vec::new();
+    }
+    let tools: [(&str, &[&str]); 2] = [
+        ("wl-paste", &["--no-newline", "--type", "text/uri-list"]),
+        (
+            "xclip",
+            &["-selection", "clipboard", "-t", "text/uri-list", "-o"],
+        ),
+    ];
+    for (tool, args) in tools {
+        let output = Command::new(tool)
+            .args(args)
+            .stdin(Stdio::null())
+            .stderr(Stdio::null())
+            .output();
+        if let Some(output) = output.ok().filter(|o| o.status.success()) {
+            return parse_uri_list(&String::from_utf8_lossy(&output.stdout));
+        }
+    }
+    Vec::new()
+}
+
+/// Returns `true` if rfm runs inside a Wayland or X11 session (where a clipboard for files exists).
+fn graphical_session() -> bool {
+    ["WAYLAND_DISPLAY", "DISPLAY"]
+        .iter()
+        .any(|var| std::env::var_os(var).is_some_and(|value| !value.is_empty()))
+}
+
+/// Returns the local files of a `text/uri-list` - comments and other schemes are skipped.
+fn parse_uri_list(uri_list: &str) -> Vec<PathBuf> {
+    uri_list
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.starts_with('#'))
+        .filter_map(|line| line.strip_prefix("file://"))
+        // The host part is empty or "localhost"
+        .filter_map(|rest| rest.find('/').map(|idx| &rest[idx..]))
+        .map(decode_path)
+        .collect()
+}
+
+/// Encodes the bytes as base64 (with padding).
+fn base64(bytes: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut out = String::with_capacity(bytes.len().div_ceil(3) * 4);
+    for chunk in bytes.chunks(3) {
+        let n = chunk
+            .iter()
+            .enumerate()
+            .fold(0u32, |n, (i, b)| n | (*b as u32) << (16 - 8 * i));
+        for i in 0..4 {
+            if i <= chunk.len() {
+                out.push(ALPHABET[(n >> (18 - 6 * i)) as usize & 63] as char);
+            } else {
+                out.push('=');
+            }
+        }
+    }
+    out
+}
+
 pub trait ExactWidth: std::fmt::Display {
     fn exact_width(&self, len: usize) -> String {
         let mut out = format!("{:len$}", self);
@@ -46,6 +230,41 @@ pub trait ExactWidth: std::fmt::Display {
 
 impl<T: std::fmt::Display> ExactWidth for T {}
 
+/// Measures how long the individual phases of the startup take.
+///
+/// Every call to [`StartupProfile::phase`] logs the time since the previous phase.
+/// If the profile is disabled, nothing is measured or logged.
+pub struct StartupProfile {
+    enabled: bool,
+    start: Instant,
+    last: Instant,
+}
+
+impl StartupProfile {
+    pub fn new(enabled: bool) -> Self {
+        let now = Instant::now();
+        StartupProfile {
+            enabled,
+            start: now,
+            last: now,
+        }
+    }
+
+    /// Marks the end of the phase with the given name.
+    pub fn phase(&mut self, name: &str) {
+        if !self.enabled {
+            return;
+        }
+        let now = Instant::now();
+        info!(
+            "startup: {name} took {:.2?} (total: {:.2?})",
+            now.duration_since(self.last),
+            now.duration_since(self.start)
+        );
+        self.last = now;
+    }
+}
+
 /// Calculates the destination path when we want to copy or move items from 'source' to 'destination'.
 ///
 /// Note: Destination must be a directory, otherwise this function will fail.
@@ -57,10 +276,10 @@ where
     let from = source.as_ref();
     let to = destination.as_ref();
     if !to.is_dir() {
-        return Err(std::io::Error::new(
-            std::io::ErrorKind::Other,
-            format!("{} is not a directory", to.display()),
-        ));
+        return Err(std::io::Error::other(format!(
+            "{} is not a directory",
+            to.display()
+        )));
     }
     let mut dest_name = from
         .file_name()
@@ -76,7 +295,95 @@ where
     Ok(result)
 }
 
-pub fn move_item<P, Q>(source: P, destination: Q) -> Result<(), Box<dyn Error>>
+/// Renames all items as a single transaction - either all renames are applied, or none.
+///
+/// The items are first moved to temporary names (in their own directories),
+/// so the result does not depend on the order of the renames.
+/// Existing files are never overwritten. If a step fails,
+/// the renames that were already applied are rolled back.
+pub fn rename_all(renames: &[(PathBuf, PathBuf)]) -> std::io::Result<()> {
+    // Every rename that was applied, in the order they happened
+    let mut log = Vec::new();
+    let result = apply_renames(renames, &mut log);
+    if result.is_err() {
+        for (from, to) in log.iter().rev() {
+            if let Err(e) = rename_noreplace(to, from) {
+                error!(
+                    "Cannot roll back '{}' to '{}': {e}",
+                    to.display(),
+                    from.display()
+                );
+            }
+        }
+    }
+    result
+}
+
+fn apply_renames(
+    renames: &[(PathBuf, PathBuf)],
+    log: &mut Vec<(PathBuf, PathBuf)>,
+) -> std::io::Result<()> {
+    let mut temps = Vec::new();
+    for (idx, (from, _)) in renames.iter().enumerate() {
+        let temp = from.with_file_name(format!(".rfm-rename-{}-{idx}", std::process::id()));
+        rename_noreplace(from, &temp)?;
+        log.push((from.clone(), temp.clone()));
+        temps.push(temp);
+    }
+    for (temp, (_, to)) in temps.iter().zip(renames) {
+        rename_noreplace(temp, to)?;
+        log.push((temp.clone(), to.clone()));
+    }
+    Ok(())
+}
+
+/// Renames the item, but fails with `AlreadyExists` instead of replacing an existing `to`.
+fn rename_noreplace(from: &Path, to: &Path) -> std::io::Result<()> {
+    use std::os::unix::ffi::OsStrExt;
+
+    let c_path = |path: &Path| {
+        std::ffi::CString::new(path.as_os_str().as_bytes())
+            .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidInput, e))
+    };
+    let (c_from, c_to) = (c_path(from)?, c_path(to)?);
+    let result = unsafe {
+        libc::renameat2(
+            libc::AT_FDCWD,
+            c_from.as_ptr(),
+            libc::AT_FDCWD,
+            c_to.as_ptr(),
+            libc::RENAME_NOREPLACE,
+        )
+    };
+    if result == 0 {
+        return Ok(());
+    }
+    let e = std::io::Error::last_os_error();
+    let unsupported = matches!(e.raw_os_error(), Some(libc::EINVAL | libc::ENOSYS));
+    if !unsupported {
+        return Err(match e.kind() {
+            std::io::ErrorKind::AlreadyExists => std::io::Error::new(
+                std::io::ErrorKind::AlreadyExists,
+                format!("'{}' already exists", to.display()),
+            ),
+            _ => e,
+        });
+    }
+    // The filesystem does not support the flag - linking fails if `to` exists as well
+    if from.symlink_metadata()?.is_dir() {
+        if to.symlink_metadata().is_ok() {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::AlreadyExists,
+                format!("'{}' already exists", to.display()),
+            ));
+        }
+        return std::fs::rename(from, to);
+    }
+    std::fs::hard_link(from, to)?;
+    std::fs::remove_file(from)
+}
+
+pub fn move_item<P, Q>(source: P, destination: Q, config: CopyConfig) -> Result<(), Box<dyn Error>>
 where
     P: AsRef<Path>,
     Q: AsRef<Path>,
@@ -96,25 +403,883 @@ where
         return Ok(());
     }
     let to = get_destination(&source, destination)?;
-    std::fs::rename(from, to)?;
+    match std::fs::rename(from, &to) {
+        // Renaming does not work across filesystems - copy the item instead
+        Err(e) if e.raw_os_error() == Some(libc::EXDEV) => move_across_devices(from, &to, config),
+        result => Ok(result?),
+    }
+}
+
+/// Moves an item to another filesystem by copying, verifying and deleting it.
+///
+/// If anything goes wrong before the copy is complete, the partial copy is removed
+/// and the source is left untouched.
+fn move_across_devices(from: &Path, to: &Path, config: CopyConfig) -> Result<(), Box<dyn Error>> {
+    info!(
+        "'{}' is moved to another filesystem - copying it",
+        from.display()
+    );
+    // A move keeps the metadata - this also applies the permissions of the directories
+    // at the end, in case they are read-only
+    let copied = match config.backend {
+        CopyBackend::Rsync if rsync_installed() => rsync_item(from, to, true, config),
+        _ => copy_and_verify(from, to, false, config).and_then(|_| preserve_metadata(from, to)),
+    };
+    if let Err(e) = copied {
+        let cleanup = if to.is_dir() && !to.is_symlink() {
+            std::fs::remove_dir_all(to)
+        } else {
+            std::fs::remove_file(to)
+        };
+        if let Err(cleanup_err) = cleanup {
+            error!(
+                "Cannot remove partial copy '{}': {cleanup_err}",
+                to.display()
+            );
+        }
+        return Err(e);
+    }
+    if from.is_dir() && !from.is_symlink() {
+        std::fs::remove_dir_all(from)?;
+    } else {
+        std::fs::remove_file(from)?;
+    }
     Ok(())
 }
 
-pub fn copy_item<P, Q>(source: P, destination: Q) -> Result<(), Box<dyn Error>>
+/// Translates a path below `from` to the same path below `to`.
+fn target_path(from: &Path, to: &Path, path: &Path) -> Result<PathBuf, Box<dyn Error>> {
+    let relative = path.strip_prefix(from)?;
+    // NOTE: Joining an empty path would append a trailing slash
+    if relative.as_os_str().is_empty() {
+        Ok(to.to_path_buf())
+    } else {
+        Ok(to.join(relative))
+    }
+}
+
+/// Copies the item (recursively), and checks that every file has the size of its source -
+/// and with `config.verify` also the same content, as read back from the disk.
+///
+/// The directories and symlinks are created first, then up to `config.parallel` threads
+/// copy the files - each one takes the next file, as soon as it is done with the last one.
+/// Progress is reported in steps of 10%. Files that fail with a transient error
+/// are copied again (at most `config.retries` times). With `config.max_rate`,
+/// all threads together are throttled to that many bytes per second.
+fn copy_and_verify(
+    from: &Path,
+    to: &Path,
+    reflink: bool,
+    config: CopyConfig,
+) -> Result<(), Box<dyn Error>> {
+    let entries: Vec<walkdir::DirEntry> = walkdir::WalkDir::new(from)
+        .into_iter()
+        .collect::<Result<_, _>>()?;
+    let mut files = Vec::new();
+    for entry in entries.iter() {
+        let source = entry.path();
+        let target = target_path(from, to, source)?;
+        let file_type = entry.file_type();
+        if file_type.is_symlink() {
+            std::os::unix::fs::symlink(std::fs::read_link(source)?, &target)?;
+        } else if file_type.is_dir() {
+            std::fs::create_dir(&target)?;
+        } else {
+            files.push((source, target, entry.metadata()?.len()));
+        }
+    }
+    let total: u64 = files.iter().map(|(_, _, size)| size).sum();
+    let next = AtomicUsize::new(0);
+    let copied = AtomicU64::new(0);
+    let reported = Mutex::new(0);
+    let failure = Mutex::new(None);
+    let throttle = config.max_rate.map(Throttle::new);
+    let worker = || {
+        while failure.lock().is_none() {
+            let Some((source, target, expected)) = files.get(next.fetch_add(1, Ordering::Relaxed))
+            else {
+                return;
+            };
+            let result = retry(source, config.retries, || {
+                copy_file(source, target, reflink, throttle.as_ref())
+            })
+            .map_err(|e| e.to_string())
+            .and_then(|size| verify_copy(source, target, size, *expected, config.verify));
+            if let Err(e) = result {
+                failure.lock().get_or_insert(e);
+                return;
+            }
+            let copied = copied.fetch_add(*expected, Ordering::Relaxed) + expected;
+            let percent = (copied * 100).checked_div(total).unwrap_or(100);
+            let mut reported = reported.lock();
+            if percent >= *reported + 10 {
+                *reported = percent - percent % 10;
+                info!("Copying '{}': {}%", from.display(), *reported);
+            }
+        }
+    };
+    let threads = config.parallel.clamp(1, files.len().max(1));
+    std::thread::scope(|scope| {
+        for _ in 1..threads {
+            scope.spawn(worker);
+        }
+        worker();
+    });
+    match failure.into_inner() {
+        Some(e) => Err(e.into()),
+        None => Ok(()),
+    }
+}
+
+/// Checks the size of the copy, and with `checksum` also its content.
+fn verify_copy(
+    source: &Path,
+    target: &Path,
+    size: u64,
+    expected: u64,
+    checksum: bool,
+) -> Result<(), String> {
+    let target_size = target.metadata().map_err(|e| e.to_string())?.len();
+    if size != expected || target_size != expected {
+        return Err(format!(
+            "verification of '{}' failed: copied {size} of {expected} bytes",
+            target.display()
+        ));
+    }
+    let differs = || -> std::io::Result<bool> { Ok(crc32(source)? != crc32_from_disk(target)?) };
+    if checksum && differs().map_err(|e| e.to_string())? {
+        return Err(format!(
+            "verification of '{}' failed: the checksum differs from '{}'",
+            target.display(),
+            source.display()
+        ));
+    }
+    Ok(())
+}
+
+fn crc32(path: &Path) -> std::io::Result<u32> {
+    crc32_of(std::fs::File::open(path)?)
+}
+
+/// Like [`crc32`], but flushes the file and drops it from the page cache first,
+/// so the data is really read from the disk.
+fn crc32_from_disk(path: &Path) -> std::io::Result<u32> {
+    // Read-only is enough for both calls - the copy may have the read-only mode of its source
+    let file = std::fs::File::open(path)?;
+    file.sync_all()?;
+    // SAFETY: The file descriptor stays open for the duration of the call.
+    unsafe { libc::posix_fadvise(file.as_raw_fd(), 0, 0, libc::POSIX_FADV_DONTNEED) };
+    crc32_of(file)
+}
+
+fn crc32_of(mut file: std::fs::File) -> std::io::Result<u32> {
+    let mut hasher = crc32fast::Hasher::new();
+    let mut buffer = vec![0; 64 * 1024];
+    loop {
+        match file.read(&mut buffer)? {
+            0 => return Ok(hasher.finalize()),
+            n => hasher.update(&buffer[..n]),
+        }
+    }
+}
+
+/// First pause between two attempts of a failed copy - doubled after every attempt.
+const RETRY_BACKOFF: Duration = Duration::from_millis(500);
+
+/// Runs the operation again (with growing pauses) as long as it fails with a transient error,
+/// but at most `retries` times.
+fn retry<T>(
+    path: &Path,
+    retries: u32,
+    mut operation: impl FnMut() -> std::io::Result<T>,
+) -> std::io::Result<T> {
+    let mut backoff = RETRY_BACKOFF;
+    let mut attempt = 0;
+    loop {
+        match operation() {
+            Err(e) if attempt < retries && is_transient(&e) => {
+                attempt += 1;
+                warn!(
+                    "'{}': {e} - retrying in {backoff:?} ({attempt}/{retries})",
+                    path.display()
+                );
+                std::thread::sleep(backoff);
+                backoff *= 2;
+            }
+            result => return result,
+        }
+    }
+}
+
+/// Returns `true` for errors that may go away by themselves (e.g. on network filesystems).
+fn is_transient(e: &std::io::Error) -> bool {
+    matches!(
+        e.kind(),
+        std::io::ErrorKind::Interrupted
+            | std::io::ErrorKind::WouldBlock
+            | std::io::ErrorKind::TimedOut
+    ) || matches!(
+        e.raw_os_error(),
+        Some(libc::EBUSY | libc::EIO | libc::ESTALE | libc::ECONNRESET | libc::EHOSTUNREACH)
+    )
+}
+
+/// `FICLONE` ioctl - `_IOW(0x94, 9, int)`
+const FICLONE: libc::c_ulong = 0x40049409;
+
+/// Copies a single file and returns the number of bytes copied.
+///
+/// If `reflink` is set, the file is cloned first (which shares the data blocks and is instant),
+/// and only copied, if the filesystem does not support it.
+fn copy_file(
+    from: &Path,
+    to: &Path,
+    reflink: bool,
+    throttle: Option<&Throttle>,
+) -> std::io::Result<u64> {
+    if reflink {
+        match clone_file(from, to) {
+            Ok(size) => return Ok(size),
+            Err(e) => debug!("Cannot reflink '{}': {e}", from.display()),
+        }
+    }
+    let Some(throttle) = throttle else {
+        return std::fs::copy(from, to);
+    };
+    let mut source = std::fs::File::open(from)?;
+    let mut target = std::fs::File::create(to)?;
+    let mut buffer = vec![0; 64 * 1024];
+    let mut size = 0;
+    loop {
+        let n = source.read(&mut buffer)?;
+        if n == 0 {
+            break;
+        }
+        target.write_all(&buffer[..n])?;
+        size += n as u64;
+        throttle.consume(n as u64);
+    }
+    target.set_permissions(source.metadata()?.permissions())?;
+    Ok(size)
+}
+
+/// Limits the speed of a copy (shared by all of its threads), by sleeping whenever it is ahead of the rate.
+struct Throttle {
+    /// Bytes per second
+    rate: u64,
+    start: Instant,
+    bytes: AtomicU64,
+}
+
+impl Throttle {
+    fn new(rate: u64) -> Self {
+        Throttle {
+            rate: rate.max(1),
+            start: Instant::now(),
+            bytes: AtomicU64::new(0),
+        }
+    }
+
+    fn consume(&self, bytes: u64) {
+        let total = self.bytes.fetch_add(bytes, Ordering::Relaxed) + bytes;
+        let due = Duration::from_secs_f64(total as f64 / self.rate as f64);
+        if let Some(ahead) = due.checked_sub(self.start.elapsed()) {
+            std::thread::sleep(ahead);
+        }
+    }
+}
+
+/// Lowers the CPU and I/O priority of the calling thread (like `nice` and `ionice`).
+///
+/// The priority cannot be raised again without privileges, so this must only be called
+/// in a thread of its own.
+pub fn lower_priority(nice: i32, io_priority: IoPriority) {
+    // SAFETY: gettid has no preconditions.
+    let tid = unsafe { libc::syscall(libc::SYS_gettid) } as libc::id_t;
+    if nice != 0 {
+        // SAFETY: Only changes the priority of this thread.
+        if unsafe { libc::setpriority(libc::PRIO_PROCESS, tid, nice) } == -1 {
+            warn!(
+                "Cannot set nice {nice}: {}",
+                std::io::Error::last_os_error()
+            );
+        }
+    }
+    // ioprio = class << 13 | level (see ioprio_set(2))
+    const IOPRIO_WHO_PROCESS: libc::c_long = 1;
+    let ioprio: libc::c_long = match io_priority {
+        IoPriority::Normal => return,
+        IoPriority::Low => 2 << 13 | 7,
+        IoPriority::Idle => 3 << 13,
+    };
+    // SAFETY: Only changes the I/O priority of this thread.
+    if unsafe { libc::syscall(libc::SYS_ioprio_set, IOPRIO_WHO_PROCESS, tid, ioprio) } == -1 {
+        warn!(
+            "Cannot set the I/O priority: {}",
+            std::io::Error::last_os_error()
+        );
+    }
+}
+
+/// Clones the file with the `FICLONE` ioctl.
+fn clone_file(from: &Path, to: &Path) -> std::io::Result<u64> {
+    let source = std::fs::File::open(from)?;
+    let target = std::fs::OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .open(to)?;
+    // SAFETY: Both file descriptors stay open for the duration of the call.
+    let result = unsafe { libc::ioctl(target.as_raw_fd(), FICLONE as _, source.as_raw_fd()) };
+    if result == -1 {
+        let e = std::io::Error::last_os_error();
+        drop(target);
+        std::fs::remove_file(to)?;
+        return Err(e);
+    }
+    let metadata = source.metadata()?;
+    target.set_permissions(metadata.permissions())?;
+    Ok(metadata.len())
+}
+
+pub fn copy_item<P, Q>(source: P, destination: Q, config: CopyConfig) -> Result<(), Box<dyn Error>>
 where
     P: AsRef<Path>,
     Q: AsRef<Path>,
 {
     let from = source.as_ref();
+    let same_device = match (from.metadata(), destination.as_ref().metadata()) {
+        (Ok(a), Ok(b)) => a.dev() == b.dev(),
+        _ => false,
+    };
     let to = get_destination(&source, destination)?;
-    if from.is_dir() {
-        fs_extra::dir::copy(from, to, &CopyOptions::default().copy_inside(true))?;
+    if config.backend == CopyBackend::Rsync && rsync_installed() {
+        return rsync_item(from, &to, config.preserve, config);
+    }
+    copy_and_verify(from, &to, config.reflink && same_device, config)?;
+    if config.preserve {
+        preserve_metadata(from, &to)?;
+    }
+    Ok(())
+}
+
+/// Exit codes of rsync for errors that may go away by themselves
+/// (socket, file and stream I/O, partial transfers, vanished files and timeouts).
+const RSYNC_TRANSIENT: [i32; 7] = [10, 11, 12, 23, 24, 30, 35];
+
+fn rsync_installed() -> bool {
+    static INSTALLED: std::sync::OnceLock<bool> = std::sync::OnceLock::new();
+    *INSTALLED.get_or_init(|| {
+        let installed = exec_allowed()
+            && Command::new("rsync")
+                .arg("--version")
+                .stdout(Stdio::null())
+                .stderr(Stdio::null())
+                .status()
+                .is_ok_and(|status| status.success());
+        if !installed {
+            warn!("rsync is not installed - using the builtin copy");
+        }
+        installed
+    })
+}
+
+/// Copies the item with rsync - with `archive` all metadata is kept (like `cp -a`),
+/// otherwise only the permissions.
+///
+/// Partial files are kept, so retries (after transient errors) resume where the last attempt stopped.
+/// Progress is reported in steps of 10%, like the builtin copy.
+fn rsync_item(
+    from: &Path,
+    to: &Path,
+    archive: bool,
+    config: CopyConfig,
+) -> Result<(), Box<dyn Error>> {
+    let mut command = Command::new("rsync");
+    command.arg(if archive { "-aX" } else { "-rlp" }).args([
+        "--partial",
+        "--no-inc-recursive",
+        "--info=progress2",
+    ]);
+    if let Some(rate) = config.max_rate {
+        command.arg(format!("--bwlimit={}", (rate / 1024).max(1)));
+    }
+    // With a trailing slash, rsync copies the content of the directory instead of the directory
+    let mut source = from.as_os_str().to_os_string();
+    if from.is_dir() && !from.is_symlink() {
+        source.push("/");
+    }
+    command
+        .arg(source)
+        .arg(to)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped());
+    let mut backoff = RETRY_BACKOFF;
+    for attempt in 0.. {
+        let mut child = command.spawn()?;
+        // Drained on its own, so rsync cannot block on a full stderr pipe while we read stdout
+        let stderr = child.stderr.take().map(|mut pipe| {
+            std::thread::spawn(move || {
+                let mut stderr = String::new();
+                let _ = pipe.read_to_string(&mut stderr);
+                stderr
+            })
+        });
+        let mut reported = 0;
+        if let Some(stdout) = child.stdout.take() {
+            // Progress lines are separated by carriage returns
+            for line in std::io::BufReader::new(stdout).split(b'\r') {
+                let Some(percent) = rsync_percent(&String::from_utf8_lossy(&line?)) else {
+                    continue;
+                };
+                if percent >= reported + 10 {
+                    reported = percent - percent % 10;
+                    info!("Copying '{}': {reported}%", from.display());
+                }
+            }
+        }
+        let status = child.wait()?;
+        let stderr = stderr
+            .and_then(|reader| reader.join().ok())
+            .unwrap_or_default();
+        match status.code() {
+            Some(0) => return Ok(()),
+            Some(code) if attempt < config.retries && RSYNC_TRANSIENT.contains(&code) => {
+                warn!(
+                    "rsync '{}': {} - retrying in {backoff:?} ({}/{})",
+                    from.display(),
+                    stderr.trim(),
+                    attempt + 1,
+                    config.retries
+                );
+                std::thread::sleep(backoff);
+                backoff *= 2;
+            }
+            _ => return Err(format!("rsync failed ({status}): {}", stderr.trim()).into()),
+        }
+    }
+    unreachable!()
+}
+
+/// Overall progress of a line of `rsync --info=progress2`,
+/// like `  1,234,567  45%   10.00MB/s    0:00:01 (xfr#1, to-chk=0/1)`.
+fn rsync_percent(line: &str) -> Option<u64> {
+    line.split_whitespace()
+        .find_map(|field| field.strip_suffix('%')?.parse().ok())
+}
+
+/// Applies ownership, mode, extended attributes and timestamps of `from` to `to` (recursively).
+///
+/// Changing the ownership requires privileges, so it is silently skipped if it is not permitted.
+/// Extended attributes that cannot be set (e.g. `security.*`) only cause a warning.
+pub fn preserve_metadata(from: &Path, to: &Path) -> Result<(), Box<dyn Error>> {
+    // Children first - otherwise copying them would change the timestamps of their directory again
+    for entry in walkdir::WalkDir::new(from).contents_first(true) {
+        let entry = entry?;
+        let source = entry.path();
+        let target = target_path(from, to, source)?;
+        let metadata = source.symlink_metadata()?;
+        let is_symlink = metadata.file_type().is_symlink();
+
+        // NOTE: chown resets the setuid bit, so it must come before the mode
+        match std::os::unix::fs::lchown(&target, Some(metadata.uid()), Some(metadata.gid())) {
+            Err(e) if e.kind() != std::io::ErrorKind::PermissionDenied => return Err(e.into()),
+            _ => (),
+        }
+        if !is_symlink {
+            std::fs::set_permissions(&target, metadata.permissions())?;
+            if xattr::SUPPORTED_PLATFORM {
+                for name in xattr::list(source)? {
+                    let result = xattr::get(source, &name).and_then(|value| match value {
+                        Some(value) => xattr::set(&target, &name, &value),
+                        None => Ok(()),
+                    });
+                    if let Err(e) = result {
+                        warn!(
+                            "Cannot copy attribute {name:?} of '{}': {e}",
+                            source.display()
+                        );
+                    }
+                }
+            }
+        }
+        filetime::set_symlink_file_times(
+            &target,
+            FileTime::from_last_access_time(&metadata),
+            FileTime::from_last_modification_time(&metadata),
+        )?;
+    }
+    Ok(())
+}
+
+/// Creates a symlink to `source` inside the `destination` directory.
+///
+/// If `relative` is set, the link target is relative to the destination,
+/// so the link survives when both are moved together (e.g. inside a dotfile repository).
+pub fn link_item<P, Q>(source: P, destination: Q, relative: bool) -> Result<(), Box<dyn Error>>
+where
+    P: AsRef<Path>,
+    Q: AsRef<Path>,
+{
+    let from = source.as_ref().canonicalize()?;
+    let to = get_destination(&from, &destination)?;
+    let target = if relative {
+        relative_path(&destination.as_ref().canonicalize()?, &from)
     } else {
-        std::fs::copy(from, to)?;
+        from
+    };
+    std::os::unix::fs::symlink(target, to)?;
+    Ok(())
+}
+
+/// Creates a hardlink to the file `source` inside the `destination` directory.
+///
+/// Hardlinks only work for files on the same filesystem, which is checked upfront.
+pub fn hardlink_item<P, Q>(source: P, destination: Q) -> Result<(), Box<dyn Error>>
+where
+    P: AsRef<Path>,
+    Q: AsRef<Path>,
+{
+    let from = source.as_ref();
+    let metadata = from.symlink_metadata()?;
+    if metadata.is_dir() {
+        return Err(format!("{} is a directory", from.display()).into());
+    }
+    if metadata.dev() != destination.as_ref().metadata()?.dev() {
+        return Err(format!(
+            "{} is on another filesystem than {}",
+            from.display(),
+            destination.as_ref().display()
+        )
+        .into());
+    }
+    let to = get_destination(from, destination)?;
+    std::fs::hard_link(from, to)?;
+    Ok(())
+}
+
+/// Brace expansions with more results are cut off.
+const MAX_BRACE_EXPANSION: usize = 10_000;
+
+/// Expands braces like the shell: `{src,tests}` becomes `src` and `tests`,
+/// `img_{01..3}` becomes `img_01`, `img_02` and `img_03` (numbers and single letters, also descending).
+///
+/// Braces can be nested. Braces without a comma or a range (like `{x}`) are kept as they are.
+pub fn expand_braces(pattern: &str) -> Vec<String> {
+    let mut results = Vec::new();
+    expand_braces_into(pattern, &mut results);
+    results
+}
+
+fn expand_braces_into(pattern: &str, results: &mut Vec<String>) {
+    if results.len() >= MAX_BRACE_EXPANSION {
+        return;
+    }
+    let Some((start, end, alternatives)) = find_braces(pattern) else {
+        results.push(pattern.to_string());
+        return;
+    };
+    for alternative in alternatives {
+        let expanded = format!("{}{alternative}{}", &pattern[..start], &pattern[end + 1..]);
+        expand_braces_into(&expanded, results);
+    }
+}
+
+/// First pair of braces that expands - with the byte offsets of both braces and the alternatives.
+fn find_braces(pattern: &str) -> Option<(usize, usize, Vec<String>)> {
+    for (start, _) in pattern.match_indices('{') {
+        let mut depth = 0;
+        let mut commas = Vec::new();
+        let mut end = None;
+        for (idx, c) in pattern[start..].char_indices() {
+            match c {
+                '{' => depth += 1,
+                '}' => {
+                    depth -= 1;
+                    if depth == 0 {
+                        end = Some(start + idx);
+                        break;
+                    }
+                }
+                ',' if depth == 1 => commas.push(start + idx),
+                _ => (),
+            }
+        }
+        let Some(end) = end else {
+            continue;
+        };
+        if !commas.is_empty() {
+            let mut alternatives = Vec::new();
+            let mut from = start + 1;
+            for comma in commas.into_iter().chain(std::iter::once(end)) {
+                alternatives.push(pattern[from..comma].to_string());
+                from = comma + 1;
+            }
+            return Some((start, end, alternatives));
+        }
+        if let Some(range) = brace_range(&pattern[start + 1..end]) {
+            return Some((start, end, range));
+        }
+    }
+    None
+}
+
+/// Expands `1..10`, `001..010`, `10..1` or `a..e`.
+fn brace_range(inner: &str) -> Option<Vec<String>> {
+    let (from, to) = inner.split_once("..")?;
+    if let (Ok(a), Ok(b)) = (from.parse::<i64>(), to.parse::<i64>()) {
+        // Leading zeros pad all numbers to the same width
+        let padded = |s: &str| {
+            s.trim_start_matches('-').len() > 1 && s.trim_start_matches('-').starts_with('0')
+        };
+        let width = if padded(from) || padded(to) {
+            from.len().max(to.len())
+        } else {
+            0
+        };
+        let numbers: Box<dyn Iterator<Item = i64>> = if a <= b {
+            Box::new(a..=b)
+        } else {
+            Box::new((b..=a).rev())
+        };
+        return Some(
+            numbers
+                .take(MAX_BRACE_EXPANSION)
+                .map(|n| format!("{n:0width$}"))
+                .collect(),
+        );
+    }
+    let (mut a, mut b) = (from.chars(), to.chars());
+    match (a.next(), a.next(), b.next(), b.next()) {
+        (Some(a), None, Some(b), None) if a.is_ascii_alphabetic() && b.is_ascii_alphabetic() => {
+            let chars: Vec<char> = if a <= b {
+                (a..=b).collect()
+            } else {
+                (b..=a).rev().collect()
+            };
+            Some(chars.into_iter().map(String::from).collect())
+        }
+        _ => None,
+    }
+}
+
+/// Creates a directory or an (empty) file - missing parent directories are created as well (like `mkdir -p`).
+///
+/// Creating a directory that exists already fails, while an existing file is left untouched.
+pub fn create_item(path: &Path, is_dir: bool) -> std::io::Result<()> {
+    if is_dir {
+        if path.exists() {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::AlreadyExists,
+                format!("{} exists already", path.display()),
+            ));
+        }
+        return std::fs::create_dir_all(path);
+    }
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    std::fs::OpenOptions::new()
+        .read(true)
+        .append(true)
+        .create(true)
+        .open(path)?;
+    Ok(())
+}
+
+/// Returns the path of `target` relative to the directory `base`.
+///
+/// Both paths must be absolute.
+fn relative_path(base: &Path, target: &Path) -> PathBuf {
+    let common = base
+        .components()
+        .zip(target.components())
+        .take_while(|(a, b)| a == b)
+        .count();
+    let mut result = PathBuf::new();
+    for _ in base.components().skip(common) {
+        result.push("..");
+    }
+    for component in target.components().skip(common) {
+        result.push(component);
+    }
+    result
+}
+
+/// Counts lines and words of a text file (like `wc -lw`).
+///
+/// Returns `None` if the file is not valid UTF-8.
+pub fn text_stats<P: AsRef<Path>>(path: P) -> Option<(usize, usize)> {
+    let content = std::fs::read_to_string(path).ok()?;
+    Some((content.lines().count(), content.split_whitespace().count()))
+}
+
+/// Returns the longest common prefix of the names, or `None` if there are no names.
+pub fn common_prefix<'a>(mut names: impl Iterator<Item = &'a str>) -> Option<&'a str> {
+    let first = names.next()?;
+    Some(names.fold(first, |prefix, name| {
+        let len = prefix
+            .char_indices()
+            .zip(name.chars())
+            .take_while(|((_, a), b)| a == b)
+            .last()
+            .map(|((idx, c), _)| idx + c.len_utf8())
+            .unwrap_or(0);
+        &prefix[..len]
+    }))
+}
+
+/// Completes a (relative) path that is typed into a prompt against the entries in `dir`.
+///
+/// Only the last component is completed - to the longest common prefix of all matching entries.
+/// A unique match that is a directory gets a trailing `/`, so the next component can be completed.
+/// Hidden entries are only completed if the typed component starts with a dot.
+pub fn complete_path(dir: &Path, input: &str) -> Option<String> {
+    let (parent, part) = match input.rfind('/') {
+        Some(idx) => input.split_at(idx + 1),
+        None => ("", input),
+    };
+    let mut matches: Vec<(String, bool)> = std::fs::read_dir(dir.join(parent))
+        .ok()?
+        .flatten()
+        .filter_map(|entry| {
+            let name = entry.file_name().to_str()?.to_string();
+            let is_dir = entry.path().is_dir();
+            Some((name, is_dir))
+        })
+        .filter(|(name, _)| name.starts_with(part))
+        .filter(|(name, _)| part.starts_with('.') || !name.starts_with('.'))
+        .collect();
+    matches.sort();
+    let prefix = common_prefix(matches.iter().map(|(name, _)| name.as_str()))?;
+    let slash = match matches.as_slice() {
+        [(_, true)] => "/",
+        _ => "",
+    };
+    Some(format!("{parent}{prefix}{slash}"))
+}
+
+/// Free space of the filesystem that contains the path (for unprivileged users).
+pub fn free_space(path: &Path) -> Option<u64> {
+    fs_space(path).map(|(_, free)| free)
+}
+
+/// Total size and free space (for unprivileged users) of the filesystem that contains the path.
+pub fn fs_space(path: &Path) -> Option<(u64, u64)> {
+    use std::os::unix::ffi::OsStrExt;
+
+    let path = std::ffi::CString::new(path.as_os_str().as_bytes()).ok()?;
+    let mut stat: libc::statvfs = unsafe { std::mem::zeroed() };
+    if unsafe { libc::statvfs(path.as_ptr(), &mut stat) } != 0 {
+        return None;
+    }
+    let block_size = stat.f_frsize as u64;
+    Some((
+        stat.f_blocks as u64 * block_size,
+        stat.f_bavail as u64 * block_size,
+    ))
+}
+
+/// Whether the path lies on a copy-on-write filesystem (btrfs, ZFS, bcachefs).
+///
+/// Overwriting a file there writes new blocks, so the old content survives a [`shred`].
+pub fn is_copy_on_write(path: &Path) -> bool {
+    use std::os::unix::ffi::OsStrExt;
+
+    const BTRFS_MAGIC: i64 = 0x9123_683e;
+    const ZFS_MAGIC: i64 = 0x2fc1_2fc1;
+    const BCACHEFS_MAGIC: i64 = 0xca45_1a4e;
+    let Ok(path) = std::ffi::CString::new(path.as_os_str().as_bytes()) else {
+        return false;
+    };
+    let mut stat: libc::statfs = unsafe { std::mem::zeroed() };
+    if unsafe { libc::statfs(path.as_ptr(), &mut stat) } != 0 {
+        return false;
+    }
+    #[allow(clippy::unnecessary_cast)]
+    let magic = stat.f_type as i64;
+    [BTRFS_MAGIC, ZFS_MAGIC, BCACHEFS_MAGIC].contains(&magic)
+}
+
+/// Number of times the content of a file is overwritten by [`shred`].
+const SHRED_PASSES: usize = 3;
+
+/// Overwrites the content of the file (or of every file below the directory) with random data,
+/// and removes it afterwards. Symlinks are removed, but their targets are left alone.
+///
+/// NOTE: This is no guarantee - on copy-on-write or journaling filesystems and on SSDs
+/// (wear leveling), copies of the old content may survive.
+pub fn shred(path: &Path) -> Result<(), Box<dyn Error>> {
+    let metadata = std::fs::symlink_metadata(path)?;
+    if !metadata.is_dir() {
+        if metadata.is_file() {
+            overwrite(path, metadata.len())?;
+        }
+        std::fs::remove_file(path)?;
+        return Ok(());
+    }
+    for entry in walkdir::WalkDir::new(path).contents_first(true) {
+        let entry = entry?;
+        if entry.file_type().is_dir() {
+            std::fs::remove_dir(entry.path())?;
+        } else {
+            if entry.file_type().is_file() {
+                overwrite(entry.path(), entry.metadata()?.len())?;
+            }
+            std::fs::remove_file(entry.path())?;
+        }
     }
     Ok(())
 }
 
+fn overwrite(path: &Path, len: u64) -> std::io::Result<()> {
+    use rand::RngCore;
+
+    let mut file = std::fs::OpenOptions::new().write(true).open(path)?;
+    let mut buffer = vec![0; 64 * 1024];
+    for _ in 0..SHRED_PASSES {
+        file.rewind()?;
+        let mut written = 0;
+        while written < len {
+            let n = buffer.len().min((len - written) as usize);
+            rand::thread_rng().fill_bytes(&mut buffer[..n]);
+            file.write_all(&buffer[..n])?;
+            written += n as u64;
+        }
+        // Every pass has to reach the disk, otherwise only the last one would be written
+        file.sync_all()?;
+    }
+    // Don't leave the size behind
+    file.set_len(0)?;
+    file.sync_all()
+}
+
+/// Directory to start in (canonicalized), and the item that is selected there.
+///
+/// A file is opened in its directory, with the cursor on the file.
+pub fn start_location(path: &Path) -> std::io::Result<(PathBuf, Option<PathBuf>)> {
+    if path.is_dir() {
+        return Ok((path.canonicalize()?, None));
+    }
+    // Keep the name of a symlink, instead of selecting its target
+    std::fs::symlink_metadata(path)?;
+    let parent = match path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent,
+        _ => Path::new("."),
+    };
+    let dir = parent.canonicalize()?;
+    let selected = path.file_name().map(|name| dir.join(name));
+    Ok((dir, selected))
+}
+
+/// `$XDG_STATE_HOME`, or else `~/.local/state`.
+pub fn xdg_state_home() -> Option<PathBuf> {
+    match std::env::var_os("XDG_STATE_HOME").filter(|dir| !dir.is_empty()) {
+        Some(dir) => Some(PathBuf::from(dir)),
+        None => std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".local/state")),
+    }
+}
+
 /// Query the XDG Config Home (usually ~/.config) according to
 /// https://specifications.freedesktop.org/basedir-spec/basedir-spec-latest.html
 pub fn xdg_config_home() -> Result<PathBuf, Box<dyn Error>> {
@@ -122,9 +1287,302 @@ pub fn xdg_config_home() -> Result<PathBuf, Box<dyn Error>> {
         Ok(xdg_config) => Ok(PathBuf::from(xdg_config)),
         Err(_) => match std::env::var("HOME") {
             Ok(home) => Ok(PathBuf::from(home).join(".config")),
-            Err(_) => Err(format!(
-                "Neither the XDG_CONFIG_HOME nor the HOME environment variable was set."
-            ))?,
+            Err(_) => {
+                Err("Neither the XDG_CONFIG_HOME nor the HOME environment variable was set.")?
+            }
         },
     }
 }
+
+#[test]
+fn test_shred() {
+    let dir = tempfile::tempdir().unwrap();
+    let file = dir.path().join("secret");
+    std::fs::write(&file, "password").unwrap();
+    std::fs::create_dir_all(dir.path().join("sub/deeper")).unwrap();
+    std::fs::write(dir.path().join("sub/deeper/key"), vec![1; 100_000]).unwrap();
+    std::os::unix::fs::symlink(&file, dir.path().join("sub/link")).unwrap();
+
+    shred(&dir.path().join("sub")).unwrap();
+    assert!(!dir.path().join("sub").exists());
+    // The symlink is removed, but not its target
+    assert_eq!(std::fs::read_to_string(&file).unwrap(), "password");
+    shred(&file).unwrap();
+    assert!(!file.exists());
+}
+
+#[test]
+fn test_expand_braces() {
+    assert_eq!(expand_braces("{src,tests,docs}"), ["src", "tests", "docs"]);
+    assert_eq!(
+        expand_braces("img_{001..003}.txt"),
+        ["img_001.txt", "img_002.txt", "img_003.txt"]
+    );
+    assert_eq!(
+        expand_braces("{3..1}{a..b}"),
+        ["3a", "3b", "2a", "2b", "1a", "1b"]
+    );
+    assert_eq!(
+        expand_braces("src/{a,b/{c,d}}"),
+        ["src/a", "src/b/c", "src/b/d"]
+    );
+    assert_eq!(expand_braces("{x}/{,y}"), ["{x}/", "{x}/y"]);
+    assert_eq!(expand_braces("plain{"), ["plain{"]);
+    assert_eq!(expand_braces("{1..100000}").len(), MAX_BRACE_EXPANSION);
+}
+
+#[test]
+fn test_create_item() {
+    let dir = tempfile::tempdir().unwrap();
+    create_item(&dir.path().join("a/b/c"), true).unwrap();
+    assert!(dir.path().join("a/b/c").is_dir());
+    assert!(create_item(&dir.path().join("a/b"), true).is_err());
+    create_item(&dir.path().join("x/y/file.txt"), false).unwrap();
+    assert!(dir.path().join("x/y/file.txt").is_file());
+    create_item(&dir.path().join("x/y/file.txt"), false).unwrap();
+}
+
+#[test]
+fn test_relative_path() {
+    let rel = |base: &str, target: &str| relative_path(Path::new(base), Path::new(target));
+    assert_eq!(
+        rel("/home/user", "/home/user/notes.txt"),
+        Path::new("notes.txt")
+    );
+    assert_eq!(
+        rel("/home/user/.config", "/home/user/dotfiles/nvim"),
+        Path::new("../dotfiles/nvim")
+    );
+    assert_eq!(rel("/mnt/media", "/srv/a"), Path::new("../../srv/a"));
+}
+
+#[test]
+fn test_start_location() {
+    let dir = tempfile::tempdir().unwrap();
+    let root = dir.path().canonicalize().unwrap();
+    std::fs::create_dir(root.join("sub")).unwrap();
+    std::fs::write(root.join("sub/file"), "").unwrap();
+
+    assert_eq!(
+        start_location(&root.join("sub")).unwrap(),
+        (root.join("sub"), None)
+    );
+    assert_eq!(
+        start_location(&root.join("sub/../sub/file")).unwrap(),
+        (root.join("sub"), Some(root.join("sub/file")))
+    );
+    assert!(start_location(&root.join("missing")).is_err());
+}
+
+#[test]
+fn test_complete_path() {
+    let dir = tempfile::tempdir().unwrap();
+    std::fs::create_dir_all(dir.path().join("src/panel")).unwrap();
+    std::fs::write(dir.path().join("src/parser.rs"), "").unwrap();
+    std::fs::write(dir.path().join(".secret"), "").unwrap();
+
+    assert_eq!(complete_path(dir.path(), "s").as_deref(), Some("src/"));
+    assert_eq!(
+        complete_path(dir.path(), "src/p").as_deref(),
+        Some("src/pa")
+    );
+    assert_eq!(
+        complete_path(dir.path(), "src/pan").as_deref(),
+        Some("src/panel/")
+    );
+    assert_eq!(complete_path(dir.path(), "").as_deref(), Some("src/"));
+    assert_eq!(complete_path(dir.path(), ".").as_deref(), Some(".secret"));
+    assert_eq!(complete_path(dir.path(), "x"), None);
+    assert_eq!(complete_path(dir.path(), "nope/x"), None);
+}
+
+#[test]
+fn test_copy_and_verify() {
+    let dir = tempfile::tempdir().unwrap();
+    let from = dir.path().join("from");
+    std::fs::create_dir_all(from.join("sub")).unwrap();
+    std::fs::write(from.join("sub/notes.txt"), "hello").unwrap();
+    std::os::unix::fs::symlink("sub/notes.txt", from.join("link")).unwrap();
+    for idx in 0..20 {
+        std::fs::write(from.join(format!("sub/{idx}")), idx.to_string()).unwrap();
+    }
+    let to = dir.path().join("to");
+    let config = CopyConfig {
+        retries: 0,
+        parallel: 2,
+        ..Default::default()
+    };
+    copy_and_verify(&from, &to, true, config).unwrap();
+    for idx in 0..20 {
+        let content = std::fs::read_to_string(to.join(format!("sub/{idx}"))).unwrap();
+        assert_eq!(content, idx.to_string());
+    }
+    assert_eq!(
+        std::fs::read_to_string(to.join("sub/notes.txt")).unwrap(),
+        "hello"
+    );
+    assert_eq!(
+        std::fs::read_link(to.join("link")).unwrap(),
+        Path::new("sub/notes.txt")
+    );
+    let file = dir.path().join("file.txt");
+    let config = CopyConfig {
+        verify: true,
+        ..config
+    };
+    copy_and_verify(&from.join("sub/notes.txt"), &file, false, config).unwrap();
+    assert_eq!(std::fs::read_to_string(&file).unwrap(), "hello");
+    assert_eq!(
+        crc32(&file).unwrap(),
+        crc32_from_disk(&from.join("sub/notes.txt")).unwrap()
+    );
+    std::fs::write(&file, "hallo").unwrap();
+    assert_ne!(
+        crc32(&file).unwrap(),
+        crc32(&from.join("sub/notes.txt")).unwrap()
+    );
+
+    // The copy of a read-only file is read-only as well
+    use std::os::unix::fs::PermissionsExt;
+    let read_only = dir.path().join("read-only.txt");
+    std::fs::write(&read_only, "hello").unwrap();
+    std::fs::set_permissions(&read_only, std::fs::Permissions::from_mode(0o444)).unwrap();
+    let file = dir.path().join("read-only-copy.txt");
+    copy_and_verify(&read_only, &file, false, config).unwrap();
+    assert_eq!(std::fs::read_to_string(&file).unwrap(), "hello");
+}
+
+#[test]
+fn test_base64() {
+    assert_eq!(base64(b""), "");
+    assert_eq!(base64(b"f"), "Zg==");
+    assert_eq!(base64(b"fo"), "Zm8=");
+    assert_eq!(base64(b"foo"), "Zm9v");
+    assert_eq!(base64(b"/home/user"), "L2hvbWUvdXNlcg==");
+}
+
+#[test]
+fn test_parse_uri_list() {
+    let uri_list = "# copied\r\nfile:///home/user/my%20notes.txt\r\nfile://localhost/tmp/a\r\nhttps://example.org/\r\n";
+    assert_eq!(
+        parse_uri_list(uri_list),
+        [
+            PathBuf::from("/home/user/my notes.txt"),
+            PathBuf::from("/tmp/a")
+        ]
+    );
+}
+
+#[test]
+fn test_retry() {
+    let mut attempts = 0;
+    let result = retry(Path::new("file"), 1, || {
+        attempts += 1;
+        match attempts {
+            1 => Err(std::io::Error::from(std::io::ErrorKind::TimedOut)),
+            _ => Ok(attempts),
+        }
+    });
+    assert_eq!(result.unwrap(), 2);
+    // Permanent errors are not retried
+    attempts = 0;
+    let result: std::io::Result<()> = retry(Path::new("file"), 3, || {
+        attempts += 1;
+        Err(std::io::Error::from(std::io::ErrorKind::NotFound))
+    });
+    assert!(result.is_err());
+    assert_eq!(attempts, 1);
+}
+
+#[test]
+fn test_preserve_metadata() {
+    use std::os::unix::fs::PermissionsExt;
+
+    let dir = tempfile::tempdir().unwrap();
+    let from = dir.path().join("script.sh");
+    std::fs::write(&from, "echo hello").unwrap();
+    std::fs::set_permissions(&from, std::fs::Permissions::from_mode(0o750)).unwrap();
+    let mtime = FileTime::from_unix_time(1_000_000_000, 0);
+    filetime::set_file_mtime(&from, mtime).unwrap();
+
+    let target = dir.path().join("target");
+    std::fs::create_dir(&target).unwrap();
+    let config = CopyConfig {
+        preserve: true,
+        ..Default::default()
+    };
+    copy_item(&from, &target, config).unwrap();
+    let metadata = target.join("script.sh").metadata().unwrap();
+    assert_eq!(metadata.permissions().mode() & 0o777, 0o750);
+    assert_eq!(FileTime::from_last_modification_time(&metadata), mtime);
+}
+
+#[test]
+fn test_rename_all() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = |name: &str| dir.path().join(name);
+    for name in ["a", "b", "taken"] {
+        std::fs::write(path(name), name).unwrap();
+    }
+    // The second rename fails - the first one must be rolled back
+    assert!(rename_all(&[(path("a"), path("c")), (path("b"), path("taken"))]).is_err());
+    assert_eq!(std::fs::read_to_string(path("a")).unwrap(), "a");
+    assert_eq!(std::fs::read_to_string(path("b")).unwrap(), "b");
+    assert!(!path("c").exists());
+
+    rename_all(&[(path("a"), path("c")), (path("b"), path("d"))]).unwrap();
+    assert_eq!(std::fs::read_to_string(path("c")).unwrap(), "a");
+    assert_eq!(std::fs::read_to_string(path("d")).unwrap(), "b");
+    assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 3);
+
+    // Swaps and chains (d -> taken -> c -> d)
+    rename_all(&[
+        (path("d"), path("taken")),
+        (path("taken"), path("c")),
+        (path("c"), path("d")),
+    ])
+    .unwrap();
+    assert_eq!(std::fs::read_to_string(path("taken")).unwrap(), "b");
+    assert_eq!(std::fs::read_to_string(path("c")).unwrap(), "taken");
+    assert_eq!(std::fs::read_to_string(path("d")).unwrap(), "a");
+    assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 3);
+
+    // A swap, where the last rename fails after the swap was applied
+    std::fs::write(path("e"), "e").unwrap();
+    assert!(rename_all(&[
+        (path("c"), path("d")),
+        (path("d"), path("c")),
+        (path("e"), path("taken")),
+    ])
+    .is_err());
+    assert_eq!(std::fs::read_to_string(path("c")).unwrap(), "taken");
+    assert_eq!(std::fs::read_to_string(path("d")).unwrap(), "a");
+    assert_eq!(std::fs::read_to_string(path("e")).unwrap(), "e");
+    assert_eq!(std::fs::read_to_string(path("taken")).unwrap(), "b");
+    assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 4);
+}
+
+#[test]
+fn test_rsync_percent() {
+    assert_eq!(
+        rsync_percent("  1,234,567  45%   10.00MB/s    0:00:01 (xfr#1, to-chk=0/1)"),
+        Some(45)
+    );
+    assert_eq!(rsync_percent("sending incremental file list"), None);
+}
+
+#[test]
+fn test_throttled_copy() {
+    let dir = tempfile::tempdir().unwrap();
+    let (from, to) = (dir.path().join("from"), dir.path().join("to"));
+    std::fs::write(&from, vec![7; 100 * 1024]).unwrap();
+    let start = Instant::now();
+    let throttle = Throttle::new(1024 * 1024);
+    assert_eq!(
+        copy_file(&from, &to, false, Some(&throttle)).unwrap(),
+        100 * 1024
+    );
+    // 100 KiB at 1 MiB/s take about 100ms
+    assert!(start.elapsed() >= Duration::from_millis(90));
+    assert_eq!(std::fs::read(&to).unwrap(), std::fs::read(&from).unwrap());
+}
@@ -0,0 +1,187 @@
+use std::{
+    collections::BTreeSet,
+    path::{Path, PathBuf},
+    process::{Command, Stdio},
+};
+
+use log::{info, warn};
+
+use crate::{opener::run_interactive, util::exec_allowed};
+
+/// Remote location like `sftp://user@host:2222/path`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RemoteUrl {
+    pub user: Option<String>,
+    pub host: String,
+    pub port: Option<u16>,
+    /// Absolute path on the host
+    pub path: PathBuf,
+}
+
+impl RemoteUrl {
+    /// Parses `sftp://[user@]host[:port][/path]` - without a path, the root directory is used.
+    pub fn parse(url: &str) -> Option<Self> {
+        let rest = url.trim().strip_prefix("sftp://")?;
+        let (authority, path) = match rest.find('/') {
+            Some(idx) => rest.split_at(idx),
+            None => (rest, "/"),
+        };
+        let (user, host) = match authority.rsplit_once('@') {
+            Some((user, host)) => (Some(user.to_string()), host),
+            None => (None, authority),
+        };
+        let (host, port) = match host.rsplit_once(':') {
+            Some((host, port)) => (host, Some(port.parse().ok()?)),
+            None => (host, None),
+        };
+        if host.is_empty() {
+            return None;
+        }
+        Some(RemoteUrl {
+            user,
+            host: host.to_string(),
+            port,
+            path: PathBuf::from(path),
+        })
+    }
+
+    /// `user@host` (or just the host) for ssh.
+    fn login(&self) -> String {
+        match &self.user {
+            Some(user) => format!("{user}@{}", self.host),
+            None => self.host.clone(),
+        }
+    }
+
+    /// Name of the connection - there is one connection per user, host and port.
+    fn connection(&self) -> String {
+        match self.port {
+            Some(port) => format!("{}:{port}", self.login()),
+            None => self.login(),
+        }
+    }
+}
+
+/// Directory below which the connections of this rfm instance are mounted.
+pub fn mount_root() -> PathBuf {
+    std::env::var_os("XDG_RUNTIME_DIR")
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+        .unwrap_or_else(std::env::temp_dir)
+        .join("rfm")
+        .join(format!("sftp-{}", std::process::id()))
+}
+
+/// Returns true, if the path belongs to a remote connection (so reading it is slow).
+pub fn is_remote(path: &Path) -> bool {
+    path.starts_with(mount_root())
+}
+
+/// Connections to remote hosts.
+///
+/// Every connection is mounted with sshfs below [`mount_root`], so remote directories can be browsed,
+/// previewed and copied like local ones. The connections are unmounted again when rfm exits.
+#[derive(Debug)]
+pub struct Vfs {
+    root: PathBuf,
+    /// Names of the mounted connections
+    connections: BTreeSet<String>,
+}
+
+impl Default for Vfs {
+    fn default() -> Self {
+        Vfs {
+            root: mount_root(),
+            connections: BTreeSet::new(),
+        }
+    }
+}
+
+impl Vfs {
+    /// Local directory of the URL - connects to the host first, if there is no connection yet.
+    ///
+    /// sshfs runs on the normal screen of the terminal, so it can ask for a password.
+    pub fn open(&mut self, url: &RemoteUrl) -> Result<PathBuf, String> {
+        let name = url.connection();
+        let mount_point = self.root.join(&name);
+        if !self.connections.contains(&name) {
+            if !exec_allowed() {
+                return Err("external programs are disabled (--no-exec)".to_string());
+            }
+            std::fs::create_dir_all(&mount_point).map_err(|e| e.to_string())?;
+            let mut command = Command::new("sshfs");
+            command
+                .arg(format!("{}:/", url.login()))
+                .arg(&mount_point)
+                .args(["-o", "reconnect,ServerAliveInterval=15"]);
+            if let Some(port) = url.port {
+                command.arg("-p").arg(port.to_string());
+            }
+            let status =
+                run_interactive(&mut command).map_err(|e| format!("cannot run sshfs: {e}"))?;
+            if !status.success() {
+                let _ = std::fs::remove_dir(&mount_point);
+                return Err(format!("sshfs failed ({status})"));
+            }
+            info!("Connected to {name}");
+            self.connections.insert(name);
+        }
+        let relative = url.path.strip_prefix("/").unwrap_or(&url.path);
+        Ok(mount_point.join(relative))
+    }
+
+    /// URL of a path inside a connection (e.g. to show it in the header).
+    pub fn url(&self, path: &Path) -> Option<String> {
+        let mut components = path.strip_prefix(&self.root).ok()?.components();
+        let name = components.next()?.as_os_str().to_string_lossy().to_string();
+        Some(format!("sftp://{name}/{}", components.as_path().display()))
+    }
+}
+
+impl Drop for Vfs {
+    fn drop(&mut self) {
+        for name in &self.connections {
+            let mount_point = self.root.join(name);
+            let unmounted = ["fusermount3", "fusermount"].iter().any(|program| {
+                Command::new(program)
+                    .arg("-u")
+                    .arg(&mount_point)
+                    .stdout(Stdio::null())
+                    .stderr(Stdio::null())
+                    .status()
+                    .is_ok_and(|status| status.success())
+            });
+            if unmounted {
+                let _ = std::fs::remove_dir(&mount_point);
+            } else {
+                warn!("Cannot unmount {}", mount_point.display());
+            }
+        }
+        let _ = std::fs::remove_dir(&self.root);
+    }
+}
+
+#[test]
+fn test_remote_url() {
+    let url = RemoteUrl::parse("sftp://me@example.org:2222/srv/data").unwrap();
+    assert_eq!(url.user.as_deref(), Some("me"));
+    assert_eq!(url.host, "example.org");
+    assert_eq!(url.port, Some(2222));
+    assert_eq!(url.path, PathBuf::from("/srv/data"));
+    assert_eq!(url.connection(), "me@example.org:2222");
+    assert_eq!(
+        RemoteUrl::parse("sftp://example.org").map(|url| url.path),
+        Some(PathBuf::from("/"))
+    );
+    assert_eq!(RemoteUrl::parse("sftp:///path"), None);
+    assert_eq!(RemoteUrl::parse("/local/path"), None);
+
+    let vfs = Vfs::default();
+    let path = mount_root().join("me@example.org").join("srv/data");
+    assert!(is_remote(&path));
+    assert_eq!(
+        vfs.url(&path).as_deref(),
+        Some("sftp://me@example.org/srv/data")
+    );
+    assert_eq!(vfs.url(Path::new("/srv/data")), None);
+}